@@ -0,0 +1,181 @@
+// Copyright Rivtower Technologies LLC.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Encode contract calls by function signature, e.g. `transfer(address,uint256)`.
+//!
+//! The selector is the first 4 bytes of the keccak256 hash of the signature, which is what
+//! solidity compiles into contracts. So it's keccak256 on SM chains as well, not SM3.
+
+use anyhow::{anyhow, ensure, Result};
+use ethabi::{
+    encode,
+    param_type::{ParamType, Reader},
+    token::{LenientTokenizer, Tokenizer},
+};
+use sha3::{Digest, Keccak256};
+
+/// A function parsed from its signature.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FunctionSignature {
+    pub name: String,
+    pub inputs: Vec<ParamType>,
+}
+
+impl FunctionSignature {
+    pub fn parse(s: &str) -> Result<Self> {
+        let s = s.replace(' ', "");
+        let err = || {
+            anyhow!("invalid function signature `{s}`, expected like `transfer(address,uint256)`")
+        };
+        let (name, params) = s.split_once('(').ok_or_else(err)?;
+        let params = params.strip_suffix(')').ok_or_else(err)?;
+        ensure!(
+            !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_'),
+            err()
+        );
+
+        let inputs = split_args(params)
+            .iter()
+            .map(|ty| read_type(ty).map_err(|e| anyhow!("invalid type `{ty}` in `{s}`: {e}")))
+            .collect::<Result<_>>()?;
+        Ok(Self {
+            name: name.into(),
+            inputs,
+        })
+    }
+
+    /// The canonical signature, e.g. `uint` becomes `uint256`.
+    pub fn signature(&self) -> String {
+        let inputs: Vec<String> = self.inputs.iter().map(ToString::to_string).collect();
+        format!("{}({})", self.name, inputs.join(","))
+    }
+
+    pub fn selector(&self) -> [u8; 4] {
+        let hash = Keccak256::digest(self.signature().as_bytes());
+        [hash[0], hash[1], hash[2], hash[3]]
+    }
+
+    /// Encode the call data. Numbers in args can be in decimal.
+    pub fn encode_call(&self, args: &[String]) -> Result<Vec<u8>> {
+        ensure!(
+            self.inputs.len() == args.len(),
+            "`{}` takes {} args, but {} are given",
+            self.signature(),
+            self.inputs.len(),
+            args.len()
+        );
+        let tokens = self
+            .inputs
+            .iter()
+            .zip(args)
+            .map(|(ty, arg)| {
+                LenientTokenizer::tokenize(ty, arg)
+                    .map_err(|e| anyhow!("invalid `{ty}` arg `{arg}`: {e}"))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok([&self.selector()[..], &encode(&tokens)].concat())
+    }
+}
+
+fn read_type(ty: &str) -> Result<ParamType> {
+    let param = Reader::read(ty)?;
+    // ethabi reads unknown names as `uint8` for solidity enums, but here it's more likely a typo
+    // that ends up with a wrong selector.
+    ensure!(param.to_string() == expand_aliases(ty), "unknown type");
+    Ok(param)
+}
+
+/// `uint` and `int` are aliases of `uint256` and `int256`.
+fn expand_aliases(ty: &str) -> String {
+    let mut expanded = String::new();
+    let mut rest = ty;
+    while !rest.is_empty() {
+        let end = rest
+            .find(|c: char| !c.is_ascii_alphanumeric())
+            .unwrap_or(rest.len());
+        let (word, tail) = rest.split_at(end);
+        expanded.push_str(match word {
+            "uint" => "uint256",
+            "int" => "int256",
+            word => word,
+        });
+        let (delimiter, tail) = tail.split_at(tail.chars().next().map_or(0, char::len_utf8));
+        expanded.push_str(delimiter);
+        rest = tail;
+    }
+    expanded
+}
+
+/// Split comma separated args, commas inside `[]` or `()` are kept, e.g. `[1,2],0xab` is `[1,2]` and `0xab`.
+pub fn split_args(s: &str) -> Vec<String> {
+    if s.is_empty() {
+        return vec![];
+    }
+    let mut parts = vec![];
+    let mut depth = 0i32;
+    let mut start = 0;
+    for (i, c) in s.char_indices() {
+        match c {
+            '[' | '(' => depth += 1,
+            ']' | ')' => depth -= 1,
+            ',' if depth == 0 => {
+                parts.push(s[start..i].to_string());
+                start = i + 1;
+            }
+            _ => (),
+        }
+    }
+    parts.push(s[start..].to_string());
+    parts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode_call() -> Result<()> {
+        let transfer = FunctionSignature::parse("transfer(address, uint)")?;
+        assert_eq!(transfer.signature(), "transfer(address,uint256)");
+        assert_eq!(transfer.selector(), [0xa9, 0x05, 0x9c, 0xbb]);
+
+        let to = format!("0x{}", "ab".repeat(20));
+        let data = transfer.encode_call(&split_args(&format!("{to},1000")))?;
+        assert_eq!(data.len(), 4 + 32 * 2);
+        assert_eq!(&data[4 + 12..4 + 32], &[0xab; 20]);
+        assert_eq!(&data[4 + 62..], &[0x03, 0xe8]);
+
+        let err = transfer.encode_call(&split_args(&to)).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "`transfer(address,uint256)` takes 2 args, but 1 are given"
+        );
+
+        let f = FunctionSignature::parse("f(bool,bytes,string,uint[],(int,bytes32))")?;
+        assert_eq!(
+            f.signature(),
+            "f(bool,bytes,string,uint256[],(int256,bytes32))"
+        );
+        let data = f.encode_call(&split_args(&format!(
+            "true,0x1234,hello,[1,2],(-1,0x{})",
+            "00".repeat(32)
+        )))?;
+        assert_eq!(&data[..4], &f.selector());
+        assert!(FunctionSignature::parse("f()")?.encode_call(&[])?.len() == 4);
+
+        assert!(FunctionSignature::parse("transfer").is_err());
+        assert!(FunctionSignature::parse("f(uint256,foo)").is_err());
+        Ok(())
+    }
+}
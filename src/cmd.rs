@@ -17,9 +17,11 @@ mod admin;
 mod bench;
 mod cldi;
 mod context;
+mod crypto;
 mod ethabi;
 mod evm;
 mod rpc;
+mod tx;
 mod watch;
 
 use anyhow::{anyhow, bail, Result};
@@ -29,11 +31,18 @@ use std::ffi::OsString;
 
 pub use cldi::cldi_cmd;
 
+type Handler<'help, Ctx> =
+    Box<dyn Fn(&Command<'help, Ctx>, &ArgMatches, &mut Ctx) -> Result<()> + 'help>;
+
 pub struct Command<'help, Ctx: 'help> {
     cmd: clap::Command<'help>,
-    handler: Box<dyn Fn(&Self, &ArgMatches, &mut Ctx) -> Result<()> + 'help>,
+    handler: Handler<'help, Ctx>,
 
     subcmds: HashMap<String, Self>,
+    // Kept for completions since clap's generators only include visible aliases.
+    aliases: Vec<&'help str>,
+    // Ids of the args that carry secrets, see [`Command::contains_secret`].
+    sensitive_args: Vec<&'help str>,
 }
 
 impl<'help, Ctx: 'help> Command<'help, Ctx> {
@@ -43,6 +52,8 @@ impl<'help, Ctx: 'help> Command<'help, Ctx> {
             cmd: clap::Command::new(name),
             handler: Box::new(Self::dispatch_subcmd),
             subcmds: HashMap::new(),
+            aliases: vec![],
+            sensitive_args: vec![],
         }
     }
 
@@ -53,12 +64,15 @@ impl<'help, Ctx: 'help> Command<'help, Ctx> {
     }
 
     pub fn alias<S: Into<&'help str>>(mut self, name: S) -> Self {
+        let name = name.into();
         self.cmd = self.cmd.alias(name);
+        self.aliases.push(name);
         self
     }
 
     pub fn aliases(mut self, names: &[&'help str]) -> Self {
         self.cmd = self.cmd.aliases(names);
+        self.aliases.extend_from_slice(names);
         self
     }
 
@@ -107,6 +121,22 @@ impl<'help, Ctx: 'help> Command<'help, Ctx> {
         self
     }
 
+    /// Same as [`Command::arg`], but the arg carries a secret, e.g. a password or a private key.
+    /// Input lines with it are not kept in the interactive history.
+    pub fn sensitive_arg(mut self, a: Arg<'help>) -> Self {
+        self.sensitive_args.push(a.get_id());
+        self.arg(a)
+    }
+
+    pub fn args<I, T>(mut self, args: I) -> Self
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<Arg<'help>>,
+    {
+        self.cmd = self.cmd.args(args);
+        self
+    }
+
     pub fn handler<H>(mut self, handler: H) -> Self
     where
         H: Fn(&Self, &ArgMatches, &mut Ctx) -> Result<()> + 'help,
@@ -144,7 +174,7 @@ impl<'help, Ctx: 'help> Command<'help, Ctx> {
             .arg(
                 Arg::new("shell")
                     .required(true)
-                    .possible_values(&[
+                    .possible_values([
                         "bash",
                         "zsh",
                         "powershell",
@@ -155,8 +185,7 @@ impl<'help, Ctx: 'help> Command<'help, Ctx> {
             );
 
         let cmd_for_completions = self
-            .cmd
-            .clone()
+            .completion_cmd()
             .subcommand(completions_without_handler.cmd.clone());
         let completions = completions_without_handler.handler(move |_cmd, m, _ctx| {
             let shell: clap_complete::Shell = m.value_of("shell").unwrap().parse().unwrap();
@@ -174,6 +203,18 @@ impl<'help, Ctx: 'help> Command<'help, Ctx> {
         self.subcommand(completions)
     }
 
+    /// Reconstruct the clap command tree from the registered subcommands for completion generators.
+    /// Aliases are made visible, otherwise they won't be completed.
+    fn completion_cmd(&self) -> clap::Command<'help> {
+        let mut cmd = self.cmd.clone().visible_aliases(&self.aliases);
+        for (name, subcmd) in &self.subcmds {
+            if let Some(sc) = cmd.find_subcommand_mut(name) {
+                *sc = subcmd.completion_cmd();
+            }
+        }
+        cmd
+    }
+
     #[allow(dead_code)]
     pub fn exec(&self, ctx: &mut Ctx) -> Result<()> {
         let m = self.cmd.clone().get_matches();
@@ -206,6 +247,34 @@ impl<'help, Ctx: 'help> Command<'help, Ctx> {
         Ok(())
     }
 
+    /// Whether the args (without the bin name) give any [`Command::sensitive_arg`] of the
+    /// invoked command or its parents.
+    ///
+    /// It only scans the words, so that lines that fail to parse are checked as well.
+    /// A sensitive positional arg makes the whole command secret.
+    pub fn contains_secret(&self, args: &[String]) -> bool {
+        let mut path = vec![self];
+        for word in args.iter().filter(|w| !w.starts_with('-')) {
+            let cmd = path.last().unwrap();
+            if let Some(subcmd) = cmd.find_subcommand(word) {
+                path.push(subcmd);
+            }
+        }
+
+        path.iter().any(|cmd| {
+            cmd.cmd
+                .get_arguments()
+                .filter(|a| cmd.sensitive_args.contains(&a.get_id()))
+                .any(|a| a.is_positional() || args.iter().any(|w| is_flag_of(w, a)))
+        })
+    }
+
+    fn find_subcommand(&self, name: &str) -> Option<&Self> {
+        self.subcmds
+            .values()
+            .find(|c| c.get_name() == name || c.cmd.get_all_aliases().any(|a| a == name))
+    }
+
     /// Get name of the underlaying clap App.
     pub fn get_name(&self) -> &str {
         self.cmd.get_name()
@@ -246,3 +315,78 @@ impl<'help, Ctx: 'help> Command<'help, Ctx> {
         self.cmd.get_all_aliases()
     }
 }
+
+/// Whether the word gives this flag, e.g. `--password`, `--password=123`, `-p` or `-p123`.
+fn is_flag_of(word: &str, arg: &Arg) -> bool {
+    if let Some(long) = word.strip_prefix("--") {
+        let long = long.split_once('=').map_or(long, |(long, _)| long);
+        return arg.get_long() == Some(long);
+    }
+    match (word.strip_prefix('-'), arg.get_short()) {
+        (Some(flags), Some(short)) => flags.starts_with(short),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::context::Context;
+    use crate::core::mock::{MockControllerClient, MockEvmClient, MockExecutorClient};
+
+    fn collect_aliases<'a>(cmd: &'a clap::Command, aliases: &mut Vec<&'a str>) {
+        aliases.extend(cmd.get_all_aliases());
+        for subcmd in cmd.get_subcommands() {
+            collect_aliases(subcmd, aliases);
+        }
+    }
+
+    #[test]
+    fn test_contains_secret() {
+        let cldi_cmd: Command<Context<MockControllerClient, MockExecutorClient, MockEvmClient>> =
+            cldi_cmd();
+        let secret = |line: &str| {
+            let args: Vec<String> = line.split_whitespace().map(String::from).collect();
+            cldi_cmd.contains_secret(&args)
+        };
+        assert!(secret("account unlock Alice -p 123"));
+        assert!(secret("-u Alice -p123 send 0x00"));
+        assert!(secret("account generate --password=123"));
+        assert!(secret("a import 0x1234"));
+        assert!(secret("-u Alice account import --mnemonic abandon"));
+        assert!(secret("account derive-address --private-key 0x1234"));
+        assert!(secret("key derive-address --private-key=0x1234"));
+        // Lines with typos are checked as well.
+        assert!(secret("account derive-address --private-key 0x1234 --typo"));
+        assert!(!secret("account derive-address --public-key 0x1234"));
+        assert!(!secret("account import-dir ./keys --password-env PW"));
+        assert!(!secret("account generate --mnemonic --show-private-key"));
+        assert!(!secret("get block-number"));
+    }
+
+    #[test]
+    fn test_completion_cmd_aliases() {
+        let cldi_cmd: Command<Context<MockControllerClient, MockExecutorClient, MockEvmClient>> =
+            cldi_cmd();
+        let mut completion_cmd = cldi_cmd.completion_cmd();
+
+        let mut aliases = vec![];
+        collect_aliases(cldi_cmd.get_clap_command(), &mut aliases);
+        assert!(aliases.contains(&"key"));
+
+        let mut script = vec![];
+        clap_complete::generate(
+            clap_complete::Shell::Zsh,
+            &mut completion_cmd,
+            "cldi",
+            &mut script,
+        );
+        let script = String::from_utf8(script).unwrap();
+        for alias in aliases {
+            assert!(
+                script.contains(&format!("'{alias}:")),
+                "alias `{alias}` is missing"
+            );
+        }
+    }
+}
@@ -14,22 +14,110 @@
 
 use std::str::FromStr;
 
-use anyhow::anyhow;
-use clap::Arg;
+use anyhow::{anyhow, bail, ensure, Context as _, Result};
+use clap::{Arg, ArgMatches};
 use serde_json::json;
 
 use crate::{
     cmd::Command,
-    config::CryptoType,
+    config::{ContextSetting, CryptoType},
     core::{
         context::Context,
         wallet::{Account, MaybeLocked, MultiCryptoAccount},
     },
-    crypto::{EthCrypto, SmCrypto},
+    crypto::{
+        checksum_address, decrypt_keystore, encrypt_keystore, Address, Crypto, DerivationPath,
+        EthCrypto, Kdf, SmCrypto,
+    },
     display::Display,
-    utils::{hex, parse_sk},
+    log::info,
+    utils::{hex, parse_mnemonic, parse_pk, parse_sk, safe_save},
 };
 
+/// ETH addresses are shown in EIP-55 checksum form, SM addresses in lowercase.
+fn account_addr(crypto_type: CryptoType, addr: &Address) -> String {
+    match crypto_type {
+        CryptoType::Sm => hex(addr),
+        CryptoType::Eth => checksum_address(addr),
+    }
+}
+
+const DERIVATION_PATH_HELP: &str =
+    "The BIP-32 derivation path. [default: m/44'/60'/0'/0/0 for ETH, m/44'/60'/0'/0'/0' for SM]";
+
+fn account_from_mnemonic(
+    crypto_type: CryptoType,
+    mnemonic: &bip39::Mnemonic,
+    path: Option<&str>,
+) -> Result<MultiCryptoAccount> {
+    let path = path.map(str::parse::<DerivationPath>).transpose()?;
+    let account = match crypto_type {
+        CryptoType::Sm => Account::<SmCrypto>::from_mnemonic(mnemonic, path.as_ref())?.into(),
+        CryptoType::Eth => Account::<EthCrypto>::from_mnemonic(mnemonic, path.as_ref())?.into(),
+    };
+    Ok(account)
+}
+
+fn keystore_password<Co, Ex, Ev>(ctx: &Context<Co, Ex, Ev>, m: &ArgMatches) -> Result<String> {
+    let var = m
+        .value_of("password-env")
+        .ok_or_else(|| anyhow!("`--password-env` is required for keystore format"))?;
+    (ctx.env)(var).with_context(|| format!("cannot read keystore password from env `{var}`"))
+}
+
+fn keystore_kdf(m: &ArgMatches) -> Result<Kdf> {
+    let kdf = match m.value_of("kdf").unwrap() {
+        "scrypt" => Kdf::Scrypt {
+            log_n: m.value_of("scrypt-log-n").unwrap().parse()?,
+            r: m.value_of("scrypt-r").unwrap().parse()?,
+            p: m.value_of("scrypt-p").unwrap().parse()?,
+        },
+        "pbkdf2" => Kdf::Pbkdf2 {
+            c: m.value_of("pbkdf2-iterations").unwrap().parse()?,
+        },
+        _ => unreachable!(),
+    };
+    Ok(kdf)
+}
+
+fn password_env_arg<'help>() -> Arg<'help> {
+    Arg::new("password-env")
+        .help("The env var that holds the keystore password, only for keystore format")
+        .long("password-env")
+        .takes_value(true)
+}
+
+fn show_private_key_args<'help>() -> [Arg<'help>; 2] {
+    [
+        Arg::new("show-private-key")
+            .help("Print the private key in plain text, it's asked to type a confirmation first")
+            .long("show-private-key"),
+        Arg::new("yes")
+            .help("Don't ask to type the confirmation for showing the private key")
+            .short('y')
+            .long("yes")
+            .requires("show-private-key"),
+    ]
+}
+
+/// Fails if the user doesn't confirm it, or the confirmation can't be read.
+fn confirm_show_private_key<Co, Ex, Ev>(
+    ctx: &mut Context<Co, Ex, Ev>,
+    m: &ArgMatches,
+) -> Result<()> {
+    if m.is_present("yes") {
+        return Ok(());
+    }
+    const CONFIRMATION: &str = "show private key";
+    info!(ctx.verbosity, "The private key will be printed in plain text, anyone who sees it has full control of the account.");
+    let prompt = format!("Type `{CONFIRMATION}` to confirm: ");
+    match ctx.editor.readline(&prompt) {
+        Ok(s) if s.trim() == CONFIRMATION => Ok(()),
+        Ok(_) => bail!("confirmation mismatched, the private key is not shown"),
+        Err(e) => bail!("confirmation required, pass `-y` to skip it (cannot read the input: {e})"),
+    }
+}
+
 pub fn generate_account<'help, Co, Ex, Ev>() -> Command<'help, Context<Co, Ex, Ev>> {
     Command::<Context<Co, Ex, Ev>>::new("generate-account")
         .about("generate a new account")
@@ -39,7 +127,7 @@ pub fn generate_account<'help, Co, Ex, Ev>() -> Command<'help, Context<Co, Ex, E
                 .long("name")
                 .takes_value(true),
         )
-        .arg(
+        .sensitive_arg(
             Arg::new("password")
                 .short('p')
                 .long("password")
@@ -54,15 +142,51 @@ pub fn generate_account<'help, Co, Ex, Ev>() -> Command<'help, Context<Co, Ex, E
                 .ignore_case(true)
                 .validator(CryptoType::from_str)
         )
+        .arg(
+            Arg::new("mnemonic")
+                .help("Generate a BIP-39 mnemonic and derive the account from it. The mnemonic is as sensitive as the private key, so it's only shown with `--show-private-key`")
+                .long("mnemonic")
+                .requires("show-private-key")
+        )
+        .arg(
+            Arg::new("words")
+                .help("The number of words in the generated mnemonic")
+                .long("words")
+                .takes_value(true)
+                .possible_values(["12", "15", "18", "21", "24"])
+                .default_value("12")
+                .requires("mnemonic")
+        )
+        .arg(
+            Arg::new("derivation-path")
+                .help(DERIVATION_PATH_HELP)
+                .long("path")
+                .takes_value(true)
+                .requires("mnemonic")
+                .validator(DerivationPath::from_str)
+        )
+        .args(show_private_key_args())
         .handler(|_cmd, m, ctx| {
+            let show_private_key = m.is_present("show-private-key");
+            if show_private_key {
+                confirm_show_private_key(ctx, m)?;
+            }
             let name = m.value_of("name").map(str::to_string);
             let pw = m.value_of("password").map(str::as_bytes);
             let crypto_type = m.value_of("crypto-type")
                 .map(|s| s.parse::<CryptoType>().unwrap())
                 .unwrap_or(ctx.current_setting.crypto_type);
-            let account: MultiCryptoAccount = match crypto_type {
-                CryptoType::Sm => Account::<SmCrypto>::generate().into(),
-                CryptoType::Eth => Account::<EthCrypto>::generate().into(),
+            let (account, mnemonic) = if m.is_present("mnemonic") {
+                let words = m.value_of("words").unwrap().parse::<usize>().unwrap();
+                let mnemonic = bip39::Mnemonic::generate(words)?;
+                let account = account_from_mnemonic(crypto_type, &mnemonic, m.value_of("derivation-path"))?;
+                (account, Some(mnemonic))
+            } else {
+                let account: MultiCryptoAccount = match crypto_type {
+                    CryptoType::Sm => Account::<SmCrypto>::generate().into(),
+                    CryptoType::Eth => Account::<EthCrypto>::generate().into(),
+                };
+                (account, None)
             };
 
             let maybe_locked: MaybeLocked = if let Some(pw) = pw {
@@ -70,8 +194,14 @@ pub fn generate_account<'help, Co, Ex, Ev>() -> Command<'help, Context<Co, Ex, E
             } else {
                 account.into()
             };
-            // TODO: don't display secret key
-            let output = serde_json::to_string_pretty(&maybe_locked)?;
+            let mut output = if show_private_key {
+                json!(maybe_locked)
+            } else {
+                maybe_locked.to_json(ctx.display_options())
+            };
+            if let Some(mnemonic) = mnemonic {
+                output["mnemonic"] = json!(mnemonic.to_string());
+            }
 
             let name = name.unwrap_or_else(|| hex(maybe_locked.address()));
             ctx.wallet.save(name.clone(), maybe_locked)?;
@@ -80,7 +210,7 @@ pub fn generate_account<'help, Co, Ex, Ev>() -> Command<'help, Context<Co, Ex, E
                 ctx.wallet.unlock(&name, pw)?;
             }
 
-            println!("{output}");
+            ctx.emit(&output);
             Ok(())
         })
 }
@@ -89,22 +219,26 @@ pub fn list_account<'help, Co, Ex, Ev>() -> Command<'help, Context<Co, Ex, Ev>>
     Command::<Context<Co, Ex, Ev>>::new("list")
         .about("list accounts")
         .handler(|_cmd, _m, ctx| {
+            let default_account_name = ctx
+                .get_context_setting(&ctx.config.default_context)
+                .map(|setting| setting.account_name.as_str())
+                .ok();
             let accounts = ctx
                 .wallet
                 .list()
                 .map(|(name, account)| {
                     json!({
                         "name": name,
-                        "address": hex(account.address()),
+                        "address": account_addr(account.crypto_type(), account.address()),
                         "pubkey": hex(account.public_key()),
                         "is_locked": account.is_locked(),
+                        "is_default": Some(name.as_str()) == default_account_name,
                         "crypto_type": account.crypto_type(),
                     })
                 })
                 .collect::<Vec<_>>();
 
-            let output = serde_json::to_string_pretty(&accounts)?;
-            println!("{}", output);
+            ctx.emit(&json!(accounts));
 
             Ok(())
         })
@@ -113,11 +247,36 @@ pub fn list_account<'help, Co, Ex, Ev>() -> Command<'help, Context<Co, Ex, Ev>>
 pub fn import_account<'help, Co, Ex, Ev>() -> Command<'help, Context<Co, Ex, Ev>> {
     Command::<Context<Co, Ex, Ev>>::new("import")
         .about("import account")
-        .arg(
+        .sensitive_arg(
             Arg::new("secret-key")
-                .help("The secret key")
+                .help("The secret key, or the keystore file path for keystore format")
                 .takes_value(true)
-                .required(true),
+                .required_unless_present("mnemonic"),
+        )
+        .arg(
+            Arg::new("format")
+                .help("The format of the secret key")
+                .long("format")
+                .possible_values(["hex", "keystore"])
+                .default_value("hex")
+                .conflicts_with("mnemonic"),
+        )
+        .arg(password_env_arg())
+        .sensitive_arg(
+            Arg::new("mnemonic")
+                .help("Derive the account from this BIP-39 mnemonic instead of a secret key")
+                .long("mnemonic")
+                .takes_value(true)
+                .conflicts_with("secret-key")
+                .validator(parse_mnemonic),
+        )
+        .arg(
+            Arg::new("derivation-path")
+                .help(DERIVATION_PATH_HELP)
+                .long("path")
+                .takes_value(true)
+                .requires("mnemonic")
+                .validator(DerivationPath::from_str),
         )
         .arg(
             Arg::new("name")
@@ -125,7 +284,7 @@ pub fn import_account<'help, Co, Ex, Ev>() -> Command<'help, Context<Co, Ex, Ev>
                 .long("name")
                 .takes_value(true),
         )
-        .arg(
+        .sensitive_arg(
             Arg::new("password")
                 .help("The password to encrypt the account")
                 .short('p')
@@ -143,12 +302,26 @@ pub fn import_account<'help, Co, Ex, Ev>() -> Command<'help, Context<Co, Ex, Ev>
         .handler(|_cmd, m, ctx| {
             let name = m.value_of("name").map(str::to_string);
             let pw = m.value_of("password").map(str::as_bytes);
-            let sk = m.value_of("secret-key").unwrap();
             let crypto_type = m.value_of("crypto-type")
                 .map(|s| s.parse::<CryptoType>().unwrap())
                 .unwrap_or(ctx.current_setting.crypto_type);
 
-            let account: MultiCryptoAccount = match crypto_type {
+            let account: MultiCryptoAccount = if let Some(mnemonic) = m.value_of("mnemonic") {
+                let mnemonic = parse_mnemonic(mnemonic)?;
+                account_from_mnemonic(crypto_type, &mnemonic, m.value_of("derivation-path"))?
+            } else if m.value_of("format") == Some("keystore") {
+                ensure!(
+                    crypto_type == CryptoType::Eth || m.value_of("crypto-type").is_none(),
+                    "keystore format is only for ETH accounts"
+                );
+                let path = m.value_of("secret-key").unwrap();
+                let keystore = std::fs::read_to_string(path)
+                    .with_context(|| format!("cannot read keystore file `{path}`"))?;
+                let sk = decrypt_keystore(&keystore, keystore_password(ctx, m)?.as_bytes())?;
+                Account::<EthCrypto>::from_secret_key(sk).into()
+            } else {
+                let sk = m.value_of("secret-key").unwrap();
+                match crypto_type {
                 CryptoType::Sm => {
                     let sk = parse_sk::<SmCrypto>(sk)
                         .map_err(|e| anyhow!("invalid secret key for crypto type SM: {}", e))?;
@@ -159,16 +332,16 @@ pub fn import_account<'help, Co, Ex, Ev>() -> Command<'help, Context<Co, Ex, Ev>
                         .map_err(|e| anyhow!("invalid secret key for crypto type ETH: {}", e))?;
                     Account::<EthCrypto>::from_secret_key(sk).into()
                 }
+                }
             };
 
-            let addr = hex(account.address());
             let pubkey = hex(account.public_key());
             let info = json!({
-                "address": addr,
+                "address": account_addr(account.crypto_type(), account.address()),
                 "pubkey": pubkey,
             });
 
-            let name = name.unwrap_or(addr);
+            let name = name.unwrap_or_else(|| hex(account.address()));
             if let Some(pw) = pw {
                 let locked = account.lock(pw);
 
@@ -178,7 +351,81 @@ pub fn import_account<'help, Co, Ex, Ev>() -> Command<'help, Context<Co, Ex, Ev>
                 ctx.wallet.save(name, account)?;
             };
 
-            println!("{}", info.display());
+            ctx.emit(&info);
+            Ok(())
+        })
+}
+
+pub fn import_dir<'help, Co, Ex, Ev>() -> Command<'help, Context<Co, Ex, Ev>> {
+    Command::<Context<Co, Ex, Ev>>::new("import-dir")
+        .about("import all the V3 keystore files in a dir, e.g. a geth keystore dir. Only for ETH")
+        .arg(
+            Arg::new("dir")
+                .help("The dir of the keystore files, sub dirs are not scanned")
+                .takes_value(true)
+                .required(true),
+        )
+        .arg(
+            Arg::new("password-env")
+                .help("The env var that holds the password of the keystore files")
+                .long("password-env")
+                .takes_value(true)
+                .required(true),
+        )
+        .sensitive_arg(
+            Arg::new("password")
+                .help("The password to encrypt the imported accounts")
+                .short('p')
+                .long("password")
+                .takes_value(true),
+        )
+        .handler(|_cmd, m, ctx| {
+            ensure!(
+                ctx.current_setting.crypto_type == CryptoType::Eth,
+                "keystore files are only for ETH accounts, but the current context uses `{}`",
+                ctx.current_setting.crypto_type,
+            );
+            let dir = m.value_of("dir").unwrap();
+            let keystore_pw = keystore_password(ctx, m)?;
+            let pw = m.value_of("password").map(str::as_bytes);
+
+            let mut paths = std::fs::read_dir(dir)
+                .with_context(|| format!("cannot read dir `{dir}`"))?
+                .map(|entry| entry.map(|e| e.path()))
+                .collect::<std::io::Result<Vec<_>>>()?;
+            paths.retain(|p| p.is_file());
+            paths.sort();
+
+            let mut imported = vec![];
+            let mut skipped = vec![];
+            for path in paths {
+                let res = std::fs::read_to_string(&path)
+                    .context("cannot read file")
+                    .and_then(|keystore| decrypt_keystore(&keystore, keystore_pw.as_bytes()))
+                    .and_then(|sk| {
+                        let account = Account::<EthCrypto>::from_secret_key(sk);
+                        let name = hex(account.address());
+                        match pw {
+                            Some(pw) => ctx.wallet.save(name.clone(), account.lock(pw))?,
+                            None => ctx.wallet.save(name.clone(), account)?,
+                        }
+                        Ok(name)
+                    });
+                match res {
+                    Ok(name) => imported.push(name),
+                    Err(e) => skipped.push(json!({
+                        "file": path.display().to_string(),
+                        "reason": format!("{e:#}"),
+                    })),
+                }
+            }
+
+            ctx.emit(&json!({
+                "imported": imported.len(),
+                "skipped": skipped.len(),
+                "accounts": imported,
+                "skipped_files": skipped,
+            }));
             Ok(())
         })
 }
@@ -192,29 +439,113 @@ pub fn export_account<'help, Co, Ex, Ev>() -> Command<'help, Context<Co, Ex, Ev>
                 .required(true)
                 .takes_value(true),
         )
-        .arg(
+        .sensitive_arg(
             Arg::new("password")
                 .help("The password to decrypt the account")
                 .short('p')
                 .long("password")
                 .takes_value(true),
         )
+        .arg(
+            Arg::new("format")
+                .help("The export format, keystore is the Ethereum V3 keystore and only for ETH accounts")
+                .long("format")
+                .possible_values(["json", "keystore"])
+                .default_value("json"),
+        )
+        .arg(
+            Arg::new("out")
+                .help("Write the exported account to this file instead of stdout")
+                .long("out")
+                .takes_value(true),
+        )
+        .arg(password_env_arg())
+        .arg(
+            Arg::new("kdf")
+                .help("The KDF for keystore format")
+                .long("kdf")
+                .possible_values(["scrypt", "pbkdf2"])
+                .default_value("scrypt"),
+        )
+        .arg(
+            Arg::new("scrypt-log-n")
+                .help("The scrypt cost param log2(n)")
+                .long("scrypt-log-n")
+                .takes_value(true)
+                .default_value("18")
+                .validator(str::parse::<u8>),
+        )
+        .arg(
+            Arg::new("scrypt-r")
+                .help("The scrypt block size param r")
+                .long("scrypt-r")
+                .takes_value(true)
+                .default_value("8")
+                .validator(str::parse::<u32>),
+        )
+        .arg(
+            Arg::new("scrypt-p")
+                .help("The scrypt parallelization param p")
+                .long("scrypt-p")
+                .takes_value(true)
+                .default_value("1")
+                .validator(str::parse::<u32>),
+        )
+        .arg(
+            Arg::new("pbkdf2-iterations")
+                .help("The pbkdf2 iteration count")
+                .long("pbkdf2-iterations")
+                .takes_value(true)
+                .default_value("262144")
+                .validator(str::parse::<u32>),
+        )
+        .args(show_private_key_args())
         .handler(|_cmd, m, ctx| {
             let name = m.value_of("name").unwrap();
             let pw = m.value_of("password").map(str::as_bytes);
+            // Keystore is encrypted, so it's not considered as showing the private key.
+            let is_json = m.value_of("format").unwrap() == "json";
+            if is_json && m.is_present("show-private-key") {
+                confirm_show_private_key(ctx, m)?;
+            }
 
             let maybe_locked = ctx.wallet.get(name)?;
 
-            let json = if let Some(pw) = pw {
-                let unlocked = maybe_locked.unlock(pw)?;
-                json!(unlocked)
+            let export = |unlocked: &MultiCryptoAccount| -> Result<String> {
+                match m.value_of("format").unwrap() {
+                    "keystore" => {
+                        let account = match unlocked {
+                            MultiCryptoAccount::Eth(account) => account,
+                            MultiCryptoAccount::Sm(_) => {
+                                bail!("cannot export SM account `{name}` as keystore, it's only for ETH accounts")
+                            }
+                        };
+                        let keystore_pw = keystore_password(ctx, m)?;
+                        encrypt_keystore(
+                            account.expose_secret_key(),
+                            keystore_pw.as_bytes(),
+                            keystore_kdf(m)?,
+                        )
+                    }
+                    _ => Ok(json!(unlocked).display_with(ctx.display_options()).to_string()),
+                }
+            };
+            let exported = if is_json && !m.is_present("show-private-key") {
+                info!(ctx.verbosity, "the private key is omitted, use `--show-private-key` to include it");
+                maybe_locked.display_with(ctx.display_options())
+            } else if let Some(pw) = pw {
+                export(&maybe_locked.unlock(pw)?)?
             } else {
-                let unlocked = maybe_locked.unlocked()?;
-                json!(unlocked)
+                export(maybe_locked.unlocked()?)?
             };
 
-            let output = serde_json::to_string_pretty(&json)?;
-            println!("{}", output);
+            if let Some(out) = m.value_of("out") {
+                // It's written to a temp file first, which is only accessible by the owner.
+                safe_save(out, (exported + "\n").as_bytes(), true)
+                    .with_context(|| format!("cannot write to `{out}`"))?;
+            } else {
+                ctx.emit_text(exported);
+            }
 
             Ok(())
         })
@@ -222,26 +553,28 @@ pub fn export_account<'help, Co, Ex, Ev>() -> Command<'help, Context<Co, Ex, Ev>
 
 pub fn unlock_account<'help, Co, Ex, Ev>() -> Command<'help, Context<Co, Ex, Ev>> {
     Command::<Context<Co, Ex, Ev>>::new("unlock-account")
-        .about("unlock account in keystore")
+        .about("unlock account in keystore, the secret key is stored unencrypted")
         .arg(
             Arg::new("name")
                 .help("The name of the account")
                 .takes_value(true)
                 .required(true),
         )
-        .arg(
+        .sensitive_arg(
             Arg::new("password")
-                .help("The password of the account")
+                .help("The password of the account, it's prompted for if not given")
                 .short('p')
                 .long("password")
-                .takes_value(true)
-                .required(true),
+                .takes_value(true),
         )
         .handler(|_cmd, m, ctx| {
             let name = m.value_of("name").unwrap();
-            let pw = m.value_of("password").unwrap().as_bytes();
+            let pw = match m.value_of("password") {
+                Some(pw) => pw.to_string(),
+                None => ctx.read_password(&format!("Password for account `{name}`: "))?,
+            };
 
-            ctx.wallet.unlock_in_keystore(name, pw)?;
+            ctx.wallet.unlock_in_keystore(name, pw.as_bytes())?;
 
             Ok(())
         })
@@ -249,26 +582,118 @@ pub fn unlock_account<'help, Co, Ex, Ev>() -> Command<'help, Context<Co, Ex, Ev>
 
 pub fn lock_account<'help, Co, Ex, Ev>() -> Command<'help, Context<Co, Ex, Ev>> {
     Command::<Context<Co, Ex, Ev>>::new("lock-account")
-        .about("lock account in keystore")
+        .about("lock account in keystore, the secret key is encrypted with a key derived from the password by scrypt")
         .arg(
             Arg::new("name")
                 .help("The name of the account")
                 .takes_value(true)
                 .required(true),
         )
-        .arg(
+        .sensitive_arg(
             Arg::new("password")
-                .help("The password to lock the account")
+                .help("The password to lock the account, it's prompted for if not given")
                 .short('p')
                 .long("password")
+                .takes_value(true),
+        )
+        .handler(|_cmd, m, ctx| {
+            let name = m.value_of("name").unwrap();
+            // Check it before asking for the password.
+            ctx.wallet.get(name)?;
+            let pw = match m.value_of("password") {
+                Some(pw) => pw.to_string(),
+                None => {
+                    let pw = ctx.read_password("New password: ")?;
+                    if ctx.password_env.is_none() {
+                        let repeated = ctx.read_password("Repeat password: ")?;
+                        ensure!(pw == repeated, "passwords mismatched");
+                    }
+                    pw
+                }
+            };
+
+            ctx.wallet.lock(name, pw.as_bytes())?;
+
+            Ok(())
+        })
+}
+
+pub fn use_account<'help, Co, Ex, Ev>() -> Command<'help, Context<Co, Ex, Ev>> {
+    Command::<Context<Co, Ex, Ev>>::new("use-account")
+        .about("use the account for the current context and remember it in the context's setting")
+        .arg(
+            Arg::new("name")
+                .help("The name of the account")
                 .takes_value(true)
                 .required(true),
         )
         .handler(|_cmd, m, ctx| {
             let name = m.value_of("name").unwrap();
-            let pw = m.value_of("password").unwrap().as_bytes();
+            let crypto_type = ctx.wallet.get(name)?.crypto_type();
+            ensure!(
+                crypto_type == ctx.current_setting.crypto_type,
+                "account `{name}`'s crypto type `{crypto_type}` mismatched with current context's crypto type `{}`",
+                ctx.current_setting.crypto_type,
+            );
+            ctx.current_setting.account_name = name.into();
 
-            ctx.wallet.lock(name, pw)?;
+            // Remember it in the current context, which isn't always the default one, e.g. `-c testnet`.
+            if let Some(setting) = ctx.config.context_settings.get_mut(&ctx.current_context) {
+                setting.account_name = name.into();
+                ctx.config.save()?;
+            }
+
+            Ok(())
+        })
+}
+
+/// Names of the contexts that use the account of the current wallet.
+fn contexts_using_account<Co, Ex, Ev>(ctx: &Context<Co, Ex, Ev>, name: &str) -> Vec<String> {
+    let wallet_dir = ctx.config.wallet_dir(&ctx.current_setting);
+    ctx.config
+        .context_settings
+        .iter()
+        .filter(|(_, setting)| {
+            setting.account_name == name && ctx.config.wallet_dir(setting) == wallet_dir
+        })
+        .map(|(context_name, _)| context_name.clone())
+        .collect()
+}
+
+pub fn rename_account<'help, Co, Ex, Ev>() -> Command<'help, Context<Co, Ex, Ev>> {
+    Command::<Context<Co, Ex, Ev>>::new("rename-account")
+        .about("rename account, contexts using it are updated")
+        .arg(
+            Arg::new("old-name")
+                .help("The name of the account")
+                .takes_value(true)
+                .required(true),
+        )
+        .arg(
+            Arg::new("new-name")
+                .help("The new name of the account")
+                .takes_value(true)
+                .required(true),
+        )
+        .handler(|_cmd, m, ctx| {
+            let old_name = m.value_of("old-name").unwrap();
+            let new_name = m.value_of("new-name").unwrap();
+            let contexts = contexts_using_account(ctx, old_name);
+            ctx.wallet.rename(old_name, new_name)?;
+
+            if ctx.current_setting.account_name == old_name {
+                ctx.current_setting.account_name = new_name.into();
+            }
+            if !contexts.is_empty() {
+                for context_name in contexts {
+                    ctx.config
+                        .context_settings
+                        .get_mut(&context_name)
+                        .unwrap()
+                        .account_name = new_name.into();
+                }
+                ctx.config.save()?;
+            }
 
             Ok(())
         })
@@ -289,10 +714,21 @@ pub fn delete_account<'help, Co, Ex, Ev>() -> Command<'help, Context<Co, Ex, Ev>
                 .short('y')
                 .long("yes"),
         )
+        .arg(
+            Arg::new("force")
+                .help("Delete it even if it's used by contexts, they will use the `default` account instead")
+                .long("force"),
+        )
         .handler(|_cmd, m, ctx| {
             let name = m.value_of("name").unwrap();
             // If account doesn't exit, report it now.
             ctx.wallet.get(name)?;
+            let contexts = contexts_using_account(ctx, name);
+            ensure!(
+                contexts.is_empty() || m.is_present("force"),
+                "account `{name}` is used by context `{}`, use `--force` to delete it anyway",
+                contexts.join("`, `"),
+            );
             if !m.is_present("yes") {
                 let prompt = format!("Are you sure to delete the account `{name}`? (y/n) ");
                 loop {
@@ -311,14 +747,82 @@ pub fn delete_account<'help, Co, Ex, Ev>() -> Command<'help, Context<Co, Ex, Ev>
                 }
             }
             ctx.wallet.remove(name)?;
-            println!("account `{name}` deleted");
+            info!(ctx.verbosity, "account `{name}` deleted");
+
+            let fallback = ContextSetting::default().account_name;
+            if ctx.current_setting.account_name == name {
+                ctx.current_setting.account_name = fallback.clone();
+            }
+            if !contexts.is_empty() {
+                for context_name in contexts {
+                    ctx.config.context_settings.get_mut(&context_name).unwrap().account_name =
+                        fallback.clone();
+                }
+                ctx.config.save()?;
+            }
 
             Ok(())
         })
 }
 
+pub fn derive_address<'help, Co, Ex, Ev>() -> Command<'help, Context<Co, Ex, Ev>> {
+    Command::<Context<Co, Ex, Ev>>::new("derive-address")
+        .about("Derive the address from a private or public key without importing it")
+        .sensitive_arg(
+            Arg::new("private-key")
+                .help("The private key")
+                .long("private-key")
+                .takes_value(true)
+                .required_unless_present("public-key")
+                .conflicts_with("public-key"),
+        )
+        .arg(
+            Arg::new("public-key")
+                .help("The public key")
+                .long("public-key")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::new("crypto-type")
+                .help("The crypto type of the key. [default: <current-context-crypto-type>]")
+                .long("crypto")
+                .possible_values(["SM", "ETH"])
+                .ignore_case(true)
+                .validator(CryptoType::from_str),
+        )
+        .handler(|_cmd, m, ctx| {
+            let crypto_type = m
+                .value_of("crypto-type")
+                .map(|s| s.parse::<CryptoType>().unwrap())
+                .unwrap_or(ctx.current_setting.crypto_type);
+
+            fn derive<C: Crypto>(m: &ArgMatches, crypto_type: CryptoType) -> Result<Address> {
+                if let Some(sk) = m.value_of("private-key") {
+                    let sk = parse_sk::<C>(sk).with_context(|| {
+                        format!("invalid private key for crypto type {crypto_type}")
+                    })?;
+                    Ok(C::sk2addr(&sk))
+                } else {
+                    let pk =
+                        parse_pk::<C>(m.value_of("public-key").unwrap()).with_context(|| {
+                            format!("invalid public key for crypto type {crypto_type}")
+                        })?;
+                    Ok(C::pk2addr(&pk))
+                }
+            }
+            let addr = match crypto_type {
+                CryptoType::Sm => derive::<SmCrypto>(m, crypto_type)?,
+                CryptoType::Eth => derive::<EthCrypto>(m, crypto_type)?,
+            };
+
+            ctx.emit(&account_addr(crypto_type, &addr));
+            Ok(())
+        })
+}
+
 pub fn account_cmd<'help, Co, Ex, Ev>() -> Command<'help, Context<Co, Ex, Ev>> {
     Command::<Context<Co, Ex, Ev>>::new("account")
+        .alias("key")
         .about("Account commands")
         .subcommand_required_else_help(true)
         .subcommands([
@@ -327,9 +831,13 @@ pub fn account_cmd<'help, Co, Ex, Ev>() -> Command<'help, Context<Co, Ex, Ev>> {
                 .aliases(&["gen", "g", "create"]),
             list_account().name("list").aliases(&["ls", "l"]),
             import_account().name("import"),
+            import_dir(),
             export_account().name("export"),
-            unlock_account().name("unlock"),
-            lock_account().name("lock"),
+            unlock_account().name("unlock").alias("decrypt"),
+            lock_account().name("lock").alias("encrypt"),
+            use_account().name("use"),
+            derive_address(),
+            rename_account().name("rename").alias("mv"),
             delete_account()
                 .name("delete")
                 .aliases(&["del", "rm", "remove"]),
@@ -339,6 +847,7 @@ pub fn account_cmd<'help, Co, Ex, Ev>() -> Command<'help, Context<Co, Ex, Ev>> {
 #[cfg(test)]
 mod tests {
     use crate::cmd::cldi_cmd;
+    use crate::config::Config;
     use crate::core::mock::context;
 
     #[test]
@@ -355,8 +864,355 @@ mod tests {
         cldi_cmd
             .exec_from(["cldi", "account", "export", "test"], &mut ctx)
             .unwrap();
+        cldi_cmd
+            .exec_from(
+                [
+                    "cldi",
+                    "account",
+                    "export",
+                    "test",
+                    "--show-private-key",
+                    "-y",
+                ],
+                &mut ctx,
+            )
+            .unwrap();
+        // The mnemonic is only shown with the private key.
+        assert!(cldi_cmd
+            .exec_from(["cldi", "account", "generate", "--mnemonic"], &mut ctx)
+            .is_err());
         cldi_cmd
             .exec_from(["cldi", "account", "delete", "test", "--yes"], &mut ctx)
             .unwrap();
     }
+
+    #[test]
+    fn test_use_account() {
+        let cldi_cmd = cldi_cmd();
+        let (mut ctx, _temp_dir) = context();
+
+        cldi_cmd
+            .exec_from(["cldi", "account", "generate", "--name", "alice"], &mut ctx)
+            .unwrap();
+        cldi_cmd
+            .exec_from(["cldi", "use", "alice"], &mut ctx)
+            .unwrap();
+        assert_eq!(ctx.current_setting.account_name, "alice");
+
+        let config = Config::open(&ctx.config.data_dir).unwrap();
+        let default_setting = &config.context_settings[&config.default_context];
+        assert_eq!(default_setting.account_name, "alice");
+
+        assert!(cldi_cmd
+            .exec_from(["cldi", "use", "bob"], &mut ctx)
+            .is_err());
+
+        // Only the context it's used in remembers it.
+        let testnet = ctx.current_setting.clone();
+        ctx.config
+            .context_settings
+            .insert("testnet".into(), testnet);
+        cldi_cmd
+            .exec_from(["cldi", "account", "generate", "--name", "bob"], &mut ctx)
+            .unwrap();
+        cldi_cmd
+            .exec_from(["cldi", "-c", "testnet", "use", "bob"], &mut ctx)
+            .unwrap();
+        assert_eq!(ctx.current_context, "default");
+        assert_eq!(ctx.current_setting.account_name, "alice");
+        let config = Config::open(&ctx.config.data_dir).unwrap();
+        assert_eq!(config.context_settings["testnet"].account_name, "bob");
+        assert_eq!(config.context_settings["default"].account_name, "alice");
+    }
+
+    #[test]
+    fn test_rename_delete_account() {
+        use crate::core::wallet::Wallet;
+
+        let cldi_cmd = cldi_cmd();
+        let (mut ctx, _temp_dir) = context();
+        let default_account_name = |ctx: &crate::core::context::Context<_, _, _>| {
+            ctx.config.context_settings[&ctx.config.default_context]
+                .account_name
+                .clone()
+        };
+
+        cldi_cmd
+            .exec_from(["cldi", "account", "rename", "default", "main"], &mut ctx)
+            .unwrap();
+        assert_eq!(ctx.current_setting.account_name, "main");
+        assert_eq!(default_account_name(&ctx), "main");
+        let wallet = Wallet::open(&ctx.config.data_dir).unwrap();
+        assert!(wallet.get("main").is_ok());
+        assert!(wallet.get("default").is_err());
+
+        cldi_cmd
+            .exec_from(["cldi", "account", "generate", "--name", "alice"], &mut ctx)
+            .unwrap();
+        let err = cldi_cmd
+            .exec_from(["cldi", "account", "rename", "main", "alice"], &mut ctx)
+            .unwrap_err();
+        assert_eq!(err.to_string(), "account `alice` already exists");
+
+        let err = cldi_cmd
+            .exec_from(["cldi", "account", "delete", "main", "--yes"], &mut ctx)
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "account `main` is used by context `default`, use `--force` to delete it anyway"
+        );
+        cldi_cmd
+            .exec_from(
+                ["cldi", "account", "delete", "main", "--yes", "--force"],
+                &mut ctx,
+            )
+            .unwrap();
+        assert_eq!(ctx.current_setting.account_name, "default");
+        assert_eq!(default_account_name(&ctx), "default");
+    }
+
+    #[test]
+    fn test_keystore_export_import() {
+        let cldi_cmd = cldi_cmd();
+        let (mut ctx, temp_dir) = context();
+        let keystore_file = temp_dir.path().join("acct.json");
+        let keystore_file = keystore_file.to_str().unwrap();
+        ctx.env =
+            Box::new(|var| (var == "CLDI_TEST_KEYSTORE_PW").then(|| "keystore-pw".to_string()));
+
+        cldi_cmd
+            .exec_from(
+                [
+                    "cldi", "account", "generate", "--name", "eth", "--crypto", "ETH",
+                ],
+                &mut ctx,
+            )
+            .unwrap();
+        cldi_cmd
+            .exec_from(
+                [
+                    "cldi",
+                    "account",
+                    "export",
+                    "eth",
+                    "--format",
+                    "keystore",
+                    "--kdf",
+                    "pbkdf2",
+                    "--pbkdf2-iterations",
+                    "1024",
+                    "--password-env",
+                    "CLDI_TEST_KEYSTORE_PW",
+                    "--out",
+                    keystore_file,
+                ],
+                &mut ctx,
+            )
+            .unwrap();
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(keystore_file)
+                .unwrap()
+                .permissions()
+                .mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+        cldi_cmd
+            .exec_from(
+                [
+                    "cldi",
+                    "account",
+                    "import",
+                    keystore_file,
+                    "--format",
+                    "keystore",
+                    "--password-env",
+                    "CLDI_TEST_KEYSTORE_PW",
+                    "--name",
+                    "imported",
+                ],
+                &mut ctx,
+            )
+            .unwrap();
+        assert_eq!(
+            ctx.wallet.get("eth").unwrap().address(),
+            ctx.wallet.get("imported").unwrap().address()
+        );
+
+        // SM accounts can't be exported as keystore.
+        assert!(cldi_cmd
+            .exec_from(
+                [
+                    "cldi",
+                    "account",
+                    "export",
+                    "default",
+                    "--format",
+                    "keystore",
+                    "--password-env",
+                    "CLDI_TEST_KEYSTORE_PW",
+                ],
+                &mut ctx,
+            )
+            .is_err());
+    }
+
+    #[test]
+    fn test_import_dir() {
+        use crate::config::CryptoType;
+        use crate::crypto::{encrypt_keystore, Crypto, EthCrypto, Kdf};
+        use crate::utils::hex;
+
+        let cldi_cmd = cldi_cmd();
+        let (mut ctx, temp_dir) = context();
+        let keystore_dir = temp_dir.path().join("keystore");
+        std::fs::create_dir(&keystore_dir).unwrap();
+        ctx.env =
+            Box::new(|var| (var == "CLDI_TEST_IMPORT_DIR_PW").then(|| "keystore-pw".to_string()));
+
+        let kdf = Kdf::Pbkdf2 { c: 1024 };
+        let sk = EthCrypto::generate_secret_key();
+        let addr = hex(&EthCrypto::pk2addr(&EthCrypto::sk2pk(&sk)));
+        let keystore = encrypt_keystore(&sk, b"keystore-pw", kdf).unwrap();
+        std::fs::write(keystore_dir.join("UTC--a"), keystore).unwrap();
+        let other_sk = EthCrypto::generate_secret_key();
+        let other = encrypt_keystore(&other_sk, b"other-pw", kdf).unwrap();
+        std::fs::write(keystore_dir.join("UTC--b"), other).unwrap();
+        std::fs::write(keystore_dir.join("notes.txt"), "not a keystore").unwrap();
+
+        let args = [
+            "cldi",
+            "account",
+            "import-dir",
+            keystore_dir.to_str().unwrap(),
+            "--password-env",
+            "CLDI_TEST_IMPORT_DIR_PW",
+        ];
+        // The mock context uses SM.
+        assert!(cldi_cmd.exec_from(args, &mut ctx).is_err());
+
+        ctx.current_setting.crypto_type = CryptoType::Eth;
+        let (res, outputs) = ctx.capture(|ctx| cldi_cmd.exec_from(args, ctx));
+        res.unwrap();
+        assert_eq!(outputs[0]["imported"], 1);
+        assert_eq!(outputs[0]["skipped"], 2);
+        assert_eq!(outputs[0]["accounts"][0], addr.as_str());
+        let reason = outputs[0]["skipped_files"][0]["reason"].as_str().unwrap();
+        assert!(reason.contains("wrong password"), "{reason}");
+        assert_eq!(
+            ctx.wallet.get(&addr).unwrap().crypto_type(),
+            CryptoType::Eth
+        );
+
+        // Already imported.
+        let (res, outputs) = ctx.capture(|ctx| cldi_cmd.exec_from(args, ctx));
+        res.unwrap();
+        assert_eq!(outputs[0]["imported"], 0);
+    }
+
+    #[test]
+    fn test_encrypt_account() {
+        use crate::core::wallet::{Account, Wallet};
+        use crate::crypto::{ArrayLike, Crypto, SmCrypto};
+
+        let cldi_cmd = cldi_cmd();
+        let (mut ctx, _temp_dir) = context();
+        ctx.env = Box::new(|var| (var == "CLDI_TEST_ACCOUNT_PW").then(|| "account-pw".to_string()));
+
+        cldi_cmd
+            .exec_from(
+                [
+                    "cldi",
+                    "--password-env",
+                    "CLDI_TEST_ACCOUNT_PW",
+                    "account",
+                    "encrypt",
+                    "default",
+                ],
+                &mut ctx,
+            )
+            .unwrap();
+        assert!(ctx.wallet.get("default").unwrap().is_locked());
+        assert!(ctx.current_account().is_err());
+        // The password is only read when signing.
+        ctx.password_env = Some("CLDI_TEST_ACCOUNT_PW".into());
+        ctx.signing_account().unwrap();
+        assert!(ctx.current_account().is_ok());
+        ctx.unlocked_signer.take();
+        ctx.password_env = None;
+
+        cldi_cmd
+            .exec_from(
+                ["cldi", "account", "decrypt", "default", "-p", "account-pw"],
+                &mut ctx,
+            )
+            .unwrap();
+        assert!(!ctx.wallet.get("default").unwrap().is_locked());
+
+        // Accounts locked by old versions use the password as the key directly.
+        let account = Account::<SmCrypto>::generate();
+        let legacy = format!(
+            "crypto_type = \"SM\"\naddress = \"{}\"\npublic_key = \"{}\"\nencrypted_sk = \"{}\"\n",
+            account.address().to_hex(),
+            account.public_key().to_hex(),
+            SmCrypto::encrypt(account.expose_secret_key().as_slice(), b"legacy-pw").to_hex(),
+        );
+        let wallet_dir = ctx.config.wallet_dir(&ctx.current_setting);
+        std::fs::write(wallet_dir.join("accounts/legacy.toml"), legacy).unwrap();
+        let wallet = Wallet::open(&wallet_dir).unwrap();
+        let legacy = wallet.get("legacy").unwrap();
+        assert!(legacy.is_locked());
+        assert_eq!(
+            legacy.unlock(b"legacy-pw").unwrap().address(),
+            account.address()
+        );
+    }
+
+    #[test]
+    fn test_derive_address() {
+        use crate::core::wallet::Account;
+        use crate::crypto::{ArrayLike, EthCrypto};
+        use crate::utils::parse_sk;
+
+        let cldi_cmd = cldi_cmd();
+        let (mut ctx, _temp_dir) = context();
+
+        // The same key as in the signing test of the controller.
+        let sk = format!("0x{}", "11".repeat(32));
+        let account = Account::<EthCrypto>::from_secret_key(parse_sk::<EthCrypto>(&sk).unwrap());
+        let pk = account.public_key().to_hex();
+        for args in [["--private-key", &sk], ["--public-key", &pk]] {
+            let (res, outputs) = ctx.capture(|ctx| {
+                cldi_cmd.exec_from(
+                    ["cldi", "account", "derive-address", "--crypto", "ETH"]
+                        .into_iter()
+                        .chain(args),
+                    ctx,
+                )
+            });
+            res.unwrap();
+            // In EIP-55 checksum form.
+            assert_eq!(
+                outputs,
+                [serde_json::json!(
+                    "0x19E7E376E7C213B7E7e7e46cc70A5dD086DAff2A"
+                )]
+            );
+        }
+
+        // SM public key isn't 32 bytes.
+        let err = cldi_cmd
+            .exec_from(
+                ["cldi", "account", "derive-address", "--public-key", &sk],
+                &mut ctx,
+            )
+            .unwrap_err();
+        assert_eq!(
+            format!("{err:#}"),
+            "invalid public key for crypto type SM: expected 64 bytes, got 32"
+        );
+        // No wallet writes.
+        assert_eq!(ctx.wallet.list().count(), 1);
+    }
 }
@@ -12,20 +12,80 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use anyhow::Result;
-use clap::Arg;
+use anyhow::{bail, ensure, Context as _, Result};
+use clap::{Arg, ArgMatches};
+use serde_json::json;
 
 use crate::{
     cmd::Command,
-    core::{admin::AdminBehaviour, context::Context},
-    crypto::Address,
-    display::Display,
-    utils::parse_addr,
+    core::{
+        admin::{
+            emergency_brake_output, set_block_interval_output, update_admin_output,
+            update_validators_output, AdminBehaviour,
+        },
+        context::Context,
+        controller::{ControllerBehaviour, SignerBehaviour, TransactionSenderBehaviour, UtxoType},
+    },
+    crypto::{Address, ArrayLike},
+    log::{info, warning},
+    proto::blockchain::{raw_transaction::Tx, RawTransaction, UtxoTransaction},
+    utils::{
+        hex, load_raw_tx, parse_data_input, parse_hash, save_raw_tx, validate_addr,
+        validate_data_input,
+    },
 };
 
+fn export_arg<'help>() -> Arg<'help> {
+    Arg::new("export")
+        .help("Sign and save the utxo to this file instead of sending it, so that other admins can co-sign it")
+        .long("export")
+        .takes_value(true)
+}
+
+fn utxo_info(raw: &RawTransaction) -> Result<serde_json::Value> {
+    match &raw.tx {
+        Some(Tx::UtxoTx(utxo)) => Ok(json!({
+            "utxo_hash": hex(&utxo.transaction_hash),
+            "signed_by": utxo.witnesses.iter().map(|w| hex(&w.sender)).collect::<Vec<_>>(),
+        })),
+        _ => bail!("not an utxo transaction"),
+    }
+}
+
+fn export_utxo<Co, Ex, Ev>(
+    ctx: &Context<Co, Ex, Ev>,
+    path: &str,
+    output: Vec<u8>,
+    utxo_type: UtxoType,
+) -> Result<()>
+where
+    Co: ControllerBehaviour + Send + Sync,
+{
+    let utxo = ctx
+        .rt
+        .block_on(ctx.controller.build_utxo(output, utxo_type))??;
+    export_built_utxo(ctx, path, utxo)
+}
+
+fn export_built_utxo<Co, Ex, Ev>(
+    ctx: &Context<Co, Ex, Ev>,
+    path: &str,
+    utxo: UtxoTransaction,
+) -> Result<()>
+where
+    Co: ControllerBehaviour,
+{
+    let signer = ctx.rt.block_on(ctx.signer())??;
+    let raw = signer.sign_raw_utxo(utxo);
+    save_raw_tx(path, &raw, false)?;
+
+    ctx.emit(&utxo_info(&raw)?);
+    Ok(())
+}
+
 pub fn update_admin<'help, Co, Ex, Ev>() -> Command<'help, Context<Co, Ex, Ev>>
 where
-    Co: AdminBehaviour,
+    Co: ControllerBehaviour + Send + Sync,
 {
     Command::<Context<Co, Ex, Ev>>::new("update-admin")
         .about("Update admin of the chain")
@@ -33,78 +93,216 @@ where
             Arg::new("admin")
                 .help("the address of the new admin")
                 .required(true)
-                .validator(parse_addr),
+                .validator(validate_addr),
         )
+        .arg(export_arg())
         .handler(|_cmd, m, ctx| {
-            let new_admin_addr = parse_addr(m.value_of("admin").unwrap())?;
-            let old_admin_signer = ctx.current_account()?;
+            let new_admin_addr = ctx.parse_addr(m.value_of("admin").unwrap())?;
+            if let Some(path) = m.value_of("export") {
+                let output = update_admin_output(new_admin_addr);
+                return export_utxo(ctx, path, output, UtxoType::Admin);
+            }
+            let old_admin_signer = ctx.rt.block_on(ctx.signer())??;
             let tx_hash = ctx.rt.block_on(async {
                 ctx.controller
                     .update_admin(old_admin_signer, new_admin_addr)
                     .await
             })??;
-            println!("{}", tx_hash.display());
+            ctx.emit(&tx_hash);
             Ok(())
         })
 }
 
+fn check_validators(validators: &[Address]) -> Result<()> {
+    ensure!(!validators.is_empty(), "validators must not be empty");
+    for (i, v) in validators.iter().enumerate() {
+        ensure!(
+            !validators[..i].contains(v),
+            "duplicate validator `{}`",
+            hex(v)
+        );
+    }
+    Ok(())
+}
+
+fn current_validators<Co, Ex, Ev>(ctx: &Context<Co, Ex, Ev>) -> Result<Vec<Address>>
+where
+    Co: ControllerBehaviour,
+{
+    let system_config = ctx.rt.block_on(ctx.controller.get_system_config())??;
+    system_config
+        .validators
+        .iter()
+        .map(|v| {
+            Address::try_from_slice(v)
+                .with_context(|| format!("current validator `{}` is not an address", hex(v)))
+        })
+        .collect()
+}
+
+/// Submit the full validator set, or export it if `--export` is given.
+fn submit_validators<Co, Ex, Ev>(
+    ctx: &Context<Co, Ex, Ev>,
+    m: &ArgMatches,
+    validators: &[Address],
+) -> Result<()>
+where
+    Co: ControllerBehaviour + Send + Sync,
+{
+    check_validators(validators)?;
+    if let Some(path) = m.value_of("export") {
+        let output = update_validators_output(validators);
+        return export_utxo(ctx, path, output, UtxoType::Validators);
+    }
+
+    let admin_signer = ctx.rt.block_on(ctx.signer())??;
+    let tx_hash = ctx.rt.block_on(async {
+        ctx.controller
+            .update_validators(admin_signer, validators)
+            .await
+    })??;
+    ctx.emit(&tx_hash);
+    Ok(())
+}
+
 pub fn update_validators<'help, Co, Ex, Ev>() -> Command<'help, Context<Co, Ex, Ev>>
 where
-    Co: AdminBehaviour,
+    Co: ControllerBehaviour + Send + Sync,
 {
     Command::<Context<Co, Ex, Ev>>::new("update-validators")
+        .alias("set-validators")
         .about("Update validators of the chain")
         .arg(
             Arg::new("validators")
-                .help("a space-separated list of the new validator addresses, e.g. `cldi update-validators 0x12..34 0xab..cd`")
+                .help("a space or comma separated list of the new validator addresses, e.g. `cldi update-validators 0x12..34,0xab..cd`")
                 .required(true)
                 .multiple_values(true)
-                .validator(parse_addr)
+                .use_value_delimiter(true)
+                .validator(validate_addr)
         )
+        .arg(export_arg())
         .handler(|_cmd, m, ctx| {
             let validators = m
                 .values_of("validators")
                 .unwrap()
-                .map(parse_addr)
+                .map(|s| ctx.parse_addr(s))
                 .collect::<Result<Vec<Address>>>()?;
+            submit_validators(ctx, m, &validators)
+        })
+}
 
-            let admin_signer = ctx.current_account()?;
-            let tx_hash = ctx.rt.block_on(async {
-                ctx.controller.update_validators(admin_signer, &validators).await
-            })??;
-            println!("{}", tx_hash.display());
-            Ok(())
+pub fn add_validator<'help, Co, Ex, Ev>() -> Command<'help, Context<Co, Ex, Ev>>
+where
+    Co: ControllerBehaviour + Send + Sync,
+{
+    Command::<Context<Co, Ex, Ev>>::new("add-validator")
+        .about("Add a validator to the current validators")
+        .arg(
+            Arg::new("validator")
+                .help("the address of the validator to add")
+                .required(true)
+                .validator(validate_addr),
+        )
+        .arg(export_arg())
+        .handler(|_cmd, m, ctx| {
+            let validator = ctx.parse_addr(m.value_of("validator").unwrap())?;
+            let mut validators = current_validators(ctx)?;
+            ensure!(
+                !validators.contains(&validator),
+                "`{}` is already a validator",
+                hex(&validator)
+            );
+            validators.push(validator);
+            submit_validators(ctx, m, &validators)
         })
 }
 
+pub fn remove_validator<'help, Co, Ex, Ev>() -> Command<'help, Context<Co, Ex, Ev>>
+where
+    Co: ControllerBehaviour + Send + Sync,
+{
+    Command::<Context<Co, Ex, Ev>>::new("remove-validator")
+        .about("Remove a validator from the current validators")
+        .arg(
+            Arg::new("validator")
+                .help("the address of the validator to remove")
+                .required(true)
+                .validator(validate_addr),
+        )
+        .arg(export_arg())
+        .handler(|_cmd, m, ctx| {
+            let validator = ctx.parse_addr(m.value_of("validator").unwrap())?;
+            let mut validators = current_validators(ctx)?;
+            let len = validators.len();
+            validators.retain(|v| v != &validator);
+            ensure!(
+                validators.len() < len,
+                "`{}` is not a validator",
+                hex(&validator)
+            );
+            submit_validators(ctx, m, &validators)
+        })
+}
+
+const MAX_BLOCK_INTERVAL: u32 = 3600;
+
+fn parse_block_interval(s: &str) -> Result<u32> {
+    let block_interval = s.parse::<u32>()?;
+    ensure!(
+        (1..=MAX_BLOCK_INTERVAL).contains(&block_interval),
+        "block interval must be within 1..={MAX_BLOCK_INTERVAL} seconds"
+    );
+    Ok(block_interval)
+}
+
+/// Returns a warning if the new interval is at least 10 times longer or shorter than the current one.
+fn block_interval_warning(current: u32, new: u32) -> Option<String> {
+    let (current, new) = (current as u64, new as u64);
+    (current > 0 && (new >= current * 10 || new * 10 <= current)).then(|| {
+        format!("the new block interval `{new}s` is drastically different from the current `{current}s`")
+    })
+}
+
 pub fn set_block_interval<'help, Co, Ex, Ev>() -> Command<'help, Context<Co, Ex, Ev>>
 where
-    Co: AdminBehaviour,
+    Co: ControllerBehaviour + Send + Sync,
 {
     Command::<Context<Co, Ex, Ev>>::new("set-block-interval")
         .about("Set block interval")
         .arg(
             Arg::new("block_interval")
-                .help("new block interval")
+                .help("new block interval in seconds")
                 .required(true)
-                .validator(str::parse::<u32>),
+                .validator(parse_block_interval),
         )
+        .arg(export_arg())
         .handler(|_cmd, m, ctx| {
-            let block_interval = m.value_of("block_interval").unwrap().parse::<u32>()?;
-            let admin_signer = ctx.current_account()?;
+            let block_interval = parse_block_interval(m.value_of("block_interval").unwrap())?;
+            let current = ctx
+                .rt
+                .block_on(ctx.controller.get_system_config())??
+                .block_interval;
+            if let Some(warning) = block_interval_warning(current, block_interval) {
+                warning!("{warning}");
+            }
+            if let Some(path) = m.value_of("export") {
+                let output = set_block_interval_output(block_interval);
+                return export_utxo(ctx, path, output, UtxoType::BlockInterval);
+            }
+            let admin_signer = ctx.rt.block_on(ctx.signer())??;
             let tx_hash = ctx.rt.block_on(async {
                 ctx.controller
                     .set_block_interval(admin_signer, block_interval)
                     .await
             })??;
-            println!("{}", tx_hash.display());
+            ctx.emit(&tx_hash);
             Ok(())
         })
 }
 
 pub fn emergency_brake<'help, Co, Ex, Ev>() -> Command<'help, Context<Co, Ex, Ev>>
 where
-    Co: AdminBehaviour,
+    Co: ControllerBehaviour + Send + Sync,
 {
     Command::<Context<Co, Ex, Ev>>::new("emergency-brake")
         .about("Send emergency brake cmd to chain")
@@ -112,22 +310,196 @@ where
             Arg::new("switch")
                 .help("turn on/off")
                 .required(true)
-                .possible_values(&["on", "off"]),
+                .possible_values(["on", "off"]),
         )
+        .arg(export_arg())
+        .arg(brake_force_arg())
         .handler(|_cmd, m, ctx| {
             let switch = m.value_of("switch").unwrap() == "on";
-            let admin_signer = ctx.current_account()?;
+            // The exported one is confirmed when it's sent by `send-signed-utxo`.
+            if let Some(path) = m.value_of("export") {
+                let output = emergency_brake_output(switch);
+                return export_utxo(ctx, path, output, UtxoType::EmergencyBrake);
+            }
+            if switch && !m.is_present("force") {
+                confirm_emergency_brake(ctx)?;
+            }
+            let admin_signer = ctx.rt.block_on(ctx.signer())??;
             let tx_hash = ctx
                 .rt
                 .block_on(async { ctx.controller.emergency_brake(admin_signer, switch).await })??;
-            println!("{}", tx_hash.display());
+            ctx.emit(&tx_hash);
+            if switch {
+                warning!("once the utxo is committed, the chain only accepts admin utxos until `cldi admin emergency-brake off`");
+            }
+            Ok(())
+        })
+}
+
+fn brake_force_arg<'help>() -> Arg<'help> {
+    Arg::new("force")
+        .help("Don't ask to type the chain id for confirming turning on the emergency brake")
+        .short('y')
+        .long("force")
+        .visible_alias("yes")
+}
+
+fn is_emergency_brake_on(utxo: &UtxoTransaction) -> bool {
+    utxo.lock_id == UtxoType::EmergencyBrake as u64 && utxo.output != emergency_brake_output(false)
+}
+
+/// Ask to type the chain id before sending an utxo that turns on the emergency brake.
+fn confirm_emergency_brake<Co, Ex, Ev>(ctx: &mut Context<Co, Ex, Ev>) -> Result<()>
+where
+    Co: ControllerBehaviour,
+{
+    let system_config = ctx.rt.block_on(ctx.controller.get_system_config())??;
+    let chain_id = system_config.chain_id_hash()?.to_hex();
+    info!(ctx.verbosity, "Turning on the emergency brake halts the chain, normal txs will be rejected until it's turned off.");
+    let prompt = format!("Type the chain id `{chain_id}` to confirm: ");
+    match ctx.editor.readline(&prompt) {
+        Ok(s) if s.trim().eq_ignore_ascii_case(&chain_id) => Ok(()),
+        Ok(_) => bail!("chain id mismatched, the emergency brake is not sent"),
+        Err(e) => {
+            bail!("confirmation required, pass `--force` to skip it (cannot read the input: {e})")
+        }
+    }
+}
+
+pub fn sign_utxo<'help, Co, Ex, Ev>() -> Command<'help, Context<Co, Ex, Ev>> {
+    Command::<Context<Co, Ex, Ev>>::new("sign-utxo")
+        .about("Co-sign an exported utxo with the current account")
+        .arg(
+            Arg::new("file")
+                .help("the exported utxo file, the new witness is appended in place")
+                .required(true)
+                .takes_value(true),
+        )
+        .handler(|_cmd, m, ctx| {
+            let path = m.value_of("file").unwrap();
+            let mut raw = load_raw_tx(path)?;
+            ctx.signing_account()?.append_witness_to_utxo(&mut raw)?;
+            save_raw_tx(path, &raw, true)?;
+
+            ctx.emit(&utxo_info(&raw)?);
+            Ok(())
+        })
+}
+
+pub fn send_signed_utxo<'help, Co, Ex, Ev>() -> Command<'help, Context<Co, Ex, Ev>>
+where
+    Co: ControllerBehaviour,
+{
+    Command::<Context<Co, Ex, Ev>>::new("send-signed-utxo")
+        .about("Send an exported utxo that has been signed by enough admins")
+        .arg(
+            Arg::new("file")
+                .help("the exported utxo file")
+                .required(true)
+                .takes_value(true),
+        )
+        .arg(brake_force_arg())
+        .handler(|_cmd, m, ctx| {
+            let raw = load_raw_tx(m.value_of("file").unwrap())?;
+            // Make sure it's an utxo.
+            utxo_info(&raw)?;
+            if let Some(Tx::UtxoTx(utxo)) = &raw.tx {
+                let brake_on = utxo.transaction.as_ref().is_some_and(is_emergency_brake_on);
+                if brake_on && !m.is_present("force") {
+                    confirm_emergency_brake(ctx)?;
+                }
+            }
+            let tx_hash = ctx.rt.block_on(ctx.controller.send_raw(raw))??;
+            ctx.emit(&tx_hash);
+            Ok(())
+        })
+}
+
+pub fn send_utxo<'help, Co, Ex, Ev>() -> Command<'help, Context<Co, Ex, Ev>>
+where
+    Co: ControllerBehaviour + Send + Sync,
+{
+    Command::<Context<Co, Ex, Ev>>::new("send-utxo")
+        .about("Send an utxo of any type with a raw output, the other admin commands are built on it. Use them if possible")
+        .arg(
+            Arg::new("type")
+                .help("the utxo type, i.e. its lock id. The known ones are 1002(admin), 1003(block interval), 1004(validators) and 1005(emergency brake)")
+                .long("type")
+                .required(true)
+                .takes_value(true)
+                .validator(str::parse::<u64>),
+        )
+        .arg(
+            Arg::new("output")
+                .help("the raw utxo output, hex or `@<path>` to read hex from file, `-` for stdin")
+                .long("output")
+                .required(true)
+                .takes_value(true)
+                .validator(validate_data_input),
+        )
+        .arg(
+            Arg::new("pre-tx-hash")
+                .help("the hash of the previous utxo of this type, required for unknown types [default: the one in the system config]")
+                .long("pre-tx-hash")
+                .takes_value(true)
+                .validator(parse_hash),
+        )
+        .arg(
+            Arg::new("allow-unknown-type")
+                .help("Allow the types unknown to cldi, e.g. added by a newer controller")
+                .long("allow-unknown-type"),
+        )
+        .arg(export_arg())
+        .arg(brake_force_arg())
+        .handler(|_cmd, m, ctx| {
+            let lock_id = m.value_of("type").unwrap().parse::<u64>()?;
+            let utxo_type = UtxoType::from_lock_id(lock_id);
+            ensure!(
+                utxo_type.is_some() || m.is_present("allow-unknown-type"),
+                "unknown utxo type `{lock_id}`, pass `--allow-unknown-type` if the controller supports it"
+            );
+            let output = parse_data_input(m.value_of("output").unwrap())?;
+            let pre_tx_hash = m.value_of("pre-tx-hash").map(parse_hash).transpose()?;
+
+            let utxo = ctx.rt.block_on(async {
+                match (utxo_type, pre_tx_hash) {
+                    (Some(utxo_type), None) => ctx.controller.build_utxo(output, utxo_type).await,
+                    (_, Some(pre_tx_hash)) => {
+                        let system_config = ctx
+                            .controller
+                            .get_system_config()
+                            .await
+                            .context("failed to get system config")?;
+                        Ok(UtxoTransaction {
+                            version: system_config.version,
+                            pre_tx_hash: pre_tx_hash.to_vec(),
+                            output,
+                            lock_id,
+                        })
+                    }
+                    (None, None) => {
+                        bail!("`--pre-tx-hash` is required for the unknown utxo type `{lock_id}`")
+                    }
+                }
+            })??;
+            if let Some(path) = m.value_of("export") {
+                return export_built_utxo(ctx, path, utxo);
+            }
+            if is_emergency_brake_on(&utxo) && !m.is_present("force") {
+                confirm_emergency_brake(ctx)?;
+            }
+            let signer = ctx.rt.block_on(ctx.signer())??;
+            let tx_hash = ctx
+                .rt
+                .block_on(ctx.controller.send_raw_utxo(signer, utxo))??;
+            ctx.emit(&tx_hash);
             Ok(())
         })
 }
 
 pub fn admin_cmd<'help, Co, Ex, Ev>() -> Command<'help, Context<Co, Ex, Ev>>
 where
-    Co: AdminBehaviour,
+    Co: ControllerBehaviour + Send + Sync,
 {
     Command::new("admin")
         .about("The admin commands for managing chain")
@@ -135,16 +507,23 @@ where
         .subcommands([
             update_admin(),
             update_validators(),
+            add_validator(),
+            remove_validator(),
             set_block_interval(),
             emergency_brake(),
+            sign_utxo(),
+            send_signed_utxo(),
+            send_utxo(),
         ])
 }
 
 #[cfg(test)]
 mod tests {
+    use super::*;
     use crate::cmd::cldi_cmd;
-    use crate::core::mock::context;
+    use crate::core::mock::{context, recording_controller};
     use crate::crypto::Hash;
+    use crate::proto::blockchain::raw_transaction::Tx;
     use crate::proto::controller::SystemConfig;
 
     #[test]
@@ -160,7 +539,10 @@ mod tests {
             .returning(|_utxo| Ok(Hash::default()));
 
         cldi_cmd
-            .exec_from(["cldi", "admin", "emergency-brake", "on"], &mut ctx)
+            .exec_from(
+                ["cldi", "admin", "emergency-brake", "on", "--force"],
+                &mut ctx,
+            )
             .unwrap();
 
         cldi_cmd
@@ -196,4 +578,183 @@ mod tests {
             )
             .unwrap();
     }
+
+    #[test]
+    fn test_validator_subcmds() {
+        let cldi_cmd = cldi_cmd();
+        let (mut ctx, _temp_dir) = context();
+        ctx.controller.expect_get_system_config().returning(|| {
+            Ok(SystemConfig {
+                validators: vec![vec![1; 20], vec![2; 20]],
+                ..Default::default()
+            })
+        });
+        ctx.controller
+            .expect_send_raw()
+            .times(3)
+            .returning(|_utxo| Ok(Hash::default()));
+
+        let addr = |b: u8| format!("0x{}", hex::encode([b; 20]));
+        let exec = |args: &[&str], ctx: &mut _| {
+            let args = ["cldi", "admin"].iter().chain(args);
+            cldi_cmd.exec_from(args, ctx)
+        };
+        exec(
+            &["set-validators", &format!("{},{}", addr(1), addr(3))],
+            &mut ctx,
+        )
+        .unwrap();
+        exec(&["add-validator", &addr(3)], &mut ctx).unwrap();
+        exec(&["remove-validator", &addr(2)], &mut ctx).unwrap();
+
+        let err = exec(
+            &["set-validators", &format!("{},{}", addr(1), addr(1))],
+            &mut ctx,
+        )
+        .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            format!("duplicate validator `{}`", addr(1))
+        );
+        assert!(exec(&["set-validators", "0x1234"], &mut ctx).is_err());
+        assert!(exec(&["add-validator", &addr(1)], &mut ctx).is_err());
+        assert!(exec(&["remove-validator", &addr(3)], &mut ctx).is_err());
+    }
+
+    #[test]
+    fn test_block_interval_check() {
+        assert_eq!(parse_block_interval("3").unwrap(), 3);
+        assert!(parse_block_interval("0").is_err());
+        assert!(parse_block_interval("3601").is_err());
+        assert!(parse_block_interval("-1").is_err());
+
+        assert_eq!(block_interval_warning(3, 6), None);
+        assert_eq!(block_interval_warning(3, 1), None);
+        assert!(block_interval_warning(3, 30).is_some());
+        assert!(block_interval_warning(30, 3).is_some());
+        // The current interval is unknown.
+        assert_eq!(block_interval_warning(0, 3600), None);
+    }
+
+    #[test]
+    fn test_is_emergency_brake_on() {
+        let brake = |output| UtxoTransaction {
+            lock_id: UtxoType::EmergencyBrake as u64,
+            output,
+            ..Default::default()
+        };
+        assert!(is_emergency_brake_on(&brake(emergency_brake_output(true))));
+        assert!(!is_emergency_brake_on(&brake(emergency_brake_output(
+            false
+        ))));
+        let block_interval = UtxoTransaction {
+            lock_id: UtxoType::BlockInterval as u64,
+            ..brake(vec![0])
+        };
+        assert!(!is_emergency_brake_on(&block_interval));
+    }
+
+    #[test]
+    fn test_send_utxo() {
+        let cldi_cmd = cldi_cmd();
+        let (mut ctx, _temp_dir) = context();
+        let (controller, sent) = recording_controller(SystemConfig {
+            block_interval_pre_hash: vec![3; 32],
+            ..Default::default()
+        });
+        ctx.controller = controller;
+
+        let exec = |args: &[&str], ctx: &mut _| {
+            let args = ["cldi", "admin", "send-utxo"].iter().chain(args);
+            cldi_cmd.exec_from(args, ctx)
+        };
+        let last_utxo = || match sent.lock().unwrap().last().unwrap().tx.clone() {
+            Some(Tx::UtxoTx(utxo)) => utxo.transaction.unwrap(),
+            _ => panic!("not an utxo"),
+        };
+
+        exec(&["--type", "1003", "--output", "0x00000006"], &mut ctx).unwrap();
+        let utxo = last_utxo();
+        assert_eq!(utxo.lock_id, 1003);
+        assert_eq!(utxo.output, [0, 0, 0, 6]);
+        assert_eq!(utxo.pre_tx_hash, [3; 32]);
+
+        let unknown = ["--type", "2000", "--output", "0x01"];
+        let err = exec(&unknown, &mut ctx).unwrap_err();
+        assert!(err.to_string().contains("--allow-unknown-type"));
+        let allowed = [&unknown[..], &["--allow-unknown-type"]].concat();
+        assert!(exec(&allowed, &mut ctx).is_err());
+
+        let pre_tx_hash = hex(&[7; 32]);
+        exec(
+            &[&allowed[..], &["--pre-tx-hash", &pre_tx_hash]].concat(),
+            &mut ctx,
+        )
+        .unwrap();
+        let utxo = last_utxo();
+        assert_eq!(utxo.lock_id, 2000);
+        assert_eq!(utxo.pre_tx_hash, [7; 32]);
+        assert_eq!(sent.lock().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_multisig_utxo() {
+        let cldi_cmd = cldi_cmd();
+
+        let (mut ctx, temp_dir) = context();
+        ctx.controller
+            .expect_get_system_config()
+            .returning(|| Ok(SystemConfig::default()));
+        ctx.controller
+            .expect_send_raw()
+            .times(1)
+            .returning(|_utxo| Ok(Hash::default()));
+
+        let utxo_file = temp_dir.path().join("brake.utxo");
+        let utxo_file = utxo_file.to_str().unwrap();
+        cldi_cmd
+            .exec_from(
+                [
+                    "cldi",
+                    "admin",
+                    "emergency-brake",
+                    "on",
+                    "--export",
+                    utxo_file,
+                ],
+                &mut ctx,
+            )
+            .unwrap();
+        // Signed by the same account twice.
+        assert!(cldi_cmd
+            .exec_from(["cldi", "admin", "sign-utxo", utxo_file], &mut ctx)
+            .is_err());
+
+        cldi_cmd
+            .exec_from(
+                [
+                    "cldi", "account", "generate", "--name", "admin2", "--crypto", "SM",
+                ],
+                &mut ctx,
+            )
+            .unwrap();
+        ctx.current_setting.account_name = "admin2".into();
+        cldi_cmd
+            .exec_from(["cldi", "admin", "sign-utxo", utxo_file], &mut ctx)
+            .unwrap();
+
+        let raw = crate::utils::load_raw_tx(utxo_file).unwrap();
+        match raw.tx {
+            Some(Tx::UtxoTx(utxo)) => assert_eq!(utxo.witnesses.len(), 2),
+            _ => panic!("not an utxo"),
+        }
+
+        // It turns on the emergency brake, `-y` skips the confirmation.
+        cldi_cmd
+            .exec_from(
+                ["cldi", "admin", "send-signed-utxo", utxo_file, "-y"],
+                &mut ctx,
+            )
+            .unwrap();
+    }
 }
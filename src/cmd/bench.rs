@@ -12,36 +12,54 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use std::{future::Future, sync::Arc, time::Duration};
+use std::{
+    future::Future,
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
 use anyhow::{bail, Context as _, Result};
-use clap::Arg;
+use clap::{Arg, ArgMatches};
 use crossbeam::atomic::AtomicCell;
 use parking_lot::Mutex;
 use rand::{thread_rng, Rng};
 use rayon::prelude::*;
+use serde_json::{json, Value as Json};
 
 use crate::{
-    cmd::{watch, Command},
+    cmd::{evm::nonce_strategy_arg, watch, Command},
     core::executor::ExecutorBehaviour,
     core::{
         client::GrpcClientBehaviour,
         context::Context,
         controller::{ControllerBehaviour, SignerBehaviour},
+        error::{is_dry_run, SdkResult},
+        evm::EvmBehaviour,
+    },
+    display::{Display, DisplayOptions},
+    log::{info, Verbosity},
+    proto::blockchain::{RawTransaction, Transaction},
+    utils::{
+        get_block_height_at, parse_data, parse_nonce_strategy, parse_position, parse_value,
+        validate_addr, NonceStrategy,
     },
-    proto::blockchain::Transaction,
-    utils::{get_block_height_at, parse_addr, parse_data, parse_position, parse_value},
 };
 
 pub fn bench_cmd<'help, Co, Ex, Ev>() -> Command<'help, Context<Co, Ex, Ev>>
 where
     Co: ControllerBehaviour + GrpcClientBehaviour + Clone + Send + Sync + 'static,
     Ex: ExecutorBehaviour + GrpcClientBehaviour + Clone + Send + Sync + 'static,
+    Ev: EvmBehaviour,
 {
     Command::<Context<Co, Ex, Ev>>::new("bench")
         .about("Simple benchmarks")
         .subcommand_required_else_help(true)
-        .subcommands([bench_send().name("send"), bench_call().name("call")])
+        .subcommands([
+            bench_send().name("send"),
+            bench_send_tx().name("send-tx"),
+            bench_call().name("call"),
+            bench_rpc().name("rpc"),
+        ])
 }
 
 fn bench_basic<'help, Co, Ex, Ev>() -> Command<'help, Context<Co, Ex, Ev>> {
@@ -86,6 +104,7 @@ fn bench_basic<'help, Co, Ex, Ev>() -> Command<'help, Context<Co, Ex, Ev>> {
 pub fn bench_send<'help, Co, Ex, Ev>() -> Command<'help, Context<Co, Ex, Ev>>
 where
     Co: ControllerBehaviour + GrpcClientBehaviour + Clone + Send + Sync + 'static,
+    Ev: EvmBehaviour,
 {
     bench_basic::<Co, Ex, Ev>()
         .name("bench-send")
@@ -96,7 +115,7 @@ where
                 .short('t')
                 .long("to")
                 .takes_value(true)
-                .validator(parse_addr),
+                .validator(validate_addr),
         )
         .arg(
             Arg::new("data")
@@ -108,7 +127,7 @@ where
         )
         .arg(
             Arg::new("value")
-                .help("the value of this tx")
+                .help("the value of this tx, e.g. `1.5ether`, `200gwei`, `100`(wei) or `0x64`")
                 .short('v')
                 .long("value")
                 .takes_value(true)
@@ -132,6 +151,8 @@ where
                 .default_value("+95")
                 .validator(parse_position),
         )
+        .arg(nonce_strategy_arg())
+        .arg(presign_threads_arg())
         .arg(
             Arg::new("disable-watch")
                 .help("don't watch blocks")
@@ -146,6 +167,7 @@ where
                 .map(|s| s.parse::<u64>().unwrap())
                 .unwrap_or(total);
 
+            let presign_threads = presign_threads_of(m);
             let watch_blocks = !m.is_present("disable-watch");
             let watch_begin = Arc::new(AtomicCell::new(Option::<u64>::None));
 
@@ -154,7 +176,7 @@ where
                 let mut rng = thread_rng();
 
                 let to = match m.value_of("to") {
-                    Some(to) => parse_addr(to).unwrap(),
+                    Some(to) => ctx.parse_addr(to)?,
                     None => rng.gen(),
                 }.to_vec();
                 let data = match m.value_of("data") {
@@ -171,18 +193,22 @@ where
                 let system_config = ctx.controller.get_system_config().await
                     .context("failed to fetch chain status")?;
 
-                let signer = ctx.current_account()?;
-                let workload_builder = || {
-                    let nonce = {
-                        // Nonce must be different to avoid dup tx,
-                        // and workload builder may be passed to other threads.
-                        let mut rng = thread_rng();
-                        rng.gen::<u64>().to_string()
+                let signer = ctx.signer().await?;
+                let nonce_base = reserve_bench_nonces(ctx, m, total).await?;
+                info!(ctx.verbosity, "Signing transactions..");
+                let (raw_txs, signing) = presign(total, presign_threads, |i| {
+                    let nonce = match nonce_base {
+                        Some(base) => (base + i).to_string(),
+                        None => {
+                            // Nonce must be different to avoid dup tx.
+                            let mut rng = thread_rng();
+                            rng.gen::<u64>().to_string()
+                        }
                     };
                     let raw_tx = Transaction {
-                        to,
-                        data,
-                        value,
+                        to: to.clone(),
+                        data: data.clone(),
+                        value: value.clone(),
                         nonce,
                         quota,
                         valid_until_block,
@@ -190,25 +216,36 @@ where
                         version: system_config.version,
                     };
                     signer.sign_raw_tx(raw_tx)
+                })?;
+                info!(ctx.verbosity, "{}", signing_summary(total, signing));
+                // The txs are signed, the builder only hands them out.
+                let raw_txs = Mutex::new(raw_txs.into_iter());
+                let raw_txs = &raw_txs;
+                let workload_builder = || {
+                    raw_txs
+                        .lock()
+                        .next()
+                        .expect("there should be a signed tx for each workload")
                 };
 
                 // Connection builder
                 let controller_addr = ctx.current_controller_addr();
+                let client_setting = &ctx.client_setting();
                 let connector = || async {
                     if timeout > 0 {
-                        Co::connect_timeout(controller_addr, Duration::from_secs(timeout)).await
+                        Co::connect_timeout(controller_addr, client_setting, Duration::from_secs(timeout)).await
                     } else {
-                        Co::connect(controller_addr).await
+                        Co::connect(controller_addr, client_setting).await
                     }
                 };
 
                 // Work
                 let worker_fn =
-                    |client: Co, raw| async move { client.send_raw(raw).await.map(|_| ()) };
+                    |client: Co, raw| async move { client.send_raw(raw).await?; anyhow::Ok(()) };
 
                 // before fns
                 let before_preparing = || async {
-                    println!("Preparing connections and transactions..");
+                    info!(ctx.verbosity, "Preparing connections..");
                     anyhow::Ok(())
                 };
 
@@ -221,7 +258,7 @@ where
                         watch_begin.store(Some(current_block_height));
                     }
 
-                    println!("Sending transactions..");
+                    info!(ctx.verbosity, "Sending transactions..");
                     anyhow::Ok(())
                 };
 
@@ -229,6 +266,7 @@ where
                     total,
                     connections,
                     workers,
+                    ctx.verbosity,
                     connector,
                     workload_builder,
                     worker_fn,
@@ -258,6 +296,538 @@ where
         })
 }
 
+fn presign_threads_arg<'help>() -> Arg<'help> {
+    Arg::new("presign-threads")
+        .help(
+            "Number of threads to sign the txs before sending them [default: number of CPU cores]",
+        )
+        .long("presign-threads")
+        .takes_value(true)
+        .validator(|s| match s.parse::<usize>() {
+            Ok(0) => Err("presign threads must be at least 1".to_string()),
+            Ok(_) => Ok(()),
+            Err(e) => Err(e.to_string()),
+        })
+}
+
+fn presign_threads_of(m: &ArgMatches) -> Option<usize> {
+    m.value_of("presign-threads").map(|s| s.parse().unwrap())
+}
+
+/// Sign the `total` txs built by `sign(i)` on a dedicated thread pool.
+///
+/// Signing is CPU-bound, so it's done before sending and off the runtime threads,
+/// otherwise it would eat into the sending throughput. Returns the txs and how long it took.
+fn presign<F>(
+    total: u64,
+    threads: Option<usize>,
+    sign: F,
+) -> Result<(Vec<RawTransaction>, Duration)>
+where
+    F: Fn(u64) -> RawTransaction + Send + Sync,
+{
+    // Zero means the rayon default, i.e. the number of CPU cores.
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(threads.unwrap_or(0))
+        .build()
+        .context("failed to build the thread pool for signing")?;
+    let start = Instant::now();
+    let raw_txs = pool.install(|| (0..total).into_par_iter().map(sign).collect());
+    Ok((raw_txs, start.elapsed()))
+}
+
+fn signing_summary(total: u64, elapsed: Duration) -> String {
+    let rate = total as f64 / elapsed.as_secs_f64().max(f64::EPSILON);
+    format!(
+        "`{total}` txs signed in `{}` ms, `{rate:.1}` txs/s",
+        elapsed.as_millis()
+    )
+}
+
+/// The first of `total` sequential nonces given by `--nonce-strategy`, `None` if it's absent.
+async fn reserve_bench_nonces<Co, Ex, Ev>(
+    ctx: &Context<Co, Ex, Ev>,
+    m: &ArgMatches,
+    total: u64,
+) -> Result<Option<u64>>
+where
+    Ev: EvmBehaviour,
+{
+    match m.value_of("nonce-strategy").map(parse_nonce_strategy).transpose()? {
+        Some(NonceStrategy::LocalIncrement) => Ok(Some(ctx.reserve_nonces(total).await?)),
+        Some(NonceStrategy::Fetch) => bail!(
+            "`--nonce-strategy fetch` doesn't work with bench since txs are signed before sending, use `local-increment` instead"
+        ),
+        None => Ok(None),
+    }
+}
+
+pub fn bench_send_tx<'help, Co, Ex, Ev>() -> Command<'help, Context<Co, Ex, Ev>>
+where
+    Co: ControllerBehaviour + GrpcClientBehaviour + Clone + Send + Sync + 'static,
+    Ev: EvmBehaviour,
+{
+    Command::<Context<Co, Ex, Ev>>::new("bench-send-tx")
+        .about("Send transactions at {--tps} for {--duration} seconds over {--connections} connections")
+        .arg(
+            Arg::new("tps")
+                .help("The target TPS")
+                .long("tps")
+                .takes_value(true)
+                .required(true)
+                .validator(|s| match s.parse::<u64>() {
+                    Ok(0) => Err("tps must be greater than 0".to_string()),
+                    Ok(_) => Ok(()),
+                    Err(e) => Err(e.to_string()),
+                }),
+        )
+        .arg(
+            Arg::new("duration")
+                .help("How long to send transactions (in seconds)")
+                .long("duration")
+                .takes_value(true)
+                .default_value("10")
+                .validator(str::parse::<u64>),
+        )
+        .arg(
+            Arg::new("connections")
+                .help("Number of connections connects to server")
+                .long("connections")
+                .takes_value(true)
+                .default_value("1")
+                .validator(|s| match s.parse::<u64>() {
+                    Ok(0) => Err("connections must be greater than 0".to_string()),
+                    Ok(_) => Ok(()),
+                    Err(e) => Err(e.to_string()),
+                }),
+        )
+        .arg(
+            Arg::new("to")
+                .help("the target address of this tx. Default to random")
+                .short('t')
+                .long("to")
+                .takes_value(true)
+                .validator(validate_addr),
+        )
+        .arg(
+            Arg::new("data")
+                .help("the data of this tx. Default to random 32 bytes")
+                .short('d')
+                .long("data")
+                .takes_value(true)
+                .validator(parse_data),
+        )
+        .arg(
+            Arg::new("quota")
+                .help("the quota of this tx")
+                .short('q')
+                .long("quota")
+                .takes_value(true)
+                .default_value("3000000")
+                .validator(str::parse::<u64>),
+        )
+        .arg(
+            Arg::new("valid-until-block")
+                .help("this tx is valid until the given block height. `+h` means `<current-height> + h`")
+                .long("until")
+                .takes_value(true)
+                .default_value("+95")
+                .validator(parse_position),
+        )
+        .arg(nonce_strategy_arg())
+        .arg(presign_threads_arg())
+        .handler(|_cmd, m, ctx| {
+            let tps = m.value_of("tps").unwrap().parse::<u64>().unwrap();
+            let presign_threads = presign_threads_of(m);
+            let duration = m.value_of("duration").unwrap().parse::<u64>().unwrap();
+            let connections = m.value_of("connections").unwrap().parse::<u64>().unwrap();
+            let total = tps * duration;
+
+            let mut report = ctx.rt.block_on(async {
+                let mut rng = thread_rng();
+                let to = match m.value_of("to") {
+                    Some(to) => ctx.parse_addr(to)?,
+                    None => rng.gen(),
+                }
+                .to_vec();
+                let data = match m.value_of("data") {
+                    Some(data) => parse_data(data).unwrap(),
+                    None => rng.gen::<[u8; 32]>().to_vec(),
+                };
+                let quota = m.value_of("quota").unwrap().parse::<u64>()?;
+                let valid_until_block = {
+                    let pos = parse_position(m.value_of("valid-until-block").unwrap())?;
+                    get_block_height_at(&ctx.controller, pos).await?
+                };
+                let system_config = ctx
+                    .controller
+                    .get_system_config()
+                    .await
+                    .context("failed to fetch chain status")?;
+
+                info!(ctx.verbosity, "Preparing connections and transactions..");
+                let mut conns = Vec::with_capacity(connections as usize);
+                for _ in 0..connections {
+                    let conn = Co::connect(ctx.current_controller_addr(), &ctx.client_setting())
+                        .await
+                        .context("preparing connections failed")?;
+                    conns.push(conn);
+                }
+
+                // Sequential nonces from a random base, so that they are distinct within this run
+                // and unlikely to collide with other runs, unless `--nonce-strategy` is given.
+                let signer = ctx.signer().await?;
+                let nonce_base = match reserve_bench_nonces(ctx, m, total).await? {
+                    Some(base) => base,
+                    None => rng.gen::<u64>() >> 1,
+                };
+                let (raw_txs, signing) = presign(total, presign_threads, |i| {
+                    let tx = Transaction {
+                        to: to.clone(),
+                        data: data.clone(),
+                        value: vec![0; 32],
+                        nonce: (nonce_base + i).to_string(),
+                        quota,
+                        valid_until_block,
+                        chain_id: system_config.chain_id.clone(),
+                        version: system_config.version,
+                    };
+                    signer.sign_raw_tx(tx)
+                })?;
+
+                info!(ctx.verbosity, "Sending transactions..");
+                let mut bucket = TokenBucket::new(tps);
+                let start = std::time::Instant::now();
+                let mut handles = Vec::with_capacity(total as usize);
+                for (raw, conn) in raw_txs.into_iter().zip(conns.iter().cycle()) {
+                    bucket.acquire().await;
+                    let conn = conn.clone();
+                    handles.push(tokio::spawn(async move {
+                        let t = std::time::Instant::now();
+                        let res = conn.send_raw(raw).await;
+                        (t.elapsed(), res)
+                    }));
+                }
+
+                let mut report = BenchReport {
+                    signing: Some((total, signing)),
+                    ..Default::default()
+                };
+                for h in handles {
+                    match h.await {
+                        Ok((latency, Ok(_))) => report.latencies.push(latency),
+                        Ok((_, Err(e))) => report.record_error(e.into()),
+                        Err(e) => report.record_error(e.into()),
+                    }
+                }
+                report.elapsed = start.elapsed();
+                report.check_dry_run()?;
+
+                anyhow::Ok(report)
+            })??;
+
+            ctx.emit(&report.summary(tps));
+            Ok(())
+        })
+}
+
+/// Pacing requests at `rate` per second. It allows a small burst for catching up
+/// with the rate after a delay.
+struct TokenBucket {
+    rate: f64,
+    burst: f64,
+    tokens: f64,
+    last: std::time::Instant,
+}
+
+impl TokenBucket {
+    fn new(rate: u64) -> Self {
+        let rate = rate as f64;
+        Self {
+            rate,
+            // 50ms worth of tokens.
+            burst: (rate / 20.0).max(1.0),
+            tokens: 1.0,
+            last: std::time::Instant::now(),
+        }
+    }
+
+    async fn acquire(&mut self) {
+        loop {
+            let now = std::time::Instant::now();
+            let refilled = now.duration_since(self.last).as_secs_f64() * self.rate;
+            self.tokens = (self.tokens + refilled).min(self.burst);
+            self.last = now;
+
+            if self.tokens >= 1.0 {
+                self.tokens -= 1.0;
+                return;
+            }
+            let wait = (1.0 - self.tokens) / self.rate;
+            tokio::time::sleep(Duration::from_secs_f64(wait)).await;
+        }
+    }
+}
+
+#[derive(Default)]
+struct BenchReport {
+    latencies: Vec<Duration>,
+    errors: u64,
+    first_error: Option<anyhow::Error>,
+    elapsed: Duration,
+    /// How many txs were signed before sending and how long it took.
+    signing: Option<(u64, Duration)>,
+}
+
+impl BenchReport {
+    fn record_error(&mut self, e: anyhow::Error) {
+        self.errors += 1;
+        self.first_error.get_or_insert(e);
+    }
+
+    /// A dry run fails each request the same way, so its request is the output instead of a report.
+    fn check_dry_run(&mut self) -> Result<()> {
+        match self.first_error.take() {
+            Some(e) if is_dry_run(&e) => Err(e),
+            first_error => {
+                self.first_error = first_error;
+                Ok(())
+            }
+        }
+    }
+
+    fn summary(&mut self, target_tps: u64) -> BenchSummary {
+        self.latencies.sort_unstable();
+        let success = self.latencies.len();
+        let achieved_tps = success as f64 / self.elapsed.as_secs_f64().max(f64::EPSILON);
+        let sent = success as u64 + self.errors;
+        let mut json = json!({
+            "sent": sent,
+            "success": success,
+            "failure": self.errors,
+            "elapsed_ms": self.elapsed.as_millis() as u64,
+            "tps": achieved_tps,
+            "target_tps": target_tps,
+        });
+        let mut lines = vec![];
+        if let Some((signed, signing)) = self.signing {
+            json["signed"] = json!(signed);
+            json["signing_ms"] = json!(signing.as_millis() as u64);
+            lines.push(signing_summary(signed, signing));
+        }
+        lines.extend([
+            format!(
+                "`{}` txs sent in `{}` ms, achieved TPS `{:.1}` (target `{}`)",
+                sent,
+                self.elapsed.as_millis(),
+                achieved_tps,
+                target_tps,
+            ),
+            format!("`{}` success, `{}` failure", success, self.errors),
+        ]);
+        lines.extend(self.latency_and_error("send_raw", &mut json));
+        BenchSummary {
+            json,
+            text: lines.join("\n"),
+        }
+    }
+
+    fn rpc_summary(&mut self, method: &str) -> BenchSummary {
+        self.latencies.sort_unstable();
+        let success = self.latencies.len();
+        let sent = success as u64 + self.errors;
+        let qps = success as f64 / self.elapsed.as_secs_f64().max(f64::EPSILON);
+        let mut json = json!({
+            "method": method,
+            "sent": sent,
+            "success": success,
+            "failure": self.errors,
+            "elapsed_ms": self.elapsed.as_millis() as u64,
+            "qps": qps,
+        });
+        let mut lines = vec![
+            format!(
+                "`{}` `{method}` requests in `{}` ms, achieved QPS `{qps:.1}`",
+                sent,
+                self.elapsed.as_millis(),
+            ),
+            format!("`{}` success, `{}` failure", success, self.errors),
+        ];
+        lines.extend(self.latency_and_error(method, &mut json));
+        BenchSummary {
+            json,
+            text: lines.join("\n"),
+        }
+    }
+
+    /// The lines of the latencies and the first error, which are also added to `json`.
+    ///
+    /// The latencies must be sorted.
+    fn latency_and_error(&self, method: &str, json: &mut Json) -> Vec<String> {
+        let mut lines = vec![];
+        if !self.latencies.is_empty() {
+            let [p50, p95, p99] = [50, 95, 99].map(|p| percentile(&self.latencies, p).as_millis());
+            json["p50_ms"] = json!(p50 as u64);
+            json["p95_ms"] = json!(p95 as u64);
+            json["p99_ms"] = json!(p99 as u64);
+            lines.push(format!(
+                "{method} latency: p50 `{p50}` ms, p95 `{p95}` ms, p99 `{p99}` ms",
+            ));
+        }
+        if let Some(e) = &self.first_error {
+            json["first_error"] = json!(format!("{e:#}"));
+            lines.push(format!("the first reported error is `{:?}`", e));
+        }
+        lines
+    }
+}
+
+/// The summary of a bench, the lines of text or a JSON object for `-o json`.
+struct BenchSummary {
+    json: Json,
+    text: String,
+}
+
+impl Display for BenchSummary {
+    fn to_json(&self, _opts: DisplayOptions) -> Json {
+        self.json.clone()
+    }
+
+    fn display(&self, _opts: DisplayOptions) -> String {
+        self.text.clone()
+    }
+}
+
+/// Nearest-rank percentile of the sorted samples, which must be non-empty.
+fn percentile(sorted: &[Duration], p: usize) -> Duration {
+    let rank = (sorted.len() * p).div_ceil(100).max(1);
+    sorted[rank - 1]
+}
+
+/// The read-only controller methods `bench rpc` can call.
+#[derive(Debug, Clone, Copy)]
+enum RpcMethod {
+    BlockNumber,
+    BlockByNumber(u64),
+    PeerCount,
+}
+
+impl RpcMethod {
+    async fn call<Co: ControllerBehaviour>(self, controller: &Co) -> SdkResult<()> {
+        match self {
+            Self::BlockNumber => controller.get_block_number(false).await.map(drop),
+            Self::BlockByNumber(h) => controller.get_block_by_number(h).await.map(drop),
+            Self::PeerCount => controller.get_peer_count().await.map(drop),
+        }
+    }
+}
+
+pub fn bench_rpc<'help, Co, Ex, Ev>() -> Command<'help, Context<Co, Ex, Ev>>
+where
+    Co: ControllerBehaviour + Clone + Send + Sync + 'static,
+{
+    Command::<Context<Co, Ex, Ev>>::new("bench-rpc")
+        .about("Call a read-only controller method with {--concurrency} workers for {--duration} seconds")
+        .arg(
+            Arg::new("method")
+                .help("The method to call")
+                .long("method")
+                .takes_value(true)
+                .possible_values(["get-block-number", "get-block-by-number", "get-peer-count"])
+                .default_value("get-block-number"),
+        )
+        .arg(
+            Arg::new("height")
+                .help("The block height for `get-block-by-number` [default: the current height]")
+                .long("height")
+                .takes_value(true)
+                .validator(str::parse::<u64>),
+        )
+        .arg(
+            Arg::new("concurrency")
+                .help("Number of workers, each sends the next request after the previous one returns. \
+                    They share the connection pool of the context, see `--pool-size`")
+                .short('c')
+                .long("concurrency")
+                .takes_value(true)
+                .default_value("16")
+                .validator(|s| match s.parse::<u64>() {
+                    Ok(0) => Err("concurrency must be greater than 0".to_string()),
+                    Ok(_) => Ok(()),
+                    Err(e) => Err(e.to_string()),
+                }),
+        )
+        .arg(
+            Arg::new("duration")
+                .help("How long to send requests (in seconds)")
+                .long("duration")
+                .takes_value(true)
+                .default_value("10")
+                .validator(str::parse::<u64>),
+        )
+        .handler(|_cmd, m, ctx| {
+            let method_name = m.value_of("method").unwrap();
+            let concurrency = m.value_of("concurrency").unwrap().parse::<u64>().unwrap();
+            let duration = m.value_of("duration").unwrap().parse::<u64>().unwrap();
+
+            let mut report = ctx.rt.block_on(async {
+                let method = match method_name {
+                    "get-block-number" => RpcMethod::BlockNumber,
+                    "get-block-by-number" => {
+                        let height = match m.value_of("height") {
+                            Some(h) => h.parse::<u64>()?,
+                            None => ctx.controller.get_block_number(false).await?,
+                        };
+                        RpcMethod::BlockByNumber(height)
+                    }
+                    "get-peer-count" => RpcMethod::PeerCount,
+                    _ => unreachable!("unknown method"),
+                };
+
+                info!(ctx.verbosity, "Sending `{method_name}` requests..");
+                let start = std::time::Instant::now();
+                let deadline = start + Duration::from_secs(duration);
+                let workers = (0..concurrency)
+                    .map(|_| {
+                        let controller = ctx.controller.clone();
+                        tokio::spawn(async move {
+                            let mut report = BenchReport::default();
+                            while std::time::Instant::now() < deadline {
+                                let t = std::time::Instant::now();
+                                match method.call(&controller).await {
+                                    Ok(()) => report.latencies.push(t.elapsed()),
+                                    Err(e) => report.record_error(e.into()),
+                                }
+                            }
+                            report
+                        })
+                    })
+                    .collect::<Vec<_>>();
+
+                let mut report = BenchReport::default();
+                for w in workers {
+                    match w.await {
+                        Ok(mut r) => {
+                            report.latencies.append(&mut r.latencies);
+                            report.errors += r.errors;
+                            if let Some(e) = r.first_error {
+                                report.first_error.get_or_insert(e);
+                            }
+                        }
+                        Err(e) => report.record_error(e.into()),
+                    }
+                }
+                report.elapsed = start.elapsed();
+                report.check_dry_run()?;
+
+                anyhow::Ok(report)
+            })??;
+
+            ctx.emit(&report.rpc_summary(method_name));
+            Ok(())
+        })
+}
+
 pub fn bench_call<'help, Co, Ex, Ev>() -> Command<'help, Context<Co, Ex, Ev>>
 where
     Ex: ExecutorBehaviour + GrpcClientBehaviour + Clone + Send + Sync + 'static,
@@ -271,7 +841,7 @@ where
                 .short('f')
                 .long("from")
                 .takes_value(true)
-                .validator(parse_addr),
+                .validator(validate_addr),
         )
         .arg(
             Arg::new("to")
@@ -279,7 +849,7 @@ where
                 .short('t')
                 .long("to")
                 .takes_value(true)
-                .validator(parse_addr),
+                .validator(validate_addr),
         )
         .arg(
             Arg::new("data")
@@ -303,11 +873,11 @@ where
                 let mut rng = thread_rng();
 
                 let from = match m.value_of("from") {
-                    Some(from) => parse_addr(from).unwrap(),
+                    Some(from) => ctx.parse_addr(from)?,
                     None => *ctx.current_account()?.address(),
                 };
                 let to = match m.value_of("to") {
-                    Some(to) => parse_addr(to).unwrap(),
+                    Some(to) => ctx.parse_addr(to)?,
                     None => rng.gen(),
                 };
                 let data = match m.value_of("data") {
@@ -319,26 +889,33 @@ where
 
                 // Connection builder
                 let executor_addr = ctx.current_executor_addr();
+                let client_setting = &ctx.client_setting();
                 let connector = || async {
                     if timeout > 0 {
-                        Ex::connect_timeout(executor_addr, Duration::from_secs(timeout)).await
+                        Ex::connect_timeout(
+                            executor_addr,
+                            client_setting,
+                            Duration::from_secs(timeout),
+                        )
+                        .await
                     } else {
-                        Ex::connect(executor_addr).await
+                        Ex::connect(executor_addr, client_setting).await
                     }
                 };
 
                 // Work
                 let worker_fn = |client: Ex, (from, to, data)| async move {
-                    client.call(from, to, data).await.map(|_| ())
+                    client.call(from, to, data).await?;
+                    anyhow::Ok(())
                 };
 
                 // before fns
                 let before_preparing = || async {
-                    println!("Preparing connections and call requests..");
+                    info!(ctx.verbosity, "Preparing connections and call requests..");
                     anyhow::Ok(())
                 };
                 let before_working = || async {
-                    println!("Sending call requests..");
+                    info!(ctx.verbosity, "Sending call requests..");
                     anyhow::Ok(())
                 };
 
@@ -346,6 +923,7 @@ where
                     total,
                     connections,
                     workers,
+                    ctx.verbosity,
                     connector,
                     workload_builder,
                     worker_fn,
@@ -377,6 +955,7 @@ async fn bench_fn_with_progbar<
     total: u64,
     connections: u64,
     workers: u64,
+    verbosity: Verbosity,
 
     connector: Connector,
     workload_builder: WorkloadBuilder,
@@ -410,6 +989,9 @@ where
                 )
                 .progress_chars("=> "),
         );
+        if verbosity == Verbosity::Quiet {
+            progbar.set_draw_target(indicatif::ProgressDrawTarget::hidden());
+        }
         Arc::new(progbar)
     };
 
@@ -442,6 +1024,14 @@ where
     )
     .await;
 
+    let bench_res = match bench_res {
+        // Its request is the output instead of the counters.
+        Err(e) if is_dry_run(&e) => {
+            progbar.finish_and_clear();
+            return Err(e);
+        }
+        res => res,
+    };
     progbar.finish_at_current_pos();
 
     if let Some(t) = t {
@@ -626,3 +1216,115 @@ where
         .map(Err)
         .unwrap_or(Ok(()))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_percentile() {
+        let samples = (1..=100).map(Duration::from_millis).collect::<Vec<_>>();
+        assert_eq!(percentile(&samples, 50), Duration::from_millis(50));
+        assert_eq!(percentile(&samples, 99), Duration::from_millis(99));
+        assert_eq!(percentile(&samples[..1], 95), Duration::from_millis(1));
+    }
+
+    #[test]
+    fn test_summary() {
+        // The signing rate is of the signed txs, not the sent ones.
+        let mut report = BenchReport {
+            latencies: vec![Duration::from_millis(1); 3],
+            errors: 1,
+            elapsed: Duration::from_secs(1),
+            signing: Some((10, Duration::from_secs(1))),
+            ..Default::default()
+        };
+        let summary = report.summary(10);
+        let text = summary.display(DisplayOptions::default());
+        assert!(text.starts_with("`10` txs signed"), "{text}");
+        assert!(text.contains("`4` txs sent"), "{text}");
+        let json = summary.to_json(DisplayOptions::default());
+        assert_eq!(json["signed"], 10);
+        assert_eq!(json["sent"], 4);
+        assert_eq!(json["p50_ms"], 1);
+    }
+
+    #[test]
+    fn test_presign() {
+        use crate::proto::blockchain::{raw_transaction::Tx, UnverifiedTransaction};
+
+        let (raw_txs, _signing) = presign(10, Some(2), |i| {
+            assert_eq!(rayon::current_num_threads(), 2);
+            RawTransaction {
+                tx: Some(Tx::NormalTx(UnverifiedTransaction {
+                    transaction_hash: vec![i as u8],
+                    ..Default::default()
+                })),
+            }
+        })
+        .unwrap();
+        // In the order of the index.
+        let hashes = raw_txs
+            .iter()
+            .map(|raw| match &raw.tx {
+                Some(Tx::NormalTx(utx)) => utx.transaction_hash[0],
+                _ => unreachable!(),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(hashes, (0..10).collect::<Vec<u8>>());
+    }
+
+    #[test]
+    fn test_bench_rpc() {
+        use crate::cmd::cldi_cmd;
+        use crate::core::mock::{context, MockControllerClient};
+
+        let cldi_cmd = cldi_cmd();
+        let (mut ctx, _temp_dir) = context();
+        ctx.controller.expect_clone().times(4).returning(|| {
+            let mut c = MockControllerClient::default();
+            // Like a real request, so that the workers don't spin on it for the whole duration.
+            c.expect_get_peer_count().returning(|| {
+                std::thread::sleep(Duration::from_millis(10));
+                Ok(1)
+            });
+            c
+        });
+        let (res, outputs) = ctx.capture(|ctx| {
+            cldi_cmd.exec_from(
+                [
+                    "cldi",
+                    "bench",
+                    "rpc",
+                    "--method",
+                    "get-peer-count",
+                    "-c",
+                    "4",
+                    "--duration",
+                    "1",
+                ],
+                ctx,
+            )
+        });
+        res.unwrap();
+        assert_eq!(outputs[0]["method"], "get-peer-count");
+        assert_eq!(outputs[0]["failure"], 0);
+        assert!(outputs[0]["success"].as_u64().unwrap() > 0);
+    }
+
+    #[test]
+    fn test_token_bucket() {
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        let elapsed = rt.block_on(async {
+            let mut bucket = TokenBucket::new(1000);
+            let t = std::time::Instant::now();
+            for _ in 0..201 {
+                bucket.acquire().await;
+            }
+            t.elapsed()
+        });
+        // The first token is free.
+        assert!(elapsed >= Duration::from_millis(200));
+        assert!(elapsed < Duration::from_millis(1000));
+    }
+}
@@ -12,22 +12,31 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use anyhow::ensure;
 use clap::{crate_authors, crate_version, AppSettings, Arg, ColorChoice};
+use std::path::Path;
 use std::str::FromStr;
+use std::time::Duration;
 use tonic::transport::Endpoint;
 
 use crate::{
-    cmd::{account, admin, bench, context, ethabi, evm, rpc, watch, Command},
+    cmd::{account, admin, bench, context, crypto, ethabi, evm, rpc, tx, watch, Command},
     config::{ContextSetting, CryptoType},
     core::{
-        client::GrpcClientBehaviour, context::Context, controller::ControllerBehaviour,
-        evm::EvmBehaviour, executor::ExecutorBehaviour,
+        client::{GrpcClientBehaviour, RequestPolicy},
+        context::Context,
+        controller::ControllerBehaviour,
+        error::SdkError,
+        evm::EvmBehaviour,
+        executor::ExecutorBehaviour,
     },
+    display::{ColorMode, OutputFormat},
+    log::{warning, Verbosity},
 };
 
 pub fn get_cmd<'help, Co, Ex, Ev>() -> Command<'help, Context<Co, Ex, Ev>>
 where
-    Co: ControllerBehaviour,
+    Co: ControllerBehaviour + Send + Sync,
     Ev: EvmBehaviour,
 {
     Command::new("get")
@@ -50,6 +59,15 @@ where
         ])
 }
 
+/// `--executor-addr` without the global `-e`, for the subcommands that use `-e` for others.
+pub fn executor_addr_arg<'help>() -> Arg<'help> {
+    Arg::new("executor-addr")
+        .help("executor address, it can also be given after the subcommand for a one-off override")
+        .long("executor-addr")
+        .takes_value(true)
+        .validator(|s| Endpoint::from_shared(s.to_string()).map(|_| ()))
+}
+
 pub fn cldi_cmd<'help, Co, Ex, Ev>() -> Command<'help, Context<Co, Ex, Ev>>
 where
     Co: ControllerBehaviour + GrpcClientBehaviour + Clone + Send + Sync + 'static,
@@ -64,37 +82,78 @@ where
         .global_setting(AppSettings::DeriveDisplayOrder)
         .arg(
             Arg::new("context")
-                .help("context setting")
+                .help("context setting, also called profile")
                 .short('c')
                 .long("context")
+                .alias("profile")
                 .takes_value(true),
         )
         .arg(
             Arg::new("controller-addr")
-                .help("controller address")
+                .help("controller address, it can also be given after the subcommand for a one-off override")
                 .short('r')
+                .long("controller-addr")
                 .takes_value(true)
+                // So it can appear on any subcommand, and the last one given wins.
+                .global(true)
                 .validator(|s| Endpoint::from_shared(s.to_string()).map(|_| ())),
         )
         .arg(
-            Arg::new("executor-addr")
-                .help("executor address")
+            executor_addr_arg()
                 .short('e')
+                .global(true),
+        )
+        .arg(
+            Arg::new("ca-cert")
+                .help("PEM encoded CA certificate for `https://` addresses [default: system roots]")
+                .long("ca-cert")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::new("tls-domain")
+                .help("Domain name to verify the TLS certificate against, useful when the address is an IP")
+                .long("tls-domain")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::new("pool-size")
+                .help("Number of connections to each service, concurrent requests are spread over them [default: 1]")
+                .long("pool-size")
                 .takes_value(true)
-                .validator(|s| Endpoint::from_shared(s.to_string()).map(|_| ())),
+                .validator(|s| match s.parse::<usize>() {
+                    Ok(0) => Err("pool size must be at least 1".to_string()),
+                    Ok(_) => Ok(()),
+                    Err(e) => Err(e.to_string()),
+                }),
+        )
+        .arg(
+            Arg::new("keep-alive")
+                .help("Seconds between HTTP/2 keep-alive pings on idle connections, 0 disables it [default: 30]")
+                .long("keep-alive")
+                .takes_value(true)
+                .validator(str::parse::<u64>),
         )
         .arg(
             Arg::new("account-name")
-                .help("account name")
+                .help("account name, it can also be given as `--account` after the subcommand to sign with another account for this command")
                 .short('u')
-                .takes_value(true),
+                .long("account")
+                .takes_value(true)
+                .global(true),
         )
-        .arg(
+        .sensitive_arg(
             Arg::new("password")
                 .help("password to unlock the account")
                 .short('p')
                 .takes_value(true),
         )
+        .arg(
+            Arg::new("password-env")
+                .help("The env var that holds the password to unlock the account when signing, it's prompted for if not given")
+                .long("password-env")
+                .takes_value(true)
+                .conflicts_with("password"),
+        )
         .arg(
             Arg::new("crypto-type")
                 .help("The crypto type of the target chain")
@@ -103,65 +162,331 @@ where
                 .ignore_case(true)
                 .validator(CryptoType::from_str),
         )
+        .arg(
+            Arg::new("output-format")
+                .help("The output format, `json` is more friendly to scripts. `bin` writes the raw protobuf of the response for `get block/tx/receipt`")
+                .short('o')
+                .long("output")
+                .possible_values(["text", "json", "bin"])
+                .ignore_case(true)
+                .validator(OutputFormat::from_str),
+        )
+        .arg(
+            Arg::new("color")
+                .help("When to colorize the text output, `auto` disables it if stdout isn't a terminal or `NO_COLOR` is set [default: auto]")
+                .long("color")
+                .takes_value(true)
+                .possible_values(["auto", "always", "never"])
+                .ignore_case(true)
+                .validator(ColorMode::from_str),
+        )
+        .arg(
+            Arg::new("out")
+                .help("Also write the output of the command to this file, in the same output format. Colors are only written with `--color always`")
+                .long("out")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::new("overwrite")
+                .help("Overwrite the file of `--out` if it exists")
+                .long("overwrite")
+                .requires("out"),
+        )
+        .arg(
+            Arg::new("no-cache")
+                .help("Don't use the local block hash -> height cache")
+                .long("no-cache"),
+        )
+        .arg(
+            Arg::new("fresh")
+                .help("Always fetch the current height for `+h` positions, instead of reusing the one fetched in the last 2 seconds")
+                .long("fresh")
+                .global(true),
+        )
+        .arg(
+            Arg::new("strict-crypto")
+                .help("Error instead of warning if the chain doesn't seem to use the configured crypto type")
+                .long("strict-crypto"),
+        )
+        .arg(
+            Arg::new("worker-threads")
+                .help("Worker threads of the async runtime, it overrides `worker_threads` in the config and only takes effect at startup [default: number of CPU cores]")
+                .long("worker-threads")
+                .takes_value(true)
+                .validator(|s| match s.parse::<usize>() {
+                    Ok(0) => Err("worker threads must be at least 1".to_string()),
+                    Ok(_) => Ok(()),
+                    Err(e) => Err(e.to_string()),
+                }),
+        )
+        .arg(
+            Arg::new("timeout")
+                .help("Timeout in seconds for each gRPC request [default: 30]")
+                .long("timeout")
+                .takes_value(true)
+                .validator(str::parse::<u64>),
+        )
+        .arg(
+            Arg::new("retries")
+                .help("Retry read-only requests this many times on transient failures, sending txs is never retried [default: 0]")
+                .long("retries")
+                .takes_value(true)
+                .validator(str::parse::<u32>),
+        )
+        .arg(
+            Arg::new("retry-backoff")
+                .help("The initial backoff in milliseconds between retries, doubled after each retry [default: 200]")
+                .long("retry-backoff")
+                .takes_value(true)
+                .validator(str::parse::<u64>),
+        )
+        .arg(
+            Arg::new("dry-run")
+                .help("Print the gRPC request instead of sending it. The system config and block number are still fetched to fill in txs. To run a tx without sending it, use `send-tx --simulate`")
+                .long("dry-run"),
+        )
+        .arg(
+            Arg::new("quiet")
+                .help("Only print the results and warnings, e.g. no progress. There is no `-q` since it's `--quota` for some commands")
+                .long("quiet")
+                .global(true)
+                .conflicts_with("verbose"),
+        )
+        .arg(
+            Arg::new("verbose")
+                .help("Also print the gRPC requests and responses to stderr. There is no `-v` since it's `--value` for some commands")
+                .long("verbose")
+                .global(true),
+        )
         .handler(|cmd, m, ctx| {
+            ensure!(
+                !(m.is_present("color")
+                    && m.value_of("output-format")
+                        .is_some_and(|f| f.eq_ignore_ascii_case("bin"))),
+                "`--color` cannot be used with the `bin` output format"
+            );
+            ensure!(
+                !m.is_present("out") || m.subcommand().is_some(),
+                "`--out` needs a command whose output to write, e.g. `--out block.json get block 1`"
+            );
             // If a subcommand is present, context modifiers(e.g. -r) will construct a tmp context for that subcommand.
             // Otherwise modify the current context.
             let mut previous_setting: Option<ContextSetting> = None;
+            let mut previous_context: Option<String> = None;
+            let mut previous_output_format: Option<OutputFormat> = None;
+            let mut previous_color_mode: Option<ColorMode> = None;
+            let mut previous_password_env: Option<Option<String>> = None;
+            let mut previous_cache_enabled: Option<bool> = None;
+            let mut previous_fresh_height: Option<bool> = None;
+            let mut previous_strict_crypto: Option<bool> = None;
+            let mut previous_request_policy: Option<RequestPolicy> = None;
+            let mut previous_verbosity: Option<Verbosity> = None;
             let mut current_setting = ctx.current_setting.clone();
 
             let is_tmp_ctx = m.subcommand().is_some()
                 && (m.is_present("context")
                     || m.is_present("controller-addr")
                     || m.is_present("executor-addr")
+                    || m.is_present("ca-cert")
+                    || m.is_present("tls-domain")
+                    || m.is_present("pool-size")
+                    || m.is_present("keep-alive")
                     || m.is_present("account-name")
                     || m.is_present("password")
-                    || m.is_present("crypto-type"));
+                    || m.is_present("password-env")
+                    || m.is_present("crypto-type")
+                    || m.is_present("output-format")
+                    || m.is_present("color")
+                    || m.is_present("no-cache")
+                    || m.is_present("fresh")
+                    || m.is_present("strict-crypto")
+                    || m.is_present("timeout")
+                    || m.is_present("retries")
+                    || m.is_present("retry-backoff")
+                    || m.is_present("dry-run")
+                    || m.is_present("quiet")
+                    || m.is_present("verbose"));
             if is_tmp_ctx {
                 previous_setting.replace(current_setting.clone());
+                previous_context.replace(ctx.current_context.clone());
+                previous_output_format.replace(ctx.output_format);
+                previous_color_mode.replace(ctx.color_mode);
+                previous_password_env.replace(ctx.password_env.clone());
+                previous_cache_enabled.replace(ctx.block_cache.is_enabled());
+                previous_fresh_height.replace(ctx.fresh_height);
+                previous_strict_crypto.replace(ctx.strict_crypto);
+                previous_request_policy.replace(ctx.request_policy());
+                previous_verbosity.replace(ctx.verbosity);
             }
             // (account_name, password) for restoring previous account lock status if it's in tmp context.
             let mut relock_info: Option<(String, String)> = None;
+            let ret = (|| {
+                if let Some(setting_name) = m.value_of("context") {
+                    current_setting = ctx.get_context_setting(setting_name)?.clone();
+                }
+                if let Some(controller_addr) = m.value_of("controller-addr") {
+                    current_setting.controller_addr = controller_addr.into();
+                }
+                if let Some(executor_addr) = m.value_of("executor-addr") {
+                    current_setting.executor_addr = executor_addr.into();
+                }
+                if let Some(ca_cert) = m.value_of("ca-cert") {
+                    current_setting.tls.ca_cert = Some(ca_cert.into());
+                }
+                if let Some(domain_name) = m.value_of("tls-domain") {
+                    current_setting.tls.domain_name = Some(domain_name.into());
+                }
+                if let Some(pool_size) = m.value_of("pool-size") {
+                    current_setting.pool_size = pool_size.parse().unwrap();
+                }
+                if let Some(interval) = m.value_of("keep-alive") {
+                    current_setting.keep_alive.interval = interval.parse().unwrap();
+                }
+                if let Some(account_name) = m.value_of("account-name") {
+                    // Check if the account exists, before anything is sent.
+                    ctx.wallet.get(account_name)?;
+                    current_setting.account_name = account_name.into();
+                }
+                if let Some(crypto_type) = m.value_of("crypto-type") {
+                    current_setting.crypto_type = crypto_type.parse().unwrap();
+                }
+                // Switch first, so that everything changed below is restored even if it fails.
+                ctx.switch_context(current_setting)?;
+                if let Some(setting_name) = m.value_of("context") {
+                    ctx.current_context = setting_name.into();
+                }
 
-            if let Some(setting_name) = m.value_of("context") {
-                current_setting = ctx.get_context_setting(setting_name)?.clone();
-            }
-            if let Some(controller_addr) = m.value_of("controller-addr") {
-                current_setting.controller_addr = controller_addr.into();
-            }
-            if let Some(executor_addr) = m.value_of("executor-addr") {
-                current_setting.executor_addr = executor_addr.into();
-            }
-            if let Some(account_name) = m.value_of("account-name") {
-                // Check if the account exists.
-                ctx.wallet.get(account_name)?;
-                current_setting.account_name = account_name.into();
-            }
-            if let Some(pw) = m.value_of("password") {
-                let account_name = m
-                    .value_of("account-name")
-                    .unwrap_or(&ctx.current_setting.account_name);
-                let was_locked = ctx.wallet.get(account_name)?.is_locked();
-                ctx.wallet.unlock(account_name, pw.as_bytes())?;
-                if is_tmp_ctx && was_locked {
-                    relock_info.replace((account_name.into(), pw.into()));
+                if let Some(pw) = m.value_of("password") {
+                    let account_name = ctx.current_setting.account_name.clone();
+                    let was_locked = ctx.wallet.get(&account_name)?.is_locked();
+                    ctx.wallet.unlock(&account_name, pw.as_bytes())?;
+                    if is_tmp_ctx && was_locked {
+                        relock_info.replace((account_name, pw.into()));
+                    }
+                }
+                if let Some(var) = m.value_of("password-env") {
+                    ctx.password_env = Some(var.into());
+                }
+                if let Some(output_format) = m.value_of("output-format") {
+                    ctx.output_format = output_format.parse().unwrap();
+                }
+                if let Some(mode) = m.value_of("color") {
+                    ctx.color_mode = mode.parse().unwrap();
+                }
+                if m.is_present("no-cache") {
+                    // Entries won't be checked for reorg while it's disabled.
+                    ctx.block_cache.clear();
+                    ctx.block_cache.set_enabled(false);
+                }
+                if m.is_present("fresh") {
+                    ctx.fresh_height = true;
+                }
+                if let Some(n) = m.value_of("worker-threads") {
+                    // The runtime is built before the args are parsed in the interactive mode.
+                    if ctx.rt.worker_threads() != n.parse().ok() {
+                        warning!("`--worker-threads` only takes effect when cldi starts, ignored");
+                    }
+                }
+                if m.is_present("strict-crypto") {
+                    ctx.strict_crypto = true;
+                }
+                let mut policy = ctx.request_policy();
+                if let Some(timeout) = m.value_of("timeout") {
+                    policy.timeout = Duration::from_secs(timeout.parse().unwrap());
+                }
+                if let Some(n) = m.value_of("retries") {
+                    policy.retries = n.parse().unwrap();
+                }
+                if let Some(ms) = m.value_of("retry-backoff") {
+                    policy.retry_backoff = Duration::from_millis(ms.parse().unwrap());
+                }
+                if m.is_present("dry-run") {
+                    policy.dry_run = true;
+                }
+                if m.is_present("quiet") {
+                    ctx.verbosity = Verbosity::Quiet;
+                } else if m.is_present("verbose") {
+                    ctx.verbosity = Verbosity::Verbose;
+                }
+                policy.verbosity = ctx.verbosity;
+                if policy != ctx.request_policy() {
+                    ctx.set_request_policy(policy);
                 }
-            }
-            if let Some(crypto_type) = m.value_of("crypto-type") {
-                current_setting.crypto_type = crypto_type.parse().unwrap();
-            }
 
-            ctx.switch_context(current_setting)?;
-            let ret = cmd.dispatch_subcmd(m, ctx);
+                let run = |ctx: &mut Context<Co, Ex, Ev>| {
+                    let ret = {
+                        let _scope = ctx.rt.cancel_scope();
+                        cmd.dispatch_subcmd(m, ctx)
+                    };
+                    // The request that a dry run stops at is the output.
+                    match ret {
+                        Err(e) => match e.downcast_ref::<SdkError>() {
+                            Some(SdkError::DryRun {
+                                service,
+                                method,
+                                request,
+                            }) => {
+                                ctx.emit(&serde_json::json!({
+                                    "method": format!("{service}.{method}"),
+                                    "request": request,
+                                }));
+                                Ok(())
+                            }
+                            _ => Err(e),
+                        },
+                        ok => ok,
+                    }
+                };
+                match m.value_of("out") {
+                    Some(path) => ctx
+                        .tee(Path::new(path), m.is_present("overwrite"), run)
+                        .and_then(|ret| ret),
+                    None => run(ctx),
+                }
+            })();
+            // Accounts unlocked for signing are prompted again for the next command,
+            // use `account unlock` to keep it unlocked.
+            ctx.unlocked_signer.take();
 
             // Restore previous lock status and context setting if it's in tmp context.
-            if let Some((account_name, pw)) = relock_info {
-                ctx.wallet.lock_in_memory(&account_name, pw.as_bytes())?;
-            }
+            // The lock status is restored before switching back to the previous wallet.
+            let relocked = match relock_info {
+                Some((account_name, pw)) => ctx.wallet.lock_in_memory(&account_name, pw.as_bytes()),
+                None => Ok(()),
+            };
             if let Some(previous) = previous_setting {
                 ctx.switch_context(previous)
                     .expect("cannot restore previous context");
             }
+            if let Some(previous) = previous_context {
+                ctx.current_context = previous;
+            }
+            if let Some(previous) = previous_output_format {
+                ctx.output_format = previous;
+            }
+            if let Some(previous) = previous_password_env {
+                ctx.password_env = previous;
+            }
+            if let Some(previous) = previous_color_mode {
+                ctx.color_mode = previous;
+            }
+            if let Some(previous) = previous_cache_enabled {
+                ctx.block_cache.set_enabled(previous);
+            }
+            if let Some(previous) = previous_fresh_height {
+                ctx.fresh_height = previous;
+            }
+            if let Some(previous) = previous_strict_crypto {
+                ctx.strict_crypto = previous;
+            }
+            if let Some(previous) = previous_request_policy {
+                ctx.set_request_policy(previous);
+            }
+            if let Some(previous) = previous_verbosity {
+                ctx.verbosity = previous;
+            }
 
+            relocked?;
             ret
         })
         .subcommands([
@@ -171,11 +496,263 @@ where
             rpc::create_contract().name("create"),
             context::context_cmd(),
             account::account_cmd().alias("a"),
+            account::use_account().name("use"),
             admin::admin_cmd(),
             rpc::rpc_cmd(),
+            evm::evm_cmd(),
+            tx::tx_cmd(),
+            crypto::crypto_cmd(),
             ethabi::ethabi_cmd(),
             bench::bench_cmd().alias("b"),
             watch::watch_cmd().alias("w"),
         ])
         .with_completions_subcmd()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::mock::{MockControllerClient, MockEvmClient, MockExecutorClient};
+
+    #[test]
+    fn test_leaf_level_addr_overrides() {
+        let cldi = cldi_cmd::<MockControllerClient, MockExecutorClient, MockEvmClient>();
+        let matches = |args: &[&str]| {
+            cldi.get_clap_command()
+                .clone()
+                .try_get_matches_from(args)
+                .unwrap()
+        };
+
+        let m = matches(&["cldi", "get", "block-number", "-r", "localhost:50002"]);
+        assert_eq!(m.value_of("controller-addr"), Some("localhost:50002"));
+
+        let m = matches(&[
+            "cldi",
+            "-r",
+            "localhost:50002",
+            "-e",
+            "localhost:50003",
+            "get",
+            "block-number",
+            "--controller-addr",
+            "localhost:60002",
+        ]);
+        assert_eq!(m.value_of("controller-addr"), Some("localhost:60002"));
+        assert_eq!(m.value_of("executor-addr"), Some("localhost:50003"));
+
+        // `-e` is for `--end` in watch.
+        let m = matches(&["cldi", "-e", "localhost:50003", "watch", "-e", "+10"]);
+        assert_eq!(m.value_of("executor-addr"), Some("localhost:50003"));
+        assert_eq!(
+            m.subcommand_matches("watch").unwrap().value_of("end"),
+            Some("+10")
+        );
+        let m = matches(&["cldi", "watch", "--executor-addr", "localhost:60003"]);
+        assert_eq!(m.value_of("executor-addr"), Some("localhost:60003"));
+    }
+
+    #[test]
+    fn test_account_override() {
+        use crate::core::{
+            mock::{context, recording_controller},
+            wallet::Account,
+        };
+        use crate::crypto::SmCrypto;
+        use crate::proto::{blockchain::raw_transaction::Tx, controller::SystemConfig};
+
+        let cldi_cmd = cldi_cmd();
+        let (mut ctx, _temp_dir) = context();
+        let (mut controller, sent) = recording_controller(SystemConfig::default());
+        controller.expect_get_block_number().returning(|_| Ok(100));
+        ctx.controller = controller;
+        let bob = Account::<SmCrypto>::generate();
+        let bob_addr = *bob.address();
+        ctx.wallet.save("bob".into(), bob).unwrap();
+
+        let to = "0x".to_string() + &"11".repeat(20);
+        cldi_cmd
+            .exec_from(["cldi", "send", &to, "--account", "bob"], &mut ctx)
+            .unwrap();
+        let Some(Tx::NormalTx(tx)) = sent.lock().unwrap()[0].tx.clone() else {
+            panic!("expected a normal tx");
+        };
+        assert_eq!(tx.witness.unwrap().sender, bob_addr);
+        assert_eq!(ctx.current_setting.account_name, "default");
+
+        assert!(cldi_cmd
+            .exec_from(["cldi", "send", &to, "--account", "carol"], &mut ctx)
+            .is_err());
+        assert_eq!(sent.lock().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_restore_on_error() {
+        use crate::core::mock::context;
+
+        let cldi_cmd = cldi_cmd();
+        let (mut ctx, temp_dir) = context();
+        // The wallet of this context can't be opened.
+        let not_dir = temp_dir.path().join("not-dir");
+        std::fs::write(&not_dir, "").unwrap();
+        let mut broken = ctx.current_setting.clone();
+        broken.wallet_dir = Some(not_dir);
+        ctx.config.context_settings.insert("broken".into(), broken);
+        let setting = ctx.current_setting.clone();
+
+        let args = [
+            "cldi",
+            "-o",
+            "json",
+            "--dry-run",
+            "-c",
+            "broken",
+            "get",
+            "block-number",
+        ];
+        assert!(cldi_cmd.exec_from(args, &mut ctx).is_err());
+        assert_eq!(ctx.current_setting, setting);
+        assert_eq!(ctx.output_format, OutputFormat::Text);
+        assert!(!ctx.request_policy().dry_run);
+    }
+
+    #[test]
+    fn test_dry_run_output() {
+        use crate::core::mock::context;
+        use serde_json::json;
+
+        let cldi_cmd = cldi_cmd();
+        let (mut ctx, _temp_dir) = context();
+        ctx.controller.expect_get_block_hash().returning(|_| {
+            Err(SdkError::DryRun {
+                service: "controller".into(),
+                method: "get_block_hash".into(),
+                request: json!({ "block_number": 5 }),
+            })
+        });
+
+        let (res, outputs) = ctx.capture(|ctx| {
+            cldi_cmd.exec_from(["cldi", "--dry-run", "get", "block-hash", "5"], ctx)
+        });
+        res.unwrap();
+        assert_eq!(
+            outputs,
+            [json!({
+                "method": "controller.get_block_hash",
+                "request": { "block_number": 5 },
+            })]
+        );
+        assert!(!ctx.request_policy().dry_run);
+    }
+
+    #[test]
+    fn test_out_file() {
+        use crate::core::mock::context;
+        use serde_json::json;
+
+        let cldi_cmd = cldi_cmd();
+        let (mut ctx, temp_dir) = context();
+        ctx.controller
+            .expect_get_block_number()
+            .returning(|_for_pending| Ok(10));
+        let out = temp_dir.path().join("out.txt");
+        let out_path = out.to_str().unwrap();
+        std::fs::write(&out, "stale").unwrap();
+
+        let (res, outputs) = ctx.capture(|ctx| {
+            let exists = cldi_cmd
+                .exec_from(["cldi", "--out", out_path, "get", "block-number"], ctx)
+                .unwrap_err();
+            assert_eq!(
+                exists.to_string(),
+                format!("`{out_path}` already exists, use `--overwrite` to overwrite it")
+            );
+            assert_eq!(std::fs::read_to_string(&out).unwrap(), "stale");
+
+            cldi_cmd.exec_from(
+                [
+                    "cldi",
+                    "--out",
+                    out_path,
+                    "--overwrite",
+                    "get",
+                    "block-number",
+                ],
+                ctx,
+            )
+        });
+        res.unwrap();
+        // The output still goes to the previous sink.
+        assert_eq!(outputs, [json!(10)]);
+        assert_eq!(std::fs::read_to_string(&out).unwrap(), "10\n");
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(&out).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+
+        assert!(cldi_cmd
+            .exec_from(["cldi", "--out", out_path, "--overwrite"], &mut ctx)
+            .is_err());
+
+        #[cfg(target_os = "linux")]
+        {
+            let (res, _) = ctx.capture(|ctx| {
+                cldi_cmd.exec_from(
+                    [
+                        "cldi",
+                        "--out",
+                        "/dev/full",
+                        "--overwrite",
+                        "get",
+                        "block-number",
+                    ],
+                    ctx,
+                )
+            });
+            assert_eq!(
+                res.unwrap_err().to_string(),
+                "cannot write the output to `/dev/full`"
+            );
+        }
+    }
+
+    #[test]
+    fn test_verbosity() {
+        use crate::core::mock::context;
+        use std::cell::RefCell;
+        use std::rc::Rc;
+
+        let seen = Rc::new(RefCell::new(vec![]));
+        let seen_cloned = seen.clone();
+        let cldi_cmd = cldi_cmd().subcommand(Command::new("probe").handler(
+            move |_cmd, _m, ctx: &mut Context<_, _, _>| {
+                seen_cloned
+                    .borrow_mut()
+                    .push((ctx.verbosity, ctx.request_policy().verbosity));
+                Ok(())
+            },
+        ));
+        let (mut ctx, _temp_dir) = context();
+
+        cldi_cmd
+            .exec_from(["cldi", "--quiet", "probe"], &mut ctx)
+            .unwrap();
+        cldi_cmd
+            .exec_from(["cldi", "--verbose", "probe"], &mut ctx)
+            .unwrap();
+        assert_eq!(
+            *seen.borrow(),
+            [
+                (Verbosity::Quiet, Verbosity::Quiet),
+                (Verbosity::Verbose, Verbosity::Verbose)
+            ]
+        );
+        assert_eq!(ctx.verbosity, Verbosity::Normal);
+        assert_eq!(ctx.request_policy().verbosity, Verbosity::Normal);
+        assert!(cldi_cmd
+            .exec_from(["cldi", "--quiet", "--verbose", "probe"], &mut ctx)
+            .is_err());
+    }
+}
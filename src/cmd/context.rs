@@ -14,13 +14,13 @@
 
 use clap::Arg;
 
-use anyhow::anyhow;
+use anyhow::{anyhow, ensure};
 use serde_json::json;
 
 use crate::{
     cmd::Command,
     core::{client::GrpcClientBehaviour, context::Context},
-    display::Display,
+    utils::{hex, validate_addr, validate_alias},
 };
 
 // TODO: consider if it's appropriate to use config internals here.
@@ -68,7 +68,7 @@ pub fn list<'help, Co, Ex, Ev>() -> Command<'help, Context<Co, Ex, Ev>> {
                 "default_context": &ctx.config.default_context,
                 "contexts": &ctx.config.context_settings,
             });
-            println!("{}", json.display());
+            ctx.emit(&json);
 
             Ok(())
         })
@@ -89,11 +89,72 @@ where
             ctx.config.default_context = context_name.into();
             ctx.config.save()?;
             ctx.switch_context(setting)?;
+            ctx.current_context = context_name.into();
 
             Ok(())
         })
 }
 
+pub fn add_alias<'help, Co, Ex, Ev>() -> Command<'help, Context<Co, Ex, Ev>> {
+    Command::<Context<Co, Ex, Ev>>::new("add-alias")
+        .about("add an alias to the address book, use it as `@<alias>` or `<alias>` in place of an address")
+        .arg(
+            Arg::new("alias")
+                .takes_value(true)
+                .required(true)
+                .validator(validate_alias),
+        )
+        .arg(
+            Arg::new("addr")
+                .takes_value(true)
+                .required(true)
+                .validator(validate_addr),
+        )
+        .arg(
+            Arg::new("force")
+                .help("overwrite the existing alias")
+                .short('f')
+                .long("force"),
+        )
+        .handler(|_cmd, m, ctx| {
+            let alias = m.value_of("alias").unwrap();
+            let addr = ctx.parse_addr(m.value_of("addr").unwrap())?;
+            ensure!(
+                m.is_present("force") || !ctx.config.address_book.contains_key(alias),
+                "alias `{alias}` already exists, use `--force` to overwrite it"
+            );
+            ctx.config.address_book.insert(alias.into(), hex(&addr));
+            ctx.config.save()?;
+
+            Ok(())
+        })
+}
+
+pub fn delete_alias<'help, Co, Ex, Ev>() -> Command<'help, Context<Co, Ex, Ev>> {
+    Command::<Context<Co, Ex, Ev>>::new("delete-alias")
+        .about("delete an alias from the address book")
+        .arg(Arg::new("alias").takes_value(true).required(true))
+        .handler(|_cmd, m, ctx| {
+            let alias = m.value_of("alias").unwrap();
+            ctx.config
+                .address_book
+                .remove(alias)
+                .ok_or_else(|| anyhow!("no such alias '{alias}'"))?;
+            ctx.config.save()?;
+
+            Ok(())
+        })
+}
+
+pub fn list_aliases<'help, Co, Ex, Ev>() -> Command<'help, Context<Co, Ex, Ev>> {
+    Command::<Context<Co, Ex, Ev>>::new("list-aliases")
+        .about("list the address book")
+        .handler(|_cmd, _m, ctx| {
+            ctx.emit(&json!(&ctx.config.address_book));
+            Ok(())
+        })
+}
+
 pub fn context_cmd<'help, Co, Ex, Ev>() -> Command<'help, Context<Co, Ex, Ev>>
 where
     Co: GrpcClientBehaviour,
@@ -101,7 +162,7 @@ where
     Ev: GrpcClientBehaviour,
 {
     Command::<Context<Co, Ex, Ev>>::new("context")
-        .alias("ctx")
+        .aliases(&["ctx", "profile"])
         .about("Context commands")
         .subcommand_required_else_help(true)
         .subcommands([
@@ -109,5 +170,62 @@ where
             list().name("list").aliases(&["ls", "l"]),
             delete().name("delete").aliases(&["del", "rm"]),
             default().name("default"),
+            add_alias().name("add-alias"),
+            delete_alias().name("delete-alias").alias("rm-alias"),
+            list_aliases().name("list-aliases").alias("aliases"),
         ])
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::cmd::cldi_cmd;
+    use crate::core::mock::context;
+    use crate::proto::evm::Balance;
+
+    #[test]
+    fn test_address_alias() {
+        let cldi_cmd = cldi_cmd();
+        let (mut ctx, _temp_dir) = context();
+        let addr = format!("0x{}", "01".repeat(20));
+        cldi_cmd
+            .exec_from(["cldi", "context", "add-alias", "alice", &addr], &mut ctx)
+            .unwrap();
+        assert_eq!(ctx.config.address_book["alice"], addr);
+        assert!(cldi_cmd
+            .exec_from(["cldi", "context", "add-alias", "alice", &addr], &mut ctx)
+            .is_err());
+        // Aliases that look like hex addresses are rejected.
+        for alias in ["0xbob", "cafe", &addr[2..]] {
+            assert!(cldi_cmd
+                .exec_from(["cldi", "context", "add-alias", alias, &addr], &mut ctx)
+                .is_err());
+        }
+        // A bare 40 hex digit address is taken as an address, not an alias.
+        let bare = "02".repeat(20);
+        ctx.config.address_book.insert(bare.clone(), addr.clone());
+        assert_eq!(ctx.parse_addr(&bare).unwrap(), [2; 20]);
+        ctx.config.address_book.remove(&bare);
+
+        ctx.evm
+            .expect_get_balance()
+            .times(2)
+            .withf(|addr| addr.as_slice() == [1; 20])
+            .returning(|_| Ok(Balance::default()));
+        cldi_cmd
+            .exec_from(["cldi", "get", "balance", "@alice"], &mut ctx)
+            .unwrap();
+        cldi_cmd
+            .exec_from(["cldi", "get", "balance", "alice"], &mut ctx)
+            .unwrap();
+
+        let err = cldi_cmd
+            .exec_from(["cldi", "context", "delete-alias", "bob"], &mut ctx)
+            .unwrap_err();
+        assert_eq!(err.to_string(), "no such alias 'bob'");
+        cldi_cmd
+            .exec_from(["cldi", "context", "delete-alias", "alice"], &mut ctx)
+            .unwrap();
+        let err = ctx.parse_addr("@alice").unwrap_err();
+        assert_eq!(err.to_string(), "no such alias 'alice'");
+    }
+}
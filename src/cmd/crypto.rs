@@ -0,0 +1,254 @@
+// Copyright Rivtower Technologies LLC.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use anyhow::{bail, Result};
+use clap::{Arg, ArgMatches};
+use serde_json::json;
+use std::str::FromStr;
+
+use crate::{
+    cmd::Command,
+    config::CryptoType,
+    core::context::Context,
+    crypto::{recover_signer, Address, Crypto, EthCrypto, Hash, SmCrypto},
+    utils::{display_addr, parse_data, parse_data_input, read_input, validate_addr},
+};
+
+fn hash_with(crypto_type: CryptoType, data: &[u8]) -> Hash {
+    match crypto_type {
+        CryptoType::Sm => SmCrypto::hash(data),
+        CryptoType::Eth => EthCrypto::hash(data),
+    }
+}
+
+fn recover_signer_with(crypto_type: CryptoType, msg: &[u8], signature: &[u8]) -> Result<Address> {
+    match crypto_type {
+        CryptoType::Sm => recover_signer::<SmCrypto>(msg, signature),
+        CryptoType::Eth => recover_signer::<EthCrypto>(msg, signature),
+    }
+}
+
+fn crypto_type_arg<'help>() -> Arg<'help> {
+    Arg::new("crypto-type")
+        .help("The crypto type to use. [default: <current-context-crypto-type>]")
+        .long("crypto")
+        .possible_values(["SM", "ETH"])
+        .ignore_case(true)
+        .validator(CryptoType::from_str)
+}
+
+fn crypto_type_of<Co, Ex, Ev>(ctx: &Context<Co, Ex, Ev>, m: &ArgMatches) -> CryptoType {
+    m.value_of("crypto-type")
+        .map(|s| s.parse::<CryptoType>().unwrap())
+        .unwrap_or(ctx.current_setting.crypto_type)
+}
+
+pub fn hash<'help, Co, Ex, Ev>() -> Command<'help, Context<Co, Ex, Ev>> {
+    Command::<Context<Co, Ex, Ev>>::new("hash")
+        .about(
+            "Hash the data with the chain's hash function, e.g. an encoded tx to get its tx hash",
+        )
+        .arg(
+            Arg::new("data")
+                .help("the data to hash, or `@<path>` to read it from file, `-` for stdin")
+                .required(true)
+                .takes_value(true),
+        )
+        .arg(
+            Arg::new("hex")
+                .help("decode the data from hex instead of hashing it as is")
+                .long("hex"),
+        )
+        .arg(crypto_type_arg())
+        .handler(|_cmd, m, ctx| {
+            let data = m.value_of("data").unwrap();
+            let data = if m.is_present("hex") {
+                parse_data_input(data)?
+            } else {
+                read_input(data)?.unwrap_or_else(|| data.as_bytes().to_vec())
+            };
+            let crypto_type = crypto_type_of(ctx, m);
+
+            let hash = hash_with(crypto_type, &data);
+            ctx.emit(&hash);
+            Ok(())
+        })
+}
+
+pub fn verify<'help, Co, Ex, Ev>() -> Command<'help, Context<Co, Ex, Ev>> {
+    Command::<Context<Co, Ex, Ev>>::new("verify")
+        .about("Verify that the signature over the message is made by the address, e.g. the tx hash signed by an external signer. It fails if not")
+        .arg(
+            Arg::new("address")
+                .help("the address of the expected signer")
+                .long("address")
+                .required(true)
+                .takes_value(true)
+                .validator(validate_addr),
+        )
+        .arg(
+            Arg::new("message")
+                .help("the signed message in hex, or `@<path>` to read hex from file, `-` for stdin. Txs sign their tx hash")
+                .long("message")
+                .required(true)
+                .takes_value(true),
+        )
+        .arg(
+            Arg::new("signature")
+                .help("the signature in hex. For SM, it's `r || s || pk`")
+                .long("signature")
+                .required(true)
+                .takes_value(true)
+                .validator(parse_data),
+        )
+        .arg(crypto_type_arg())
+        .handler(|_cmd, m, ctx| {
+            let address = ctx.parse_addr(m.value_of("address").unwrap())?;
+            let message = parse_data_input(m.value_of("message").unwrap())?;
+            let signature = parse_data(m.value_of("signature").unwrap())?;
+            let crypto_type = crypto_type_of(ctx, m);
+
+            let signer = recover_signer_with(crypto_type, &message, &signature);
+            let valid = signer.as_ref().is_ok_and(|signer| signer == &address);
+            let mut verdict = json!({ "valid": valid });
+            if let Ok(signer) = &signer {
+                verdict["signer"] = json!(display_addr(signer));
+            }
+            ctx.emit(&verdict);
+
+            match signer {
+                Ok(_) if valid => Ok(()),
+                Ok(signer) => bail!(
+                    "the signature is made by `{}` instead of `{}`",
+                    display_addr(&signer),
+                    display_addr(&address)
+                ),
+                Err(e) => bail!("invalid {crypto_type} signature: {e}"),
+            }
+        })
+}
+
+pub fn crypto_cmd<'help, Co, Ex, Ev>() -> Command<'help, Context<Co, Ex, Ev>> {
+    Command::<Context<Co, Ex, Ev>>::new("crypto")
+        .about("Crypto utilities")
+        .subcommand_required_else_help(true)
+        .subcommands([hash(), verify()])
+}
+
+#[cfg(test)]
+mod tests {
+    use prost::Message;
+
+    use super::*;
+    use crate::cmd::cldi_cmd;
+    use crate::core::{
+        controller::SignerBehaviour,
+        mock::{context, tx},
+        wallet::Account,
+    };
+    use crate::crypto::ArrayLike;
+    use crate::proto::blockchain::raw_transaction::Tx;
+    use crate::utils::hex;
+
+    fn test_hash_matches_tx_hash_with<C: Crypto>(crypto_type: CryptoType) {
+        let tx = tx();
+        let raw = Account::<C>::generate().sign_raw_tx(tx.clone());
+        let Some(Tx::NormalTx(unverified)) = raw.tx else {
+            panic!("expected a normal tx");
+        };
+        assert_eq!(
+            hash_with(crypto_type, &tx.encode_to_vec()).as_slice(),
+            unverified.transaction_hash
+        );
+    }
+
+    #[test]
+    fn test_crypto_hash() {
+        test_hash_matches_tx_hash_with::<SmCrypto>(CryptoType::Sm);
+        test_hash_matches_tx_hash_with::<EthCrypto>(CryptoType::Eth);
+
+        let cldi_cmd = cldi_cmd();
+        let (mut ctx, _temp_dir) = context();
+        cldi_cmd
+            .exec_from(["cldi", "crypto", "hash", "hello"], &mut ctx)
+            .unwrap();
+        cldi_cmd
+            .exec_from(
+                [
+                    "cldi",
+                    "crypto",
+                    "hash",
+                    "--hex",
+                    "0x68656c6c6f",
+                    "--crypto",
+                    "ETH",
+                ],
+                &mut ctx,
+            )
+            .unwrap();
+        assert!(cldi_cmd
+            .exec_from(["cldi", "crypto", "hash", "--hex", "0xzz"], &mut ctx)
+            .is_err());
+    }
+
+    fn test_crypto_verify_with<C: Crypto>(crypto_type: &str) {
+        let cldi_cmd = cldi_cmd();
+        let (mut ctx, _temp_dir) = context();
+        let (pk, sk) = C::generate_keypair();
+        let address = display_addr(&C::pk2addr(&pk));
+        let message = [7; 32];
+        let signature = hex(C::sign(&message, &sk).as_slice());
+
+        let mut verify = |address: &str, message: &str| {
+            let (res, outputs) = ctx.capture(|ctx| {
+                cldi_cmd.exec_from(
+                    [
+                        "cldi",
+                        "crypto",
+                        "verify",
+                        "--address",
+                        address,
+                        "--message",
+                        message,
+                        "--signature",
+                        &signature,
+                        "--crypto",
+                        crypto_type,
+                    ],
+                    ctx,
+                )
+            });
+            (res, outputs[0]["valid"].clone())
+        };
+        let (res, valid) = verify(&address, &hex(&message));
+        res.unwrap();
+        assert_eq!(valid, true);
+
+        let other = hex(&[1; 20]);
+        let (res, valid) = verify(&other, &hex(&message));
+        assert!(res.unwrap_err().to_string().contains("instead of"));
+        assert_eq!(valid, false);
+
+        // A signature over another message is made by nobody we know.
+        let (res, valid) = verify(&address, &hex(&[8; 32]));
+        assert!(res.is_err());
+        assert_eq!(valid, false);
+    }
+
+    #[test]
+    fn test_crypto_verify() {
+        test_crypto_verify_with::<SmCrypto>("SM");
+        test_crypto_verify_with::<EthCrypto>("ETH");
+    }
+}
@@ -40,7 +40,7 @@ pub fn ethabi_encode_function_cmd<'help, Co, Ex, Ev>() -> Command<'help, Context
                 .short('l')
                 .long("lenient"),
         )
-        .handler(|_cmd, m, _ctx| {
+        .handler(|_cmd, m, ctx| {
             let abi_path = m.value_of("abi-path").unwrap();
             let function_name_or_signature = m.value_of("function_name_or_signature").unwrap();
             let params = m
@@ -51,7 +51,7 @@ pub fn ethabi_encode_function_cmd<'help, Co, Ex, Ev>() -> Command<'help, Context
             let lenient = m.is_present("lenient");
 
             let encoded = encode_input(abi_path, function_name_or_signature, &params, lenient)?;
-            println!("0x{encoded}");
+            ctx.emit_text(format!("0x{encoded}"));
 
             Ok(())
         })
@@ -74,7 +74,7 @@ pub fn ethabi_encode_params_cmd<'help, Co, Ex, Ev>() -> Command<'help, Context<C
                 .short('l')
                 .long("lenient"),
         )
-        .handler(|_cmd, m, _ctx| {
+        .handler(|_cmd, m, ctx| {
             let params = m
                 .values_of("type-or-param")
                 .unwrap_or_default()
@@ -83,7 +83,7 @@ pub fn ethabi_encode_params_cmd<'help, Co, Ex, Ev>() -> Command<'help, Context<C
             let lenient = m.is_present("lenient");
 
             let encoded = encode_params(&params, lenient)?;
-            println!("0x{encoded}");
+            ctx.emit_text(format!("0x{encoded}"));
 
             Ok(())
         })
@@ -109,13 +109,13 @@ pub fn ethabi_decode_function_cmd<'help, Co, Ex, Ev>() -> Command<'help, Context
                 .required(true),
         )
         .arg(Arg::new("data").takes_value(true).required(true))
-        .handler(|_cmd, m, _ctx| {
+        .handler(|_cmd, m, ctx| {
             let abi_path = m.value_of("abi-path").unwrap();
             let function_name_or_signature = m.value_of("function_name_or_signature").unwrap();
             let data = m.value_of("data").unwrap();
 
             let decoded = decode_call_output(abi_path, function_name_or_signature, data)?;
-            println!("{decoded}");
+            ctx.emit_text(decoded);
 
             Ok(())
         })
@@ -132,7 +132,7 @@ pub fn ethabi_decode_params_cmd<'help, Co, Ex, Ev>() -> Command<'help, Context<C
                 .multiple_occurrences(true),
         )
         .arg(Arg::new("data").takes_value(true).required(true))
-        .handler(|_cmd, m, _ctx| {
+        .handler(|_cmd, m, ctx| {
             let types = m
                 .values_of("type")
                 .unwrap_or_default()
@@ -141,7 +141,7 @@ pub fn ethabi_decode_params_cmd<'help, Co, Ex, Ev>() -> Command<'help, Context<C
             let data = m.value_of("data").unwrap();
 
             let decoded = decode_params(&types, data)?;
-            println!("{decoded}");
+            ctx.emit_text(decoded);
 
             Ok(())
         })
@@ -164,7 +164,7 @@ pub fn ethabi_decode_log_cmd<'help, Co, Ex, Ev>() -> Command<'help, Context<Co,
                 .multiple_occurrences(true),
         )
         .arg(Arg::new("data").takes_value(true).required(true))
-        .handler(|_cmd, m, _ctx| {
+        .handler(|_cmd, m, ctx| {
             let abi_path = m.value_of("abi-path").unwrap();
             let event_name_or_signature = m.value_of("event-name-or-signature").unwrap();
             let topics = m
@@ -175,7 +175,7 @@ pub fn ethabi_decode_log_cmd<'help, Co, Ex, Ev>() -> Command<'help, Context<Co,
             let data = m.value_of("data").unwrap();
 
             let decoded = decode_log(abi_path, event_name_or_signature, &topics, data)?;
-            println!("{decoded}");
+            ctx.emit_text(decoded);
 
             Ok(())
         })
@@ -211,6 +211,42 @@ pub fn ethabi_cmd<'help, Co, Ex, Ev>() -> Command<'help, Context<Co, Ex, Ev>> {
         ])
 }
 
+/// Append the ABI encoded constructor args to the contract bytecode, args are tokenized leniently.
+/// The number of args the constructor in the ABI takes, 0 if there is no constructor.
+pub fn constructor_arity(abi: &[u8]) -> anyhow::Result<usize> {
+    let contract = Contract::load(abi)?;
+    Ok(contract.constructor.map_or(0, |c| c.inputs.len()))
+}
+
+pub fn encode_constructor(abi: &[u8], code: Vec<u8>, args: &[String]) -> anyhow::Result<Vec<u8>> {
+    let contract = Contract::load(abi)?;
+    let constructor = match &contract.constructor {
+        Some(constructor) => constructor,
+        None if args.is_empty() => return Ok(code),
+        None => {
+            return Err(anyhow!(
+                "the ABI has no constructor but constructor args are given"
+            ))
+        }
+    };
+    if constructor.inputs.len() != args.len() {
+        return Err(anyhow!(
+            "the constructor takes {} args, but {} are given",
+            constructor.inputs.len(),
+            args.len()
+        ));
+    }
+
+    let params: Vec<_> = constructor
+        .inputs
+        .iter()
+        .map(|param| param.kind.clone())
+        .zip(args.iter().map(String::as_str))
+        .collect();
+    let tokens = parse_tokens(&params, true)?;
+    Ok(constructor.encode_input(code, &tokens)?)
+}
+
 // The following code is from `ethabi-cli`.
 
 fn load_function(path: &str, name_or_signature: &str) -> anyhow::Result<Function> {
@@ -328,7 +364,7 @@ fn encode_params(params: &[String], lenient: bool) -> anyhow::Result<String> {
 
 fn decode_call_output(path: &str, name_or_signature: &str, data: &str) -> anyhow::Result<String> {
     let function = load_function(path, name_or_signature)?;
-    let data: Vec<u8> = hex::decode(&data)?;
+    let data: Vec<u8> = hex::decode(data)?;
     let tokens = function.decode_output(&data)?;
     let types = function.outputs;
 
@@ -350,7 +386,7 @@ fn decode_params(types: &[String], data: &str) -> anyhow::Result<String> {
         .map(|s| Reader::read(s))
         .collect::<Result<_, _>>()?;
 
-    let data: Vec<u8> = hex::decode(&data)?;
+    let data: Vec<u8> = hex::decode(data)?;
 
     let tokens = decode(&types, &data)?;
 
@@ -388,5 +424,5 @@ fn decode_log(
 }
 
 fn hash_signature(sig: &str) -> Hash {
-    Hash::from_slice(Keccak256::digest(sig.replace(' ', "").as_bytes()).as_slice())
+    Hash::from_slice(&Keccak256::digest(sig.replace(' ', "").as_bytes()))
 }
@@ -12,19 +12,411 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use clap::Arg;
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::future::Future;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use anyhow::{bail, ensure, Context as _, Result};
+use clap::{Arg, ArgMatches};
+use ethabi::Contract;
+use futures::{stream, StreamExt, TryStreamExt};
+use serde_json::{json, Value as Json};
 
 use crate::{
-    cmd::Command,
+    abi::{split_args, FunctionSignature},
+    cmd::{
+        ethabi::{constructor_arity, encode_constructor},
+        rpc::block_tx_hashes,
+        Command,
+    },
     core::{
-        context::Context, controller::ControllerBehaviour, evm::EvmBehaviour, evm::EvmBehaviourExt,
+        context::Context,
+        controller::{ControllerBehaviour, TransactionSenderBehaviour},
+        error::{is_dry_run, SdkResult},
+        evm::{
+            estimate_quota, is_out_of_quota, quota_limit, store_abi_tx, wait_receipt, EvmBehaviour,
+            EvmBehaviourExt, LogFilter, QuotaUsage, DEFAULT_QUOTA_MARGIN,
+        },
+    },
+    crypto::{Address, ArrayLike, Hash},
+    display::{hexdump, DecodedLog, Display, OutputFormat, ReceiptDetail},
+    log::{debug, info, warning, Verbosity},
+    proto::{
+        blockchain::{raw_transaction::Tx, RawTransaction},
+        evm::{Log, Receipt},
+        executor::CallRequest,
+    },
+    utils::{
+        be_bytes_to_decimal, canonical_abi, display_addr, get_block_height_at, hex,
+        parse_data_input, parse_hash, parse_nonce, parse_nonce_strategy, parse_position,
+        parse_slot, parse_value, read_input, validate_addr, validate_data_input, Position, U256,
     },
-    display::Display,
-    utils::{get_block_height_at, parse_addr, parse_hash, parse_position},
 };
 
+pub fn quota_arg<'help>() -> Arg<'help> {
+    Arg::new("quota")
+        .help("the quota of this tx [default: `default_quota` in config]")
+        .short('q')
+        .long("quota")
+        .takes_value(true)
+        .validator(str::parse::<u64>)
+}
+
+/// The max number of in-flight requests of the commands that fetch many items.
+pub fn concurrency_arg<'help>() -> Arg<'help> {
+    Arg::new("concurrency")
+        .help("max number of concurrent requests")
+        .long("concurrency")
+        .takes_value(true)
+        .default_value("16")
+        .validator(str::parse::<usize>)
+}
+
+pub fn auto_quota_args<'help>() -> [Arg<'help>; 2] {
+    [
+        Arg::new("auto-quota")
+            .help("Estimate the quota of this tx by executing it without committing, and use the estimate with `--quota-margin` added. It fails with the revert reason if the tx would revert")
+            .long("auto-quota")
+            .conflicts_with("quota"),
+        Arg::new("quota-margin")
+            .help("the percentage added to the estimated quota for safety [default: 20]")
+            .long("quota-margin")
+            .takes_value(true)
+            .validator(str::parse::<u64>)
+            .requires("auto-quota"),
+    ]
+}
+
+/// The quota of a tx from the current account to `to` with `data`. It's estimated if
+/// `--auto-quota` of [`auto_quota_args`] is present, otherwise see [`quota_of`].
+pub async fn quota_for<Co, Ex, Ev>(
+    ctx: &Context<Co, Ex, Ev>,
+    m: &ArgMatches,
+    to: &[u8],
+    data: &[u8],
+) -> Result<u64>
+where
+    Ev: EvmBehaviour,
+{
+    if !m.is_present("auto-quota") {
+        return quota_of(ctx, m);
+    }
+    let margin = match m.value_of("quota-margin") {
+        Some(margin) => margin.parse::<u64>()?,
+        None => DEFAULT_QUOTA_MARGIN,
+    };
+    let from = *ctx.wallet.get(&ctx.current_setting.account_name)?.address();
+    let call = CallRequest {
+        from: from.to_vec(),
+        to: to.to_vec(),
+        method: data.to_vec(),
+        args: vec![],
+    };
+    let quota = estimate_quota(&ctx.evm, call, margin).await?;
+    info!(
+        ctx.verbosity,
+        "using the estimated quota `{quota}` with {margin}% margin"
+    );
+    Ok(quota)
+}
+
+pub fn valid_until_arg<'help>() -> Arg<'help> {
+    Arg::new("valid-until-block")
+        .help("this tx is valid until the given block height. `+h` means `<current-height> + h` [default: `+<valid_until_offset in config>`]")
+        .long("until")
+        .takes_value(true)
+        .validator(parse_position)
+}
+
+/// The quota given by [`quota_arg`]. Defaults to the one in config, which falls back to [`DEFAULT_QUOTA`].
+///
+/// [`DEFAULT_QUOTA`]: crate::config::DEFAULT_QUOTA
+pub fn quota_of<Co, Ex, Ev>(ctx: &Context<Co, Ex, Ev>, m: &ArgMatches) -> Result<u64> {
+    match m.value_of("quota") {
+        Some(quota) => Ok(quota.parse()?),
+        None => Ok(ctx.config.default_quota),
+    }
+}
+
+/// The position given by [`valid_until_arg`]. Defaults to `+<valid_until_offset>` in config,
+/// which falls back to [`DEFAULT_VALID_UNTIL_OFFSET`].
+///
+/// [`DEFAULT_VALID_UNTIL_OFFSET`]: crate::config::DEFAULT_VALID_UNTIL_OFFSET
+pub fn valid_until_of<Co, Ex, Ev>(ctx: &Context<Co, Ex, Ev>, m: &ArgMatches) -> Result<Position> {
+    match m.value_of("valid-until-block") {
+        Some(pos) => parse_position(pos),
+        None => Ok(Position::FromCurrent(ctx.config.valid_until_offset)),
+    }
+}
+
+pub fn nonce_strategy_arg<'help>() -> Arg<'help> {
+    Arg::new("nonce-strategy")
+        .help("how to choose the nonces instead of random ones. `fetch` gets the account's nonce from the node for each tx, `local-increment` fetches it once and increments it locally for later txs in this session, which assumes no other sender uses the account concurrently")
+        .long("nonce-strategy")
+        .takes_value(true)
+        .possible_values(["fetch", "local-increment"])
+        .validator(parse_nonce_strategy)
+}
+
+pub fn strict_nonce_arg<'help>() -> Arg<'help> {
+    Arg::new("strict")
+        .help("Error instead of warning if `--nonce` is ahead of the account nonce")
+        .long("strict")
+}
+
+/// The nonce given by `--nonce`, or chosen by [`nonce_strategy_arg`] if present. `None` means a random one.
+///
+/// A given nonce is checked by [`Context::check_nonce_gap`], with [`strict_nonce_arg`].
+pub async fn nonce_of<Co, Ex, Ev>(
+    ctx: &Context<Co, Ex, Ev>,
+    m: &ArgMatches,
+) -> Result<Option<String>>
+where
+    Ev: EvmBehaviour,
+{
+    match m.value_of("nonce-strategy") {
+        Some(strategy) => Ok(Some(ctx.next_nonce(parse_nonce_strategy(strategy)?).await?)),
+        None => {
+            let nonce = parse_nonce(m.value_of("nonce").unwrap())?;
+            if let Some(nonce) = &nonce {
+                ctx.check_nonce_gap(nonce, m.is_present("strict")).await?;
+            }
+            Ok(nonce)
+        }
+    }
+}
+
+pub fn wait_arg<'help>() -> Arg<'help> {
+    Arg::new("wait")
+        .help("Wait for the tx to be executed and print its receipt")
+        .short('w')
+        .long("wait")
+}
+
+pub fn show_quota_arg<'help>() -> Arg<'help> {
+    Arg::new("show-quota")
+        .help("Print the quota used versus the quota limit after the tx is executed, for tuning `--quota`")
+        .long("show-quota")
+        .requires("wait")
+}
+
+pub fn wait_timeout_arg<'help>() -> Arg<'help> {
+    Arg::new("wait-timeout")
+        .help("How many seconds to wait for the receipt, and the confirmations if any")
+        .long("wait-timeout")
+        .takes_value(true)
+        .default_value("60")
+        .requires("wait")
+        .validator(str::parse::<u64>)
+}
+
+/// Commands with [`wait_arg`] must have this too, it's read by [`maybe_wait_receipt`].
+pub fn confirmations_arg<'help>() -> Arg<'help> {
+    Arg::new("confirmations")
+        .help("Wait for this many blocks after the tx's block before taking it as done, and fail if the tx is reorged out meanwhile")
+        .long("confirmations")
+        .takes_value(true)
+        .default_value("0")
+        .requires("wait")
+        .validator(str::parse::<u64>)
+}
+
+const CONFIRMATION_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Wait until there are `confirmations` blocks after the block of the receipt,
+/// then check that the block is still in the chain.
+///
+/// The receipt's `block_hash` is the executor's hash, which isn't the one the controller
+/// reports, so the controller's hash of the block is recorded when the receipt arrives.
+async fn wait_confirmations<Co>(
+    controller: &Co,
+    receipt: &Receipt,
+    confirmations: u64,
+    deadline: Instant,
+) -> Result<()>
+where
+    Co: ControllerBehaviour,
+{
+    let tx_block = receipt.block_number;
+    let tx_hash = hex(&receipt.transaction_hash);
+    let included_in = controller.get_block_hash(tx_block).await?;
+    loop {
+        let current = controller.get_block_number(false).await?;
+        let confirmed = current.saturating_sub(tx_block);
+        if confirmed >= confirmations {
+            break;
+        }
+        let now = Instant::now();
+        ensure!(
+            now < deadline,
+            "tx `{tx_hash}` in block #{tx_block} has {confirmed} of {confirmations} confirmations when the wait times out"
+        );
+        tokio::time::sleep(CONFIRMATION_POLL_INTERVAL.min(deadline - now)).await;
+    }
+
+    let block_hash = controller.get_block_hash(tx_block).await?;
+    ensure!(
+        block_hash == included_in,
+        "tx `{tx_hash}` is reorged out, its block #{tx_block} `{}` is replaced by `{}`",
+        hex(&included_in),
+        hex(&block_hash)
+    );
+    Ok(())
+}
+
+pub fn function_arg<'help>() -> Arg<'help> {
+    Arg::new("function")
+        .help("encode the data from this function signature and `--args`, e.g. `transfer(address,uint256)`. The selector is from keccak256 on SM chains as well, like solidity does")
+        .long("function")
+        .takes_value(true)
+        .validator(FunctionSignature::parse)
+}
+
+pub fn function_args_arg<'help>() -> Arg<'help> {
+    Arg::new("args")
+        .help("the function args, comma separated or one by one, e.g. `--args 0xabc..,1000`. Give string args with commas one by one. Numbers can be in decimal and negative. Put it last since the values can start with `-`")
+        .long("args")
+        .takes_value(true)
+        .multiple_values(true)
+        .allow_hyphen_values(true)
+        .requires("function")
+}
+
+/// The args given by `--args` for a function that takes `arity` args.
+/// A single value is split by commas, unless the function takes only one arg.
+fn args_of(m: &ArgMatches, arity: usize) -> Vec<String> {
+    let args: Vec<&str> = m.values_of("args").unwrap_or_default().collect();
+    match args[..] {
+        [combined] if arity != 1 => split_args(combined),
+        _ => args.into_iter().map(String::from).collect(),
+    }
+}
+
+/// The data encoded from [`function_arg`] and [`function_args_arg`], `None` if no `--function` is given.
+pub fn function_data_of(m: &ArgMatches) -> Result<Option<Vec<u8>>> {
+    match m.value_of("function") {
+        Some(signature) => {
+            let function = FunctionSignature::parse(signature)?;
+            let args = args_of(m, function.inputs.len());
+            Ok(Some(function.encode_call(&args)?))
+        }
+        None => Ok(None),
+    }
+}
+
+/// Wait for the receipt of the sent tx if `--wait` is given, see [`wait_arg`].
+///
+/// It fails if the tx isn't executed in time or it's reverted, the receipt is printed for the latter.
+/// With `--confirmations`, it also fails if the tx isn't confirmed in time or it's reorged out.
+/// `quota_limit` is the quota of the tx, which is shown against the quota used if known.
+pub async fn maybe_wait_receipt<Co, Ex, Ev>(
+    ctx: &Context<Co, Ex, Ev>,
+    m: &ArgMatches,
+    tx_hash: Hash,
+    quota_limit: Option<u64>,
+) -> Result<()>
+where
+    Co: ControllerBehaviour,
+    Ev: EvmBehaviour,
+{
+    if !m.is_present("wait") {
+        return Ok(());
+    }
+    let secs = m.value_of("wait-timeout").unwrap().parse::<u64>()?;
+    let deadline = Instant::now() + Duration::from_secs(secs);
+    let receipt = match wait_receipt(&ctx.evm, tx_hash, Duration::from_secs(secs)).await? {
+        Some(receipt) => receipt,
+        None => bail!(
+            "tx `{}` is not executed after waiting {secs}s, it may still be pending",
+            hex(&tx_hash)
+        ),
+    };
+    let confirmations = m.value_of("confirmations").unwrap().parse::<u64>()?;
+    if confirmations > 0 {
+        wait_confirmations(&ctx.controller, &receipt, confirmations, deadline).await?;
+    }
+
+    let reverted = !receipt.error_message.is_empty();
+    let mut output = ReceiptDetail {
+        receipt: &receipt,
+        abi: None,
+        contract_abis: HashMap::new(),
+        quota_limit,
+    }
+    .to_json(ctx.display_options());
+    output["status"] = json!(if reverted { "reverted" } else { "success" });
+    ctx.emit(&output);
+
+    let usage = quota_limit
+        .map(|limit| QuotaUsage::of(&receipt, limit))
+        .transpose()?;
+    if m.is_present("show-quota") {
+        // Not the output of the command, so that it won't get in the way of scripts.
+        match usage {
+            Some(usage) => eprintln!(
+                "quota used: `{}` of `{}` ({:.2}%)",
+                usage.used,
+                usage.limit,
+                usage.percentage()
+            ),
+            None => eprintln!(
+                "quota used: `{}`, the quota limit is unknown",
+                be_bytes_to_decimal(&receipt.quota_used)
+            ),
+        }
+    }
+    if reverted && is_out_of_quota(&receipt) {
+        match usage {
+            Some(usage) => bail!(
+                "tx ran out of quota, it used up the quota limit `{}`. Try a larger `--quota`",
+                usage.limit
+            ),
+            None => bail!("tx ran out of quota. Try a larger `--quota`"),
+        }
+    }
+    ensure!(!reverted, "tx reverted: {}", receipt.error_message);
+    Ok(())
+}
+
+fn at_arg<'help>() -> Arg<'help> {
+    Arg::new("at")
+        .help("Query the state at this block height. The node only serves the latest state, so it must be the current height")
+        .long("at")
+        .takes_value(true)
+        .validator(str::parse::<u64>)
+}
+
+/// Run the query at the height given by [`at_arg`], or at the latest height if not given.
+///
+/// The EVM service has no block number parameter and always returns the latest state,
+/// so it fails unless the height is the current one, both before and after the query.
+async fn query_at<Co, T, F>(controller: &Co, m: &ArgMatches, query: F) -> Result<T>
+where
+    Co: ControllerBehaviour,
+    F: Future<Output = SdkResult<T>>,
+{
+    let at = match m.value_of("at") {
+        Some(at) => at.parse::<u64>()?,
+        None => return Ok(query.await?),
+    };
+    let ensure_current = |current: u64| {
+        ensure!(
+            at == current,
+            "node does not support historical queries, only the current height `{current}` is available"
+        );
+        Ok(())
+    };
+    ensure_current(controller.get_block_number(false).await?)?;
+    let res = query.await?;
+    ensure_current(controller.get_block_number(false).await?)?;
+    Ok(res)
+}
+
 pub fn get_receipt<'help, Co, Ex, Ev>() -> Command<'help, Context<Co, Ex, Ev>>
 where
+    Co: ControllerBehaviour,
     Ev: EvmBehaviour,
 {
     Command::<Context<Co, Ex, Ev>>::new("get-receipt")
@@ -36,17 +428,93 @@ where
                 .required(true)
                 .validator(parse_hash),
         )
+        .arg(
+            Arg::new("abi")
+                .help("The contract ABI file for decoding event logs. It's remembered for the contract in this session, otherwise the ABI stored on chain is used")
+                .long("abi")
+                .takes_value(true),
+        )
         .handler(|_cmd, m, ctx| {
             let tx_hash = parse_hash(m.value_of("tx_hash").unwrap())?;
+            let abi = m.value_of("abi").map(load_abi_file).transpose()?;
 
             let receipt = ctx.rt.block_on(ctx.evm.get_receipt(tx_hash))??;
-            println!("{}", receipt.display());
+            if ctx.output_format == OutputFormat::Bin {
+                return ctx.emit_bin(&receipt);
+            }
+            // The quota limit is in the tx. It's only for display, so don't fail the command.
+            let raw = match ctx.rt.block_on(ctx.controller.get_tx(tx_hash))? {
+                Ok(raw) => Some(raw),
+                Err(e) if e.is_dry_run() => return Err(e.into()),
+                Err(_) => None,
+            };
+            let quota_limit = raw.as_ref().and_then(quota_limit);
+
+            let mut contract_abis = HashMap::new();
+            if let Some(abi) = &abi {
+                let contract = Address::try_from_slice(&receipt.contract_address)
+                    .ok()
+                    .or_else(|| raw.as_ref().and_then(tx_to));
+                if let Some(contract) = contract {
+                    ctx.abi_cache.insert(contract, abi.clone());
+                }
+            } else {
+                contract_abis = ctx.rt.block_on(emitter_abis(ctx, &receipt.logs))??;
+            }
+            ctx.emit(&ReceiptDetail {
+                receipt: &receipt,
+                abi: abi.as_deref(),
+                contract_abis,
+                quota_limit,
+            });
             Ok(())
         })
 }
 
+fn load_abi_file(path: &str) -> Result<Arc<Contract>> {
+    let file = File::open(path).with_context(|| format!("cannot open ABI file `{path}`"))?;
+    let abi = Contract::load(file).with_context(|| format!("invalid ABI file `{path}`"))?;
+    Ok(Arc::new(abi))
+}
+
+/// The ABIs of the contracts that emit the logs, from the ABI cache or stored on chain.
+/// Those without one are left out, their logs are shown as is. Only a dry run fails it.
+async fn emitter_abis<Co, Ex, Ev>(
+    ctx: &Context<Co, Ex, Ev>,
+    logs: &[Log],
+) -> Result<HashMap<Address, Arc<Contract>>>
+where
+    Ev: EvmBehaviour,
+{
+    let emitters = logs
+        .iter()
+        .filter_map(|log| Address::try_from_slice(&log.address).ok())
+        .collect::<HashSet<_>>();
+    let mut abis = HashMap::new();
+    for addr in emitters {
+        match ctx.abi_cache.get_or_fetch(&ctx.evm, addr).await {
+            Ok(Some(abi)) => {
+                abis.insert(addr, abi);
+            }
+            Ok(None) => (),
+            Err(e) if is_dry_run(&e) => return Err(e),
+            Err(e) => debug!(ctx.verbosity, "no ABI for `{}`: {e}", display_addr(&addr)),
+        }
+    }
+    Ok(abis)
+}
+
+/// The callee of a normal tx, none for contract creation and utxos.
+fn tx_to(raw: &RawTransaction) -> Option<Address> {
+    match &raw.tx {
+        Some(Tx::NormalTx(tx)) => Address::try_from_slice(&tx.transaction.as_ref()?.to).ok(),
+        _ => None,
+    }
+}
+
 pub fn get_code<'help, Co, Ex, Ev>() -> Command<'help, Context<Co, Ex, Ev>>
 where
+    Co: ControllerBehaviour,
     Ev: EvmBehaviour,
 {
     Command::<Context<Co, Ex, Ev>>::new("get-code")
@@ -56,19 +524,32 @@ where
                 .help("Contract address")
                 .takes_value(true)
                 .required(true)
-                .validator(parse_addr),
+                .validator(validate_addr),
+        )
+        .arg(
+            Arg::new("hexdump")
+                .help("Print in hexdump format like `xxd` instead of a single-line hex")
+                .long("hexdump"),
         )
+        .arg(at_arg())
         .handler(|_cmd, m, ctx| {
-            let addr = parse_addr(m.value_of("addr").unwrap())?;
+            let addr = ctx.parse_addr(m.value_of("addr").unwrap())?;
 
-            let byte_code = ctx.rt.block_on(ctx.evm.get_code(addr))??;
-            println!("{}", byte_code.display());
+            let byte_code =
+                ctx.rt
+                    .block_on(query_at(&ctx.controller, m, ctx.evm.get_code(addr)))??;
+            if m.is_present("hexdump") {
+                ctx.emit_text(hexdump(&byte_code.byte_code));
+            } else {
+                ctx.emit(&byte_code);
+            }
             Ok(())
         })
 }
 
 pub fn get_balance<'help, Co, Ex, Ev>() -> Command<'help, Context<Co, Ex, Ev>>
 where
+    Co: ControllerBehaviour,
     Ev: EvmBehaviour,
 {
     Command::<Context<Co, Ex, Ev>>::new("get-balance")
@@ -77,22 +558,67 @@ where
             Arg::new("addr")
                 .help("Account address, default to current account")
                 .takes_value(true)
-                .validator(parse_addr),
+                .validator(validate_addr),
         )
+        .arg(at_arg())
         .handler(|_cmd, m, ctx| {
             let addr = match m.value_of("addr") {
-                Some(s) => parse_addr(s).unwrap(),
+                Some(s) => ctx.parse_addr(s)?,
                 None => *ctx.current_account()?.address(),
             };
 
-            let balance = ctx.rt.block_on(ctx.evm.get_balance(addr))??;
-            println!("{}", balance.display());
+            let balance =
+                ctx.rt
+                    .block_on(query_at(&ctx.controller, m, ctx.evm.get_balance(addr)))??;
+            let balance = U256::from_be_slice(&balance.value).context("invalid balance")?;
+            ctx.emit(&balance);
+            Ok(())
+        })
+}
+
+pub fn get_storage<'help, Co, Ex, Ev>() -> Command<'help, Context<Co, Ex, Ev>>
+where
+    Co: ControllerBehaviour,
+    Ev: EvmBehaviour,
+{
+    Command::<Context<Co, Ex, Ev>>::new("get-storage")
+        .about("Get the raw value of a contract storage slot")
+        .arg(
+            Arg::new("addr")
+                .help("Contract address")
+                .takes_value(true)
+                .required(true)
+                .validator(validate_addr),
+        )
+        .arg(
+            Arg::new("slot")
+                .help("The storage slot, hex with `0x` prefix or decimal")
+                .takes_value(true)
+                .required(true)
+                .validator(parse_slot),
+        )
+        .arg(at_arg())
+        .handler(|_cmd, m, ctx| {
+            let addr = ctx.parse_addr(m.value_of("addr").unwrap())?;
+            let slot = parse_slot(m.value_of("slot").unwrap())?;
+
+            let value = ctx.rt.block_on(query_at(
+                &ctx.controller,
+                m,
+                ctx.evm.get_storage_at(addr, slot.to_be_bytes()),
+            ))??;
+            let value = U256::from_be_slice(&value.hash).context("invalid storage value")?;
+            ctx.emit(&json!({
+                "hex": value.to_hex(),
+                "decimal": value.to_decimal(),
+            }));
             Ok(())
         })
 }
 
 pub fn get_account_nonce<'help, Co, Ex, Ev>() -> Command<'help, Context<Co, Ex, Ev>>
 where
+    Co: ControllerBehaviour,
     Ev: EvmBehaviour,
 {
     Command::<Context<Co, Ex, Ev>>::new("get-account-nonce")
@@ -101,16 +627,20 @@ where
             Arg::new("addr")
                 .help("Account address, default to current account")
                 .takes_value(true)
-                .validator(parse_addr),
+                .validator(validate_addr),
         )
+        .arg(at_arg())
         .handler(|_cmd, m, ctx| {
             let addr = match m.value_of("addr") {
-                Some(s) => parse_addr(s).unwrap(),
+                Some(s) => ctx.parse_addr(s)?,
                 None => *ctx.current_account()?.address(),
             };
 
-            let nonce = ctx.rt.block_on(ctx.evm.get_tx_count(addr))??;
-            println!("{}", nonce.display());
+            let nonce =
+                ctx.rt
+                    .block_on(query_at(&ctx.controller, m, ctx.evm.get_tx_count(addr)))??;
+            let nonce = U256::from_be_slice(&nonce.nonce).context("invalid account nonce")?;
+            ctx.emit(&nonce);
             Ok(())
         })
 }
@@ -126,13 +656,22 @@ where
                 .help("Contract address")
                 .required(true)
                 .takes_value(true)
-                .validator(parse_addr),
+                .validator(validate_addr),
+        )
+        .arg(
+            Arg::new("hexdump")
+                .help("Print the raw ABI bytes in hexdump format like `xxd`")
+                .long("hexdump"),
         )
         .handler(|_cmd, m, ctx| {
-            let addr = parse_addr(m.value_of("addr").unwrap())?;
+            let addr = ctx.parse_addr(m.value_of("addr").unwrap())?;
 
             let byte_abi = ctx.rt.block_on(ctx.evm.get_abi(addr))??;
-            println!("{}", byte_abi.display());
+            if m.is_present("hexdump") {
+                ctx.emit_text(hexdump(&byte_abi.bytes_abi));
+            } else {
+                ctx.emit(&byte_abi);
+            }
             Ok(())
         })
 }
@@ -140,6 +679,7 @@ where
 pub fn store_abi<'help, Co, Ex, Ev>() -> Command<'help, Context<Co, Ex, Ev>>
 where
     Co: ControllerBehaviour + Send + Sync,
+    Ev: EvmBehaviour,
 {
     Command::<Context<Co, Ex, Ev>>::new("store-abi")
         .about("Store EVM contract ABI")
@@ -147,48 +687,738 @@ where
             Arg::new("addr")
                 .required(true)
                 .takes_value(true)
-                .validator(parse_addr),
+                .validator(validate_addr),
         )
-        .arg(Arg::new("abi").required(true).takes_value(true))
         .arg(
-            Arg::new("quota")
-                .help("the quota of this tx")
-                .short('q')
-                .long("quota")
-                .takes_value(true)
-                .default_value("3000000")
-                .validator(str::parse::<u64>),
-        )
-        .arg(
-            Arg::new("valid-until-block")
-                .help("this tx is valid until the given block height. `+h` means `<current-height> + h`")
-                .long("until")
-                .takes_value(true)
-                .default_value("+95")
-                .validator(parse_position),
+            Arg::new("abi")
+                .help("the ABI json, or `@<path>` to read it from file, `-` for stdin. It's validated and stored minified")
+                .required(true)
+                .takes_value(true),
         )
+        .arg(quota_arg())
+        .args(auto_quota_args())
+        .arg(valid_until_arg())
         .handler(|_cmd, m, ctx| {
             let tx_hash = ctx.rt.block_on(async {
-                let contract_addr = parse_addr(m.value_of("addr").unwrap())?;
+                let contract_addr = ctx.parse_addr(m.value_of("addr").unwrap())?;
                 let abi = m.value_of("abi").unwrap();
-                let quota = m.value_of("quota").unwrap().parse::<u64>()?;
-                let valid_until_block = {
-                    let pos = parse_position(m.value_of("valid-until-block").unwrap())?;
-                    get_block_height_at(&ctx.controller, pos).await?
-                };
+                let abi = read_input(abi)?.unwrap_or_else(|| abi.as_bytes().to_vec());
+                let abi = canonical_abi(&abi)?;
+                let (to, data) = store_abi_tx(contract_addr, &abi)?;
+                let quota = quota_for(ctx, m, &to, &data).await?;
+                let valid_until_block =
+                    get_block_height_at(&ctx.controller, valid_until_of(ctx, m)?).await?;
 
-                let signer = ctx.current_account()?;
+                let signer = ctx.signer().await?;
                 ctx.controller
                     .store_contract_abi(
                         signer,
                         contract_addr,
-                        abi.as_bytes(),
+                        &abi,
                         quota,
                         valid_until_block,
                     )
                     .await
             })??;
-            println!("{}", tx_hash.display());
+            ctx.emit(&tx_hash);
+            Ok(())
+        })
+}
+
+pub fn deploy<'help, Co, Ex, Ev>() -> Command<'help, Context<Co, Ex, Ev>>
+where
+    Co: ControllerBehaviour + Send + Sync,
+    Ev: EvmBehaviour,
+{
+    Command::<Context<Co, Ex, Ev>>::new("deploy")
+        .about("Deploy an EVM contract, wait for it to be created and store its ABI")
+        .arg(
+            Arg::new("bytecode")
+                .help("the init bytecode of the contract, hex or `@<path>` to read hex from file, `-` for stdin")
+                .long("bytecode")
+                .required(true)
+                .takes_value(true)
+                .validator(validate_data_input),
+        )
+        .arg(
+            Arg::new("abi")
+                .help("the ABI json, or `@<path>` to read it from file. It's used to encode the constructor args and stored for the deployed contract")
+                .long("abi")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::new("args")
+                .help("the constructor args, comma separated or one by one, e.g. `--args 42,0x1234..`. Give string args with commas one by one. Numbers can be in decimal and negative. Put it last since the values can start with `-`")
+                .long("args")
+                .takes_value(true)
+                .multiple_values(true)
+                .allow_hyphen_values(true)
+                .requires("abi"),
+        )
+        .arg(
+            Arg::new("value")
+                .help("the value sent to the constructor, e.g. `1.5ether`, `200gwei`, `100`(wei) or `0x64`")
+                .short('v')
+                .long("value")
+                .takes_value(true)
+                .default_value("0x0")
+                .validator(parse_value),
+        )
+        .arg(quota_arg())
+        .args(auto_quota_args())
+        .arg(valid_until_arg())
+        .arg(
+            Arg::new("wait-timeout")
+                .help("How many seconds to wait for the contract to be created")
+                .long("wait-timeout")
+                .takes_value(true)
+                .default_value("60")
+                .validator(str::parse::<u64>),
+        )
+        .handler(|_cmd, m, ctx| {
+            let output = ctx.rt.block_on(async {
+                let abi = m
+                    .value_of("abi")
+                    .map(|abi| -> Result<Vec<u8>> {
+                        let abi = read_input(abi)?.unwrap_or_else(|| abi.as_bytes().to_vec());
+                        canonical_abi(&abi)
+                    })
+                    .transpose()?;
+                let code = parse_data_input(m.value_of("bytecode").unwrap())?;
+                let data = match &abi {
+                    Some(abi) => {
+                        encode_constructor(abi, code, &args_of(m, constructor_arity(abi)?))?
+                    }
+                    None => code,
+                };
+                let value = parse_value(m.value_of("value").unwrap())?.to_vec();
+                let quota = quota_for(ctx, m, &[], &data).await?;
+                let secs = m.value_of("wait-timeout").unwrap().parse::<u64>()?;
+
+                let signer = ctx.signer().await?;
+                let valid_until_block =
+                    ctx.valid_until_block(valid_until_of(ctx, m)?).await?;
+                let tx = ctx
+                    .build_tx(vec![], data, value, quota, valid_until_block, None)
+                    .await?;
+                let tx_hash = ctx.controller.send_raw_tx(signer, tx).await?;
+
+                let receipt = wait_receipt(&ctx.evm, tx_hash, Duration::from_secs(secs))
+                    .await?
+                    .with_context(|| {
+                        format!(
+                            "contract creation tx `{}` is not executed after waiting {secs}s, it may still be pending",
+                            hex(&tx_hash)
+                        )
+                    })?;
+                ensure!(
+                    receipt.error_message.is_empty(),
+                    "contract creation tx `{}` reverted: {}",
+                    hex(&tx_hash),
+                    receipt.error_message
+                );
+                let contract_addr = Address::try_from_slice(&receipt.contract_address)
+                    .context("the receipt has no valid contract address")?;
+
+                let mut output = json!({
+                    "contract_addr": display_addr(&contract_addr),
+                    "tx_hash": hex(&tx_hash),
+                });
+                if let Some(abi) = abi {
+                    // The ABI tx is estimated on its own, its quota has nothing to do with the code.
+                    let quota = if m.is_present("auto-quota") {
+                        let (to, data) = store_abi_tx(contract_addr, &abi)?;
+                        quota_for(ctx, m, &to, &data).await?
+                    } else {
+                        quota
+                    };
+                    let valid_until_block =
+                        ctx.valid_until_block(valid_until_of(ctx, m)?).await?;
+                    let abi_tx_hash = ctx
+                        .controller
+                        .store_contract_abi(signer, contract_addr, &abi, quota, valid_until_block)
+                        .await
+                        .with_context(|| {
+                            format!(
+                                "contract is deployed at `{}`, but its ABI isn't stored",
+                                display_addr(&contract_addr)
+                            )
+                        })?;
+                    output["abi_tx_hash"] = json!(hex(&abi_tx_hash));
+                }
+                anyhow::Ok(output)
+            })??;
+            ctx.emit(&output);
+            Ok(())
+        })
+}
+
+/// Warn that it may be slow if there are more blocks to scan for logs.
+const LOGS_SCAN_WARNING_BLOCKS: u64 = 1000;
+
+pub fn get_logs<'help, Co, Ex, Ev>() -> Command<'help, Context<Co, Ex, Ev>>
+where
+    Co: ControllerBehaviour + Send + Sync,
+    Ev: EvmBehaviour,
+{
+    let topic_args = ["topic0", "topic1", "topic2", "topic3"].map(|name| {
+        Arg::new(name)
+            .help("the topic at this position, topic0 is the event signature for non-anonymous events")
+            .long(name)
+            .takes_value(true)
+            .validator(parse_hash)
+    });
+    Command::<Context<Co, Ex, Ev>>::new("logs")
+        .about("Get the logs in [from, to] that match the filter. It scans the receipts of all the txs, which may be slow")
+        .arg(
+            Arg::new("address")
+                .help("only the logs emitted by this contract")
+                .long("address")
+                .takes_value(true)
+                .validator(validate_addr),
+        )
+        .args(topic_args)
+        .arg(
+            Arg::new("from")
+                .help("the first height")
+                .long("from")
+                .takes_value(true)
+                .required(true)
+                .validator(str::parse::<u64>),
+        )
+        .arg(
+            Arg::new("to")
+                .help("the last height, inclusive [default: current height]")
+                .long("to")
+                .takes_value(true)
+                .validator(str::parse::<u64>),
+        )
+        .arg(
+            Arg::new("abi")
+                .help("the contract ABI file for decoding the logs, otherwise the ABIs stored on chain are used")
+                .long("abi")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::new("max-blocks")
+                .help("refuse to scan more blocks than this")
+                .long("max-blocks")
+                .takes_value(true)
+                .default_value("10000")
+                .validator(str::parse::<u64>),
+        )
+        .arg(concurrency_arg())
+        .handler(|_cmd, m, ctx| {
+            let mut filter = LogFilter {
+                address: m.value_of("address").map(|s| ctx.parse_addr(s)).transpose()?,
+                ..Default::default()
+            };
+            for (i, name) in ["topic0", "topic1", "topic2", "topic3"].iter().enumerate() {
+                filter.topics[i] = m.value_of(name).map(parse_hash).transpose()?;
+            }
+            let abi = m.value_of("abi").map(load_abi_file).transpose()?;
+            let from = m.value_of("from").unwrap().parse::<u64>()?;
+            let to = match m.value_of("to") {
+                Some(to) => to.parse::<u64>()?,
+                None => ctx.rt.block_on(ctx.controller.get_block_number(false))??,
+            };
+            let max_blocks = m.value_of("max-blocks").unwrap().parse::<u64>()?;
+            let concurrency = m.value_of("concurrency").unwrap().parse::<usize>()?;
+            ensure!(from <= to, "`from` must not be greater than `to`");
+            let blocks = to - from + 1;
+            ensure!(
+                blocks <= max_blocks,
+                "there are `{blocks}` blocks in the range, more than `--max-blocks` `{max_blocks}`"
+            );
+            // The EVM service has no log filter, so all the receipts are fetched and filtered here.
+            if blocks > LOGS_SCAN_WARNING_BLOCKS {
+                warning!("scanning the receipts of all the txs in `{blocks}` blocks, it may take a while");
+            }
+
+            let progbar = indicatif::ProgressBar::new(blocks);
+            progbar.set_style(
+                indicatif::ProgressStyle::default_bar()
+                    .template("{spinner:.green} [{elapsed_precise}] [{wide_bar:.cyan/blue}] {pos:>7}/{len:7} blocks")
+                    .progress_chars("=> "),
+            );
+            if ctx.verbosity == Verbosity::Quiet {
+                progbar.set_draw_target(indicatif::ProgressDrawTarget::hidden());
+            }
+            let (controller, evm, filter_ref, progbar_ref) =
+                (&ctx.controller, &ctx.evm, &filter, &progbar);
+            let logs = ctx.rt.block_on(
+                stream::iter(from..=to)
+                    .map(|h| async move {
+                        let block = controller
+                            .get_block_by_number(h)
+                            .await
+                            .with_context(|| format!("failed to get block at height `{h}`"))?;
+                        let mut logs = vec![];
+                        for tx_hash in block_tx_hashes(&block)? {
+                            let receipt = evm.get_receipt(tx_hash).await.with_context(|| {
+                                format!("failed to get receipt of tx `{}`", hex(&tx_hash))
+                            })?;
+                            logs.extend(receipt.logs.into_iter().filter(|log| filter_ref.matches(log)));
+                        }
+                        progbar_ref.inc(1);
+                        anyhow::Ok(logs)
+                    })
+                    .buffered(concurrency.max(1))
+                    .try_concat(),
+            );
+            progbar.finish_and_clear();
+            let logs = logs??;
+
+            let contract_abis = match (&abi, filter.address) {
+                (Some(abi), Some(addr)) => {
+                    ctx.abi_cache.insert(addr, abi.clone());
+                    HashMap::new()
+                }
+                (Some(_), None) => HashMap::new(),
+                (None, _) => ctx.rt.block_on(emitter_abis(ctx, &logs))??,
+            };
+            let decoded = logs
+                .iter()
+                .map(|log| {
+                    let abi = abi.as_deref().or_else(|| {
+                        let addr = Address::try_from_slice(&log.address).ok()?;
+                        contract_abis.get(&addr).map(|abi| abi.as_ref())
+                    });
+                    DecodedLog { log, abi }.to_json(ctx.display_options())
+                })
+                .collect::<Vec<_>>();
+            ctx.emit(&Json::Array(decoded));
             Ok(())
         })
 }
+
+pub fn abi_cache<'help, Co, Ex, Ev>() -> Command<'help, Context<Co, Ex, Ev>> {
+    Command::<Context<Co, Ex, Ev>>::new("abi-cache")
+        .about("Manage the contract ABIs cached by `get receipt` in this session")
+        .subcommand_required_else_help(true)
+        .subcommand(
+            Command::<Context<Co, Ex, Ev>>::new("clear")
+                .about("Forget the cached ABIs, e.g. after a new ABI is stored")
+                .handler(|_cmd, _m, ctx| {
+                    let n = ctx.abi_cache.len();
+                    ctx.abi_cache.clear();
+                    info!(ctx.verbosity, "{n} cached ABI(s) cleared");
+                    Ok(())
+                }),
+        )
+}
+
+pub fn evm_cmd<'help, Co, Ex, Ev>() -> Command<'help, Context<Co, Ex, Ev>>
+where
+    Co: ControllerBehaviour + Send + Sync,
+    Ev: EvmBehaviour,
+{
+    Command::<Context<Co, Ex, Ev>>::new("evm")
+        .about("EVM contract commands")
+        .subcommand_required_else_help(true)
+        .subcommands([
+            deploy(),
+            store_abi(),
+            get_storage(),
+            get_logs(),
+            abi_cache(),
+        ])
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cmd::cldi_cmd;
+    use crate::core::mock::{context, recording_controller};
+    use crate::proto::{
+        blockchain::raw_transaction::Tx,
+        controller::SystemConfig,
+        evm::{Balance, Receipt},
+    };
+    use crate::utils::{canonical_abi, hex};
+
+    #[test]
+    fn test_get_balance_at() {
+        let cldi_cmd = cldi_cmd();
+        let (mut ctx, _temp_dir) = context();
+        ctx.controller
+            .expect_get_block_number()
+            .returning(|_| Ok(100));
+        ctx.evm
+            .expect_get_balance()
+            .times(2)
+            .returning(|_| Ok(Balance::default()));
+
+        cldi_cmd
+            .exec_from(["cldi", "get", "balance"], &mut ctx)
+            .unwrap();
+        cldi_cmd
+            .exec_from(["cldi", "get", "balance", "--at", "100"], &mut ctx)
+            .unwrap();
+        let err = cldi_cmd
+            .exec_from(["cldi", "get", "balance", "--at", "99"], &mut ctx)
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "node does not support historical queries, only the current height `100` is available"
+        );
+    }
+
+    #[test]
+    fn test_get_receipt_abi_cache() {
+        use crate::core::error::SdkError;
+        use crate::proto::{
+            blockchain::{RawTransaction, Transaction, UnverifiedTransaction},
+            evm::{ByteAbi, Log},
+        };
+
+        let cldi_cmd = cldi_cmd();
+        let (mut ctx, temp_dir) = context();
+        let abi = r#"[{"type":"event","name":"Ping","anonymous":false,"inputs":[]}]"#;
+        let signature = ethabi::Contract::load(abi.as_bytes())
+            .unwrap()
+            .event("Ping")
+            .unwrap()
+            .signature();
+        ctx.evm.expect_get_receipt().returning(move |_| {
+            Ok(Receipt {
+                logs: vec![Log {
+                    address: vec![0xaa; 20],
+                    topics: vec![signature.as_bytes().to_vec()],
+                    ..Default::default()
+                }],
+                ..Default::default()
+            })
+        });
+        ctx.controller.expect_get_tx().returning(|_| {
+            Ok(RawTransaction {
+                tx: Some(Tx::NormalTx(UnverifiedTransaction {
+                    transaction: Some(Transaction {
+                        to: vec![0xaa; 20],
+                        ..Default::default()
+                    }),
+                    ..Default::default()
+                })),
+            })
+        });
+        ctx.evm.expect_get_abi().times(1).returning(move |_| {
+            Ok(ByteAbi {
+                bytes_abi: abi.as_bytes().to_vec(),
+            })
+        });
+
+        let abi_file = temp_dir.path().join("abi.json");
+        std::fs::write(&abi_file, abi).unwrap();
+        let abi_file = abi_file.to_str().unwrap();
+        let tx_hash = hex(&[1; 32]);
+        // The `--abi` is remembered for the callee, so the ABI on chain isn't fetched
+        // until the cache is cleared, and it's fetched only once after that.
+        for (abi_arg, clear) in [(true, false), (false, false), (false, true), (false, false)] {
+            if clear {
+                cldi_cmd
+                    .exec_from(["cldi", "evm", "abi-cache", "clear"], &mut ctx)
+                    .unwrap();
+            }
+            let mut args = vec!["cldi", "get", "receipt", &tx_hash];
+            if abi_arg {
+                args.extend(["--abi", abi_file]);
+            }
+            let (res, outputs) = ctx.capture(|ctx| cldi_cmd.exec_from(args, ctx));
+            res.unwrap();
+            assert_eq!(outputs[0]["logs"][0]["event"], "Ping()");
+        }
+
+        // A missing ABI is left out, but a dry run stops at its request.
+        ctx.evm.checkpoint();
+        ctx.evm.expect_get_receipt().returning(|_| {
+            Ok(Receipt {
+                logs: vec![Log {
+                    address: vec![0xbb; 20],
+                    ..Default::default()
+                }],
+                ..Default::default()
+            })
+        });
+        ctx.evm.expect_get_abi().returning(|_| {
+            Err(SdkError::DryRun {
+                service: "evm".into(),
+                method: "get_abi".into(),
+                request: serde_json::json!({}),
+            })
+        });
+        let (res, outputs) =
+            ctx.capture(|ctx| cldi_cmd.exec_from(["cldi", "get", "receipt", &tx_hash], ctx));
+        res.unwrap();
+        assert_eq!(
+            outputs,
+            [serde_json::json!({ "method": "evm.get_abi", "request": {} })]
+        );
+    }
+
+    #[test]
+    fn test_get_logs() {
+        use crate::proto::{
+            blockchain::{CompactBlock, CompactBlockBody},
+            evm::{ByteAbi, Log},
+        };
+
+        let cldi_cmd = cldi_cmd();
+        let (mut ctx, _temp_dir) = context();
+        let abi = r#"[{"type":"event","name":"Ping","anonymous":false,"inputs":[]}]"#;
+        let signature = ethabi::Contract::load(abi.as_bytes())
+            .unwrap()
+            .event("Ping")
+            .unwrap()
+            .signature();
+        ctx.controller
+            .expect_get_block_number()
+            .returning(|_| Ok(3));
+        ctx.controller.expect_get_block_by_number().returning(|h| {
+            Ok(CompactBlock {
+                body: Some(CompactBlockBody {
+                    tx_hashes: vec![vec![h as u8; 32]],
+                }),
+                ..Default::default()
+            })
+        });
+        ctx.evm.expect_get_receipt().returning(move |tx_hash| {
+            let log = |address: u8, topic: &[u8]| Log {
+                address: vec![address; 20],
+                topics: vec![topic.to_vec()],
+                block_number: tx_hash[0] as u64,
+                ..Default::default()
+            };
+            Ok(Receipt {
+                logs: vec![
+                    log(0xaa, signature.as_bytes()),
+                    log(0xaa, &[0; 32]),
+                    log(0xbb, signature.as_bytes()),
+                ],
+                ..Default::default()
+            })
+        });
+        ctx.evm.expect_get_abi().times(1).returning(move |_| {
+            Ok(ByteAbi {
+                bytes_abi: abi.as_bytes().to_vec(),
+            })
+        });
+
+        let (res, outputs) = ctx.capture(|ctx| {
+            cldi_cmd.exec_from(
+                [
+                    "cldi",
+                    "evm",
+                    "logs",
+                    "--from",
+                    "2",
+                    "--address",
+                    &hex(&[0xaa; 20]),
+                    "--topic0",
+                    &hex(signature.as_bytes()),
+                ],
+                ctx,
+            )
+        });
+        res.unwrap();
+        let logs = outputs[0].as_array().unwrap();
+        assert_eq!(logs.len(), 2);
+        assert_eq!(logs[0]["block_number"], 2);
+        assert_eq!(logs[1]["block_number"], 3);
+        assert_eq!(logs[1]["event"], "Ping()");
+
+        let err = cldi_cmd
+            .exec_from(
+                [
+                    "cldi",
+                    "evm",
+                    "logs",
+                    "--from",
+                    "0",
+                    "--to",
+                    "20",
+                    "--max-blocks",
+                    "5",
+                ],
+                &mut ctx,
+            )
+            .unwrap_err();
+        assert!(err.to_string().contains("more than `--max-blocks`"));
+    }
+
+    #[test]
+    fn test_get_storage() {
+        use crate::core::error::SdkError;
+        use crate::proto::common::Hash as CloudHash;
+        use serde_json::json;
+        use tonic::Status;
+
+        let cldi_cmd = cldi_cmd();
+        let (mut ctx, _temp_dir) = context();
+        ctx.evm
+            .expect_get_storage_at()
+            .times(2)
+            .returning(|_addr, position| {
+                assert_eq!(position[31], 0x10);
+                let mut value = vec![0; 32];
+                value[31] = 42;
+                Ok(CloudHash { hash: value })
+            });
+
+        let addr = "0x".to_string() + &"11".repeat(20);
+        for slot in ["16", "0x10"] {
+            let (res, outputs) = ctx.capture(|ctx| {
+                cldi_cmd.exec_from(["cldi", "evm", "get-storage", &addr, slot], ctx)
+            });
+            res.unwrap();
+            assert_eq!(
+                outputs,
+                [json!({
+                    "hex": format!("0x{}2a", "00".repeat(31)),
+                    "decimal": "42",
+                })]
+            );
+        }
+        assert!(cldi_cmd
+            .exec_from(["cldi", "evm", "get-storage", &addr, "0xzz"], &mut ctx)
+            .is_err());
+
+        ctx.evm.checkpoint();
+        ctx.evm
+            .expect_get_storage_at()
+            .returning(|_, _| Err(SdkError::from_status("evm", 1, Status::unimplemented(""))));
+        let err = cldi_cmd
+            .exec_from(["cldi", "evm", "get-storage", &addr, "0"], &mut ctx)
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "evm doesn't support this request, maybe the node is an older version?"
+        );
+    }
+
+    #[test]
+    fn test_deploy() {
+        let cldi_cmd = cldi_cmd();
+        let (mut ctx, _temp_dir) = context();
+        let (mut controller, sent) = recording_controller(SystemConfig::default());
+        controller.expect_get_block_number().returning(|_| Ok(100));
+        ctx.controller = controller;
+        ctx.evm.expect_get_receipt().returning(|_| {
+            Ok(Receipt {
+                contract_address: vec![0xab; 20],
+                ..Default::default()
+            })
+        });
+
+        let abi = r#"[{"type":"constructor","inputs":[{"name":"x","type":"uint256"}],"stateMutability":"nonpayable"}]"#;
+        let (res, outputs) = ctx.capture(|ctx| {
+            cldi_cmd.exec_from(
+                [
+                    "cldi",
+                    "evm",
+                    "deploy",
+                    "--bytecode",
+                    "0x6080",
+                    "--abi",
+                    abi,
+                    "--args",
+                    "42",
+                ],
+                ctx,
+            )
+        });
+        res.unwrap();
+        assert_eq!(outputs[0]["contract_addr"], hex(&[0xab; 20]));
+
+        let data_of = |i: usize| {
+            let raw = sent.lock().unwrap()[i].clone();
+            let Some(Tx::NormalTx(tx)) = raw.tx else {
+                panic!("expected a normal tx");
+            };
+            tx.transaction.unwrap().data
+        };
+        let mut arg = [0; 32];
+        arg[31] = 42;
+        assert_eq!(data_of(0), [&[0x60, 0x80][..], &arg].concat());
+        let stored_abi = canonical_abi(abi.as_bytes()).unwrap();
+        assert_eq!(data_of(1), [&[0xab; 20][..], &stored_abi].concat());
+
+        assert!(cldi_cmd
+            .exec_from(
+                [
+                    "cldi",
+                    "evm",
+                    "deploy",
+                    "--bytecode",
+                    "0x6080",
+                    "--abi",
+                    abi,
+                    "--args",
+                    "1",
+                    "2"
+                ],
+                &mut ctx
+            )
+            .is_err());
+    }
+
+    #[test]
+    fn test_wait_confirmations() {
+        use std::collections::VecDeque;
+        use std::sync::Mutex;
+
+        let cldi_cmd = cldi_cmd();
+        // The controller returns these hashes for the tx's block in order, the last one repeats.
+        let context_with = |hashes: &[u8]| {
+            let (mut ctx, temp_dir) = context();
+            let (mut controller, _sent) = recording_controller(SystemConfig::default());
+            controller.expect_get_block_number().returning(|_| Ok(102));
+            let hashes = Mutex::new(hashes.iter().copied().collect::<VecDeque<_>>());
+            controller.expect_get_block_hash().returning(move |_| {
+                let mut hashes = hashes.lock().unwrap();
+                let hash = if hashes.len() > 1 {
+                    hashes.pop_front().unwrap()
+                } else {
+                    hashes[0]
+                };
+                Ok([hash; 32])
+            });
+            ctx.controller = controller;
+            // The executor's hash of the block is different from the controller's.
+            ctx.evm.expect_get_receipt().returning(|tx_hash| {
+                Ok(Receipt {
+                    transaction_hash: tx_hash.to_vec(),
+                    block_number: 90,
+                    block_hash: vec![9; 32],
+                    ..Default::default()
+                })
+            });
+            (ctx, temp_dir)
+        };
+
+        let to = "0x".to_string() + &"11".repeat(20);
+        let send = |confirmations: &str, ctx: &mut _| {
+            cldi_cmd.exec_from(
+                [
+                    "cldi",
+                    "send",
+                    &to,
+                    "--wait",
+                    "--wait-timeout",
+                    "0",
+                    "--confirmations",
+                    confirmations,
+                ],
+                ctx,
+            )
+        };
+        // The tx is in block 90 and the current height is 102.
+        let (mut ctx, _temp_dir) = context_with(&[1]);
+        send("12", &mut ctx).unwrap();
+
+        let err = send("13", &mut ctx).unwrap_err();
+        assert!(err.to_string().contains("has 12 of 13 confirmations"));
+
+        let (mut ctx, _temp_dir) = context_with(&[1, 2]);
+        let err = send("12", &mut ctx).unwrap_err();
+        assert!(err.to_string().contains("is reorged out"));
+    }
+}
@@ -12,21 +12,53 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use anyhow::Context as _;
-use clap::Arg;
+use anyhow::{bail, ensure, Context as _, Result};
+use clap::{Arg, ArgMatches};
+use futures::{stream, StreamExt, TryStreamExt};
+use serde_json::{json, Value as Json};
 use std::net::IpAddr;
+use std::num::NonZeroU64;
+use std::path::Path;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 use tokio::try_join;
 
 use crate::{
-    cmd::{evm::store_abi, Command},
+    cmd::{
+        evm::{
+            auto_quota_args, concurrency_arg, confirmations_arg, function_arg, function_args_arg,
+            function_data_of, maybe_wait_receipt, nonce_of, nonce_strategy_arg, quota_arg,
+            quota_for, show_quota_arg, store_abi, strict_nonce_arg, valid_until_arg,
+            valid_until_of, wait_arg, wait_timeout_arg,
+        },
+        Command,
+    },
+    config::CryptoType,
     core::{
         context::Context,
-        controller::{ControllerBehaviour, TransactionSenderBehaviour},
+        controller::{
+            self, verify_raw_tx, AddNodeStatus, ControllerBehaviour, ControllerBehaviourExt,
+            TimeBound, TransactionSenderBehaviour, SYSTEM_CONFIG_FIELDS,
+        },
+        error::{is_dry_run, SdkError, SdkResult},
+        evm::EvmBehaviour,
         executor::ExecutorBehaviour,
     },
-    crypto::ArrayLike,
-    display::Display,
-    utils::{get_block_height_at, parse_addr, parse_data, parse_hash, parse_position, parse_value},
+    crypto::{ArrayLike, EthCrypto, Hash, SmCrypto},
+    display::{
+        check_raw_tx, BlockStats, Display, DisplayOptions, FieldValue, FullBlock, Genesis,
+        OutputFormat,
+    },
+    log::{info, warning, Verbosity},
+    proto::{
+        blockchain::{raw_transaction::Tx, CompactBlock, RawTransaction},
+        evm::Receipt,
+    },
+    utils::{
+        be_bytes_to_decimal, display_addr, display_time, hex, parse_binary_input, parse_block_id,
+        parse_data, parse_data_input, parse_hash, parse_height_range, parse_multiaddr, parse_nonce,
+        parse_time, parse_value, safe_save, validate_addr, validate_binary_input,
+        validate_data_input, BlockId,
+    },
 };
 
 pub fn call_executor<'help, Co, Ex, Ev>() -> Command<'help, Context<Co, Ex, Ev>>
@@ -34,39 +66,48 @@ where
     Ex: ExecutorBehaviour,
 {
     Command::<Context<Co, Ex, Ev>>::new("call-executor")
-        .about("Call executor")
+        .about("Call executor, it's read-only and runs the contract without sending a tx")
         .arg(
             Arg::new("from")
                 .help("default to use current account address")
                 .short('f')
                 .long("from")
                 .takes_value(true)
-                .validator(parse_addr),
+                .validator(validate_addr),
         )
         .arg(
             Arg::new("to")
                 .help("the target contract address")
                 .required(true)
                 .takes_value(true)
-                .validator(parse_addr),
+                .validator(validate_addr),
         )
         .arg(
             Arg::new("data")
                 .help("the data of this call request")
-                .required(true)
+                .required_unless_present("function")
                 .takes_value(true)
                 .validator(parse_data),
         )
+        .arg(function_arg().conflicts_with("data"))
+        .arg(function_args_arg())
         .handler(|_cmd, m, ctx| {
             let from = match m.value_of("from") {
-                Some(from) => parse_addr(from).unwrap(),
+                Some(from) => ctx.parse_addr(from)?,
                 None => *ctx.current_account()?.address(),
             };
-            let to = parse_addr(m.value_of("to").unwrap())?;
-            let data = parse_data(m.value_of("data").unwrap())?;
+            let to = ctx.parse_addr(m.value_of("to").unwrap())?;
+            let data = match function_data_of(m)? {
+                Some(data) => data,
+                None => parse_data(m.value_of("data").unwrap())?,
+            };
 
-            let resp = ctx.rt.block_on(ctx.executor.call(from, to, data))??;
-            println!("{}", resp.display());
+            let resp = match ctx.rt.block_on(ctx.executor.call(from, to, data))? {
+                Ok(resp) => resp,
+                Err(SdkError::Reverted { reason, .. }) => bail!("call reverted: {reason}"),
+                Err(e) => return Err(e.into()),
+            };
+            ctx.emit(&resp);
             Ok(())
         })
 }
@@ -74,6 +115,8 @@ where
 pub fn send_tx<'help, Co, Ex, Ev>() -> Command<'help, Context<Co, Ex, Ev>>
 where
     Co: ControllerBehaviour + Send + Sync,
+    Ex: ExecutorBehaviour,
+    Ev: EvmBehaviour,
 {
     Command::<Context<Co, Ex, Ev>>::new("send-tx")
         .about("Send transaction")
@@ -82,60 +125,107 @@ where
                 .help("the target address of this tx")
                 .takes_value(true)
                 .required(true)
-                .validator(parse_addr),
+                .validator(validate_addr),
         )
         .arg(
             Arg::new("data")
-                .help("the data of this tx")
+                .help("the data of this tx, hex or `@<path>` to read hex from file, `-` for stdin")
                 .takes_value(true)
                 .default_value("0x")
-                .validator(parse_data),
+                .validator(validate_data_input),
+        )
+        .arg(
+            Arg::new("data-binary")
+                .help("read the raw binary data of this tx from `@<path>` or `-` for stdin, instead of hex")
+                .long("data-binary")
+                .takes_value(true)
+                .conflicts_with("data")
+                .validator(validate_binary_input),
         )
+        .arg(function_arg().conflicts_with_all(&["data", "data-binary"]))
+        .arg(function_args_arg())
         .arg(
             Arg::new("value")
-                .help("the value of this tx")
+                .help("the value of this tx, e.g. `1.5ether`, `200gwei`, `100`(wei) or `0x64`")
                 .short('v')
                 .long("value")
                 .takes_value(true)
                 .default_value("0x0")
                 .validator(parse_value),
         )
+        .arg(quota_arg())
+        .args(auto_quota_args())
+        .arg(valid_until_arg())
         .arg(
-            Arg::new("quota")
-                .help("the quota of this tx")
-                .short('q')
-                .long("quota")
+            Arg::new("nonce")
+                .help("the nonce of this tx, `rand` for a random one. To replace a pending tx, resend it with the same nonce and a lower quota")
+                .long("nonce")
                 .takes_value(true)
-                .default_value("3000000")
-                .validator(str::parse::<u64>),
+                .default_value("rand")
+                .validator(parse_nonce),
         )
+        .arg(nonce_strategy_arg().conflicts_with("nonce"))
+        .arg(strict_nonce_arg().conflicts_with("nonce-strategy"))
         .arg(
-            Arg::new("valid-until-block")
-                .help("this tx is valid until the given block height. `+h` means `<current-height> + h`")
-                .long("until")
+            Arg::new("chain-id")
+                .help("the expected chain id, it errors if the node's chain id is different. The tx always uses the node's chain id")
+                .long("chain-id")
                 .takes_value(true)
-                .default_value("+95")
-                .validator(parse_position),
+                .validator(parse_hash),
+        )
+        .arg(
+            Arg::new("simulate")
+                .help("Execute the tx by calling executor instead of sending it. No signing nor state commit. Unlike the global `--dry-run`, which prints the gRPC request, it runs the tx")
+                .long("simulate"),
         )
+        .arg(wait_arg().conflicts_with("simulate"))
+        .arg(wait_timeout_arg())
+        .arg(confirmations_arg())
+        .arg(show_quota_arg())
         .handler(|_cmd, m, ctx| {
             ctx.rt.block_on(async {
-                let to = parse_addr(m.value_of("to").unwrap())?.to_vec();
-                let data = parse_data(m.value_of("data").unwrap())?;
-                let value = parse_value(m.value_of("value").unwrap())?.to_vec();
-                let quota = m.value_of("quota").unwrap().parse::<u64>()?;
-                let valid_until_block = {
-                    let pos = parse_position(m.value_of("valid-until-block").unwrap())?;
-                    get_block_height_at(&ctx.controller, pos).await?
+                let to = ctx.parse_addr(m.value_of("to").unwrap())?.to_vec();
+                let data = match (function_data_of(m)?, m.value_of("data-binary")) {
+                    (Some(data), _) => data,
+                    (None, Some(input)) => parse_binary_input(input)?,
+                    (None, None) => parse_data_input(m.value_of("data").unwrap())?,
                 };
+                let value = parse_value(m.value_of("value").unwrap())?.to_vec();
 
-                let signer = ctx.current_account()?;
-                let tx_hash = ctx
-                    .controller
-                    .send_tx(signer, to, data, value, quota, valid_until_block)
-                    .await?;
-                println!("{}", tx_hash.display());
+                if m.is_present("simulate") {
+                    // The executor's call request has no value field, simulating with a zero value
+                    // would be misleading.
+                    ensure!(
+                        value.iter().all(|&b| b == 0),
+                        "simulating doesn't support tx with non-zero value"
+                    );
+                    // Don't require the account to be unlocked since we won't sign it.
+                    let from = *ctx.wallet.get(&ctx.current_setting.account_name)?.address();
+                    let to = ctx.parse_addr(m.value_of("to").unwrap())?;
+                    let resp = ctx
+                        .executor
+                        .call(from, to, data)
+                        .await
+                        .context("simulation failed, this tx would probably fail")?;
+                    ctx.emit(&resp);
+                    return anyhow::Ok(());
+                }
 
-                anyhow::Ok(())
+                let quota = quota_for(ctx, m, &to, &data).await?;
+                let valid_until_block =
+                    ctx.valid_until_block(valid_until_of(ctx, m)?).await?;
+                let nonce = nonce_of(ctx, m).await?;
+                if let Some(chain_id) = m.value_of("chain-id") {
+                    ctx.check_chain_id(&parse_hash(chain_id)?).await?;
+                }
+                let signer = ctx.signer().await?;
+
+                let tx = ctx
+                    .build_tx(to, data, value, quota, valid_until_block, nonce)
+                    .await?;
+                let tx_hash = ctx.controller.send_raw_tx(signer, tx).await?;
+                ctx.emit(&tx_hash);
+                maybe_wait_receipt(ctx, m, tx_hash, Some(quota)).await
             })??;
             Ok(())
         })
@@ -144,61 +234,81 @@ where
 pub fn create_contract<'help, Co, Ex, Ev>() -> Command<'help, Context<Co, Ex, Ev>>
 where
     Co: ControllerBehaviour + Send + Sync,
+    Ev: EvmBehaviour,
 {
     Command::<Context<Co, Ex, Ev>>::new("create-contract")
         .about("create an EVM contract")
         .arg(
             Arg::new("data")
-                .help("the data of this tx")
+                .help("the data of this tx, hex or `@<path>` to read hex from file, `-` for stdin")
                 .takes_value(true)
-                .required(true)
-                .validator(parse_data),
+                .required_unless_present("data-binary")
+                .validator(validate_data_input),
+        )
+        .arg(
+            Arg::new("data-binary")
+                .help("read the raw binary data of this tx from `@<path>` or `-` for stdin, instead of hex")
+                .long("data-binary")
+                .takes_value(true)
+                .conflicts_with("data")
+                .validator(validate_binary_input),
         )
         .arg(
             Arg::new("value")
-                .help("the value of this tx")
+                .help("the value of this tx, e.g. `1.5ether`, `200gwei`, `100`(wei) or `0x64`")
                 .short('v')
                 .long("value")
                 .takes_value(true)
                 .default_value("0x0")
                 .validator(parse_value),
         )
+        .arg(quota_arg())
+        .args(auto_quota_args())
+        .arg(valid_until_arg())
         .arg(
-            Arg::new("quota")
-                .help("the quota of this tx")
-                .short('q')
-                .long("quota")
+            Arg::new("nonce")
+                .help("the nonce of this tx, `rand` for a random one. To replace a pending tx, resend it with the same nonce and a lower quota")
+                .long("nonce")
                 .takes_value(true)
-                .default_value("3000000")
-                .validator(str::parse::<u64>),
+                .default_value("rand")
+                .validator(parse_nonce),
         )
+        .arg(nonce_strategy_arg().conflicts_with("nonce"))
+        .arg(strict_nonce_arg().conflicts_with("nonce-strategy"))
         .arg(
-            Arg::new("valid-until-block")
-                .help("this tx is valid until the given block height. `+h` means `<current-height> + h`")
-                .long("until")
+            Arg::new("chain-id")
+                .help("the expected chain id, it errors if the node's chain id is different. The tx always uses the node's chain id")
+                .long("chain-id")
                 .takes_value(true)
-                .default_value("+95")
-                .validator(parse_position),
+                .validator(parse_hash),
         )
+        .arg(wait_arg())
+        .arg(wait_timeout_arg())
+        .arg(confirmations_arg())
+        .arg(show_quota_arg())
         .handler(|_cmd, m, ctx| {
             ctx.rt.block_on(async {
                 let to = vec![];
-                let data = parse_data(m.value_of("data").unwrap())?;
-                let value = parse_value(m.value_of("value").unwrap())?.to_vec();
-                let quota = m.value_of("quota").unwrap().parse::<u64>()?;
-                let valid_until_block = {
-                    let pos = parse_position(m.value_of("valid-until-block").unwrap())?;
-                    get_block_height_at(&ctx.controller, pos).await?
+                let data = match m.value_of("data-binary") {
+                    Some(input) => parse_binary_input(input)?,
+                    None => parse_data_input(m.value_of("data").unwrap())?,
                 };
+                let value = parse_value(m.value_of("value").unwrap())?.to_vec();
+                let quota = quota_for(ctx, m, &to, &data).await?;
+                let valid_until_block =
+                    ctx.valid_until_block(valid_until_of(ctx, m)?).await?;
+                let nonce = nonce_of(ctx, m).await?;
+                if let Some(chain_id) = m.value_of("chain-id") {
+                    ctx.check_chain_id(&parse_hash(chain_id)?).await?;
+                }
+                let signer = ctx.signer().await?;
 
-                let signer = ctx.current_account()?;
-                let tx_hash = ctx
-                    .controller
-                    .send_tx(signer, to, data, value, quota, valid_until_block)
+                let tx = ctx
+                    .build_tx(to, data, value, quota, valid_until_block, nonce)
                     .await?;
-                println!("{}", tx_hash.display());
-
-                anyhow::Ok(())
+                let tx_hash = ctx.controller.send_raw_tx(signer, tx).await?;
+                ctx.emit(&tx_hash);
+                maybe_wait_receipt(ctx, m, tx_hash, Some(quota)).await
             })??;
             Ok(())
         })
@@ -212,7 +322,7 @@ where
         .about("Get version")
         .handler(|_cmd, _m, ctx| {
             let version = ctx.rt.block_on(ctx.controller.get_version())??;
-            println!("{}", version);
+            ctx.emit(&version);
             Ok(())
         })
 }
@@ -223,44 +333,499 @@ where
 {
     Command::<Context<Co, Ex, Ev>>::new("get-system-config")
         .about("Get system config")
-        .handler(|_cmd, _m, ctx| {
-            let system_config = ctx.rt.block_on(ctx.controller.get_system_config())??;
-            println!("{}", system_config.display());
-            Ok(())
+        .arg(
+            Arg::new("watch")
+                .help("Keep polling the system config and print the changes, the current config is printed first as the baseline")
+                .short('w')
+                .long("watch"),
+        )
+        .arg(
+            Arg::new("interval")
+                .help("Polling interval in seconds for --watch")
+                .long("interval")
+                .takes_value(true)
+                .default_value("3")
+                .requires("watch")
+                .validator(str::parse::<u64>),
+        )
+        .arg(
+            Arg::new("field")
+                .help("Only print this field, e.g. `admin`")
+                .long("field")
+                .takes_value(true)
+                .possible_values(SYSTEM_CONFIG_FIELDS)
+                .conflicts_with("watch"),
+        )
+        .handler(|_cmd, m, ctx| {
+            let mut system_config = ctx.rt.block_on(ctx.controller.get_system_config())??;
+            if let Some(field) = m.value_of("field") {
+                ctx.emit(&FieldValue(system_config.to_json(ctx.display_options())[field].take()));
+                return Ok(());
+            }
+            ctx.emit(&system_config);
+            if !m.is_present("watch") {
+                return Ok(());
+            }
+
+            let interval = Duration::from_secs(m.value_of("interval").unwrap().parse().unwrap());
+            ctx.rt.block_on(async {
+                let mut interval = tokio::time::interval(interval);
+                // The first tick completes immediately.
+                interval.tick().await;
+                loop {
+                    interval.tick().await;
+                    let latest = match ctx.controller.get_system_config().await {
+                        Ok(latest) => latest,
+                        Err(e) => {
+                            warning!("failed to get system config: `{e}`");
+                            continue;
+                        }
+                    };
+                    let changes = controller::diff_system_config(&system_config, &latest, ctx.display_options());
+                    if changes.is_empty() {
+                        continue;
+                    }
+                    if ctx.output_format == OutputFormat::Text {
+                        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+                        ctx.emit_text(format!(
+                            "\nsystem config changed at {}:",
+                            display_time(now.as_millis() as u64)
+                        ));
+                    }
+                    for change in changes {
+                        ctx.emit(&change);
+                    }
+                    system_config = latest;
+                }
+            })?
         })
 }
 
 pub fn get_block<'help, Co, Ex, Ev>() -> Command<'help, Context<Co, Ex, Ev>>
 where
-    Co: ControllerBehaviour,
+    Co: ControllerBehaviour + Send + Sync,
 {
     Command::<Context<Co, Ex, Ev>>::new("get-block")
         .about("Get block by block height or hash(0x)")
         .arg(
             Arg::new("height_or_hash")
-                .help("plain decimal number or hash with `0x` prefix")
+                .help("`latest`, `pending`, decimal or `0x` prefixed hex height, or block hash with `0x` prefix")
                 .required(true)
                 .takes_value(true)
-                .validator(|s| {
-                    if s.starts_with("0x") {
-                        parse_hash(s)?;
-                    } else {
-                        s.parse::<u64>().context("cannot parse block number, if you want to get block by hash, please prefix it with `0x`")?;
-                    }
-                    anyhow::Ok(())
-                })
+                .validator(parse_block_id)
+        )
+        .arg(
+            Arg::new("full")
+                .help("fetch and expand the txs of this block")
+                .long("full"),
         )
+        .arg(
+            Arg::new("max-txs")
+                .help("expand at most this many txs with `--full`")
+                .long("max-txs")
+                .takes_value(true)
+                .requires("full")
+                .validator(str::parse::<usize>),
+        )
+        .arg(concurrency_arg().help("max number of concurrent requests when fetching txs with `--full`"))
         .handler(|_cmd, m, ctx| {
-            let s = m.value_of("height_or_hash").unwrap();
-            let block = if s.starts_with("0x") {
-                let hash = parse_hash(s)?;
-                ctx.rt.block_on(ctx.controller.get_block_by_hash(hash))??
+            let block_id = parse_block_id(m.value_of("height_or_hash").unwrap())?;
+            let c = &ctx.controller;
+            let cache = &ctx.block_cache;
+            let block = ctx.rt.block_on(async move {
+                match block_id {
+                    BlockId::Hash(hash) => cache.get_block_by_hash(c, hash).await,
+                    BlockId::Latest => {
+                        let height = c.get_block_number(false).await?;
+                        cache.get_block_by_number(c, height).await
+                    }
+                    BlockId::Pending => {
+                        let height = c.get_block_number(true).await?;
+                        cache.get_block_by_number(c, height).await
+                    }
+                    BlockId::Number(height) => {
+                        let tip = c.get_block_number(false).await?;
+                        ensure!(
+                            height <= tip,
+                            "block height `{}` is above the current block height `{}`",
+                            height,
+                            tip,
+                        );
+                        cache.get_block_by_number(c, height).await
+                    }
+                }
+            })??;
+
+            if ctx.output_format == OutputFormat::Bin {
+                ensure!(
+                    !m.is_present("full"),
+                    "`--full` doesn't support the `bin` output format"
+                );
+                return ctx.emit_bin(&block);
+            }
+            if m.is_present("full") {
+                let tx_hashes = block_tx_hashes(&block)?;
+                let max_txs = match m.value_of("max-txs") {
+                    Some(n) => n.parse::<usize>()?.min(tx_hashes.len()),
+                    None => tx_hashes.len(),
+                };
+                let concurrency = m.value_of("concurrency").unwrap().parse()?;
+                let txs = ctx
+                    .rt
+                    .block_on(ctx.controller.get_txs(&tx_hashes[..max_txs], concurrency))??;
+                let full_block = FullBlock {
+                    block: &block,
+                    txs,
+                    omitted: tx_hashes.len() - max_txs,
+                };
+                ctx.emit(&full_block);
             } else {
-                let height = s.parse()?;
-                ctx.rt.block_on(ctx.controller.get_block_by_number(height))??
+                ctx.emit(&block);
+            }
+            Ok(())
+        })
+}
+
+pub fn genesis<'help, Co, Ex, Ev>() -> Command<'help, Context<Co, Ex, Ev>>
+where
+    Co: ControllerBehaviour + Send + Sync,
+{
+    Command::<Context<Co, Ex, Ev>>::new("genesis")
+        .about("Get the genesis block, with the genesis config that hasn't been changed since")
+        .handler(|_cmd, _m, ctx| {
+            let c = &ctx.controller;
+            let (block, hash, system_config) = ctx.rt.block_on(async {
+                try_join!(
+                    c.get_block_by_number(0),
+                    c.get_block_hash(0),
+                    c.get_system_config()
+                )
+            })??;
+            ctx.emit(&Genesis {
+                block: &block,
+                hash,
+                system_config: &system_config,
+            });
+            Ok(())
+        })
+}
+
+pub fn block_tx_hashes(block: &CompactBlock) -> Result<Vec<Hash>> {
+    block
+        .body
+        .as_ref()
+        .map(|body| body.tx_hashes.as_slice())
+        .unwrap_or_default()
+        .iter()
+        .map(|h| Hash::try_from_slice(h))
+        .collect::<Result<Vec<Hash>>>()
+        .context("the block contains invalid tx hashes")
+}
+
+pub fn get_blocks<'help, Co, Ex, Ev>() -> Command<'help, Context<Co, Ex, Ev>>
+where
+    Co: ControllerBehaviour + Send + Sync,
+{
+    Command::<Context<Co, Ex, Ev>>::new("get-blocks")
+        .about("Get blocks of the heights in [from, to]")
+        .arg(
+            Arg::new("from")
+                .help("the first height")
+                .long("from")
+                .takes_value(true)
+                .required(true)
+                .validator(str::parse::<u64>),
+        )
+        .arg(
+            Arg::new("to")
+                .help("the last height, inclusive")
+                .long("to")
+                .takes_value(true)
+                .required(true)
+                .validator(str::parse::<u64>),
+        )
+        .arg(
+            Arg::new("full")
+                .help("fetch and expand the txs of the blocks")
+                .long("full"),
+        )
+        .arg(concurrency_arg())
+        .arg(
+            Arg::new("format")
+                .help("`json` for a JSON array, `ndjson` for one compact JSON block per line")
+                .long("format")
+                .takes_value(true)
+                .possible_values(["json", "ndjson"])
+                .default_value("json"),
+        )
+        .args(out_file_args("write the blocks to this file instead of stdout"))
+        .arg(
+            Arg::new("max-range")
+                .help("refuse to fetch more blocks than this without `--force`")
+                .long("max-range")
+                .takes_value(true)
+                .default_value("1000")
+                .validator(str::parse::<u64>),
+        )
+        .arg(
+            Arg::new("force")
+                .help("fetch the blocks even if there are more than `--max-range`")
+                .long("force"),
+        )
+        .handler(|_cmd, m, ctx| {
+            let from = m.value_of("from").unwrap().parse::<u64>()?;
+            let to = m.value_of("to").unwrap().parse::<u64>()?;
+            let concurrency = m.value_of("concurrency").unwrap().parse::<usize>()?;
+            let max_range = m.value_of("max-range").unwrap().parse::<u64>()?;
+            ensure!(from <= to, "`from` must not be greater than `to`");
+            check_out_file(m)?;
+            ensure!(
+                to - from < max_range || m.is_present("force"),
+                "{} blocks are requested, more than `--max-range` {max_range}. Use `--force` if it's intended",
+                to - from + 1,
+            );
+
+            let opts = ctx.display_options();
+            let blocks = ctx.rt.block_on(async {
+                let blocks = ctx.controller.get_blocks(from..=to, concurrency).await?;
+                if !m.is_present("full") {
+                    return anyhow::Ok(blocks.into_iter().map(|b| b.map(|b| b.to_json(opts))).collect());
+                }
+                let mut full_blocks = vec![];
+                for block in blocks {
+                    let full_block = match block.and_then(|b| Ok((block_tx_hashes(&b)?, b))) {
+                        Ok((tx_hashes, block)) => {
+                            let txs = ctx.controller.get_txs(&tx_hashes, concurrency).await?;
+                            let full_block = FullBlock {
+                                block: &block,
+                                txs,
+                                omitted: 0,
+                            };
+                            Ok(full_block.to_json(opts))
+                        }
+                        Err(e) => Err(e),
+                    };
+                    full_blocks.push(full_block);
+                }
+                anyhow::Ok(full_blocks)
+            })??;
+            ensure!(blocks.iter().any(Result::is_ok), "all requests failed");
+
+            let blocks = (from..=to).zip(blocks).map(|(h, block)| match block {
+                Ok(block) => block,
+                Err(e) => json!({ "height": h, "error": format!("{e:#}") }),
+            });
+            // Plain JSON without the output format's colors, so that it's safe to write to a file.
+            let output = match m.value_of("format").unwrap() {
+                "ndjson" => blocks.map(|b| format!("{b}\n")).collect::<String>(),
+                _ => serde_json::to_string_pretty(&blocks.collect::<Vec<_>>())? + "\n",
             };
+            match m.value_of("out") {
+                Some(path) => save_out_file(m, path, &output)
+                    .with_context(|| format!("cannot write blocks to `{path}`"))?,
+                None => ctx.emit_text(output.trim_end()),
+            }
+            Ok(())
+        })
+}
+
+/// The `--out` of the commands that write plain data, e.g. a CSV, to a file instead of the
+/// output. Like the global `--out`, it doesn't overwrite an existing file without `--overwrite`.
+fn out_file_args(help: &str) -> [Arg<'_>; 2] {
+    [
+        Arg::new("out").help(help).long("out").takes_value(true),
+        Arg::new("overwrite")
+            .help("overwrite the file of `--out` if it exists")
+            .long("overwrite")
+            .requires("out"),
+    ]
+}
+
+/// Check the `--out` file of [`out_file_args`] before the data is fetched.
+fn check_out_file(m: &ArgMatches) -> Result<()> {
+    if let Some(path) = m.value_of("out") {
+        ensure!(
+            m.is_present("overwrite") || !Path::new(path).exists(),
+            "`{path}` already exists, use `--overwrite` to overwrite it"
+        );
+    }
+    Ok(())
+}
 
-            println!("{}", block.display());
+/// Save the `output` to the `--out` file of [`out_file_args`], see [`check_out_file`].
+fn save_out_file(m: &ArgMatches, path: &str, output: &str) -> Result<()> {
+    safe_save(path, output.as_bytes(), m.is_present("overwrite"))
+}
+
+/// Per-block tx counts in CSV, the height is the requested one in case the header is missing.
+fn block_stats_csv(rows: impl IntoIterator<Item = (u64, BlockStats)>) -> String {
+    let mut csv = String::from("height,tx_count,size,timestamp\n");
+    for (h, stats) in rows {
+        let timestamp = stats.timestamp.map(|ts| ts.to_string()).unwrap_or_default();
+        csv.push_str(&format!(
+            "{h},{},{},{timestamp}\n",
+            stats.tx_count, stats.size
+        ));
+    }
+    csv
+}
+
+pub fn tx_count_in_block<'help, Co, Ex, Ev>() -> Command<'help, Context<Co, Ex, Ev>>
+where
+    Co: ControllerBehaviour + Send + Sync,
+{
+    Command::<Context<Co, Ex, Ev>>::new("tx-count-in-block")
+        .alias("block-stats")
+        .about("Get the tx count, size, timestamp and proposer of a block, or a CSV of tx counts for `--range`")
+        .arg(
+            Arg::new("height")
+                .help("the block height")
+                .takes_value(true)
+                .required_unless_present("range")
+                .validator(str::parse::<u64>),
+        )
+        .arg(
+            Arg::new("range")
+                .help("print the CSV of the blocks in the inclusive range, e.g. `100-200`. Timestamps are in ms")
+                .long("range")
+                .takes_value(true)
+                .conflicts_with("height")
+                .validator(parse_height_range),
+        )
+        .arg(concurrency_arg())
+        .handler(|_cmd, m, ctx| {
+            if let Some(height) = m.value_of("height") {
+                let block = ctx
+                    .rt
+                    .block_on(ctx.controller.get_block_by_number(height.parse()?))??;
+                ctx.emit(&BlockStats::of(&block));
+                return Ok(());
+            }
+
+            let range = parse_height_range(m.value_of("range").unwrap())?;
+            let concurrency = m.value_of("concurrency").unwrap().parse::<usize>()?;
+            let blocks = ctx
+                .rt
+                .block_on(ctx.controller.get_blocks(range.clone(), concurrency))??;
+            let rows = range
+                .zip(blocks)
+                .map(|(h, block)| {
+                    let block = block.with_context(|| format!("failed to get block `{h}`"))?;
+                    anyhow::Ok((h, BlockStats::of(&block)))
+                })
+                .collect::<Result<Vec<_>>>()?;
+            ctx.emit_text(block_stats_csv(rows).trim_end());
+            Ok(())
+        })
+}
+
+/// Export the txs sent from or to the address in CSV.
+///
+/// The controller has no index by account, so this is a linear scan over all txs
+/// in the block range and may be slow for a large range.
+pub fn account_history<'help, Co, Ex, Ev>() -> Command<'help, Context<Co, Ex, Ev>>
+where
+    Co: ControllerBehaviour + Send + Sync,
+    Ev: EvmBehaviour,
+{
+    Command::<Context<Co, Ex, Ev>>::new("account-history")
+        .about("Export txs sent from or to the address in [from, to] as CSV. It scans all the txs, which may be slow")
+        .arg(
+            Arg::new("addr")
+                .help("the account address")
+                .takes_value(true)
+                .required(true)
+                .validator(validate_addr),
+        )
+        .arg(
+            Arg::new("from")
+                .help("the first height")
+                .long("from")
+                .takes_value(true)
+                .required(true)
+                .validator(str::parse::<u64>),
+        )
+        .arg(
+            Arg::new("to")
+                .help("the last height, inclusive")
+                .long("to")
+                .takes_value(true)
+                .required(true)
+                .validator(str::parse::<u64>),
+        )
+        .arg(concurrency_arg())
+        .args(out_file_args("write the CSV to this file instead of stdout"))
+        .handler(|_cmd, m, ctx| {
+            let addr = ctx.parse_addr(m.value_of("addr").unwrap())?;
+            let from = m.value_of("from").unwrap().parse::<u64>()?;
+            let to = m.value_of("to").unwrap().parse::<u64>()?;
+            let concurrency = m.value_of("concurrency").unwrap().parse::<usize>()?;
+            ensure!(from <= to, "`from` must not be greater than `to`");
+            check_out_file(m)?;
+
+            let progbar = indicatif::ProgressBar::new(to - from + 1);
+            progbar.set_style(
+                indicatif::ProgressStyle::default_bar()
+                    .template("{spinner:.green} [{elapsed_precise}] [{wide_bar:.cyan/blue}] {pos:>7}/{len:7} blocks")
+                    .progress_chars("=> "),
+            );
+            if ctx.verbosity == Verbosity::Quiet {
+                progbar.set_draw_target(indicatif::ProgressDrawTarget::hidden());
+            }
+            let (controller, evm, progbar_ref) = (&ctx.controller, &ctx.evm, &progbar);
+            // Each block fetches its txs one by one, so there are at most `concurrency` requests in flight.
+            let rows = ctx.rt.block_on(
+                stream::iter(from..=to)
+                    .map(|h| async move {
+                        let block = controller
+                            .get_block_by_number(h)
+                            .await
+                            .with_context(|| format!("failed to get block at height `{h}`"))?;
+                        let mut rows = vec![];
+                        for tx_hash in block_tx_hashes(&block)? {
+                            let raw = controller
+                                .get_tx(tx_hash)
+                                .await
+                                .with_context(|| format!("failed to get tx `{}`", hex(&tx_hash)))?;
+                            let Some(Tx::NormalTx(utx)) = &raw.tx else {
+                                continue;
+                            };
+                            let (Some(tx), Some(witness)) = (&utx.transaction, &utx.witness) else {
+                                continue;
+                            };
+                            if witness.sender != addr.as_slice() && tx.to != addr.as_slice() {
+                                continue;
+                            }
+                            let receipt = evm.get_receipt(tx_hash).await.with_context(|| {
+                                format!("failed to get receipt of tx `{}`", hex(&tx_hash))
+                            })?;
+                            let status = if receipt.error_message.is_empty() {
+                                "success"
+                            } else {
+                                "reverted"
+                            };
+                            rows.push(format!(
+                                "{h},{},{},{},{},{status}\n",
+                                hex(&tx_hash),
+                                display_addr(&witness.sender),
+                                display_addr(&tx.to),
+                                be_bytes_to_decimal(&tx.value),
+                            ));
+                        }
+                        progbar_ref.inc(1);
+                        anyhow::Ok(rows)
+                    })
+                    .buffered(concurrency.max(1))
+                    .try_concat(),
+            );
+            progbar.finish_and_clear();
+
+            let output = format!("block,hash,from,to,value,status\n{}", rows??.concat());
+            match m.value_of("out") {
+                Some(path) => save_out_file(m, path, &output)
+                    .with_context(|| format!("cannot write account history to `{path}`"))?,
+                None => ctx.emit_text(output.trim_end()),
+            }
             Ok(())
         })
 }
@@ -283,7 +848,7 @@ where
             let block_number = ctx
                 .rt
                 .block_on(ctx.controller.get_block_number(for_pending))??;
-            println!("{}", block_number);
+            ctx.emit(&block_number);
             Ok(())
         })
 }
@@ -303,8 +868,10 @@ where
         )
         .handler(|_cmd, m, ctx| {
             let height = m.value_of("height").unwrap().parse()?;
-            let hash = ctx.rt.block_on(ctx.controller.get_block_hash(height))??;
-            println!("{}", hash.display());
+            let hash = ctx
+                .rt
+                .block_on(ctx.block_cache.get_block_hash(&ctx.controller, height))??;
+            ctx.emit(&hash);
 
             Ok(())
         })
@@ -312,14 +879,66 @@ where
 
 pub fn get_tx<'help, Co, Ex, Ev>() -> Command<'help, Context<Co, Ex, Ev>>
 where
-    Co: ControllerBehaviour,
+    Co: ControllerBehaviour + Send + Sync,
 {
     Command::<Context<Co, Ex, Ev>>::new("get-tx")
         .about("Get transaction data by tx_hash")
-        .arg(Arg::new("tx_hash").required(true).validator(parse_hash))
+        .arg(
+            Arg::new("tx_hash")
+                .help("the tx hash. If multiple hashes are given, they are fetched concurrently")
+                .required(true)
+                .multiple_values(true)
+                .validator(parse_hash),
+        )
+        .arg(concurrency_arg().help("max number of concurrent requests when fetching multiple txs"))
         .handler(|_cmd, m, ctx| {
-            let s = m.value_of("tx_hash").unwrap();
-            let tx_hash = parse_hash(s)?;
+            let tx_hashes = m
+                .values_of("tx_hash")
+                .unwrap()
+                .map(parse_hash)
+                .collect::<Result<Vec<Hash>>>()?;
+            let check = match ctx.current_setting.crypto_type {
+                CryptoType::Sm => check_raw_tx::<SmCrypto>,
+                CryptoType::Eth => check_raw_tx::<EthCrypto>,
+            };
+            let crypto_type = ctx.current_setting.crypto_type;
+            let opts = ctx.display_options();
+            let checked = |tx: &RawTransaction, mut tx_json| {
+                if let Some(computed_hash) = check(tx, &mut tx_json, opts) {
+                    warning!(
+                        "tx `{}` mismatched its recomputed hash `{}`, is the crypto type `{crypto_type}` right?",
+                        tx_json["transaction"]["transaction_hash"].as_str().unwrap_or_default(),
+                        hex(&computed_hash),
+                    );
+                }
+                tx_json
+            };
+
+            if ctx.output_format == OutputFormat::Bin {
+                ensure!(
+                    tx_hashes.len() == 1,
+                    "the `bin` output format supports only one tx"
+                );
+                let tx = ctx.rt.block_on(ctx.controller.get_tx(tx_hashes[0]))??;
+                return ctx.emit_bin(&tx);
+            }
+            if tx_hashes.len() > 1 {
+                let concurrency = m.value_of("concurrency").unwrap().parse::<usize>()?;
+                let txs = ctx
+                    .rt
+                    .block_on(ctx.controller.get_txs(&tx_hashes, concurrency))??;
+                let txs = txs
+                    .iter()
+                    .map(|tx| match tx {
+                        Ok(tx) => checked(tx, tx.to_json(opts)),
+                        Err(e) => json!({ "error": format!("{e:#}") }),
+                    })
+                    .collect::<Vec<_>>();
+                ctx.emit(&json!(txs));
+                return Ok(());
+            }
+
+            let tx_hash = tx_hashes[0];
             let c = &ctx.controller;
             let tx_with_index = ctx.rt.block_on(async move {
                 try_join!(
@@ -328,8 +947,9 @@ where
                     c.get_tx_index(tx_hash),
                 )
             })??;
+            let tx_json = checked(&tx_with_index.0, tx_with_index.to_json(opts));
 
-            println!("{}", tx_with_index.display());
+            ctx.emit(&tx_json);
 
             Ok(())
         })
@@ -343,7 +963,7 @@ where
         .about("Get peer count")
         .handler(|_cmd, _m, ctx| {
             let peer_count = ctx.rt.block_on(ctx.controller.get_peer_count())??;
-            println!("{}", peer_count);
+            ctx.emit(&peer_count);
 
             Ok(())
         })
@@ -355,9 +975,25 @@ where
 {
     Command::<Context<Co, Ex, Ev>>::new("get-peers-info")
         .about("Get peers info")
-        .handler(|_cmd, _m, ctx| {
+        .arg(
+            Arg::new("json")
+                .help("output in JSON instead of a table, same as `-o json`")
+                .long("json"),
+        )
+        .handler(|_cmd, m, ctx| {
             let peers_info = ctx.rt.block_on(ctx.controller.get_peers_info())??;
-            println!("{}", peers_info.display());
+            if m.is_present("json") {
+                let opts = DisplayOptions {
+                    format: OutputFormat::Json,
+                    ..ctx.display_options()
+                };
+                let rendered = peers_info.display_with(opts);
+                ctx.output
+                    .borrow_mut()
+                    .emit(peers_info.to_json(opts), rendered);
+            } else {
+                ctx.emit(&peers_info);
+            }
 
             Ok(())
         })
@@ -372,69 +1008,1065 @@ where
         .arg(
             Arg::new("host")
                 .help("the host of the new node")
-                .required(true),
+                .required_unless_present("multiaddr"),
         )
         .arg(
             Arg::new("port")
                 .help("the port of the new node")
                 .validator(str::parse::<u16>)
-                .required(true),
+                .required_unless_present("multiaddr"),
         )
         .arg(Arg::new("tls").help("the domain name of the new node"))
+        .arg(
+            Arg::new("multiaddr")
+                .help("the multiaddr of the new node instead of host and port, e.g. `/dns4/host/tcp/40000/tls/host`")
+                .long("multiaddr")
+                .takes_value(true)
+                .validator(parse_multiaddr)
+                .conflicts_with_all(&["host", "port", "tls"]),
+        )
         .handler(|_cmd, m, ctx| {
-            let host = m.value_of("host").unwrap();
-            let port = m.value_of("port").unwrap().parse::<u64>().unwrap();
-            let tls = m.value_of("tls");
-
-            let ptcl = match host.parse::<std::net::IpAddr>() {
-                Ok(IpAddr::V4(_)) => "ip4",
-                Ok(IpAddr::V6(_)) => "ip6",
-                Err(_) => "dns4",
-            };
-
-            let multiaddr = if let Some(tls) = tls {
-                format!("/{ptcl}/{host}/tcp/{port}/tls/{tls}")
+            let multiaddr = if let Some(multiaddr) = m.value_of("multiaddr") {
+                multiaddr.to_string()
             } else {
-                format!("/{ptcl}/{host}/tcp/{port}")
+                let host = m.value_of("host").unwrap();
+                let port = m.value_of("port").unwrap().parse::<u64>().unwrap();
+                let tls = m.value_of("tls");
+
+                let ptcl = match host.parse::<std::net::IpAddr>() {
+                    Ok(IpAddr::V4(_)) => "ip4",
+                    Ok(IpAddr::V6(_)) => "ip6",
+                    Err(_) => "dns4",
+                };
+
+                let multiaddr = if let Some(tls) = tls {
+                    format!("/{ptcl}/{host}/tcp/{port}/tls/{tls}")
+                } else {
+                    format!("/{ptcl}/{host}/tcp/{port}")
+                };
+                // Catch a malformed host or tls domain before the node does.
+                parse_multiaddr(&multiaddr)?;
+                multiaddr
             };
 
-            let status = ctx.rt.block_on(ctx.controller.add_node(multiaddr))??;
-            // https://github.com/cita-cloud/status_code
-            if status == 0 {
-                println!("Success");
-            } else {
-                // I have no idea how to explain those status codes.
-                println!(
-                    "Failed with status code: `{}`, please check controler's log",
-                    status
-                );
-            }
+            let code = ctx.rt.block_on(ctx.controller.add_node(multiaddr))??;
+            let status = AddNodeStatus::from(code);
+            ctx.emit(&status);
 
             Ok(())
         })
 }
 
-pub fn rpc_cmd<'help, Co, Ex, Ev>() -> Command<'help, Context<Co, Ex, Ev>>
+pub fn find_block<'help, Co, Ex, Ev>() -> Command<'help, Context<Co, Ex, Ev>>
 where
     Co: ControllerBehaviour + Send + Sync,
-    Ex: ExecutorBehaviour,
 {
-    Command::<Context<Co, Ex, Ev>>::new("rpc")
-        .about("Other RPC commands")
-        .subcommand_required_else_help(true)
-        .subcommands([add_node(), store_abi()])
+    Command::<Context<Co, Ex, Ev>>::new("find-block")
+        .about("Find the height of the block at the boundary of a time by binary searching block timestamps")
+        .arg(
+            Arg::new("before")
+                .help("Find the last block before this RFC 3339 time, e.g. `2022-01-02T15:04:05+08:00`")
+                .long("before")
+                .takes_value(true)
+                .required_unless_present("after")
+                .validator(parse_time),
+        )
+        .arg(
+            Arg::new("after")
+                .help("Find the first block at or after this RFC 3339 time")
+                .long("after")
+                .alias("since")
+                .takes_value(true)
+                .conflicts_with("before")
+                .validator(parse_time),
+        )
+        .handler(|_cmd, m, ctx| {
+            let (time, bound) = match m.value_of("before") {
+                Some(time) => (time, TimeBound::Before),
+                None => (m.value_of("after").unwrap(), TimeBound::After),
+            };
+            let timestamp = parse_time(time)?;
+            let height = ctx
+                .rt
+                .block_on(ctx.controller.find_block_by_time(timestamp, bound))??;
+            match height {
+                Some(height) => ctx.emit(&height),
+                None => match bound {
+                    TimeBound::Before => bail!("no block before `{time}`"),
+                    TimeBound::After => bail!("no block at or after `{time}` yet"),
+                },
+            }
+            Ok(())
+        })
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::cmd::cldi_cmd;
-    use crate::core::mock::context;
+pub fn get_block_hashes<'help, Co, Ex, Ev>() -> Command<'help, Context<Co, Ex, Ev>>
+where
+    Co: ControllerBehaviour + Send + Sync,
+{
+    Command::<Context<Co, Ex, Ev>>::new("block-hashes")
+        .about("Get block hashes of the heights in [from, to]")
+        .arg(
+            Arg::new("from")
+                .help("the first height")
+                .long("from")
+                .takes_value(true)
+                .required(true)
+                .validator(str::parse::<u64>),
+        )
+        .arg(
+            Arg::new("to")
+                .help("the last height, inclusive")
+                .long("to")
+                .takes_value(true)
+                .required(true)
+                .validator(str::parse::<u64>),
+        )
+        .arg(concurrency_arg())
+        .handler(|_cmd, m, ctx| {
+            let from = m.value_of("from").unwrap().parse::<u64>()?;
+            let to = m.value_of("to").unwrap().parse::<u64>()?;
+            let concurrency = m.value_of("concurrency").unwrap().parse::<usize>()?;
+            ensure!(from <= to, "`from` must not be greater than `to`");
 
-    #[test]
-    fn test_get_peer_count() {
-        let cmd = get_peer_count();
-        let cldi_cmd = cldi_cmd();
+            let hashes = ctx
+                .rt
+                .block_on(ctx.controller.get_block_hashes(from..=to, concurrency))??;
+            for (h, hash) in (from..=to).zip(&hashes) {
+                if let Ok(hash) = hash {
+                    ctx.block_cache.insert(*hash, h);
+                }
+            }
+
+            match ctx.output_format {
+                OutputFormat::Json | OutputFormat::Bin => {
+                    let json = (from..=to)
+                        .zip(&hashes)
+                        .map(|(h, hash)| match hash {
+                            Ok(hash) => json!({ "height": h, "hash": hex(hash) }),
+                            Err(e) => json!({ "height": h, "error": format!("{e:#}") }),
+                        })
+                        .collect::<Vec<_>>();
+                    ctx.emit(&json!(json));
+                }
+                OutputFormat::Text => {
+                    for (h, hash) in (from..=to).zip(&hashes) {
+                        match hash {
+                            Ok(hash) => ctx.emit_text(format!("{h}: {}", hex(hash))),
+                            Err(e) => ctx.emit_text(format!("{h}: {e:#}")),
+                        }
+                    }
+                }
+            }
+
+            ensure!(hashes.iter().any(Result::is_ok), "all requests failed");
+            Ok(())
+        })
+}
+
+pub fn verify_tx<'help, Co, Ex, Ev>() -> Command<'help, Context<Co, Ex, Ev>>
+where
+    Co: ControllerBehaviour,
+{
+    Command::<Context<Co, Ex, Ev>>::new("verify-tx")
+        .about("Verify the tx hash and who signed it without trusting the declared senders")
+        .arg(
+            Arg::new("tx_hash")
+                .help("the tx hash")
+                .required(true)
+                .validator(parse_hash),
+        )
+        .handler(|_cmd, m, ctx| {
+            let tx_hash = parse_hash(m.value_of("tx_hash").unwrap())?;
+            let raw = ctx.rt.block_on(ctx.controller.get_tx(tx_hash))??;
+            let verification = match ctx.current_setting.crypto_type {
+                CryptoType::Sm => verify_raw_tx::<SmCrypto>(&raw)?,
+                CryptoType::Eth => verify_raw_tx::<EthCrypto>(&raw)?,
+            };
+            ctx.emit(&verification);
+
+            Ok(())
+        })
+}
+
+pub fn verify_block<'help, Co, Ex, Ev>() -> Command<'help, Context<Co, Ex, Ev>>
+where
+    Co: ControllerBehaviour,
+{
+    Command::<Context<Co, Ex, Ev>>::new("verify-block")
+        .about("Recompute the transactions root of the block and compare it to the one in header")
+        .arg(
+            Arg::new("height")
+                .help("the block height")
+                .required(true)
+                .validator(str::parse::<u64>),
+        )
+        .handler(|_cmd, m, ctx| {
+            let height = m.value_of("height").unwrap().parse()?;
+            let block = ctx
+                .rt
+                .block_on(ctx.controller.get_block_by_number(height))??;
+            let verification = match ctx.current_setting.crypto_type {
+                CryptoType::Sm => controller::verify_block::<SmCrypto>(&block)?,
+                CryptoType::Eth => controller::verify_block::<EthCrypto>(&block)?,
+            };
+            ctx.emit(&verification);
+
+            Ok(())
+        })
+}
+
+pub fn watch_blocks<'help, Co, Ex, Ev>() -> Command<'help, Context<Co, Ex, Ev>>
+where
+    Co: ControllerBehaviour,
+{
+    Command::<Context<Co, Ex, Ev>>::new("watch-blocks")
+        .about("Poll and print new blocks until Ctrl-C")
+        .arg(
+            Arg::new("interval")
+                .help("the polling interval in milliseconds")
+                .long("interval")
+                .takes_value(true)
+                .default_value("3000")
+                .validator(str::parse::<u64>),
+        )
+        .arg(
+            Arg::new("from")
+                .help("backfill blocks from this height, default to the next block")
+                .long("from")
+                .takes_value(true)
+                .validator(str::parse::<u64>),
+        )
+        .arg(
+            Arg::new("stall-timeout")
+                .help("warn that the chain is stalled if no new block comes within this many milliseconds. [default: 3x the block interval in system config]")
+                .long("stall-timeout")
+                .takes_value(true)
+                .validator(str::parse::<u64>),
+        )
+        .arg(
+            Arg::new("exit-on-stall")
+                .help("exit with an error when the chain is stalled instead of waiting for it to recover")
+                .long("exit-on-stall"),
+        )
+        .handler(|_cmd, m, ctx| {
+            let interval = m.value_of("interval").unwrap().parse::<u64>()?;
+            let from = m.value_of("from").map(|s| s.parse::<u64>().unwrap());
+            let exit_on_stall = m.is_present("exit-on-stall");
+
+            let ctx = &*ctx;
+            let c = &ctx.controller;
+            let cache = &ctx.block_cache;
+            let watching = async move {
+                let stall_timeout = match m.value_of("stall-timeout") {
+                    Some(ms) => Some(Duration::from_millis(ms.parse()?)),
+                    None => match c.get_system_config().await {
+                        Ok(sc) if sc.block_interval > 0 => Some(sc.block_interval_duration() * 3),
+                        Ok(_) => {
+                            warning!("the block interval in system config is 0, stall detection is disabled unless `--stall-timeout` is given");
+                            None
+                        }
+                        Err(e) => {
+                            warning!("failed to get system config: `{e}`, stall detection is disabled unless `--stall-timeout` is given");
+                            None
+                        }
+                    },
+                };
+                let mut next = match from {
+                    Some(from) => from,
+                    None => c.get_block_number(false).await? + 1,
+                };
+                let mut last_block_at = Instant::now();
+                let mut stalled = false;
+                let mut ticker = tokio::time::interval(Duration::from_millis(interval.max(1)));
+                ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+                loop {
+                    ticker.tick().await;
+                    let prev_next = next;
+                    match c.get_block_number(false).await {
+                        Ok(tip) => {
+                            if tip > next {
+                                info!(ctx.verbosity, "node is ahead, fetching blocks from `{next}` to `{tip}`");
+                            }
+                            // Fetch skipped blocks in order, retry from where it failed in the next tick.
+                            while next <= tip {
+                                match cache.get_block_by_number(c, next).await {
+                                    Ok(block) => {
+                                        ctx.emit(&block);
+                                        next += 1;
+                                        last_block_at = Instant::now();
+                                    }
+                                    Err(e) if is_dry_run(&e) => return Err(e),
+                                    Err(e) => {
+                                        warning!("failed to get block `{next}`: `{e}`");
+                                        break;
+                                    }
+                                }
+                            }
+                        }
+                        Err(e) => {
+                            warning!("failed to get block number: `{e}`");
+                        }
+                    }
+
+                    let waited = last_block_at.elapsed();
+                    if stalled && next > prev_next {
+                        stalled = false;
+                        info!(ctx.verbosity, "the chain resumed producing blocks at `{}`", next - 1);
+                    } else if !stalled && stall_timeout.is_some_and(|timeout| waited >= timeout) {
+                        stalled = true;
+                        let msg = format!(
+                            "the chain is stalled, no new block after `{}` for {:.1}s",
+                            next.saturating_sub(1),
+                            waited.as_secs_f64()
+                        );
+                        if exit_on_stall {
+                            bail!(msg);
+                        }
+                        warning!("{msg}");
+                    }
+                }
+                #[allow(unreachable_code)]
+                anyhow::Ok(())
+            };
+
+            // Ctrl-C is the normal way to stop watching.
+            match ctx.rt.block_on(watching) {
+                Ok(res) => res,
+                Err(_canceled) => Ok(()),
+            }
+        })
+}
+
+/// Summarize where the tx is. [`SdkError::NotFound`] of those queries means the tx isn't there yet,
+/// other errors are returned.
+fn tx_status(
+    tx_hash: &Hash,
+    location: SdkResult<(u64, u64)>,
+    receipt: SdkResult<Receipt>,
+) -> SdkResult<Json> {
+    let mut status = json!({ "tx_hash": hex(tx_hash) });
+    match location {
+        Ok((block_number, index)) => {
+            status["block_number"] = json!(block_number);
+            status["index"] = json!(index);
+        }
+        Err(SdkError::NotFound(_)) => (),
+        Err(e) => return Err(e),
+    }
+    match receipt {
+        Ok(receipt) if receipt.error_message.is_empty() => status["status"] = json!("executed"),
+        Ok(receipt) => {
+            status["status"] = json!("reverted");
+            status["error_msg"] = json!(receipt.error_message);
+        }
+        Err(SdkError::NotFound(_)) if status.get("block_number").is_some() => {
+            status["status"] = json!("included")
+        }
+        Err(SdkError::NotFound(_)) => status["status"] = json!("pending or unknown"),
+        Err(e) => return Err(e),
+    }
+    Ok(status)
+}
+
+pub fn get_tx_status<'help, Co, Ex, Ev>() -> Command<'help, Context<Co, Ex, Ev>>
+where
+    Co: ControllerBehaviour,
+    Ev: EvmBehaviour,
+{
+    Command::<Context<Co, Ex, Ev>>::new("get-tx-status")
+        .about("Get the status of a tx: pending or unknown, included, executed or reverted")
+        .arg(
+            Arg::new("tx_hash")
+                .help("the tx hash")
+                .required(true)
+                .validator(parse_hash),
+        )
+        .handler(|_cmd, m, ctx| {
+            let tx_hash = parse_hash(m.value_of("tx_hash").unwrap())?;
+            let (block_number, index, receipt) = ctx.rt.block_on(async {
+                tokio::join!(
+                    ctx.controller.get_tx_block_number(tx_hash),
+                    ctx.controller.get_tx_index(tx_hash),
+                    ctx.evm.get_receipt(tx_hash),
+                )
+            })?;
+            let location = block_number.and_then(|h| Ok((h, index?)));
+            let status = tx_status(&tx_hash, location, receipt)?;
+            ctx.emit(&status);
+            Ok(())
+        })
+}
+
+/// Round-trip latencies of the pings to a service.
+#[derive(Default)]
+struct PingStats {
+    latencies: Vec<Duration>,
+    failed: u64,
+}
+
+impl PingStats {
+    /// A failed ping is counted, but a dry run stops pinging.
+    fn record<T>(
+        &mut self,
+        verbosity: Verbosity,
+        service: &str,
+        seq: u64,
+        start: Instant,
+        res: SdkResult<T>,
+    ) -> SdkResult<Option<T>> {
+        match res {
+            Ok(v) => {
+                let rtt = start.elapsed();
+                info!(verbosity, "{service}: seq={seq} time={:.3}ms", millis(rtt));
+                self.latencies.push(rtt);
+                Ok(Some(v))
+            }
+            Err(e) if e.is_dry_run() => Err(e),
+            Err(e) => {
+                warning!("{service}: seq={seq} failed: `{e}`");
+                self.failed += 1;
+                Ok(None)
+            }
+        }
+    }
+
+    fn summary(&self) -> Json {
+        let received = self.latencies.len() as u64;
+        let mut summary = json!({
+            "sent": received + self.failed,
+            "received": received,
+        });
+        if let (Some(min), Some(max)) = (self.latencies.iter().min(), self.latencies.iter().max()) {
+            let avg = self.latencies.iter().sum::<Duration>() / received as u32;
+            summary["min_ms"] = json!(millis(*min));
+            summary["avg_ms"] = json!(millis(avg));
+            summary["max_ms"] = json!(millis(*max));
+        }
+        summary
+    }
+}
+
+fn millis(d: Duration) -> f64 {
+    (d.as_secs_f64() * 1e6).round() / 1e3
+}
+
+pub fn ping<'help, Co, Ex, Ev>() -> Command<'help, Context<Co, Ex, Ev>>
+where
+    Co: ControllerBehaviour,
+    Ex: ExecutorBehaviour,
+{
+    Command::<Context<Co, Ex, Ev>>::new("ping")
+        .about("Check that the controller and executor are up, and their round-trip latency")
+        .arg(
+            Arg::new("count")
+                .help("how many times to ping")
+                .long("count")
+                .takes_value(true)
+                .default_value("1")
+                .validator(str::parse::<NonZeroU64>),
+        )
+        .arg(
+            Arg::new("interval")
+                .help("the interval between pings in milliseconds")
+                .long("interval")
+                .takes_value(true)
+                .default_value("1000")
+                .validator(str::parse::<u64>),
+        )
+        .handler(|_cmd, m, ctx| {
+            let count = m.value_of("count").unwrap().parse::<u64>()?;
+            let interval = m.value_of("interval").unwrap().parse::<u64>()?;
+
+            let mut controller = PingStats::default();
+            let mut executor = PingStats::default();
+            let mut height = None;
+            let pinging = async {
+                for seq in 1..=count {
+                    if seq > 1 {
+                        tokio::time::sleep(Duration::from_millis(interval)).await;
+                    }
+                    let start = Instant::now();
+                    let res = ctx.controller.get_block_number(false).await;
+                    let verbosity = ctx.verbosity;
+                    if let Some(h) = controller.record(verbosity, "controller", seq, start, res)? {
+                        height = Some(h);
+                    }
+
+                    let start = Instant::now();
+                    let res = match ctx.executor.call([0; 20], [0; 20], vec![]).await {
+                        // It's replied, the executor is up.
+                        Err(SdkError::Reverted { .. }) => Ok(()),
+                        res => res.map(|_| ()),
+                    };
+                    executor.record(verbosity, "executor", seq, start, res)?;
+                }
+                SdkResult::Ok(())
+            };
+            // Ctrl-C stops pinging like `ping`, the summary is still shown.
+            if let Ok(res) = ctx.rt.block_on(pinging) {
+                res?;
+            }
+
+            let mut controller_summary = controller.summary();
+            controller_summary["height"] = json!(height);
+            ctx.emit(&json!({
+                "controller": controller_summary,
+                "executor": executor.summary(),
+            }));
+
+            let failed = controller.failed + executor.failed;
+            ensure!(failed == 0, "{failed} ping(s) failed");
+            Ok(())
+        })
+}
+
+pub fn rpc_cmd<'help, Co, Ex, Ev>() -> Command<'help, Context<Co, Ex, Ev>>
+where
+    Co: ControllerBehaviour + Send + Sync,
+    Ex: ExecutorBehaviour,
+    Ev: EvmBehaviour,
+{
+    Command::<Context<Co, Ex, Ev>>::new("rpc")
+        .about("Other RPC commands")
+        .subcommand_required_else_help(true)
+        .subcommands([
+            add_node(),
+            get_peers_info().name("peers"),
+            store_abi(),
+            get_block_hashes(),
+            find_block(),
+            get_blocks(),
+            genesis(),
+            tx_count_in_block(),
+            account_history(),
+            get_tx_status(),
+            verify_tx(),
+            verify_block(),
+            watch_blocks(),
+            ping(),
+        ])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cmd::cldi_cmd;
+    use crate::core::mock::context;
+
+    #[test]
+    fn test_call_revert() {
+        use crate::proto::executor::CallResponse;
+
+        let cldi_cmd = cldi_cmd();
+        let (mut ctx, _temp_dir) = context();
+        ctx.executor.expect_call().returning(|_from, _to, data| {
+            if data == [1] {
+                Err(SdkError::Reverted {
+                    reason: "no".into(),
+                    data: vec![],
+                })
+            } else {
+                Ok(CallResponse { value: vec![0; 32] })
+            }
+        });
+
+        let to = "0x0000000000000000000000000000000000000001";
+        cldi_cmd
+            .exec_from(["cldi", "call", to, "0x00"], &mut ctx)
+            .unwrap();
+        let err = cldi_cmd
+            .exec_from(["cldi", "call", to, "0x01"], &mut ctx)
+            .unwrap_err();
+        assert_eq!(err.to_string(), "call reverted: no");
+    }
+
+    #[test]
+    fn test_get_block_hashes() {
+        let cldi_cmd = cldi_cmd();
+        let (mut ctx, _temp_dir) = context();
+        ctx.controller.expect_get_block_hash().returning(|h| {
+            if h == 3 {
+                return Err(SdkError::NotFound("block".into()));
+            }
+            Ok([h as u8; 32])
+        });
+
+        cldi_cmd
+            .exec_from(
+                ["cldi", "rpc", "block-hashes", "--from", "1", "--to", "5"],
+                &mut ctx,
+            )
+            .unwrap();
+        assert_eq!(ctx.block_cache.get_height(&[2; 32]), Some(2));
+        assert!(cldi_cmd
+            .exec_from(
+                ["cldi", "rpc", "block-hashes", "--from", "3", "--to", "3"],
+                &mut ctx
+            )
+            .is_err());
+    }
+
+    #[test]
+    fn test_account_history() {
+        use crate::core::{controller::SignerBehaviour, wallet::Account};
+        use crate::proto::blockchain::{CompactBlockBody, Transaction};
+
+        let cldi_cmd = cldi_cmd();
+        let (mut ctx, temp_dir) = context();
+        let alice = Account::<SmCrypto>::generate();
+        let alice_addr = hex(alice.address());
+        ctx.controller.expect_get_block_by_number().returning(|h| {
+            Ok(CompactBlock {
+                body: Some(CompactBlockBody {
+                    tx_hashes: vec![vec![h as u8; 32]],
+                }),
+                ..Default::default()
+            })
+        });
+        // Block 1 has a tx sent by alice, block 2 has one sent to alice and block 3 is unrelated.
+        let txs = [
+            alice.sign_raw_tx(Transaction {
+                to: vec![1; 20],
+                value: vec![0; 32],
+                ..Default::default()
+            }),
+            Account::<SmCrypto>::generate().sign_raw_tx(Transaction {
+                to: alice.address().to_vec(),
+                value: [&[0; 31][..], &[7]].concat(),
+                ..Default::default()
+            }),
+            Account::<SmCrypto>::generate().sign_raw_tx(Transaction {
+                to: vec![1; 20],
+                ..Default::default()
+            }),
+        ];
+        ctx.controller
+            .expect_get_tx()
+            .returning(move |tx_hash| Ok(txs[tx_hash[0] as usize - 1].clone()));
+        ctx.evm
+            .expect_get_receipt()
+            .times(4)
+            .returning(|_| Ok(Receipt::default()));
+
+        let out = temp_dir.path().join("history.csv");
+        let out = out.to_str().unwrap();
+        cldi_cmd
+            .exec_from(
+                [
+                    "cldi",
+                    "rpc",
+                    "account-history",
+                    &alice_addr,
+                    "--from",
+                    "1",
+                    "--to",
+                    "3",
+                    "--out",
+                    out,
+                ],
+                &mut ctx,
+            )
+            .unwrap();
+        let csv = std::fs::read_to_string(out).unwrap();
+        let lines = csv.lines().collect::<Vec<_>>();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0], "block,hash,from,to,value,status");
+        assert!(lines[1].starts_with(&format!("1,{},{alice_addr},", hex(&[1; 32]))));
+        assert!(lines[2].ends_with(&format!(",{alice_addr},7,success")));
+        let existing = [
+            "cldi",
+            "rpc",
+            "account-history",
+            &alice_addr,
+            "--from",
+            "1",
+            "--to",
+            "1",
+            "--out",
+            out,
+        ];
+        assert!(cldi_cmd.exec_from(existing, &mut ctx).is_err());
+        assert_eq!(std::fs::read_to_string(out).unwrap(), csv);
+
+        // Without `--out`, it goes to the output sink.
+        let (res, outputs) = ctx.capture(|ctx| {
+            cldi_cmd.exec_from(
+                [
+                    "cldi",
+                    "rpc",
+                    "account-history",
+                    &alice_addr,
+                    "--from",
+                    "1",
+                    "--to",
+                    "3",
+                ],
+                ctx,
+            )
+        });
+        res.unwrap();
+        assert_eq!(outputs, [json!(csv.trim_end())]);
+    }
+
+    #[test]
+    fn test_bin_output() {
+        let cldi_cmd = cldi_cmd();
+        let (mut ctx, _temp_dir) = context();
+        ctx.controller
+            .expect_get_block_number()
+            .returning(|_| Ok(10));
+        ctx.controller
+            .expect_get_block_by_number()
+            .returning(|_| Ok(CompactBlock::default()));
+        ctx.controller
+            .expect_get_tx()
+            .times(1)
+            .returning(|_| Ok(RawTransaction::default()));
+
+        cldi_cmd
+            .exec_from(["cldi", "-o", "bin", "get", "block", "1"], &mut ctx)
+            .unwrap();
+        let err = cldi_cmd
+            .exec_from(
+                ["cldi", "-o", "bin", "get", "block", "1", "--full"],
+                &mut ctx,
+            )
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "`--full` doesn't support the `bin` output format"
+        );
+        let err = cldi_cmd
+            .exec_from(
+                ["cldi", "-o", "bin", "--color", "never", "get", "block", "1"],
+                &mut ctx,
+            )
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "`--color` cannot be used with the `bin` output format"
+        );
+
+        let tx_hash = hex(&[1; 32]);
+        cldi_cmd
+            .exec_from(["cldi", "-o", "bin", "get", "tx", &tx_hash], &mut ctx)
+            .unwrap();
+        assert!(cldi_cmd
+            .exec_from(
+                ["cldi", "-o", "bin", "get", "tx", &tx_hash, &tx_hash],
+                &mut ctx
+            )
+            .is_err());
+        assert_eq!(ctx.output_format, OutputFormat::Text);
+    }
+
+    #[test]
+    fn test_find_block() {
+        use crate::proto::blockchain::BlockHeader;
+
+        let cldi_cmd = cldi_cmd();
+        let (mut ctx, _temp_dir) = context();
+        ctx.controller
+            .expect_get_block_number()
+            .returning(|_for_pending| Ok(100));
+        // A block per second, except that the proposer of block 45 is 10.5s ahead.
+        ctx.controller.expect_get_block_by_number().returning(|h| {
+            let timestamp = if h == 45 { 55_500 } else { h * 1000 };
+            Ok(CompactBlock {
+                header: Some(BlockHeader {
+                    height: h,
+                    timestamp,
+                    ..Default::default()
+                }),
+                ..Default::default()
+            })
+        });
+
+        let (_, outputs) = ctx.capture(|ctx| {
+            for args in [
+                ["--after", "1970-01-01T00:00:55Z"],
+                ["--before", "1970-01-01T00:00:55Z"],
+                ["--since", "1970-01-01T08:00:30+08:00"],
+            ] {
+                cldi_cmd
+                    .exec_from(["cldi", "rpc", "find-block", args[0], args[1]], ctx)
+                    .unwrap();
+            }
+        });
+        assert_eq!(outputs, [json!(45), json!(54), json!(30)]);
+
+        for args in [
+            ["--after", "1970-01-01T00:02:00Z"],
+            ["--before", "1970-01-01T00:00:00Z"],
+            ["--after", "yesterday"],
+        ] {
+            assert!(cldi_cmd
+                .exec_from(["cldi", "rpc", "find-block", args[0], args[1]], &mut ctx)
+                .is_err());
+        }
+    }
+
+    #[test]
+    fn test_tx_count_in_block() {
+        use crate::proto::blockchain::{BlockHeader, CompactBlockBody};
+
+        let cldi_cmd = cldi_cmd();
+        let (mut ctx, _temp_dir) = context();
+        ctx.controller.expect_get_block_by_number().returning(|h| {
+            if h == 2 {
+                // No header.
+                return Ok(CompactBlock::default());
+            }
+            Ok(CompactBlock {
+                header: Some(BlockHeader {
+                    height: h,
+                    timestamp: 1_000,
+                    proposer: vec![1; 20],
+                    ..Default::default()
+                }),
+                body: Some(CompactBlockBody {
+                    tx_hashes: vec![vec![0; 32]; 2],
+                }),
+                ..Default::default()
+            })
+        });
+
+        let (res, outputs) =
+            ctx.capture(|ctx| cldi_cmd.exec_from(["cldi", "rpc", "tx-count-in-block", "1"], ctx));
+        res.unwrap();
+        assert_eq!(outputs[0]["height"], 1);
+        assert_eq!(outputs[0]["tx_count"], 2);
+        assert_eq!(outputs[0]["proposer"], hex(&[1; 20]));
+        assert!(outputs[0]["size"].as_u64().unwrap() > 64);
+
+        let (res, outputs) =
+            ctx.capture(|ctx| cldi_cmd.exec_from(["cldi", "rpc", "block-stats", "2"], ctx));
+        res.unwrap();
+        assert_eq!(
+            outputs[0],
+            json!({
+                "height": null,
+                "tx_count": 0,
+                "size": 0,
+                "timestamp": null,
+                "proposer": null,
+            })
+        );
+
+        let stats = |tx_count, timestamp| BlockStats {
+            height: None,
+            tx_count,
+            size: 100,
+            timestamp,
+            proposer: None,
+        };
+        assert_eq!(
+            block_stats_csv([(1, stats(2, Some(1000))), (2, stats(0, None))]),
+            "height,tx_count,size,timestamp\n1,2,100,1000\n2,0,100,\n"
+        );
+        cldi_cmd
+            .exec_from(
+                ["cldi", "rpc", "tx-count-in-block", "--range", "1-2"],
+                &mut ctx,
+            )
+            .unwrap();
+        assert!(cldi_cmd
+            .exec_from(
+                ["cldi", "rpc", "tx-count-in-block", "--range", "2-1"],
+                &mut ctx
+            )
+            .is_err());
+    }
+
+    #[test]
+    fn test_get_blocks() {
+        use crate::proto::blockchain::BlockHeader;
+
+        let cldi_cmd = cldi_cmd();
+        let (mut ctx, temp_dir) = context();
+        ctx.controller.expect_get_block_by_number().returning(|h| {
+            if h == 3 {
+                return Err(SdkError::NotFound("block".into()));
+            }
+            Ok(CompactBlock {
+                header: Some(BlockHeader {
+                    height: h,
+                    ..Default::default()
+                }),
+                ..Default::default()
+            })
+        });
+
+        let out = temp_dir.path().join("blocks.ndjson");
+        let out = out.to_str().unwrap();
+        cldi_cmd
+            .exec_from(
+                [
+                    "cldi",
+                    "rpc",
+                    "get-blocks",
+                    "--from",
+                    "1",
+                    "--to",
+                    "4",
+                    "--format",
+                    "ndjson",
+                    "--out",
+                    out,
+                ],
+                &mut ctx,
+            )
+            .unwrap();
+        let lines = std::fs::read_to_string(out).unwrap();
+        let blocks = lines
+            .lines()
+            .map(|l| serde_json::from_str::<Json>(l).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(blocks.len(), 4);
+        assert_eq!(blocks[0]["height"], 1);
+        assert_eq!(blocks[2]["height"], 3);
+        assert!(blocks[2].get("error").is_some());
+        assert_eq!(blocks[3]["height"], 4);
+
+        // The JSON array is written as plain JSON.
+        let out = temp_dir.path().join("blocks.json");
+        let out = out.to_str().unwrap();
+        cldi_cmd
+            .exec_from(
+                [
+                    "cldi",
+                    "rpc",
+                    "get-blocks",
+                    "--from",
+                    "1",
+                    "--to",
+                    "2",
+                    "--out",
+                    out,
+                ],
+                &mut ctx,
+            )
+            .unwrap();
+        let blocks: Json = serde_json::from_str(&std::fs::read_to_string(out).unwrap()).unwrap();
+        assert_eq!(blocks[1]["height"], 2);
+        // The file isn't overwritten without `--overwrite`.
+        let mut args = vec![
+            "cldi",
+            "rpc",
+            "get-blocks",
+            "--from",
+            "1",
+            "--to",
+            "1",
+            "--out",
+            out,
+        ];
+        assert!(cldi_cmd.exec_from(&args, &mut ctx).is_err());
+        assert_eq!(
+            serde_json::from_str::<Json>(&std::fs::read_to_string(out).unwrap()).unwrap(),
+            blocks
+        );
+        args.push("--overwrite");
+        cldi_cmd.exec_from(&args, &mut ctx).unwrap();
+        let blocks: Json = serde_json::from_str(&std::fs::read_to_string(out).unwrap()).unwrap();
+        assert_eq!(blocks.as_array().unwrap().len(), 1);
+
+        let get_blocks = |args: &[&str], ctx: &mut _| {
+            let args = ["cldi", "rpc", "get-blocks", "--max-range", "10"]
+                .iter()
+                .chain(args);
+            cldi_cmd.exec_from(args, ctx)
+        };
+        assert!(get_blocks(&["--from", "1", "--to", "10"], &mut ctx).is_ok());
+        assert!(get_blocks(&["--from", "1", "--to", "11"], &mut ctx).is_err());
+        assert!(get_blocks(&["--from", "1", "--to", "11", "--force"], &mut ctx).is_ok());
+        assert!(get_blocks(&["--from", "3", "--to", "3"], &mut ctx).is_err());
+    }
+
+    #[test]
+    fn test_verify_tx() {
+        use crate::core::controller::SignerBehaviour;
+        use crate::proto::blockchain::Transaction;
+
+        let cldi_cmd = cldi_cmd();
+        let (mut ctx, _temp_dir) = context();
+        let raw = ctx
+            .current_account()
+            .unwrap()
+            .sign_raw_tx(Transaction::default());
+        ctx.controller
+            .expect_get_tx()
+            .returning(move |_| Ok(raw.clone()));
+
+        let raw = ctx
+            .rt
+            .block_on(ctx.controller.get_tx([0; 32]))
+            .unwrap()
+            .unwrap();
+        assert!(verify_raw_tx::<SmCrypto>(&raw).unwrap().is_valid());
+        cldi_cmd
+            .exec_from(["cldi", "rpc", "verify-tx", &hex(&[0; 32])], &mut ctx)
+            .unwrap();
+    }
+
+    #[test]
+    fn test_tx_status() {
+        let tx_hash = [1; 32];
+        fn not_found<T>() -> SdkResult<T> {
+            Err(SdkError::NotFound("tx".into()))
+        }
+
+        let status = tx_status(&tx_hash, not_found(), not_found()).unwrap();
+        assert_eq!(status["status"], "pending or unknown");
+        assert!(status.get("block_number").is_none());
+
+        let status = tx_status(&tx_hash, Ok((10, 2)), not_found()).unwrap();
+        assert_eq!(status["status"], "included");
+        assert_eq!(status["block_number"], 10);
+        assert_eq!(status["index"], 2);
+
+        let status = tx_status(&tx_hash, Ok((10, 2)), Ok(Receipt::default())).unwrap();
+        assert_eq!(status["status"], "executed");
+
+        let reverted = Receipt {
+            error_message: "Reverted".into(),
+            ..Default::default()
+        };
+        let status = tx_status(&tx_hash, Ok((10, 2)), Ok(reverted)).unwrap();
+        assert_eq!(status["status"], "reverted");
+        assert_eq!(status["error_msg"], "Reverted");
+
+        // Other errors aren't taken as not found.
+        fn timeout<T>() -> SdkResult<T> {
+            Err(SdkError::Timeout {
+                service: "controller".into(),
+                timeout: Duration::from_secs(3),
+            })
+        }
+        assert!(tx_status(&tx_hash, timeout(), not_found()).is_err());
+        assert!(tx_status(&tx_hash, Ok((10, 2)), timeout()).is_err());
+
+        let cldi_cmd = cldi_cmd();
+        let (mut ctx, _temp_dir) = context();
+        ctx.controller
+            .expect_get_tx_block_number()
+            .returning(|_| Err(SdkError::NotFound("tx".into())));
+        ctx.controller
+            .expect_get_tx_index()
+            .returning(|_| Err(SdkError::NotFound("tx".into())));
+        ctx.evm
+            .expect_get_receipt()
+            .returning(|_| Err(SdkError::NotFound("tx".into())));
+        cldi_cmd
+            .exec_from(["cldi", "rpc", "get-tx-status", &hex(&tx_hash)], &mut ctx)
+            .unwrap();
+    }
+
+    #[test]
+    fn test_get_peer_count() {
+        let cmd = get_peer_count();
+        let cldi_cmd = cldi_cmd();
 
         let (mut ctx, _temp_dir) = context();
         ctx.controller.expect_get_peer_count().returning(|| Ok(42));
@@ -444,4 +2076,835 @@ mod tests {
             .exec_from(["cldi", "get", "peer-count"], &mut ctx)
             .unwrap();
     }
+
+    #[test]
+    fn test_ping() {
+        let cldi_cmd = cldi_cmd();
+        let (mut ctx, _temp_dir) = context();
+
+        ctx.controller
+            .expect_get_block_number()
+            .times(3)
+            .returning(|_| Ok(42));
+        ctx.executor
+            .expect_call()
+            .times(2)
+            .returning(|_, _, _| Ok(Default::default()));
+        ctx.executor.expect_call().times(1).returning(|_, _, _| {
+            Err(SdkError::Reverted {
+                reason: "no code".into(),
+                data: vec![],
+            })
+        });
+        let (res, out) = ctx.capture(|ctx| {
+            cldi_cmd.exec_from(
+                ["cldi", "rpc", "ping", "--count", "3", "--interval", "0"],
+                ctx,
+            )
+        });
+        res.unwrap();
+        let summary = &out[0];
+        assert_eq!(summary["controller"]["height"], 42);
+        assert_eq!(summary["controller"]["received"], 3);
+        assert_eq!(summary["executor"]["received"], 3);
+        assert!(summary["executor"]["max_ms"].is_number());
+
+        ctx.executor.checkpoint();
+        ctx.controller
+            .expect_get_block_number()
+            .returning(|_| Ok(42));
+        ctx.executor.expect_call().returning(|_, _, _| {
+            Err(SdkError::Timeout {
+                service: "executor".into(),
+                timeout: Duration::from_secs(1),
+            })
+        });
+        let err = cldi_cmd
+            .exec_from(["cldi", "rpc", "ping"], &mut ctx)
+            .unwrap_err();
+        assert!(err.to_string().contains("1 ping(s) failed"));
+
+        // A dry run shows the request instead of a failed ping.
+        ctx.executor.checkpoint();
+        ctx.executor.expect_call().times(1).returning(|_, _, _| {
+            Err(SdkError::DryRun {
+                service: "executor".into(),
+                method: "call".into(),
+                request: json!({}),
+            })
+        });
+        let (res, out) =
+            ctx.capture(|ctx| cldi_cmd.exec_from(["cldi", "rpc", "ping", "--count", "3"], ctx));
+        res.unwrap();
+        assert_eq!(out, [json!({ "method": "executor.call", "request": {} })]);
+    }
+
+    #[test]
+    fn test_add_node() {
+        let cmd = add_node();
+        let (mut ctx, _temp_dir) = context();
+        ctx.controller
+            .expect_add_node()
+            .withf(|multiaddr| {
+                [
+                    "/ip4/127.0.0.1/tcp/40000",
+                    "/dns4/node1/tcp/40000/tls/node1",
+                ]
+                .contains(&multiaddr.as_str())
+            })
+            .times(2)
+            .returning(|_| Ok(0));
+
+        cmd.exec_from(["add-node", "127.0.0.1", "40000"], &mut ctx)
+            .unwrap();
+        cmd.exec_from(
+            ["add-node", "--multiaddr", "/dns4/node1/tcp/40000/tls/node1"],
+            &mut ctx,
+        )
+        .unwrap();
+        // Rejected before reaching the node.
+        assert!(cmd
+            .exec_from(
+                ["add-node", "--multiaddr", "/ip4/127.0.0.1/udp/40000"],
+                &mut ctx
+            )
+            .is_err());
+        assert!(cmd
+            .exec_from(["add-node", "bad_host", "40000"], &mut ctx)
+            .is_err());
+    }
+
+    #[test]
+    fn test_get_system_config_field() {
+        use crate::proto::controller::SystemConfig;
+
+        let cldi_cmd = cldi_cmd();
+        let (mut ctx, _temp_dir) = context();
+        ctx.controller.expect_get_system_config().returning(|| {
+            Ok(SystemConfig {
+                admin: vec![0x11; 20],
+                block_interval: 3,
+                validators: vec![vec![0x22; 20], vec![0x33; 48]],
+                ..Default::default()
+            })
+        });
+
+        let (_, outputs) = ctx.capture(|ctx| {
+            for field in ["admin", "block_interval", "validators"] {
+                cldi_cmd
+                    .exec_from(["cldi", "get", "system-config", "--field", field], ctx)
+                    .unwrap();
+            }
+        });
+        assert_eq!(
+            outputs,
+            [
+                json!(format!("0x{}", "11".repeat(20))),
+                json!(3),
+                json!([
+                    format!("0x{}", "22".repeat(20)),
+                    format!("0x{}", "33".repeat(48))
+                ]),
+            ]
+        );
+        assert_eq!(
+            FieldValue(outputs[2].clone()).display(DisplayOptions::default()),
+            format!("0x{}\n0x{}", "22".repeat(20), "33".repeat(48))
+        );
+        assert!(cldi_cmd
+            .exec_from(
+                ["cldi", "get", "system-config", "--field", "admn"],
+                &mut ctx
+            )
+            .is_err());
+    }
+
+    #[test]
+    fn test_get_block() {
+        use crate::proto::blockchain::BlockHeader;
+
+        let cldi_cmd = cldi_cmd();
+
+        let (mut ctx, _temp_dir) = context();
+        ctx.controller
+            .expect_get_block_number()
+            .returning(|for_pending| Ok(if for_pending { 101 } else { 100 }));
+        ctx.controller.expect_get_block_by_number().returning(|h| {
+            Ok(CompactBlock {
+                header: Some(BlockHeader {
+                    height: h,
+                    ..Default::default()
+                }),
+                ..Default::default()
+            })
+        });
+
+        for (id, height) in [
+            ("latest", 100),
+            ("pending", 101),
+            ("100", 100),
+            ("0x64", 100),
+        ] {
+            let (res, outputs) =
+                ctx.capture(|ctx| cldi_cmd.exec_from(["cldi", "get", "block", id], ctx));
+            res.unwrap();
+            assert_eq!(outputs[0]["height"], height, "block `{id}`");
+        }
+        assert!(cldi_cmd
+            .exec_from(["cldi", "get", "block", "101"], &mut ctx)
+            .is_err());
+    }
+
+    #[test]
+    fn test_genesis() {
+        use crate::proto::blockchain::BlockHeader;
+        use crate::proto::controller::SystemConfig;
+
+        let cldi_cmd = cldi_cmd();
+        let (mut ctx, _temp_dir) = context();
+        ctx.controller
+            .expect_get_block_number()
+            .returning(|_| Ok(100));
+        ctx.controller.expect_get_block_by_number().returning(|h| {
+            Ok(CompactBlock {
+                header: Some(BlockHeader {
+                    height: h,
+                    prevhash: vec![0; 32],
+                    ..Default::default()
+                }),
+                body: None,
+                ..Default::default()
+            })
+        });
+        ctx.controller
+            .expect_get_block_hash()
+            .returning(|_| Ok([7; 32]));
+        ctx.controller.expect_get_system_config().returning(|| {
+            Ok(SystemConfig {
+                admin: vec![1; 20],
+                admin_pre_hash: vec![9; 32],
+                validators: vec![vec![2; 20]],
+                validators_pre_hash: vec![0; 32],
+                ..Default::default()
+            })
+        });
+
+        let (res, outputs) = ctx.capture(|ctx| {
+            cldi_cmd.exec_from(["cldi", "get", "block", "0"], ctx)?;
+            cldi_cmd.exec_from(["cldi", "get", "block", "1"], ctx)?;
+            cldi_cmd.exec_from(["cldi", "rpc", "genesis"], ctx)
+        });
+        res.unwrap();
+        assert_eq!(outputs[0]["prev_hash"], Json::Null);
+        assert_eq!(outputs[1]["prev_hash"], hex(&[0; 32]));
+
+        let genesis = &outputs[2];
+        assert_eq!(genesis["height"], 0);
+        assert_eq!(genesis["prev_hash"], Json::Null);
+        assert_eq!(genesis["hash"], hex(&[7; 32]));
+        let config = &genesis["genesis_config"];
+        assert_eq!(config["validators"], json!([hex(&[2; 20])]));
+        // The admin has been changed since genesis.
+        assert!(config.get("admin").is_none());
+    }
+
+    #[test]
+    fn test_get_full_block() {
+        use crate::proto::blockchain::{BlockHeader, CompactBlockBody};
+
+        let cldi_cmd = cldi_cmd();
+
+        let (mut ctx, _temp_dir) = context();
+        ctx.controller
+            .expect_get_block_number()
+            .returning(|_for_pending| Ok(100));
+        ctx.controller.expect_get_block_by_number().returning(|_| {
+            Ok(CompactBlock {
+                version: 0,
+                header: Some(BlockHeader::default()),
+                body: Some(CompactBlockBody {
+                    tx_hashes: vec![vec![1; 32], vec![2; 32], vec![3; 32]],
+                }),
+            })
+        });
+        // Only the first 2 txs are fetched.
+        ctx.controller
+            .expect_get_tx()
+            .times(2)
+            .returning(|_| Ok(RawTransaction::default()));
+
+        cldi_cmd
+            .exec_from(
+                ["cldi", "get", "block", "100", "--full", "--max-txs", "2"],
+                &mut ctx,
+            )
+            .unwrap();
+
+        let block = CompactBlock::default();
+        let full_block = FullBlock {
+            block: &block,
+            txs: vec![],
+            omitted: 1,
+        };
+        assert!(full_block
+            .display(DisplayOptions::default())
+            .ends_with("...and 1 more"));
+    }
+
+    #[test]
+    fn test_send_tx_simulate() {
+        let cldi_cmd = cldi_cmd();
+
+        let (mut ctx, _temp_dir) = context();
+        ctx.executor
+            .expect_call()
+            .returning(|_from, _to, data| Ok(crate::proto::executor::CallResponse { value: data }));
+        // Nothing should be sent.
+        ctx.controller.expect_send_raw().never();
+
+        let to = "0x".to_string() + &"11".repeat(20);
+        cldi_cmd
+            .exec_from(["cldi", "send", &to, "0xabcd", "--simulate"], &mut ctx)
+            .unwrap();
+        assert!(cldi_cmd
+            .exec_from(["cldi", "send", &to, "-v", "0x1", "--simulate"], &mut ctx)
+            .is_err());
+    }
+
+    #[test]
+    fn test_function_data() {
+        use crate::abi::FunctionSignature;
+        use crate::core::mock::recording_controller;
+        use crate::proto::controller::SystemConfig;
+        use crate::proto::executor::CallResponse;
+
+        let cldi_cmd = cldi_cmd();
+        let (mut ctx, _temp_dir) = context();
+        ctx.executor
+            .expect_call()
+            .returning(|_from, _to, data| Ok(CallResponse { value: data }));
+        let (mut controller, sent) = recording_controller(SystemConfig::default());
+        controller.expect_get_block_number().returning(|_| Ok(100));
+        ctx.controller = controller;
+
+        let to = "0x".to_string() + &"11".repeat(20);
+        let (res, outputs) = ctx.capture(|ctx| {
+            cldi_cmd.exec_from(
+                [
+                    "cldi",
+                    "call",
+                    &to,
+                    "--function",
+                    "balanceOf(address)",
+                    "--args",
+                    &to,
+                ],
+                ctx,
+            )
+        });
+        res.unwrap();
+        assert_eq!(
+            outputs[0],
+            json!(format!("0x70a08231{}{}", "00".repeat(12), "11".repeat(20)))
+        );
+
+        // A string arg with commas isn't split, and negative numbers aren't taken as flags.
+        let call = |function: &str, args: &[&str], ctx: &mut _| {
+            let (res, outputs) = Context::capture(ctx, |ctx| {
+                let input = ["cldi", "call", &to, "--function", function, "--args"];
+                cldi_cmd.exec_from(input.iter().chain(args), ctx)
+            });
+            res.unwrap();
+            let args: Vec<String> = args.iter().map(|s| s.to_string()).collect();
+            let expected = FunctionSignature::parse(function)
+                .unwrap()
+                .encode_call(&args)
+                .unwrap();
+            assert_eq!(outputs[0], json!(hex(&expected)));
+        };
+        call("f(string)", &["a,b"], &mut ctx);
+        call("g(int256,string)", &["-5", "x, y"], &mut ctx);
+        call("h(int256)", &["-5"], &mut ctx);
+
+        let transfer = ["--function", "transfer(address,uint256)", "--args"];
+        let args = format!("{to},1000");
+        cldi_cmd
+            .exec_from(
+                ["cldi", "send", &to]
+                    .into_iter()
+                    .chain(transfer)
+                    .chain([args.as_str()]),
+                &mut ctx,
+            )
+            .unwrap();
+        let Some(Tx::NormalTx(tx)) = sent.lock().unwrap()[0].tx.clone() else {
+            panic!("expected a normal tx");
+        };
+        let data = tx.transaction.unwrap().data;
+        assert_eq!(data[..4], [0xa9, 0x05, 0x9c, 0xbb]);
+        assert_eq!(data[data.len() - 2..], [0x03, 0xe8]);
+
+        // Arity mismatch and conflicting with the data.
+        assert!(cldi_cmd
+            .exec_from(
+                ["cldi", "send", &to]
+                    .into_iter()
+                    .chain(transfer)
+                    .chain([to.as_str()]),
+                &mut ctx,
+            )
+            .is_err());
+        assert!(cldi_cmd
+            .exec_from(
+                ["cldi", "send", &to, "0xabcd"]
+                    .into_iter()
+                    .chain(transfer)
+                    .chain([args.as_str()]),
+                &mut ctx,
+            )
+            .is_err());
+    }
+
+    #[test]
+    fn test_send_tx_with_nonce() {
+        use crate::proto::blockchain::raw_transaction::Tx;
+        use crate::proto::controller::SystemConfig;
+
+        let cldi_cmd = cldi_cmd();
+
+        let (mut ctx, _temp_dir) = context();
+        ctx.controller
+            .expect_get_block_number()
+            .returning(|_for_pending| Ok(100));
+        ctx.controller
+            .expect_get_system_config()
+            .returning(|| Ok(SystemConfig::default()));
+        ctx.controller
+            .expect_send_raw()
+            .times(1)
+            .returning(|raw| match raw.tx {
+                Some(Tx::NormalTx(tx)) => {
+                    let tx = tx.transaction.unwrap();
+                    assert_eq!(tx.nonce, "0x1f");
+                    assert_eq!(tx.valid_until_block, 150);
+                    Ok(Hash::default())
+                }
+                _ => panic!("expect a normal tx"),
+            });
+
+        let to = "0x".to_string() + &"11".repeat(20);
+        cldi_cmd
+            .exec_from(
+                ["cldi", "send", &to, "--nonce", "0x1f", "--until", "150"],
+                &mut ctx,
+            )
+            .unwrap();
+        // Already expired.
+        assert!(cldi_cmd
+            .exec_from(["cldi", "send", &to, "--until", "100"], &mut ctx)
+            .is_err());
+    }
+
+    #[test]
+    fn test_send_tx_auto_quota() {
+        use crate::core::{error::SdkError, mock::recording_controller};
+        use crate::proto::blockchain::raw_transaction::Tx;
+        use crate::proto::controller::SystemConfig;
+        use crate::proto::evm::ByteQuota;
+
+        let cldi_cmd = cldi_cmd();
+
+        let (mut ctx, _temp_dir) = context();
+        let (mut controller, sent) = recording_controller(SystemConfig::default());
+        controller.expect_get_block_number().returning(|_| Ok(100));
+        ctx.controller = controller;
+        ctx.evm.expect_estimate_quota().times(1).returning(|call| {
+            assert_eq!(call.method, vec![0xab, 0xcd]);
+            Ok(ByteQuota {
+                bytes_quota: 50_000u64.to_be_bytes().to_vec(),
+            })
+        });
+
+        let to = "0x".to_string() + &"11".repeat(20);
+        cldi_cmd
+            .exec_from(
+                [
+                    "cldi",
+                    "send",
+                    &to,
+                    "0xabcd",
+                    "--auto-quota",
+                    "--quota-margin",
+                    "10",
+                ],
+                &mut ctx,
+            )
+            .unwrap();
+        match &sent.lock().unwrap()[0].tx {
+            Some(Tx::NormalTx(tx)) => assert_eq!(tx.transaction.as_ref().unwrap().quota, 55_000),
+            _ => panic!("expect a normal tx"),
+        }
+
+        assert!(cldi_cmd
+            .exec_from(["cldi", "send", &to, "--auto-quota", "-q", "100"], &mut ctx)
+            .is_err());
+
+        // The revert reason is surfaced and nothing is sent.
+        ctx.evm.checkpoint();
+        ctx.evm.expect_estimate_quota().returning(|_| {
+            Err(SdkError::Reverted {
+                reason: "not the owner".into(),
+                data: vec![],
+            })
+        });
+        let err = cldi_cmd
+            .exec_from(["cldi", "send", &to, "--auto-quota"], &mut ctx)
+            .unwrap_err();
+        assert!(format!("{err:#}").contains("not the owner"));
+        assert_eq!(sent.lock().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_send_tx_nonce_strategy() {
+        use crate::proto::blockchain::raw_transaction::Tx;
+        use crate::proto::controller::SystemConfig;
+        use crate::proto::evm::Nonce;
+
+        let cldi_cmd = cldi_cmd();
+
+        let (mut ctx, _temp_dir) = context();
+        ctx.controller
+            .expect_get_block_number()
+            .returning(|_for_pending| Ok(100));
+        ctx.controller
+            .expect_get_system_config()
+            .returning(|| Ok(SystemConfig::default()));
+        // Once for `local-increment` and once for `fetch`.
+        ctx.evm.expect_get_tx_count().times(2).returning(|_addr| {
+            Ok(Nonce {
+                nonce: [&[0; 31][..], &[7]].concat(),
+            })
+        });
+        let sent = std::sync::Arc::new(parking_lot::Mutex::new(vec![]));
+        let sent_cloned = sent.clone();
+        ctx.controller
+            .expect_send_raw()
+            .times(3)
+            .returning(move |raw| match raw.tx {
+                Some(Tx::NormalTx(tx)) => {
+                    sent_cloned.lock().push(tx.transaction.unwrap().nonce);
+                    Ok(Hash::default())
+                }
+                _ => panic!("expect a normal tx"),
+            });
+
+        let to = "0x".to_string() + &"11".repeat(20);
+        for strategy in ["local-increment", "local-increment", "fetch"] {
+            cldi_cmd
+                .exec_from(
+                    ["cldi", "send", &to, "--nonce-strategy", strategy],
+                    &mut ctx,
+                )
+                .unwrap();
+        }
+        assert_eq!(*sent.lock(), ["7", "8", "7"]);
+        assert!(cldi_cmd
+            .exec_from(
+                [
+                    "cldi",
+                    "send",
+                    &to,
+                    "--nonce",
+                    "1",
+                    "--nonce-strategy",
+                    "fetch"
+                ],
+                &mut ctx
+            )
+            .is_err());
+    }
+
+    #[test]
+    fn test_send_tx_nonce_gap() {
+        use crate::core::mock::recording_controller;
+        use crate::proto::controller::SystemConfig;
+        use crate::proto::evm::Nonce;
+
+        let cldi_cmd = cldi_cmd();
+        let (mut ctx, _temp_dir) = context();
+        let (mut controller, sent) = recording_controller(SystemConfig::default());
+        controller
+            .expect_get_block_number()
+            .returning(|_for_pending| Ok(100));
+        ctx.controller = controller;
+        ctx.evm.expect_get_tx_count().returning(|_addr| {
+            Ok(Nonce {
+                nonce: [&[0; 31][..], &[7]].concat(),
+            })
+        });
+
+        let to = "0x".to_string() + &"11".repeat(20);
+        for nonce in ["7", "9"] {
+            cldi_cmd
+                .exec_from(["cldi", "send", &to, "--nonce", nonce], &mut ctx)
+                .unwrap();
+        }
+        let err = cldi_cmd
+            .exec_from(["cldi", "send", &to, "--nonce", "9", "--strict"], &mut ctx)
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "nonce `9` is ahead of the account nonce `7`, the tx will stall until the 2 tx(s) in between are executed"
+        );
+        assert_eq!(sent.lock().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_send_tx_recent_height() {
+        use crate::core::mock::recording_controller;
+        use crate::proto::blockchain::raw_transaction::Tx;
+        use crate::proto::controller::SystemConfig;
+
+        let cldi_cmd = cldi_cmd();
+        let (mut ctx, _temp_dir) = context();
+        let (mut controller, sent) = recording_controller(SystemConfig::default());
+        // Once for the first two txs, once with `--fresh` and once for the absolute position.
+        let mut height = 99;
+        controller
+            .expect_get_block_number()
+            .times(3)
+            .returning(move |_for_pending| {
+                height += 1;
+                Ok(height)
+            });
+        ctx.controller = controller;
+
+        let to = "0x".to_string() + &"11".repeat(20);
+        for args in [&[][..], &[], &["--fresh"], &["--until", "200"]] {
+            cldi_cmd
+                .exec_from(["cldi", "send", &to].iter().chain(args), &mut ctx)
+                .unwrap();
+        }
+        let valid_until_blocks: Vec<u64> = sent
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|raw| match &raw.tx {
+                Some(Tx::NormalTx(tx)) => tx.transaction.as_ref().unwrap().valid_until_block,
+                _ => panic!("expected a normal tx"),
+            })
+            .collect();
+        let offset = ctx.config.valid_until_offset;
+        assert_eq!(
+            valid_until_blocks,
+            [100 + offset, 100 + offset, 101 + offset, 200]
+        );
+        assert!(!ctx.fresh_height);
+    }
+
+    #[test]
+    fn test_send_tx_quota_usage() {
+        use crate::proto::controller::SystemConfig;
+
+        let cldi_cmd = cldi_cmd();
+        let (mut ctx, _temp_dir) = context();
+        ctx.controller
+            .expect_get_block_number()
+            .returning(|_for_pending| Ok(100));
+        ctx.controller
+            .expect_get_system_config()
+            .returning(|| Ok(SystemConfig::default()));
+        ctx.controller
+            .expect_send_raw()
+            .returning(|raw| match raw.tx {
+                Some(Tx::NormalTx(tx)) => {
+                    Ok(Hash::try_from_slice(&[tx.transaction.unwrap().quota as u8; 32]).unwrap())
+                }
+                _ => panic!("expect a normal tx"),
+            });
+        ctx.evm.expect_get_receipt().returning(|tx_hash| {
+            // Runs out of quota unless the quota is 200.
+            if tx_hash[0] == 200 {
+                Ok(Receipt {
+                    quota_used: vec![0x00, 0x32],
+                    ..Default::default()
+                })
+            } else {
+                Ok(Receipt {
+                    quota_used: vec![tx_hash[0]],
+                    error_message: "Out of quota.".into(),
+                    ..Default::default()
+                })
+            }
+        });
+
+        let to = "0x".to_string() + &"11".repeat(20);
+        let send = |ctx: &mut _, quota: &str| {
+            cldi_cmd.exec_from(
+                ["cldi", "send", &to, "-q", quota, "--wait", "--show-quota"],
+                ctx,
+            )
+        };
+        let (res, outputs) = ctx.capture(|ctx| send(ctx, "200"));
+        res.unwrap();
+        assert_eq!(
+            outputs[1]["quota"],
+            json!({"used": 50, "limit": 200, "utilization": "25.00%"})
+        );
+        let err = send(&mut ctx, "100").unwrap_err().to_string();
+        assert!(err.contains("ran out of quota"), "{err}");
+        assert!(err.contains("`100`"), "{err}");
+    }
+
+    #[test]
+    fn test_send_tx_chain_id() {
+        use crate::proto::controller::SystemConfig;
+
+        let cldi_cmd = cldi_cmd();
+        let (mut ctx, _temp_dir) = context();
+        // Fetched once for both checking and building the txs.
+        ctx.controller
+            .expect_get_system_config()
+            .times(1)
+            .returning(|| {
+                Ok(SystemConfig {
+                    chain_id: vec![1; 32],
+                    ..Default::default()
+                })
+            });
+        ctx.controller
+            .expect_get_block_number()
+            .returning(|_| Ok(100));
+        ctx.controller
+            .expect_send_raw()
+            .times(1)
+            .returning(|_| Ok(Hash::default()));
+
+        let to = hex(&[1; 20]);
+        let chain_id = hex(&[1; 32]);
+        cldi_cmd
+            .exec_from(["cldi", "send", &to, "--chain-id", &chain_id], &mut ctx)
+            .unwrap();
+        assert_eq!(*ctx.chain.borrow(), Some((vec![1; 32], 0)));
+
+        let err = cldi_cmd
+            .exec_from(
+                ["cldi", "send", &to, "--chain-id", &hex(&[2; 32])],
+                &mut ctx,
+            )
+            .unwrap_err();
+        assert!(err.to_string().starts_with("chain id mismatched"));
+    }
+
+    #[test]
+    fn test_send_tx_config_defaults() {
+        use crate::proto::blockchain::raw_transaction::Tx;
+
+        let cldi_cmd = cldi_cmd();
+        let (mut ctx, _temp_dir) = context();
+        ctx.config.default_quota = 42;
+        ctx.config.valid_until_offset = 10;
+        ctx.controller
+            .expect_get_system_config()
+            .returning(|| Ok(Default::default()));
+        ctx.controller
+            .expect_get_block_number()
+            .returning(|_| Ok(100));
+        let sent = std::sync::Arc::new(std::sync::Mutex::new(vec![]));
+        let sent_clone = sent.clone();
+        ctx.controller.expect_send_raw().returning(move |raw| {
+            let Some(Tx::NormalTx(unverified)) = raw.tx else {
+                panic!("expected a normal tx");
+            };
+            let tx = unverified.transaction.unwrap();
+            sent_clone
+                .lock()
+                .unwrap()
+                .push((tx.quota, tx.valid_until_block));
+            Ok(Hash::default())
+        });
+
+        let to = hex(&[1; 20]);
+        cldi_cmd.exec_from(["cldi", "send", &to], &mut ctx).unwrap();
+        // CLI args take precedence.
+        cldi_cmd
+            .exec_from(["cldi", "send", &to, "-q", "7", "--until", "+5"], &mut ctx)
+            .unwrap();
+        assert_eq!(*sent.lock().unwrap(), [(42, 110), (7, 105)]);
+    }
+
+    #[test]
+    fn test_send_tx_strict_crypto() {
+        use crate::crypto::{Crypto, EthCrypto};
+        use crate::proto::blockchain::BlockHeader;
+        use prost::Message;
+
+        let cldi_cmd = cldi_cmd();
+
+        let (mut ctx, _temp_dir) = context();
+        // An ETH chain, but the current context is SM.
+        let header = BlockHeader::default();
+        let block_hash = EthCrypto::hash(&header.encode_to_vec());
+        ctx.crypto_checked.set(false);
+        ctx.controller
+            .expect_get_block_number()
+            .returning(|_for_pending| Ok(0));
+        ctx.controller
+            .expect_get_block_by_number()
+            .returning(move |_| {
+                Ok(CompactBlock {
+                    header: Some(header.clone()),
+                    ..Default::default()
+                })
+            });
+        ctx.controller
+            .expect_get_block_hash()
+            .returning(move |_| Ok(block_hash));
+        ctx.controller.expect_send_raw().never();
+
+        let to = "0x".to_string() + &"11".repeat(20);
+        let err = cldi_cmd
+            .exec_from(["cldi", "--strict-crypto", "send", &to], &mut ctx)
+            .unwrap_err();
+        assert!(err.to_string().contains("crypto type `SM`"));
+        assert!(!ctx.strict_crypto);
+    }
+
+    #[test]
+    fn test_watch_blocks_stall() {
+        use std::sync::atomic::{AtomicU64, Ordering};
+
+        let cldi_cmd = cldi_cmd();
+        let (mut ctx, _temp_dir) = context();
+        // Block 11 comes in the first tick, then the chain stops.
+        let polls = AtomicU64::new(0);
+        ctx.controller
+            .expect_get_block_number()
+            .returning(
+                move |_for_pending| Ok((10 + polls.fetch_add(1, Ordering::Relaxed)).min(11)),
+            );
+        ctx.controller
+            .expect_get_block_by_number()
+            .times(1)
+            .returning(|_| Ok(CompactBlock::default()));
+
+        let err = cldi_cmd
+            .exec_from(
+                [
+                    "cldi",
+                    "rpc",
+                    "watch-blocks",
+                    "--interval",
+                    "10",
+                    "--stall-timeout",
+                    "50",
+                    "--exit-on-stall",
+                ],
+                &mut ctx,
+            )
+            .unwrap_err();
+        assert!(err
+            .to_string()
+            .starts_with("the chain is stalled, no new block after `11`"));
+    }
 }
@@ -0,0 +1,513 @@
+// Copyright Rivtower Technologies LLC.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use anyhow::{bail, ensure, Context as _, Result};
+use clap::{Arg, ArgMatches};
+use prost::Message;
+
+use crate::{
+    cmd::{
+        evm::{
+            confirmations_arg, maybe_wait_receipt, quota_arg, quota_of, show_quota_arg, wait_arg,
+            wait_timeout_arg,
+        },
+        Command,
+    },
+    config::CryptoType,
+    core::{
+        context::Context,
+        controller::{ControllerBehaviour, SignerBehaviour},
+        evm::{quota_limit, EvmBehaviour},
+    },
+    crypto::{ArrayLike, EthCrypto, Hash, SmCrypto},
+    display::{check_raw_tx, Display},
+    log::{info, warning},
+    proto::blockchain::{
+        raw_transaction::Tx, RawTransaction, Transaction as CloudNormalTransaction,
+    },
+    utils::{
+        hex, load_raw_tx, load_raw_tx_json, parse_binary_input, parse_data_input, parse_hash,
+        parse_nonce, parse_value, save_raw_tx, save_raw_tx_json, validate_addr,
+        validate_binary_input, validate_data_input,
+    },
+};
+
+fn tx_hash(raw: &RawTransaction) -> &[u8] {
+    match &raw.tx {
+        Some(Tx::NormalTx(tx)) => &tx.transaction_hash,
+        Some(Tx::UtxoTx(utxo)) => &utxo.transaction_hash,
+        None => &[],
+    }
+}
+
+fn json_arg<'help>() -> Arg<'help> {
+    Arg::new("json")
+        .help("The tx file is in JSON like the `-o json tx decode` output, instead of protobuf")
+        .long("json")
+}
+
+fn load_raw_tx_of(m: &ArgMatches) -> Result<RawTransaction> {
+    let path = m.value_of("in").unwrap();
+    let raw = if m.is_present("json") {
+        load_raw_tx_json(path)?
+    } else {
+        load_raw_tx(path)?
+    };
+    ensure!(raw.tx.is_some(), "the raw tx is empty");
+    Ok(raw)
+}
+
+/// Build and sign a tx without connecting to the chain.
+pub fn build_tx<'help, Co, Ex, Ev>() -> Command<'help, Context<Co, Ex, Ev>> {
+    Command::<Context<Co, Ex, Ev>>::new("build-tx")
+        .about("Build and sign a tx offline, and save it to file for sending later")
+        .arg(
+            Arg::new("to")
+                .help("the target address of this tx")
+                .takes_value(true)
+                .required(true)
+                .validator(validate_addr),
+        )
+        .arg(
+            Arg::new("data")
+                .help("the data of this tx, hex or `@<path>` to read hex from file, `-` for stdin")
+                .takes_value(true)
+                .default_value("0x")
+                .validator(validate_data_input),
+        )
+        .arg(
+            Arg::new("data-binary")
+                .help("read the raw binary data of this tx from `@<path>` or `-` for stdin, instead of hex")
+                .long("data-binary")
+                .takes_value(true)
+                .conflicts_with("data")
+                .validator(validate_binary_input),
+        )
+        .arg(
+            Arg::new("value")
+                .help("the value of this tx, e.g. `1.5ether`, `200gwei`, `100`(wei) or `0x64`")
+                .short('v')
+                .long("value")
+                .takes_value(true)
+                .default_value("0x0")
+                .validator(parse_value),
+        )
+        .arg(quota_arg())
+        .arg(
+            Arg::new("valid-until-block")
+                .help("this tx is valid until the given block height. It must be absolute since we can't query the current height offline")
+                .long("until")
+                .takes_value(true)
+                .required(true)
+                .validator(str::parse::<u64>),
+        )
+        .arg(
+            Arg::new("chain-id")
+                .help("the chain id of the target chain, see `get system-config`")
+                .long("chain-id")
+                .takes_value(true)
+                .required(true)
+                .validator(parse_hash),
+        )
+        .arg(
+            Arg::new("tx-version")
+                .help("the version of the target chain, see `get system-config`")
+                .long("tx-version")
+                .takes_value(true)
+                .default_value("0")
+                .validator(str::parse::<u32>),
+        )
+        .arg(
+            Arg::new("nonce")
+                .help("the nonce of this tx, `rand` for a random one")
+                .long("nonce")
+                .takes_value(true)
+                .default_value("rand")
+                .validator(parse_nonce),
+        )
+        .arg(
+            Arg::new("out")
+                .help("the file to save the signed tx to")
+                .long("out")
+                .takes_value(true)
+                .required(true),
+        )
+        .arg(json_arg().help(
+            "Save the tx in JSON like the `-o json tx decode` output, instead of protobuf",
+        ))
+        .handler(|_cmd, m, ctx| {
+            let tx = CloudNormalTransaction {
+                version: m.value_of("tx-version").unwrap().parse()?,
+                to: ctx.parse_addr(m.value_of("to").unwrap())?.to_vec(),
+                data: match m.value_of("data-binary") {
+                    Some(input) => parse_binary_input(input)?,
+                    None => parse_data_input(m.value_of("data").unwrap())?,
+                },
+                value: parse_value(m.value_of("value").unwrap())?.to_vec(),
+                nonce: parse_nonce(m.value_of("nonce").unwrap())?
+                    .unwrap_or_else(|| rand::random::<u64>().to_string()),
+                quota: quota_of(ctx, m)?,
+                valid_until_block: m.value_of("valid-until-block").unwrap().parse()?,
+                chain_id: parse_hash(m.value_of("chain-id").unwrap())?.to_vec(),
+            };
+            let raw = ctx.signing_account()?.sign_raw_tx(tx);
+            let out = m.value_of("out").unwrap();
+            if m.is_present("json") {
+                save_raw_tx_json(out, &raw, false)?;
+            } else {
+                save_raw_tx(out, &raw, false)?;
+            }
+
+            ctx.emit(&hex(tx_hash(&raw)));
+            Ok(())
+        })
+}
+
+pub fn send_raw_tx<'help, Co, Ex, Ev>() -> Command<'help, Context<Co, Ex, Ev>>
+where
+    Co: ControllerBehaviour,
+    Ev: EvmBehaviour,
+{
+    Command::<Context<Co, Ex, Ev>>::new("send-raw-tx")
+        .about("Send a signed tx that is saved in file")
+        .arg(
+            Arg::new("in")
+                .help("the file containing the signed tx")
+                .long("in")
+                .takes_value(true)
+                .required(true),
+        )
+        .arg(json_arg())
+        .arg(wait_arg())
+        .arg(wait_timeout_arg())
+        .arg(confirmations_arg())
+        .arg(show_quota_arg())
+        .handler(|_cmd, m, ctx| {
+            let raw = load_raw_tx_of(m)?;
+            let quota_limit = quota_limit(&raw);
+            ctx.rt.block_on(async {
+                let tx_hash = ctx.controller.send_raw(raw).await?;
+                ctx.emit(&tx_hash);
+                maybe_wait_receipt(ctx, m, tx_hash, quota_limit).await
+            })?
+        })
+}
+
+/// Rebroadcast a saved tx, e.g. one dropped from the pool before being included.
+///
+/// Unlike `send-raw`, it's fine if the node already knows the tx.
+pub fn resend_tx<'help, Co, Ex, Ev>() -> Command<'help, Context<Co, Ex, Ev>>
+where
+    Co: ControllerBehaviour,
+    Ev: EvmBehaviour,
+{
+    Command::<Context<Co, Ex, Ev>>::new("resend-tx")
+        .about("Rebroadcast a signed tx that is saved in file")
+        .arg(
+            Arg::new("in")
+                .help("the file containing the signed tx")
+                .long("in")
+                .takes_value(true)
+                .required(true),
+        )
+        .arg(json_arg())
+        .arg(wait_arg())
+        .arg(wait_timeout_arg())
+        .arg(confirmations_arg())
+        .arg(show_quota_arg())
+        .handler(|_cmd, m, ctx| {
+            let raw = load_raw_tx_of(m)?;
+            let saved_hash = Hash::try_from_slice(tx_hash(&raw))
+                .context("the saved tx has an invalid tx hash")?;
+            let quota_limit = quota_limit(&raw);
+            ctx.rt.block_on(async {
+                let tx_hash = match ctx.controller.send_raw(raw).await {
+                    Ok(tx_hash) => tx_hash,
+                    Err(e) if e.is_dup_tx() => {
+                        info!(
+                            ctx.verbosity,
+                            "tx `{}` is already known by the node, it may be pending or included",
+                            hex(&saved_hash)
+                        );
+                        saved_hash
+                    }
+                    Err(e) => return Err(e.into()),
+                };
+                ctx.emit(&tx_hash);
+                maybe_wait_receipt(ctx, m, tx_hash, quota_limit).await
+            })?
+        })
+}
+
+/// Decode a serialized tx, e.g. one copied from logs, and check its hash and signatures.
+pub fn decode_tx<'help, Co, Ex, Ev>() -> Command<'help, Context<Co, Ex, Ev>> {
+    Command::<Context<Co, Ex, Ev>>::new("decode-tx")
+        .about("Decode a serialized RawTransaction without sending it")
+        .arg(
+            Arg::new("raw_tx")
+                .help("the protobuf-encoded RawTransaction in hex, or `@<path>` or `-` for stdin to read hex from")
+                .required(true)
+                .validator(validate_data_input),
+        )
+        .handler(|_cmd, m, ctx| {
+            let input = m.value_of("raw_tx").unwrap();
+            let bytes = parse_data_input(input)?;
+            let raw = RawTransaction::decode(bytes.as_slice()).with_context(|| {
+                format!("the input ({} bytes) is not a valid RawTransaction", bytes.len())
+            })?;
+            match &raw.tx {
+                Some(Tx::NormalTx(tx)) => ensure!(
+                    tx.transaction.is_some(),
+                    "the decoded normal tx has no inner transaction"
+                ),
+                Some(Tx::UtxoTx(utxo)) => ensure!(
+                    utxo.transaction.is_some(),
+                    "the decoded utxo tx has no inner transaction"
+                ),
+                None => bail!("the decoded RawTransaction is empty"),
+            }
+
+            let opts = ctx.display_options();
+            let mut tx_json = raw.to_json(opts);
+            let crypto_type = ctx.current_setting.crypto_type;
+            let computed_hash = match crypto_type {
+                CryptoType::Sm => check_raw_tx::<SmCrypto>(&raw, &mut tx_json, opts),
+                CryptoType::Eth => check_raw_tx::<EthCrypto>(&raw, &mut tx_json, opts),
+            };
+            if let Some(computed_hash) = computed_hash {
+                warning!(
+                    "tx `{}` mismatched its recomputed hash `{}`, is the crypto type `{crypto_type}` right?",
+                    hex(tx_hash(&raw)),
+                    hex(&computed_hash),
+                );
+            }
+            ctx.emit(&tx_json);
+            Ok(())
+        })
+}
+
+pub fn tx_cmd<'help, Co, Ex, Ev>() -> Command<'help, Context<Co, Ex, Ev>>
+where
+    Co: ControllerBehaviour,
+    Ev: EvmBehaviour,
+{
+    Command::<Context<Co, Ex, Ev>>::new("tx")
+        .about("Offline transaction commands")
+        .subcommand_required_else_help(true)
+        .subcommands([
+            build_tx().name("build"),
+            send_raw_tx().name("send-raw"),
+            resend_tx().name("resend"),
+            decode_tx().name("decode"),
+        ])
+}
+
+#[cfg(test)]
+mod tests {
+    use prost::Message;
+
+    use crate::core::controller::SignerBehaviour;
+    use crate::proto::blockchain::{RawTransaction, Transaction};
+
+    use crate::cmd::cldi_cmd;
+    use crate::core::mock::{context, tx};
+    use crate::crypto::Hash;
+
+    #[test]
+    fn test_tx_build_and_send_raw() {
+        let cldi_cmd = cldi_cmd();
+        let (mut ctx, temp_dir) = context();
+        let tx_file = temp_dir.path().join("test.tx");
+        let tx_file = tx_file.to_str().unwrap();
+
+        ctx.controller
+            .expect_send_raw()
+            .times(1)
+            .returning(|_raw| Ok(Hash::default()));
+        cldi_cmd
+            .exec_from(
+                [
+                    "cldi",
+                    "tx",
+                    "build",
+                    "0x0000000000000000000000000000000000000001",
+                    "--until",
+                    "100",
+                    "--chain-id",
+                    "0x0000000000000000000000000000000000000000000000000000000000000001",
+                    "--out",
+                    tx_file,
+                ],
+                &mut ctx,
+            )
+            .unwrap();
+
+        let bytes = std::fs::read(tx_file).unwrap();
+        let raw = crate::utils::load_raw_tx(tx_file).unwrap();
+        assert_eq!(raw.encode_to_vec(), bytes);
+
+        cldi_cmd
+            .exec_from(["cldi", "tx", "send-raw", "--in", tx_file], &mut ctx)
+            .unwrap();
+    }
+
+    #[test]
+    fn test_tx_json() {
+        use crate::crypto::{EthCrypto, SmCrypto};
+        use crate::display::{check_raw_tx, Display, DisplayOptions};
+        use crate::proto::blockchain::UtxoTransaction;
+        use crate::utils::{load_raw_tx_json, raw_tx_from_json};
+        use std::sync::{Arc, Mutex};
+
+        let cldi_cmd = cldi_cmd();
+        let (mut ctx, temp_dir) = context();
+        let tx_file = temp_dir.path().join("test.json");
+        let tx_file = tx_file.to_str().unwrap();
+
+        let sent = Arc::new(Mutex::new(vec![]));
+        let sent_cloned = sent.clone();
+        ctx.controller
+            .expect_send_raw()
+            .times(1)
+            .returning(move |raw| {
+                sent_cloned.lock().unwrap().push(raw);
+                Ok(Hash::default())
+            });
+        cldi_cmd
+            .exec_from(
+                [
+                    "cldi",
+                    "tx",
+                    "build",
+                    "0x0000000000000000000000000000000000000001",
+                    "--until",
+                    "100",
+                    "--chain-id",
+                    "0x0000000000000000000000000000000000000000000000000000000000000001",
+                    "--out",
+                    tx_file,
+                    "--json",
+                ],
+                &mut ctx,
+            )
+            .unwrap();
+        let raw = load_raw_tx_json(tx_file).unwrap();
+        cldi_cmd
+            .exec_from(
+                ["cldi", "tx", "send-raw", "--in", tx_file, "--json"],
+                &mut ctx,
+            )
+            .unwrap();
+        assert_eq!(sent.lock().unwrap()[..], [raw]);
+        assert!(cldi_cmd
+            .exec_from(["cldi", "tx", "send-raw", "--in", tx_file], &mut ctx)
+            .is_err());
+
+        // Round trips with an empty `to`, utxo and the extra fields of `tx decode`.
+        let account = ctx.current_account().unwrap();
+        let create = Transaction {
+            to: vec![],
+            data: vec![0x60; 10],
+            ..tx()
+        };
+        let utxo = UtxoTransaction {
+            version: 1,
+            pre_tx_hash: vec![2; 32],
+            output: vec![3; 4],
+            lock_id: 1002,
+        };
+        for raw in [account.sign_raw_tx(create), account.sign_raw_utxo(utxo)] {
+            let mut json = raw.to_json(DisplayOptions::default());
+            assert_eq!(
+                raw_tx_from_json(&json).unwrap().encode_to_vec(),
+                raw.encode_to_vec()
+            );
+            check_raw_tx::<SmCrypto>(&raw, &mut json, DisplayOptions::default());
+            check_raw_tx::<EthCrypto>(&raw, &mut json, DisplayOptions::default());
+            assert_eq!(raw_tx_from_json(&json).unwrap(), raw);
+        }
+        assert!(raw_tx_from_json(&serde_json::json!({ "type": "Normal" })).is_err());
+    }
+
+    #[test]
+    fn test_tx_resend() {
+        use crate::core::error::SdkError;
+        use tonic::Status;
+
+        let cldi_cmd = cldi_cmd();
+        let (mut ctx, temp_dir) = context();
+        let tx_file = temp_dir.path().join("test.tx");
+        let tx_file = tx_file.to_str().unwrap();
+        let tx = tx();
+        let raw = ctx.current_account().unwrap().sign_raw_tx(tx);
+        crate::utils::save_raw_tx(tx_file, &raw, false).unwrap();
+
+        let mut seq = mockall::Sequence::new();
+        ctx.controller
+            .expect_send_raw()
+            .times(1)
+            .in_sequence(&mut seq)
+            .returning(|_| {
+                Err(SdkError::from_status(
+                    "controller",
+                    1,
+                    Status::invalid_argument("DupTransaction"),
+                ))
+            });
+        ctx.controller
+            .expect_send_raw()
+            .times(1)
+            .in_sequence(&mut seq)
+            .returning(|_| {
+                Err(SdkError::from_status(
+                    "controller",
+                    1,
+                    Status::invalid_argument("Expired"),
+                ))
+            });
+
+        cldi_cmd
+            .exec_from(["cldi", "tx", "resend", "--in", tx_file], &mut ctx)
+            .unwrap();
+        assert!(cldi_cmd
+            .exec_from(["cldi", "tx", "resend", "--in", tx_file], &mut ctx)
+            .is_err());
+    }
+
+    #[test]
+    fn test_tx_decode() {
+        let cldi_cmd = cldi_cmd();
+        let (mut ctx, _temp_dir) = context();
+
+        let tx = tx();
+        let raw = ctx.current_account().unwrap().sign_raw_tx(tx);
+        let raw_hex = hex::encode(raw.encode_to_vec());
+        cldi_cmd
+            .exec_from(["cldi", "tx", "decode", &raw_hex], &mut ctx)
+            .unwrap();
+
+        let err = cldi_cmd
+            .exec_from(["cldi", "tx", "decode", "0xffff"], &mut ctx)
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "the input (2 bytes) is not a valid RawTransaction"
+        );
+        let empty = hex::encode(RawTransaction::default().encode_to_vec());
+        let err = cldi_cmd
+            .exec_from(["cldi", "tx", "decode", &format!("0x{empty}")], &mut ctx)
+            .unwrap_err();
+        assert_eq!(err.to_string(), "the decoded RawTransaction is empty");
+    }
+}
@@ -17,8 +17,9 @@ use clap::Arg;
 use std::time::Duration;
 
 use crate::{
-    cmd::Command,
-    core::{context::Context, controller::ControllerBehaviour},
+    cmd::{cldi::executor_addr_arg, Command},
+    core::{context::Context, controller::ControllerBehaviour, error::is_dry_run},
+    log::warning,
     utils::parse_position,
 };
 
@@ -46,6 +47,8 @@ where
                 .allow_hyphen_values(true)
                 .validator(parse_position),
         )
+        // It shadows the global one, so that `-e` is for `--end`.
+        .arg(executor_addr_arg())
         .arg(
             Arg::new("until-finalized-txs")
                 .help("stop watching when finalized txs reach the given limit")
@@ -86,17 +89,18 @@ where
                     let current_height = match ctx.controller.get_block_number(false).await {
                         Ok(current) => current,
                         Err(e) => {
-                            println!("failed to get current height: `{e}`");
+                            warning!("failed to get current height: `{e}`");
                             retry_interval.tick().await;
                             continue;
                         }
                     };
 
                     while h <= std::cmp::min(current_height, end) {
-                        let block = match ctx.controller.get_block_by_number(h).await {
+                        let block = match ctx.block_cache.get_block_by_number(&ctx.controller, h).await {
                             Ok(block) => block,
+                            Err(e) if is_dry_run(&e) => return Err(e),
                             Err(e) => {
-                                println!("failed to get block `{h}`: `{e}`");
+                                warning!("failed to get block `{h}`: `{e}`");
                                 retry_interval.tick().await;
                                 continue;
                             },
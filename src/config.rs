@@ -16,6 +16,7 @@ use std::collections::BTreeMap;
 use std::fs;
 use std::fs::File;
 use std::io::Write;
+use std::num::NonZeroUsize;
 use std::path::Path;
 use std::path::PathBuf;
 use std::str::FromStr;
@@ -28,6 +29,10 @@ use crate::utils::safe_save;
 
 pub const CLOUD_CLI_CONFIG_FILE_NAME: &str = "config.toml";
 pub const CLOUD_CLI_DATA_DIR_NAME: &str = ".cloud-cli";
+pub const CLOUD_CLI_HISTORY_FILE_NAME: &str = "history";
+
+pub const DEFAULT_QUOTA: u64 = 3_000_000;
+pub const DEFAULT_VALID_UNTIL_OFFSET: u64 = 95;
 
 #[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Config {
@@ -35,8 +40,32 @@ pub struct Config {
     #[serde(skip)]
     pub data_dir: PathBuf,
 
+    // Context settings are also called profiles.
+    #[serde(alias = "default_profile")]
     pub default_context: String,
+
+    /// The quota of txs if `--quota` isn't given.
+    #[serde(default = "default_quota")]
+    pub default_quota: u64,
+    /// Txs are valid until `<current-height> + valid_until_offset` if `--until` isn't given.
+    #[serde(default = "default_valid_until_offset")]
+    pub valid_until_offset: u64,
+
+    #[serde(alias = "profiles")]
     pub context_settings: BTreeMap<String, ContextSetting>,
+
+    /// Address aliases, commands accept `@<alias>` or a bare alias in place of an address.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub address_book: BTreeMap<String, String>,
+
+    /// Where the interactive mode keeps its command history, relative paths are relative to the data dir.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub history_file: Option<PathBuf>,
+
+    /// Worker threads of the async runtime, default to the number of CPU cores.
+    /// More threads may help heavy bench workloads, but each of them reserves its own stack(2 MiB).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub worker_threads: Option<NonZeroUsize>,
 }
 
 impl Config {
@@ -67,6 +96,102 @@ impl Config {
         let content = toml::to_string_pretty(self)?;
         safe_save(path, content.as_bytes(), true)
     }
+
+    /// The wallet dir used by the setting, relative paths are relative to the data dir.
+    /// It's expanded by [`expand_path`].
+    pub fn wallet_dir(&self, setting: &ContextSetting) -> PathBuf {
+        match &setting.wallet_dir {
+            Some(wallet_dir) => self.data_dir.join(expand_path(wallet_dir)),
+            None => self.data_dir.clone(),
+        }
+    }
+
+    /// The history file of the interactive mode, `<data-dir>/history` by default.
+    pub fn history_file(&self) -> PathBuf {
+        match &self.history_file {
+            Some(history_file) => self.data_dir.join(expand_path(history_file)),
+            None => self.data_dir.join(CLOUD_CLI_HISTORY_FILE_NAME),
+        }
+    }
+}
+
+/// Expand `~`, env vars like `$HOME`, `${HOME}` or `%USERPROFILE%`, and strip `file://`,
+/// so that the paths in config work across machines.
+///
+/// They are expanded when used rather than when loaded, so that saving the config keeps them as is.
+/// Unknown env vars are kept as is.
+pub fn expand_path(path: &Path) -> PathBuf {
+    expand_path_with(path, |name| std::env::var(name).ok())
+}
+
+/// [`expand_path`] with the env vars looked up by `env`.
+fn expand_path_with(path: &Path, env: impl Fn(&str) -> Option<String>) -> PathBuf {
+    let s = match path.to_str() {
+        Some(s) => s,
+        None => return path.to_path_buf(),
+    };
+    let s = s.strip_prefix("file://").unwrap_or(s);
+    let s = expand_env_vars(s, env);
+    match (s.strip_prefix('~'), home::home_dir()) {
+        (Some(""), Some(home)) => home,
+        (Some(rest), Some(home)) if rest.starts_with(['/', '\\']) => {
+            home.join(rest.trim_start_matches(['/', '\\']))
+        }
+        _ => PathBuf::from(s),
+    }
+}
+
+fn expand_env_vars(s: &str, env: impl Fn(&str) -> Option<String>) -> String {
+    let var = |name: &str| {
+        env(name).or_else(|| {
+            // They may be missing on the other OS.
+            matches!(name, "HOME" | "USERPROFILE")
+                .then(home::home_dir)
+                .flatten()
+                .and_then(|home| home.to_str().map(str::to_string))
+        })
+    };
+    let is_name_char = |c: char| c.is_ascii_alphanumeric() || c == '_';
+
+    let mut expanded = String::new();
+    let mut rest = s;
+    while let Some(start) = rest.find(['$', '%']) {
+        expanded.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+        // (name, the text it replaces)
+        let found = if rest[start..].starts_with('%') {
+            after
+                .find('%')
+                .map(|end| (&after[..end], &rest[start..start + end + 2]))
+        } else if let Some(braced) = after.strip_prefix('{') {
+            braced
+                .find('}')
+                .map(|end| (&braced[..end], &rest[start..start + end + 3]))
+        } else {
+            let end = after.find(|c| !is_name_char(c)).unwrap_or(after.len());
+            Some((&after[..end], &rest[start..start + end + 1]))
+        };
+        match found {
+            Some((name, text)) if !name.is_empty() && name.chars().all(is_name_char) => {
+                expanded.push_str(&var(name).unwrap_or_else(|| text.to_string()));
+                rest = &rest[start + text.len()..];
+            }
+            _ => {
+                expanded.push_str(&rest[start..start + 1]);
+                rest = after;
+            }
+        }
+    }
+    expanded.push_str(rest);
+    expanded
+}
+
+fn default_quota() -> u64 {
+    DEFAULT_QUOTA
+}
+
+fn default_valid_until_offset() -> u64 {
+    DEFAULT_VALID_UNTIL_OFFSET
 }
 
 impl Default for Config {
@@ -84,7 +209,12 @@ impl Default for Config {
         Self {
             default_context,
             data_dir,
+            default_quota: DEFAULT_QUOTA,
+            valid_until_offset: DEFAULT_VALID_UNTIL_OFFSET,
             context_settings,
+            address_book: BTreeMap::new(),
+            history_file: None,
+            worker_threads: None,
         }
     }
 }
@@ -98,11 +228,90 @@ pub enum CryptoType {
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct ContextSetting {
+    #[serde(alias = "controller")]
     pub controller_addr: String,
+    #[serde(alias = "executor")]
     pub executor_addr: String,
 
+    #[serde(alias = "default_account")]
     pub account_name: String,
     pub crypto_type: CryptoType,
+
+    /// Use a separate wallet instead of the one in data dir.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub wallet_dir: Option<PathBuf>,
+
+    /// Only used for `https://` addresses.
+    #[serde(default, skip_serializing_if = "TlsSetting::is_empty")]
+    pub tls: TlsSetting,
+
+    /// Number of connections to each service, requests are spread over them.
+    #[serde(
+        default = "default_pool_size",
+        skip_serializing_if = "is_default_pool_size"
+    )]
+    pub pool_size: usize,
+
+    #[serde(default, skip_serializing_if = "KeepAliveSetting::is_default")]
+    pub keep_alive: KeepAliveSetting,
+}
+
+fn default_pool_size() -> usize {
+    1
+}
+
+fn is_default_pool_size(pool_size: &usize) -> bool {
+    *pool_size == default_pool_size()
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TlsSetting {
+    /// PEM encoded CA certificate to verify the server with, system roots are used if not set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ca_cert: Option<PathBuf>,
+    /// Domain name for SNI and certificate verification, useful when the address is an IP.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub domain_name: Option<String>,
+}
+
+impl TlsSetting {
+    pub fn is_empty(&self) -> bool {
+        self.ca_cert.is_none() && self.domain_name.is_none()
+    }
+}
+
+/// HTTP/2 keep-alive pings, so that an idle connection in a long interactive session isn't
+/// dropped silently by the network, and the next request doesn't pay for reconnecting.
+///
+/// A connection whose ping isn't acked in time is closed and reconnected on the next request,
+/// so a node that doesn't answer pings costs a reconnect instead of a failure.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct KeepAliveSetting {
+    /// Seconds between pings, 0 disables keep-alive.
+    pub interval: u64,
+    /// Seconds to wait for the ping ack before closing the connection.
+    pub timeout: u64,
+    /// Also ping when there is no request in flight.
+    pub while_idle: bool,
+}
+
+impl KeepAliveSetting {
+    pub const DEFAULT: Self = Self {
+        interval: 30,
+        timeout: 20,
+        while_idle: true,
+    };
+
+    pub fn is_default(&self) -> bool {
+        *self == Self::DEFAULT
+    }
+}
+
+impl Default for KeepAliveSetting {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
 }
 
 impl FromStr for CryptoType {
@@ -125,6 +334,10 @@ impl Default for ContextSetting {
             executor_addr: "localhost:50002".into(),
             account_name: "default".into(),
             crypto_type: CryptoType::Sm,
+            wallet_dir: None,
+            tls: TlsSetting::default(),
+            pool_size: default_pool_size(),
+            keep_alive: KeepAliveSetting::default(),
         }
     }
 }
@@ -137,3 +350,129 @@ impl std::fmt::Display for CryptoType {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_profiles() {
+        let flat = r#"
+            default_context = "default"
+            [context_settings.default]
+            controller_addr = "localhost:50004"
+            executor_addr = "localhost:50002"
+            account_name = "default"
+            crypto_type = "SM"
+        "#;
+        let config: Config = toml::from_str(flat).unwrap();
+        assert_eq!(
+            config.context_settings["default"],
+            ContextSetting::default()
+        );
+
+        let profiles = r#"
+            default_profile = "testnet"
+            [profiles.testnet]
+            controller = "testnet:50004"
+            executor = "testnet:50002"
+            default_account = "alice"
+            crypto_type = "ETH"
+            wallet_dir = "testnet-wallet"
+        "#;
+        let config: Config = toml::from_str(profiles).unwrap();
+        assert_eq!(config.default_context, "testnet");
+        let testnet = &config.context_settings["testnet"];
+        assert_eq!(testnet.controller_addr, "testnet:50004");
+        assert_eq!(testnet.account_name, "alice");
+        assert_eq!(
+            config.wallet_dir(testnet),
+            config.data_dir.join("testnet-wallet")
+        );
+        assert_eq!(testnet.keep_alive, KeepAliveSetting::DEFAULT);
+
+        let keep_alive = r#"
+            controller_addr = "localhost:50004"
+            executor_addr = "localhost:50002"
+            account_name = "default"
+            crypto_type = "SM"
+            [keep_alive]
+            interval = 0
+        "#;
+        let setting: ContextSetting = toml::from_str(keep_alive).unwrap();
+        assert_eq!(
+            setting.keep_alive,
+            KeepAliveSetting {
+                interval: 0,
+                ..KeepAliveSetting::DEFAULT
+            }
+        );
+    }
+
+    #[test]
+    fn test_worker_threads() {
+        let config = r#"
+            default_context = "default"
+            worker_threads = 2
+            [context_settings]
+        "#;
+        let config: Config = toml::from_str(config).unwrap();
+        assert_eq!(config.worker_threads, NonZeroUsize::new(2));
+
+        let zero = r#"
+            default_context = "default"
+            worker_threads = 0
+            [context_settings]
+        "#;
+        assert!(toml::from_str::<Config>(zero).is_err());
+    }
+
+    #[test]
+    fn test_expand_path() {
+        // Inject the env vars rather than set them, which races with the other tests.
+        let env = |name: &str| (name == "CLDI_TEST_EXPAND_PATH").then(|| "wallets".to_string());
+        let home = home::home_dir().unwrap();
+        assert_eq!(expand_path_with(Path::new("~"), env), home);
+        assert_eq!(
+            expand_path_with(Path::new("~/wallets"), env),
+            home.join("wallets")
+        );
+        assert_eq!(
+            expand_path_with(Path::new("~\\wallets"), env),
+            home.join("wallets")
+        );
+        assert_eq!(expand_path_with(Path::new("$HOME"), env), home);
+        assert_eq!(expand_path_with(Path::new("%USERPROFILE%"), env), home);
+
+        assert_eq!(
+            expand_path_with(
+                Path::new("file:///data/${CLDI_TEST_EXPAND_PATH}/$CLDI_TEST_EXPAND_PATH"),
+                env
+            ),
+            PathBuf::from("/data/wallets/wallets")
+        );
+        assert_eq!(
+            expand_path_with(Path::new("a/%CLDI_TEST_EXPAND_PATH%"), env),
+            PathBuf::from("a/wallets")
+        );
+        // Unknown env vars and stray `$`, `%` are kept.
+        assert_eq!(
+            expand_path_with(Path::new("$CLDI_TEST_UNKNOWN/100%/$"), env),
+            PathBuf::from("$CLDI_TEST_UNKNOWN/100%/$")
+        );
+        assert_eq!(
+            expand_path_with(Path::new("a~/b"), env),
+            PathBuf::from("a~/b")
+        );
+
+        let config = Config {
+            data_dir: PathBuf::from("data"),
+            ..Default::default()
+        };
+        let setting = ContextSetting {
+            wallet_dir: Some("~/wallets".into()),
+            ..Default::default()
+        };
+        assert_eq!(config.wallet_dir(&setting), home.join("wallets"));
+    }
+}
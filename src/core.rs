@@ -13,11 +13,15 @@
 // limitations under the License.
 
 pub mod admin;
+pub mod cache;
 pub mod client;
+#[cfg(feature = "cli")]
 pub mod context;
 pub mod controller;
+pub mod error;
 pub mod evm;
 pub mod executor;
 #[cfg(test)]
 pub mod mock;
+pub mod signer;
 pub mod wallet;
@@ -12,10 +12,37 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use super::controller::{SignerBehaviour, TransactionSenderBehaviour, UtxoType};
+use super::controller::{TransactionSenderBehaviour, UtxoType};
+use super::signer::Signer;
 use crate::crypto::{Address, ArrayLike, Hash};
 use anyhow::{Context, Result};
 
+// Those utxo output formats are defined by controller.
+
+pub fn update_admin_output(new_admin_addr: Address) -> Vec<u8> {
+    new_admin_addr.to_vec()
+}
+
+pub fn set_block_interval_output(block_interval: u32) -> Vec<u8> {
+    block_interval.to_be_bytes().to_vec()
+}
+
+pub fn update_validators_output(validators: &[Address]) -> Vec<u8> {
+    let mut output = vec![];
+    validators
+        .iter()
+        .for_each(|v| output.extend_from_slice(v.as_slice()));
+    output
+}
+
+pub fn emergency_brake_output(switch: bool) -> Vec<u8> {
+    if switch {
+        vec![0]
+    } else {
+        vec![]
+    }
+}
+
 /// CITA-Cloud's system config is managed by [UTXO](https://github.com/cita-cloud/rfcs/blob/master/rfcs/0002-technology/0002-technology.md#%E7%B3%BB%E7%BB%9F%E9%85%8D%E7%BD%AE).
 /// Admin commands depend on and will change system config.
 #[tonic::async_trait]
@@ -23,16 +50,16 @@ pub trait AdminBehaviour {
     // TODO: maybe we can use some concrete error types that allows user to handle them better.
     async fn update_admin<S>(&self, old_admin_signer: &S, new_admin_addr: Address) -> Result<Hash>
     where
-        S: SignerBehaviour + Send + Sync;
+        S: Signer + ?Sized;
     async fn set_block_interval<S>(&self, admin_signer: &S, block_interval: u32) -> Result<Hash>
     where
-        S: SignerBehaviour + Send + Sync;
+        S: Signer + ?Sized;
     async fn update_validators<S>(&self, admin_signer: &S, validators: &[Address]) -> Result<Hash>
     where
-        S: SignerBehaviour + Send + Sync;
+        S: Signer + ?Sized;
     async fn emergency_brake<S>(&self, admin_signer: &S, switch: bool) -> Result<Hash>
     where
-        S: SignerBehaviour + Send + Sync;
+        S: Signer + ?Sized;
 }
 
 #[tonic::async_trait]
@@ -40,13 +67,11 @@ impl<T> AdminBehaviour for T
 where
     T: TransactionSenderBehaviour + Send + Sync,
 {
-    // Those utxo output formats are defined by controller.
-
     async fn update_admin<S>(&self, old_admin_signer: &S, new_admin_addr: Address) -> Result<Hash>
     where
-        S: SignerBehaviour + Send + Sync,
+        S: Signer + ?Sized,
     {
-        let output = new_admin_addr.to_vec();
+        let output = update_admin_output(new_admin_addr);
         self.send_utxo(old_admin_signer, output, UtxoType::Admin)
             .await
             .context("failed to send `update_admin` utxo")
@@ -54,9 +79,9 @@ where
 
     async fn set_block_interval<S>(&self, admin_signer: &S, block_interval: u32) -> Result<Hash>
     where
-        S: SignerBehaviour + Send + Sync,
+        S: Signer + ?Sized,
     {
-        let output = block_interval.to_be_bytes().to_vec();
+        let output = set_block_interval_output(block_interval);
         self.send_utxo(admin_signer, output, UtxoType::BlockInterval)
             .await
             .context("failed to send `set_block_interval` utxo")
@@ -64,16 +89,9 @@ where
 
     async fn update_validators<S>(&self, admin_signer: &S, validators: &[Address]) -> Result<Hash>
     where
-        S: SignerBehaviour + Send + Sync,
+        S: Signer + ?Sized,
     {
-        let output = {
-            let mut output = vec![];
-            validators
-                .iter()
-                .for_each(|v| output.extend_from_slice(v.as_slice()));
-            output
-        };
-
+        let output = update_validators_output(validators);
         self.send_utxo(admin_signer, output, UtxoType::Validators)
             .await
             .context("failed to send `update_validators` utxo")
@@ -81,9 +99,9 @@ where
 
     async fn emergency_brake<S>(&self, admin_signer: &S, switch: bool) -> Result<Hash>
     where
-        S: SignerBehaviour + Send + Sync,
+        S: Signer + ?Sized,
     {
-        let output = if switch { vec![0] } else { vec![] };
+        let output = emergency_brake_output(switch);
         self.send_utxo(admin_signer, output, UtxoType::EmergencyBrake)
             .await
             .context("failed to send `emergency_brake` utxo")
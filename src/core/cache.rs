@@ -0,0 +1,306 @@
+// Copyright Rivtower Technologies LLC.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::HashMap;
+use std::num::NonZeroUsize;
+use std::sync::Arc;
+
+use anyhow::{Context as _, Result};
+use ethabi::Contract;
+use lru::LruCache;
+use parking_lot::Mutex;
+
+use super::{controller::ControllerBehaviour, evm::EvmBehaviour};
+use crate::crypto::{Address, ArrayLike, Hash};
+use crate::proto::blockchain::CompactBlock;
+
+const DEFAULT_CAPACITY: usize = 4096;
+
+/// A hash -> height cache that lets `get_block_by_hash` be served by the cheaper `get_block_by_number`.
+///
+/// It's populated whenever we learn a block's height, i.e. from `get_block_hash`, `get_block_by_hash`
+/// and the prevhash in the header of any fetched block.
+/// The whole cache is dropped once a reorg is detected (same height, different hash).
+pub struct BlockHashCache {
+    enabled: bool,
+    inner: Mutex<Inner>,
+}
+
+struct Inner {
+    hash_to_height: LruCache<Hash, u64>,
+    height_to_hash: LruCache<u64, Hash>,
+}
+
+impl BlockHashCache {
+    pub fn new() -> Self {
+        Self::with_capacity(DEFAULT_CAPACITY)
+    }
+
+    pub fn with_capacity(capacity: usize) -> Self {
+        let capacity = NonZeroUsize::new(capacity).unwrap_or(NonZeroUsize::MIN);
+        Self {
+            enabled: true,
+            inner: Mutex::new(Inner {
+                hash_to_height: LruCache::new(capacity),
+                height_to_hash: LruCache::new(capacity),
+            }),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+    }
+
+    pub fn clear(&self) {
+        let mut inner = self.inner.lock();
+        inner.hash_to_height.clear();
+        inner.height_to_hash.clear();
+    }
+
+    pub fn get_height(&self, hash: &Hash) -> Option<u64> {
+        if !self.enabled {
+            return None;
+        }
+        self.inner.lock().hash_to_height.get(hash).copied()
+    }
+
+    pub fn insert(&self, hash: Hash, height: u64) {
+        if !self.enabled {
+            return;
+        }
+        let mut inner = self.inner.lock();
+        if let Some(known) = inner.height_to_hash.get(&height) {
+            if known != &hash {
+                // Reorg detected, what we have learned may be all wrong.
+                inner.hash_to_height.clear();
+                inner.height_to_hash.clear();
+            }
+        }
+        inner.hash_to_height.put(hash, height);
+        inner.height_to_hash.put(height, hash);
+    }
+
+    /// Learn the height of the block's parent.
+    pub fn observe_block(&self, block: &CompactBlock) {
+        if let Some(header) = &block.header {
+            if let (Some(parent_height), Ok(prevhash)) = (
+                header.height.checked_sub(1),
+                Hash::try_from_slice(&header.prevhash),
+            ) {
+                self.insert(prevhash, parent_height);
+            }
+        }
+    }
+
+    pub async fn get_block_hash<Co: ControllerBehaviour>(
+        &self,
+        controller: &Co,
+        height: u64,
+    ) -> Result<Hash> {
+        let hash = controller.get_block_hash(height).await?;
+        self.insert(hash, height);
+        Ok(hash)
+    }
+
+    pub async fn get_block_by_number<Co: ControllerBehaviour>(
+        &self,
+        controller: &Co,
+        height: u64,
+    ) -> Result<CompactBlock> {
+        let block = controller.get_block_by_number(height).await?;
+        self.observe_block(&block);
+        Ok(block)
+    }
+
+    pub async fn get_block_by_hash<Co: ControllerBehaviour>(
+        &self,
+        controller: &Co,
+        hash: Hash,
+    ) -> Result<CompactBlock> {
+        if let Some(height) = self.get_height(&hash) {
+            let (block, current_hash) = futures::try_join!(
+                controller.get_block_by_number(height),
+                controller.get_block_hash(height),
+            )?;
+            // This also invalidates the cache if it's reorged.
+            self.insert(current_hash, height);
+            if current_hash == hash {
+                self.observe_block(&block);
+                return Ok(block);
+            }
+        }
+
+        let block = controller.get_block_by_hash(hash).await?;
+        if let Some(header) = &block.header {
+            self.insert(hash, header.height);
+        }
+        self.observe_block(&block);
+        Ok(block)
+    }
+}
+
+impl Default for BlockHashCache {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Parsed contract ABIs by contract address, so that they are fetched and parsed only once in a session.
+///
+/// Contracts without a stored ABI aren't cached, it may be stored later.
+#[derive(Default)]
+pub struct AbiCache {
+    abis: Mutex<HashMap<Address, Arc<Contract>>>,
+}
+
+impl AbiCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.abis.lock().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.abis.lock().is_empty()
+    }
+
+    pub fn clear(&self) {
+        self.abis.lock().clear();
+    }
+
+    pub fn get(&self, addr: &Address) -> Option<Arc<Contract>> {
+        self.abis.lock().get(addr).cloned()
+    }
+
+    pub fn insert(&self, addr: Address, abi: Arc<Contract>) {
+        self.abis.lock().insert(addr, abi);
+    }
+
+    /// Returns the cached ABI, or fetch it by `get_abi`. `None` if the contract has no ABI stored.
+    pub async fn get_or_fetch<Ev: EvmBehaviour>(
+        &self,
+        evm: &Ev,
+        addr: Address,
+    ) -> Result<Option<Arc<Contract>>> {
+        if let Some(abi) = self.get(&addr) {
+            return Ok(Some(abi));
+        }
+        let byte_abi = evm.get_abi(addr).await?;
+        if byte_abi.bytes_abi.is_empty() {
+            return Ok(None);
+        }
+        let abi = Contract::load(byte_abi.bytes_abi.as_slice())
+            .context("invalid ABI stored for the contract")?;
+        let abi = Arc::new(abi);
+        self.insert(addr, abi.clone());
+        Ok(Some(abi))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::mock::{MockControllerClient, MockEvmClient};
+    use crate::proto::{blockchain::BlockHeader, evm::ByteAbi};
+
+    fn block(height: u64, prevhash: Hash) -> CompactBlock {
+        CompactBlock {
+            header: Some(BlockHeader {
+                height,
+                prevhash: prevhash.to_vec(),
+                ..Default::default()
+            }),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_block_hash_cache() {
+        let cache = BlockHashCache::new();
+        cache.insert([1; 32], 1);
+        cache.observe_block(&block(3, [2; 32]));
+        assert_eq!(cache.get_height(&[1; 32]), Some(1));
+        assert_eq!(cache.get_height(&[2; 32]), Some(2));
+
+        // reorg at height 2
+        cache.insert([22; 32], 2);
+        assert_eq!(cache.get_height(&[1; 32]), None);
+        assert_eq!(cache.get_height(&[2; 32]), None);
+        assert_eq!(cache.get_height(&[22; 32]), Some(2));
+
+        let mut cache = BlockHashCache::new();
+        cache.set_enabled(false);
+        cache.insert([1; 32], 1);
+        assert_eq!(cache.get_height(&[1; 32]), None);
+    }
+
+    #[test]
+    fn test_get_block_by_hash_from_cache() {
+        let mut controller = MockControllerClient::default();
+        controller
+            .expect_get_block_by_number()
+            .returning(|h| Ok(block(h, [h as u8 - 1; 32])));
+        controller
+            .expect_get_block_hash()
+            .returning(|h| Ok([h as u8; 32]));
+        controller
+            .expect_get_block_by_hash()
+            .times(1)
+            .returning(|hash| Ok(block(hash[0] as u64, [hash[0] - 1; 32])));
+
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        let cache = BlockHashCache::new();
+        rt.block_on(async {
+            // Learn 9 -> [9; 32] from block 10's header.
+            cache.get_block_by_number(&controller, 10).await.unwrap();
+            let b = cache.get_block_by_hash(&controller, [9; 32]).await.unwrap();
+            assert_eq!(b.header.unwrap().height, 9);
+            // Not in cache, ask controller.
+            let b = cache.get_block_by_hash(&controller, [5; 32]).await.unwrap();
+            assert_eq!(b.header.unwrap().height, 5);
+        });
+    }
+
+    #[test]
+    fn test_abi_cache() {
+        let abi = r#"[{"type":"event","name":"Ping","anonymous":false,"inputs":[]}]"#;
+        let mut evm = MockEvmClient::default();
+        evm.expect_get_abi().times(2).returning(move |addr| {
+            let bytes_abi = if addr == [1; 20] { abi } else { "" };
+            Ok(ByteAbi {
+                bytes_abi: bytes_abi.as_bytes().to_vec(),
+            })
+        });
+
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        let cache = AbiCache::new();
+        rt.block_on(async {
+            for _ in 0..2 {
+                let abi = cache.get_or_fetch(&evm, [1; 20]).await.unwrap().unwrap();
+                assert!(abi.event("Ping").is_ok());
+            }
+            // No ABI stored.
+            assert!(cache.get_or_fetch(&evm, [2; 20]).await.unwrap().is_none());
+        });
+        assert_eq!(cache.len(), 1);
+        cache.clear();
+        assert!(cache.is_empty());
+    }
+}
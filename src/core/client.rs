@@ -12,63 +12,734 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use anyhow::Result;
+use anyhow::{Context as _, Result};
+use std::fmt::Debug;
+use std::fs;
+use std::future::Future;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
 use std::time::Duration;
 use tonic::transport::Channel;
-use tonic::transport::Endpoint;
+use tonic::transport::{Certificate, ClientTlsConfig, Endpoint};
+use tonic::{Code, Status};
 
-use super::{controller::ControllerClient, evm::EvmClient, executor::ExecutorClient};
+use super::{
+    controller::{ControllerBehaviour, ControllerClient},
+    error::{SdkError, SdkResult},
+    evm::{EvmBehaviour, EvmClient},
+    executor::{ExecutorBehaviour, ExecutorClient},
+};
+use crate::config::{expand_path, ContextSetting, KeepAliveSetting, TlsSetting};
+use crate::crypto::{Address, Hash};
+use crate::log::{debug, Verbosity};
+use crate::proto::{
+    self,
+    blockchain::{CompactBlock, RawTransaction},
+    common::{Hash as CloudHash, TotalNodeInfo},
+    controller::SystemConfig,
+    evm::{Balance, ByteAbi, ByteCode, ByteQuota, Nonce, Receipt},
+    executor::{CallRequest, CallResponse},
+};
 
 const TCP_KEEPALIVE: Duration = Duration::from_secs(60);
 
+pub const DEFAULT_REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+pub const DEFAULT_RETRY_BACKOFF: Duration = Duration::from_millis(200);
+
+/// How the requests are sent, set by `--timeout`, `--retries`, `--retry-backoff` and `--dry-run`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RequestPolicy {
+    /// For each attempt, connecting time included.
+    pub timeout: Duration,
+    /// Max retries of transient failures, no retry by default.
+    pub retries: u32,
+    /// The initial backoff between retries, doubled after each retry.
+    pub retry_backoff: Duration,
+    /// See [`check_dry_run`].
+    pub dry_run: bool,
+    /// The requests and responses are logged if it's [`Verbosity::Verbose`].
+    pub verbosity: Verbosity,
+}
+
+impl RequestPolicy {
+    pub const DEFAULT: Self = Self {
+        timeout: DEFAULT_REQUEST_TIMEOUT,
+        retries: 0,
+        retry_backoff: DEFAULT_RETRY_BACKOFF,
+        dry_run: false,
+        verbosity: Verbosity::Normal,
+    };
+}
+
+impl Default for RequestPolicy {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+/// A tonic client with the [`RequestPolicy`] for its requests.
+#[derive(Debug, Clone)]
+pub struct GrpcClient<T> {
+    pub(crate) inner: T,
+    pub(crate) policy: RequestPolicy,
+}
+
+impl<T> GrpcClient<T> {
+    pub fn new(inner: T) -> Self {
+        Self {
+            inner,
+            policy: RequestPolicy::DEFAULT,
+        }
+    }
+}
+
+/// Fails with [`SdkError::DryRun`] instead of sending the request if dry run is enabled,
+/// `request` renders the request message for it.
+///
+/// `get_system_config`, `get_block_number` and `get_transaction_count` don't check it, since
+/// they only fill in the chain id, version, `valid_until_block` and nonce of txs, so that a dry
+/// run shows the real tx.
+pub fn check_dry_run(
+    policy: &RequestPolicy,
+    service: &str,
+    method: &str,
+    request: impl FnOnce() -> serde_json::Value,
+) -> SdkResult<()> {
+    if policy.dry_run {
+        return Err(SdkError::DryRun {
+            service: service.into(),
+            method: method.into(),
+            request: request(),
+        });
+    }
+    Ok(())
+}
+
+fn debug_response<T: Debug>(policy: &RequestPolicy, service: &str, resp: T) -> T {
+    debug!(policy.verbosity, "{service} response: {resp:?}");
+    resp
+}
+
+fn timeout_error(service: &str, timeout: Duration) -> SdkError {
+    SdkError::Timeout {
+        service: service.into(),
+        timeout,
+    }
+}
+
+/// Run the gRPC call with the request timeout of the `policy`, connecting time included.
+pub async fn call_with_timeout<T, F>(policy: &RequestPolicy, service: &str, call: F) -> SdkResult<T>
+where
+    T: Debug,
+    F: Future<Output = Result<tonic::Response<T>, Status>>,
+{
+    let timeout = policy.timeout;
+    match tokio::time::timeout(timeout, call).await {
+        Ok(Ok(resp)) => Ok(debug_response(policy, service, resp.into_inner())),
+        Ok(Err(status)) => Err(SdkError::from_status(service, 1, status)),
+        Err(_elapsed) => Err(timeout_error(service, timeout)),
+    }
+}
+
+fn is_transient(status: &Status) -> bool {
+    match status.code() {
+        Code::Unavailable | Code::DeadlineExceeded => true,
+        // Connection failures are reported as unknown with a transport error as the source.
+        Code::Unknown => std::error::Error::source(status)
+            .map(|e| e.is::<tonic::transport::Error>())
+            .unwrap_or(false),
+        _ => false,
+    }
+}
+
+/// Like [`call_with_timeout`], but retry transient failures with exponential backoff
+/// according to the `policy`. Each attempt gets a fresh clone of the `client` and `req`.
+///
+/// Only use it for idempotent calls, a retried `send_raw` may end up sending the tx twice.
+pub async fn call_with_retry<C, R, T, F, Fut>(
+    policy: &RequestPolicy,
+    service: &str,
+    client: &C,
+    req: R,
+    mut call: F,
+) -> SdkResult<T>
+where
+    C: Clone,
+    R: Clone + Debug,
+    T: Debug,
+    F: FnMut(C, R) -> Fut,
+    Fut: Future<Output = Result<tonic::Response<T>, Status>>,
+{
+    let RequestPolicy {
+        timeout,
+        retries,
+        retry_backoff: backoff,
+        verbosity,
+        ..
+    } = *policy;
+    let mut attempts = 0;
+    debug!(verbosity, "{service} request: {req:?}");
+    loop {
+        attempts += 1;
+        match tokio::time::timeout(timeout, call(client.clone(), req.clone())).await {
+            Ok(Ok(resp)) => return Ok(debug_response(policy, service, resp.into_inner())),
+            Ok(Err(status)) => {
+                if !is_transient(&status) || attempts > retries {
+                    return Err(SdkError::from_status(service, attempts, status));
+                }
+                debug!(verbosity, "{service} request failed, retrying: {status}");
+            }
+            Err(_elapsed) => {
+                if attempts > retries {
+                    return Err(timeout_error(service, timeout));
+                }
+                debug!(verbosity, "{service} request timed out, retrying");
+            }
+        }
+        tokio::time::sleep(backoff.saturating_mul(1 << (attempts - 1).min(16))).await;
+    }
+}
+
+/// How the gRPC clients connect and send requests, see [`GrpcClientBehaviour`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClientSetting {
+    /// Only used for `https://` addresses.
+    pub tls: TlsSetting,
+    pub keep_alive: KeepAliveSetting,
+    /// The number of connections of a [`ClientPool`].
+    pub pool_size: usize,
+    pub policy: RequestPolicy,
+}
+
+impl ClientSetting {
+    pub fn of(setting: &ContextSetting, policy: RequestPolicy) -> Self {
+        Self {
+            tls: setting.tls.clone(),
+            keep_alive: setting.keep_alive,
+            pool_size: setting.pool_size,
+            policy,
+        }
+    }
+}
+
+impl Default for ClientSetting {
+    fn default() -> Self {
+        Self {
+            tls: TlsSetting::default(),
+            keep_alive: KeepAliveSetting::DEFAULT,
+            pool_size: 1,
+            policy: RequestPolicy::DEFAULT,
+        }
+    }
+}
+
+/// Build the endpoint for `addr`, TLS is enabled for `https://` addresses.
+/// Addresses without a scheme are treated as `http://`.
+pub fn endpoint(addr: &str, tls: &TlsSetting, keep_alive: KeepAliveSetting) -> Result<Endpoint> {
+    let ep = if addr.starts_with("https://") {
+        let mut tls_config = ClientTlsConfig::new();
+        if let Some(ca_cert) = &tls.ca_cert {
+            let ca_cert = expand_path(ca_cert);
+            let pem = fs::read(&ca_cert).with_context(|| {
+                format!("cannot read CA certificate from `{}`", ca_cert.display())
+            })?;
+            tls_config = tls_config.ca_certificate(Certificate::from_pem(pem));
+        }
+        if let Some(domain_name) = &tls.domain_name {
+            tls_config = tls_config.domain_name(domain_name);
+        }
+        Endpoint::from_shared(addr.to_string())?.tls_config(tls_config)?
+    } else if addr.starts_with("http://") {
+        Endpoint::from_shared(addr.to_string())?
+    } else {
+        Endpoint::from_shared(format!("http://{addr}"))?
+    };
+    Ok(with_keep_alive(ep, keep_alive))
+}
+
+fn with_keep_alive(ep: Endpoint, setting: KeepAliveSetting) -> Endpoint {
+    if setting.interval == 0 {
+        return ep;
+    }
+    ep.http2_keep_alive_interval(Duration::from_secs(setting.interval))
+        .keep_alive_timeout(Duration::from_secs(setting.timeout))
+        .keep_alive_while_idle(setting.while_idle)
+}
+
 #[tonic::async_trait]
 pub trait GrpcClientBehaviour: Sized {
     fn from_channel(ch: Channel) -> Self;
 
-    async fn connect(addr: &str) -> Result<Self> {
-        let addr = format!("http://{addr}");
-        let ch = Endpoint::from_shared(addr)?.connect().await?;
-        Ok(Self::from_channel(ch))
+    /// Change the policy of the following requests, clients that don't send requests,
+    /// e.g. mocks, can ignore it.
+    fn set_policy(&mut self, _policy: RequestPolicy) {}
+
+    fn from_channel_with(ch: Channel, policy: RequestPolicy) -> Self {
+        let mut client = Self::from_channel(ch);
+        client.set_policy(policy);
+        client
+    }
+
+    async fn connect(addr: &str, setting: &ClientSetting) -> Result<Self> {
+        let ch = endpoint(addr, &setting.tls, setting.keep_alive)?
+            .connect()
+            .await?;
+        Ok(Self::from_channel_with(ch, setting.policy))
     }
 
     // TODO: maybe add async.
     // Endpoint::connect_lazy, although no async fn, does require running in a async runtime
-    fn connect_lazy(addr: &str) -> Result<Self> {
-        let addr = format!("http://{addr}");
-        let ch = Endpoint::from_shared(addr)?
+    fn connect_lazy(addr: &str, setting: &ClientSetting) -> Result<Self> {
+        let ch = endpoint(addr, &setting.tls, setting.keep_alive)?
             .tcp_keepalive(TCP_KEEPALIVE.into())
             .connect_lazy();
-        Ok(Self::from_channel(ch))
+        Ok(Self::from_channel_with(ch, setting.policy))
     }
 
-    async fn connect_timeout(addr: &str, dur: Duration) -> Result<Self> {
-        let addr = format!("http://{addr}");
-        let ch = Endpoint::from_shared(addr)?
+    async fn connect_timeout(addr: &str, setting: &ClientSetting, dur: Duration) -> Result<Self> {
+        let ch = endpoint(addr, &setting.tls, setting.keep_alive)?
             .tcp_keepalive(TCP_KEEPALIVE.into())
             .timeout(dur)
             .connect()
             .await?;
-        Ok(Self::from_channel(ch))
+        Ok(Self::from_channel_with(ch, setting.policy))
     }
 }
 
 #[tonic::async_trait]
 impl GrpcClientBehaviour for ControllerClient {
     fn from_channel(ch: Channel) -> Self {
-        Self::new(ch)
+        Self::new(proto::controller::rpc_service_client::RpcServiceClient::new(ch))
+    }
+
+    fn set_policy(&mut self, policy: RequestPolicy) {
+        self.policy = policy;
     }
 }
 
 #[tonic::async_trait]
 impl GrpcClientBehaviour for ExecutorClient {
     fn from_channel(ch: Channel) -> Self {
-        Self::new(ch)
+        Self::new(proto::executor::executor_service_client::ExecutorServiceClient::new(ch))
+    }
+
+    fn set_policy(&mut self, policy: RequestPolicy) {
+        self.policy = policy;
     }
 }
 
 #[tonic::async_trait]
 impl GrpcClientBehaviour for EvmClient {
     fn from_channel(ch: Channel) -> Self {
-        Self::new(ch)
+        Self::new(proto::evm::rpc_service_client::RpcServiceClient::new(ch))
+    }
+
+    fn set_policy(&mut self, policy: RequestPolicy) {
+        self.policy = policy;
+    }
+}
+
+/// A pool of clients with separate connections, requests are handed out round-robin.
+///
+/// So concurrent requests, e.g. in `bench` or `get_txs`, won't serialize on one HTTP/2 connection.
+/// The pool size is [`ClientSetting::pool_size`]. Lazily connected channels are also
+/// reconnected on demand, so a dead connection is replaced by the next request.
+pub struct ClientPool<C> {
+    clients: Arc<Vec<C>>,
+    next: Arc<AtomicUsize>,
+}
+
+impl<C> ClientPool<C> {
+    pub fn new(clients: Vec<C>) -> Self {
+        assert!(!clients.is_empty(), "client pool must not be empty");
+        Self {
+            clients: Arc::new(clients),
+            next: Arc::new(AtomicUsize::new(0)),
+        }
+    }
+
+    pub fn get(&self) -> &C {
+        let i = self.next.fetch_add(1, Ordering::Relaxed);
+        &self.clients[i % self.clients.len()]
+    }
+}
+
+impl<C> Clone for ClientPool<C> {
+    fn clone(&self) -> Self {
+        Self {
+            clients: self.clients.clone(),
+            next: self.next.clone(),
+        }
+    }
+}
+
+#[tonic::async_trait]
+impl<C> GrpcClientBehaviour for ClientPool<C>
+where
+    C: GrpcClientBehaviour + Clone + Send,
+{
+    fn from_channel(ch: Channel) -> Self {
+        Self::new(vec![C::from_channel(ch)])
+    }
+
+    fn set_policy(&mut self, policy: RequestPolicy) {
+        // The clients are cloned if the pool is shared, the connections are kept.
+        for client in Arc::make_mut(&mut self.clients) {
+            client.set_policy(policy);
+        }
+    }
+
+    async fn connect(addr: &str, setting: &ClientSetting) -> Result<Self> {
+        let mut clients = vec![];
+        for _ in 0..setting.pool_size.max(1) {
+            clients.push(C::connect(addr, setting).await?);
+        }
+        Ok(Self::new(clients))
+    }
+
+    fn connect_lazy(addr: &str, setting: &ClientSetting) -> Result<Self> {
+        let clients = (0..setting.pool_size.max(1))
+            .map(|_| C::connect_lazy(addr, setting))
+            .collect::<Result<_>>()?;
+        Ok(Self::new(clients))
+    }
+
+    async fn connect_timeout(addr: &str, setting: &ClientSetting, dur: Duration) -> Result<Self> {
+        let mut clients = vec![];
+        for _ in 0..setting.pool_size.max(1) {
+            clients.push(C::connect_timeout(addr, setting, dur).await?);
+        }
+        Ok(Self::new(clients))
+    }
+}
+
+#[tonic::async_trait]
+impl<C> ControllerBehaviour for ClientPool<C>
+where
+    C: ControllerBehaviour + Send + Sync,
+{
+    async fn send_raw(&self, raw: RawTransaction) -> SdkResult<Hash> {
+        self.get().send_raw(raw).await
+    }
+
+    async fn get_version(&self) -> SdkResult<String> {
+        self.get().get_version().await
+    }
+
+    async fn get_system_config(&self) -> SdkResult<SystemConfig> {
+        self.get().get_system_config().await
+    }
+
+    async fn get_block_number(&self, for_pending: bool) -> SdkResult<u64> {
+        self.get().get_block_number(for_pending).await
+    }
+
+    async fn get_block_hash(&self, block_number: u64) -> SdkResult<Hash> {
+        self.get().get_block_hash(block_number).await
+    }
+
+    async fn get_block_by_number(&self, block_number: u64) -> SdkResult<CompactBlock> {
+        self.get().get_block_by_number(block_number).await
+    }
+
+    async fn get_block_by_hash(&self, hash: Hash) -> SdkResult<CompactBlock> {
+        self.get().get_block_by_hash(hash).await
+    }
+
+    async fn get_tx(&self, tx_hash: Hash) -> SdkResult<RawTransaction> {
+        self.get().get_tx(tx_hash).await
+    }
+
+    async fn get_tx_index(&self, tx_hash: Hash) -> SdkResult<u64> {
+        self.get().get_tx_index(tx_hash).await
+    }
+
+    async fn get_tx_block_number(&self, tx_hash: Hash) -> SdkResult<u64> {
+        self.get().get_tx_block_number(tx_hash).await
+    }
+
+    async fn get_peer_count(&self) -> SdkResult<u64> {
+        self.get().get_peer_count().await
+    }
+
+    async fn get_peers_info(&self) -> SdkResult<TotalNodeInfo> {
+        self.get().get_peers_info().await
+    }
+
+    async fn add_node(&self, multiaddr: String) -> SdkResult<u32> {
+        self.get().add_node(multiaddr).await
+    }
+}
+
+#[tonic::async_trait]
+impl<C> ExecutorBehaviour for ClientPool<C>
+where
+    C: ExecutorBehaviour + Send + Sync,
+{
+    async fn call(&self, from: Address, to: Address, data: Vec<u8>) -> SdkResult<CallResponse> {
+        self.get().call(from, to, data).await
+    }
+}
+
+#[tonic::async_trait]
+impl<C> EvmBehaviour for ClientPool<C>
+where
+    C: EvmBehaviour + Send + Sync,
+{
+    async fn get_receipt(&self, hash: Hash) -> SdkResult<Receipt> {
+        self.get().get_receipt(hash).await
+    }
+
+    async fn get_code(&self, addr: Address) -> SdkResult<ByteCode> {
+        self.get().get_code(addr).await
+    }
+
+    async fn get_balance(&self, addr: Address) -> SdkResult<Balance> {
+        self.get().get_balance(addr).await
+    }
+
+    async fn get_tx_count(&self, addr: Address) -> SdkResult<Nonce> {
+        self.get().get_tx_count(addr).await
+    }
+
+    async fn get_abi(&self, addr: Address) -> SdkResult<ByteAbi> {
+        self.get().get_abi(addr).await
+    }
+
+    async fn get_storage_at(&self, addr: Address, position: Hash) -> SdkResult<CloudHash> {
+        self.get().get_storage_at(addr, position).await
+    }
+
+    async fn estimate_quota(&self, call: CallRequest) -> SdkResult<ByteQuota> {
+        self.get().estimate_quota(call).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_endpoint() {
+        let tls = TlsSetting::default();
+        let keep_alive = KeepAliveSetting::DEFAULT;
+        let ep = endpoint("localhost:50004", &tls, keep_alive).unwrap();
+        assert_eq!(ep.uri().to_string(), "http://localhost:50004/");
+        let ep = endpoint("http://localhost:50004", &tls, keep_alive).unwrap();
+        assert_eq!(ep.uri().to_string(), "http://localhost:50004/");
+        let ep = endpoint("https://127.0.0.1:443", &tls, keep_alive).unwrap();
+        assert_eq!(ep.uri().scheme_str(), Some("https"));
+
+        let tls = TlsSetting {
+            ca_cert: Some("/nonexistent/ca.pem".into()),
+            domain_name: None,
+        };
+        assert!(endpoint("https://127.0.0.1:443", &tls, keep_alive).is_err());
+        // Not used for plain http.
+        assert!(endpoint("http://127.0.0.1:80", &tls, keep_alive).is_ok());
+    }
+
+    #[test]
+    fn test_keep_alive_degrades() {
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        // A node that doesn't speak HTTP/2 at all, let alone answer pings.
+        let addr = rt.block_on(async {
+            let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let addr = listener.local_addr().unwrap();
+            tokio::spawn(async move {
+                while let Ok((conn, _)) = listener.accept().await {
+                    drop(conn);
+                }
+            });
+            addr
+        });
+
+        let setting = ClientSetting {
+            keep_alive: KeepAliveSetting {
+                interval: 1,
+                timeout: 1,
+                while_idle: true,
+            },
+            ..Default::default()
+        };
+        let client = rt
+            .block_on(async { ControllerClient::connect_lazy(&addr.to_string(), &setting) })
+            .unwrap();
+        // Each request fails as a normal transport error and reconnects, instead of hanging.
+        for _ in 0..2 {
+            let res = rt.block_on(async {
+                tokio::time::timeout(Duration::from_secs(5), client.get_block_number(false)).await
+            });
+            assert!(
+                matches!(res, Ok(Err(SdkError::Transport { .. }))),
+                "{res:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_call_with_timeout() {
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        let policy = RequestPolicy {
+            timeout: Duration::from_millis(10),
+            ..Default::default()
+        };
+        let err = rt
+            .block_on(call_with_timeout(
+                &policy,
+                "controller",
+                std::future::pending::<Result<tonic::Response<()>, tonic::Status>>(),
+            ))
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "request to controller timed out after 0.01s"
+        );
+    }
+
+    #[test]
+    fn test_call_with_retry() {
+        use std::sync::atomic::{AtomicU32, Ordering};
+
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        let policy = RequestPolicy {
+            retries: 2,
+            retry_backoff: Duration::from_millis(1),
+            ..Default::default()
+        };
+
+        // Succeed on the last attempt.
+        let calls = AtomicU32::new(0);
+        let resp = rt.block_on(call_with_retry(&policy, "controller", &(), (), |_, _| {
+            let n = calls.fetch_add(1, Ordering::SeqCst);
+            async move {
+                if n < 2 {
+                    Err(Status::unavailable("flaky"))
+                } else {
+                    Ok(tonic::Response::new(n))
+                }
+            }
+        }));
+        assert_eq!(resp.unwrap(), 2);
+
+        // Give up after retries are exhausted.
+        let calls = AtomicU32::new(0);
+        let err = rt
+            .block_on(call_with_retry(&policy, "controller", &(), (), |_, _| {
+                calls.fetch_add(1, Ordering::SeqCst);
+                async { Err::<tonic::Response<()>, _>(Status::unavailable("down")) }
+            }))
+            .unwrap_err();
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+        assert_eq!(
+            err.to_string(),
+            "request to controller failed after 3 attempts: Unavailable: down"
+        );
+
+        // Don't retry non-transient errors.
+        let calls = AtomicU32::new(0);
+        let err = rt
+            .block_on(call_with_retry(&policy, "controller", &(), (), |_, _| {
+                calls.fetch_add(1, Ordering::SeqCst);
+                async { Err::<tonic::Response<()>, _>(Status::invalid_argument("bad")) }
+            }))
+            .unwrap_err();
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+        assert!(matches!(
+            err,
+            SdkError::Transport { ref status, attempts: 1, .. } if status.message() == "bad"
+        ));
+    }
+
+    #[test]
+    fn test_client_pool_round_robin() {
+        use crate::core::mock::MockControllerClient;
+
+        let clients = (1..=3)
+            .map(|n| {
+                let mut c = MockControllerClient::default();
+                c.expect_get_block_number().returning(move |_| Ok(n));
+                c
+            })
+            .collect();
+        let pool = ClientPool::new(clients);
+
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        let heights = (0..6)
+            .map(|_| rt.block_on(pool.get_block_number(false)).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(heights, [1, 2, 3, 1, 2, 3]);
+    }
+
+    #[test]
+    fn test_set_policy() {
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        let setting = ClientSetting {
+            pool_size: 2,
+            ..Default::default()
+        };
+        let mut pool = rt
+            .block_on(async {
+                ClientPool::<ControllerClient>::connect_lazy("127.0.0.1:1", &setting)
+            })
+            .unwrap();
+        let shared = pool.clone();
+
+        pool.set_policy(RequestPolicy {
+            dry_run: true,
+            ..Default::default()
+        });
+        for _ in 0..2 {
+            let res = rt.block_on(pool.get_block_hash(5));
+            assert!(matches!(res, Err(SdkError::DryRun { .. })), "{res:?}");
+        }
+        // A clone taken before keeps its policy.
+        let res = rt.block_on(shared.get_block_hash(5));
+        assert!(matches!(res, Err(SdkError::Transport { .. })), "{res:?}");
+    }
+
+    #[test]
+    fn test_dry_run() {
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        let setting = ClientSetting {
+            policy: RequestPolicy {
+                dry_run: true,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let client = rt
+            .block_on(async { ControllerClient::connect_lazy("127.0.0.1:1", &setting) })
+            .unwrap();
+
+        let res = rt.block_on(client.get_block_hash(5));
+        let raw = rt.block_on(client.send_raw(RawTransaction::default()));
+        // Not checked, they fail on connecting.
+        let height = rt.block_on(client.get_block_number(false));
+        let evm = rt
+            .block_on(async { EvmClient::connect_lazy("127.0.0.1:1", &setting) })
+            .unwrap();
+        let nonce = rt.block_on(evm.get_tx_count([0; 20]));
+
+        match res {
+            Err(SdkError::DryRun {
+                service,
+                method,
+                request,
+            }) => {
+                assert_eq!(format!("{service}.{method}"), "controller.get_block_hash");
+                assert_eq!(request, serde_json::json!({ "block_number": 5 }));
+            }
+            res => panic!("unexpected {res:?}"),
+        }
+        assert!(matches!(raw, Err(SdkError::DryRun { .. })), "{raw:?}");
+        assert!(
+            matches!(height, Err(SdkError::Transport { .. })),
+            "{height:?}"
+        );
+        assert!(
+            matches!(nonce, Err(SdkError::Transport { .. })),
+            "{nonce:?}"
+        );
     }
 }
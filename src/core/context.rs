@@ -12,15 +12,47 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use anyhow::{anyhow, ensure, Context as _, Result};
+use anyhow::{anyhow, bail, ensure, Context as _, Result};
 use rustyline::Editor;
+use serde_json::Value as Json;
+use std::cell::{Cell, OnceCell, RefCell};
+use std::fs::{File, OpenOptions};
 use std::future::Future;
+use std::io::Write;
+use std::num::NonZeroUsize;
+use std::path::Path;
+use std::rc::Rc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 use super::{
-    client::GrpcClientBehaviour,
+    cache::{AbiCache, BlockHashCache},
+    client::{ClientSetting, GrpcClientBehaviour, RequestPolicy},
+    controller::{chain_uses_crypto, ControllerBehaviour},
+    error::{is_dry_run, SdkError},
+    evm::EvmBehaviour,
     wallet::{MultiCryptoAccount, Wallet},
 };
-use crate::config::{Config, ContextSetting};
+use crate::{
+    config::{Config, ContextSetting, CryptoType},
+    crypto::{Address, EthCrypto, SmCrypto},
+    display::{
+        strip_colors, ColorMode, Display, DisplayOptions, OutputFormat, OutputSink, StdoutSink,
+    },
+    log::{warning, Verbosity},
+    proto::blockchain::Transaction,
+    utils::{
+        check_address_checksum, get_valid_until_block, hex, parse_addr, resolve_alias,
+        restrict_permissions, valid_until_block_at, NonceStrategy, Position, U256,
+    },
+};
+
+/// How long the current height is reused for `+h` positions, see [`Context::valid_until_block`].
+pub const RECENT_HEIGHT_TTL: Duration = Duration::from_secs(2);
+
+/// Look up an env var, see [`Context::env`].
+pub type EnvLookup = Box<dyn Fn(&str) -> Option<String>>;
 
 pub struct Context<Co, Ex, Ev> {
     /// Those gRPC client are connected lazily.
@@ -31,8 +63,47 @@ pub struct Context<Co, Ex, Ev> {
     pub wallet: Wallet,
 
     pub config: Config,
+    /// The name of the context that the current setting comes from, see `--context`.
+    pub current_context: String,
     pub current_setting: ContextSetting,
 
+    pub output_format: OutputFormat,
+    pub color_mode: ColorMode,
+    /// Where the outputs go, see [`Context::emit`].
+    pub output: RefCell<Box<dyn OutputSink>>,
+
+    /// Cleared when switching to another controller.
+    pub block_cache: BlockHashCache,
+    /// Cleared when switching to another executor.
+    pub abi_cache: AbiCache,
+
+    /// Error instead of warning if the chain's crypto type mismatched, see [`Context::check_chain_crypto`].
+    pub strict_crypto: bool,
+    /// Reset when switching to another chain or crypto type.
+    pub crypto_checked: Cell<bool>,
+    /// The chain id and tx version fetched from controller, reset when switching to another chain.
+    /// See [`Context::chain_id`].
+    pub chain: RefCell<Option<(Vec<u8>, u32)>>,
+
+    /// The env var that holds the password for unlocking accounts, see [`Context::read_password`].
+    pub password_env: Option<String>,
+    /// How env vars like the `--password-env` one are read, so that tests don't set the process ones.
+    pub env: EnvLookup,
+    /// The current account unlocked on demand for signing, reset when switching context.
+    pub unlocked_signer: OnceCell<MultiCryptoAccount>,
+    /// The next nonce of the account for `local-increment`, reset when switching context.
+    /// See [`Context::next_nonce`].
+    pub local_nonce: Cell<Option<(Address, u64)>>,
+    /// The current height and when it's fetched, reset when switching to another chain.
+    /// See [`Context::valid_until_block`].
+    pub recent_height: Cell<Option<(Instant, u64)>>,
+    /// Don't reuse `recent_height`, set by `--fresh`.
+    pub fresh_height: bool,
+    /// The policy of the clients' requests, see [`Context::set_request_policy`].
+    request_policy: RequestPolicy,
+    /// Set by `--quiet` and `--verbose`, see [`crate::log`].
+    pub verbosity: Verbosity,
+
     // rustyline::Editor, used for interactive cmd.
     pub editor: Editor<()>,
     pub rt: CtrlCSignalCapturedRuntime,
@@ -45,27 +116,28 @@ impl<Co, Ex, Ev> Context<Co, Ex, Ev> {
         Ex: GrpcClientBehaviour,
         Ev: GrpcClientBehaviour,
     {
-        let rt = CtrlCSignalCapturedRuntime(tokio::runtime::Runtime::new()?);
+        let rt = CtrlCSignalCapturedRuntime::new(config.worker_threads)?;
         let editor = rustyline::Editor::<()>::new();
-        let wallet = Wallet::open(&config.data_dir)?;
 
         let default_context_setting = config
             .context_settings
             .get(&config.default_context)
             .cloned()
             .unwrap_or_else(|| {
-                println!(
-                    "The configured default context setting `{}` is missing.",
+                warning!(
+                    "the configured default context setting `{}` is missing, using a local default context",
                     config.default_context
                 );
-                println!("Using a local default context..");
                 ContextSetting::default()
             });
+        let wallet = Wallet::open(config.wallet_dir(&default_context_setting))?;
         // connect_lazy must run in async environment.
         let (controller, executor, evm) = rt.block_on(async {
-            let co = Co::connect_lazy(&default_context_setting.controller_addr)?;
-            let ex = Ex::connect_lazy(&default_context_setting.executor_addr)?;
-            let ev = Ev::connect_lazy(&default_context_setting.executor_addr)?;
+            let setting = &default_context_setting;
+            let client_setting = ClientSetting::of(setting, RequestPolicy::DEFAULT);
+            let co = Co::connect_lazy(&setting.controller_addr, &client_setting)?;
+            let ex = Ex::connect_lazy(&setting.executor_addr, &client_setting)?;
+            let ev = Ev::connect_lazy(&setting.executor_addr, &client_setting)?;
             anyhow::Ok((co, ex, ev))
         })??;
 
@@ -74,13 +146,198 @@ impl<Co, Ex, Ev> Context<Co, Ex, Ev> {
             executor,
             evm,
             wallet,
+            current_context: config.default_context.clone(),
             config,
             current_setting: default_context_setting,
+            output_format: OutputFormat::default(),
+            color_mode: ColorMode::default(),
+            output: RefCell::new(Box::new(StdoutSink)),
+            block_cache: BlockHashCache::new(),
+            abi_cache: AbiCache::new(),
+            strict_crypto: false,
+            crypto_checked: Cell::new(false),
+            chain: RefCell::new(None),
+            password_env: None,
+            env: Box::new(|var| std::env::var(var).ok()),
+            unlocked_signer: OnceCell::new(),
+            local_nonce: Cell::new(None),
+            recent_height: Cell::new(None),
+            fresh_height: false,
+            request_policy: RequestPolicy::DEFAULT,
+            verbosity: Verbosity::Normal,
             editor,
             rt,
         })
     }
 
+    /// Send the output of a command to the output sink, commands shouldn't print their outputs directly.
+    pub fn emit(&self, output: &impl Display) {
+        let opts = self.display_options();
+        let rendered = output.display_with(opts);
+        self.output
+            .borrow_mut()
+            .emit(output.to_json(opts), rendered);
+    }
+
+    /// How the outputs are rendered in the current context.
+    pub fn display_options(&self) -> DisplayOptions {
+        DisplayOptions {
+            format: self.output_format,
+            address_checksum: self.address_checksum(),
+            color: self.color_mode.enabled((self.env)("NO_COLOR").as_deref()),
+        }
+    }
+
+    /// Send the raw protobuf of the `bin` output format to the output sink.
+    pub fn emit_bin(&self, msg: &impl prost::Message) -> Result<()> {
+        self.output.borrow_mut().emit_bin(&msg.encode_to_vec())?;
+        Ok(())
+    }
+
+    /// Send preformatted text, e.g. a hexdump, to the output sink. It's shown as is in all output formats.
+    pub fn emit_text(&self, text: impl Into<String>) {
+        let text = text.into();
+        self.output
+            .borrow_mut()
+            .emit(Json::String(text.clone()), text);
+    }
+
+    /// Run `f` with the outputs also written to the file at `path`, see `--out`.
+    /// It fails without running `f` if the file exists and `overwrite` is false.
+    pub fn tee<T>(
+        &mut self,
+        path: &Path,
+        overwrite: bool,
+        f: impl FnOnce(&mut Self) -> T,
+    ) -> Result<T> {
+        struct Tee {
+            inner: Rc<RefCell<Box<dyn OutputSink>>>,
+            file: File,
+            /// Only write colors to the file if asked explicitly, `auto` is for the terminal.
+            keep_colors: bool,
+            /// The first error writing to the file, it stops writing the rest.
+            error: Rc<RefCell<Option<std::io::Error>>>,
+        }
+        impl Tee {
+            fn write(&mut self, bytes: &[u8]) {
+                let mut error = self.error.borrow_mut();
+                if error.is_none() {
+                    if let Err(e) = self.file.write_all(bytes) {
+                        error.replace(e);
+                    }
+                }
+            }
+        }
+        impl OutputSink for Tee {
+            fn emit(&mut self, value: Json, rendered: String) {
+                let text = if self.keep_colors {
+                    format!("{rendered}\n")
+                } else {
+                    format!("{}\n", strip_colors(&rendered))
+                };
+                self.write(text.as_bytes());
+                self.inner.borrow_mut().emit(value, rendered);
+            }
+
+            fn emit_bin(&mut self, bytes: &[u8]) -> std::io::Result<()> {
+                self.write(bytes);
+                self.inner.borrow_mut().emit_bin(bytes)
+            }
+        }
+
+        let mut options = OpenOptions::new();
+        options.write(true);
+        if overwrite {
+            options.create(true).truncate(true);
+        } else {
+            options.create_new(true);
+        }
+        // The outputs may contain secrets, e.g. `account export --show-private-key`.
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            options.mode(0o600);
+        }
+        let file = match options.open(path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {
+                bail!(
+                    "`{}` already exists, use `--overwrite` to overwrite it",
+                    path.display()
+                )
+            }
+            Err(e) => return Err(e).with_context(|| format!("cannot create `{}`", path.display())),
+        };
+        // The mode above is only for new files. Don't touch the non-regular ones like `/dev/stderr`.
+        if file.metadata()?.is_file() {
+            restrict_permissions(path)?;
+        }
+
+        let inner = Rc::new(RefCell::new(self.output.replace(Box::new(StdoutSink))));
+        let error = Rc::new(RefCell::new(None));
+        self.output.replace(Box::new(Tee {
+            inner: Rc::clone(&inner),
+            file,
+            keep_colors: self.color_mode == ColorMode::Always,
+            error: Rc::clone(&error),
+        }));
+        let ret = f(self);
+        // Drop the tee to get the previous sink back.
+        self.output.replace(Box::new(StdoutSink));
+        let previous = Rc::try_unwrap(inner)
+            .ok()
+            .expect("the tee sink should have been dropped")
+            .into_inner();
+        self.output.replace(previous);
+        if let Some(e) = error.take() {
+            return Err(e)
+                .with_context(|| format!("cannot write the output to `{}`", path.display()));
+        }
+        Ok(ret)
+    }
+
+    /// Run `f` with the outputs collected instead of sent to the sink, e.g.
+    /// `ctx.capture(|ctx| cmd.exec_from(["cldi", "get", "block-number"], ctx))`.
+    #[allow(dead_code)]
+    pub fn capture<T>(&mut self, f: impl FnOnce(&mut Self) -> T) -> (T, Vec<Json>) {
+        struct Collector(Rc<RefCell<Vec<Json>>>);
+        impl OutputSink for Collector {
+            fn emit(&mut self, value: Json, _rendered: String) {
+                self.0.borrow_mut().push(value);
+            }
+        }
+
+        let collected = Rc::new(RefCell::new(vec![]));
+        let previous = self
+            .output
+            .replace(Box::new(Collector(Rc::clone(&collected))));
+        let ret = f(self);
+        self.output.replace(previous);
+        (ret, collected.take())
+    }
+
+    /// Parse an address arg, which can also be `@<alias>` or a bare alias from the address book.
+    /// Such args are validated by [`validate_addr`](crate::utils::validate_addr).
+    /// Hex addresses must pass the EIP-55 checksum on ETH chains if they are in mixed case.
+    pub fn parse_addr(&self, s: &str) -> Result<Address> {
+        match resolve_alias(&self.config.address_book, s)? {
+            Some(addr) => Ok(addr),
+            None => {
+                let addr = parse_addr(s)?;
+                if self.address_checksum() {
+                    check_address_checksum(s, &addr)?;
+                }
+                Ok(addr)
+            }
+        }
+    }
+
+    /// Whether addresses are EIP-55 checksummed, in both the outputs and the inputs.
+    /// It's enabled for ETH chains.
+    pub fn address_checksum(&self) -> bool {
+        self.current_setting.crypto_type == CryptoType::Eth
+    }
+
     pub fn current_account(&self) -> Result<&MultiCryptoAccount> {
         let current_name = &self.current_setting.account_name;
         let current = self
@@ -97,15 +354,304 @@ impl<Co, Ex, Ev> Context<Co, Ex, Ev> {
             self.current_setting.crypto_type,
         );
 
+        if let Some(unlocked) = self.unlocked_signer.get() {
+            return Ok(unlocked);
+        }
         current
             .unlocked()
             .with_context(|| format!("cannot get current account `{}` ", current_name))
     }
 
+    /// Same as [`Context::current_account`], but check the chain's crypto type first.
+    /// Use it for accounts that sign txs to send.
+    pub async fn signer(&self) -> Result<&MultiCryptoAccount>
+    where
+        Co: ControllerBehaviour,
+    {
+        self.check_chain_crypto().await?;
+        self.signing_account()
+    }
+
+    /// The nonce of the next tx sent by the current account, chosen by `strategy`.
+    pub async fn next_nonce(&self, strategy: NonceStrategy) -> Result<String>
+    where
+        Ev: EvmBehaviour,
+    {
+        let nonce = match strategy {
+            NonceStrategy::Fetch => self.fetch_nonce(self.current_address()?).await?,
+            NonceStrategy::LocalIncrement => self.reserve_nonces(1).await?,
+        };
+        Ok(nonce.to_string())
+    }
+
+    /// Reserve `count` sequential nonces of the current account and return the first one.
+    ///
+    /// The starting nonce is fetched from the node the first time, later ones are incremented
+    /// locally. It assumes no other sender uses the account concurrently.
+    pub async fn reserve_nonces(&self, count: u64) -> Result<u64>
+    where
+        Ev: EvmBehaviour,
+    {
+        let addr = self.current_address()?;
+        let first = match self.local_nonce.get() {
+            Some((local_addr, next)) if local_addr == addr => next,
+            _ => self.fetch_nonce(addr).await?,
+        };
+        self.local_nonce.set(Some((addr, first + count)));
+        Ok(first)
+    }
+
+    /// Warn if `nonce` is ahead of the current account's nonce on the node, since the tx would
+    /// stall until the txs in between are sent. It's an error with `strict`.
+    ///
+    /// Only decimal nonces are checked.
+    pub async fn check_nonce_gap(&self, nonce: &str, strict: bool) -> Result<()>
+    where
+        Ev: EvmBehaviour,
+    {
+        let Ok(nonce) = nonce.parse::<u64>() else {
+            return Ok(());
+        };
+        let expected = match self.fetch_nonce(self.current_address()?).await {
+            Ok(expected) => expected,
+            Err(e) if is_dry_run(&e) => return Err(e),
+            Err(e) if strict => return Err(e.context("cannot check the nonce gap")),
+            Err(e) => {
+                warning!("cannot check the nonce gap: {e:#}");
+                return Ok(());
+            }
+        };
+        if nonce > expected {
+            let msg = format!(
+                "nonce `{nonce}` is ahead of the account nonce `{expected}`, the tx will stall until the {} tx(s) in between are executed",
+                nonce - expected
+            );
+            ensure!(!strict, msg);
+            warning!("{msg}");
+        }
+        Ok(())
+    }
+
+    // Don't require the account to be unlocked since it's only the address.
+    fn current_address(&self) -> Result<Address> {
+        Ok(*self
+            .wallet
+            .get(&self.current_setting.account_name)?
+            .address())
+    }
+
+    async fn fetch_nonce(&self, addr: Address) -> Result<u64>
+    where
+        Ev: EvmBehaviour,
+    {
+        let nonce = self
+            .evm
+            .get_tx_count(addr)
+            .await
+            .context("failed to fetch account nonce")?;
+        U256::from_be_slice(&nonce.nonce)
+            .and_then(U256::to_u64)
+            .context("invalid account nonce")
+    }
+
+    /// Same as [`Context::current_account`], but a locked account is unlocked with the password
+    /// from [`Context::read_password`] instead of being an error. The keystore is unchanged.
+    pub fn signing_account(&self) -> Result<&MultiCryptoAccount> {
+        let current_name = &self.current_setting.account_name;
+        match self.wallet.get(current_name) {
+            Ok(current) if current.is_locked() && self.unlocked_signer.get().is_none() => {
+                let pw = self.read_password(&format!("Password for account `{current_name}`: "))?;
+                let unlocked = current
+                    .unlock(pw.as_bytes())
+                    .with_context(|| format!("cannot unlock current account `{current_name}`"))?;
+                // The crypto type is checked in `current_account`.
+                let _ = self.unlocked_signer.set(unlocked);
+            }
+            _ => (),
+        }
+        self.current_account()
+    }
+
+    /// Read the password from the env var given by `--password-env`, or prompt for it if it's in a terminal.
+    pub fn read_password(&self, prompt: &str) -> Result<String> {
+        if let Some(var) = &self.password_env {
+            return (self.env)(var)
+                .with_context(|| format!("cannot read password from env `{var}`"));
+        }
+        let term = console::Term::stderr();
+        ensure!(
+            term.is_term(),
+            "password is required, give it by `-p` or `--password-env`"
+        );
+        term.write_str(prompt)?;
+        Ok(term.read_secure_line()?)
+    }
+
+    /// Check the chain's crypto type against the current setting, only once for each context.
+    ///
+    /// A mismatch is warned, or it's an error with `strict_crypto`.
+    /// In non-strict mode, failing to check is ignored and it will be checked next time.
+    pub async fn check_chain_crypto(&self) -> Result<()>
+    where
+        Co: ControllerBehaviour,
+    {
+        // The check needs requests that a dry run won't send.
+        if self.crypto_checked.get() || self.request_policy.dry_run {
+            return Ok(());
+        }
+
+        let crypto_type = self.current_setting.crypto_type;
+        let matched = match crypto_type {
+            CryptoType::Sm => chain_uses_crypto::<SmCrypto, _>(&self.controller).await,
+            CryptoType::Eth => chain_uses_crypto::<EthCrypto, _>(&self.controller).await,
+        };
+        match matched {
+            Ok(true) => (),
+            Ok(false) => {
+                let e = SdkError::CryptoMismatch {
+                    expected: crypto_type,
+                    reason: "its block hash mismatched".into(),
+                };
+                if self.strict_crypto {
+                    return Err(e.into());
+                }
+                warning!("{e}");
+            }
+            Err(e) if is_dry_run(&e) => return Err(e),
+            Err(e) if self.strict_crypto => {
+                return Err(e.context("cannot check the chain's crypto type"));
+            }
+            Err(e) => {
+                warning!("cannot check the chain's crypto type: {e:#}");
+                return Ok(());
+            }
+        }
+        self.crypto_checked.set(true);
+
+        Ok(())
+    }
+
+    /// The chain id of the current controller, it's fetched only once for each context
+    /// together with the tx version.
+    pub async fn chain_id(&self) -> Result<Vec<u8>>
+    where
+        Co: ControllerBehaviour,
+    {
+        Ok(self.chain().await?.0)
+    }
+
+    async fn chain(&self) -> Result<(Vec<u8>, u32)>
+    where
+        Co: ControllerBehaviour,
+    {
+        if let Some(chain) = self.chain.borrow().as_ref() {
+            return Ok(chain.clone());
+        }
+        let system_config = self
+            .controller
+            .get_system_config()
+            .await
+            .context("failed to get chain id")?;
+        let chain = (system_config.chain_id, system_config.version);
+        self.chain.replace(Some(chain.clone()));
+        Ok(chain)
+    }
+
+    /// A normal tx for the current chain with the cached chain id and version, see [`Context::chain_id`].
+    /// The nonce is random if it's `None`.
+    pub async fn build_tx(
+        &self,
+        to: Vec<u8>,
+        data: Vec<u8>,
+        value: Vec<u8>,
+        quota: u64,
+        valid_until_block: u64,
+        nonce: Option<String>,
+    ) -> Result<Transaction>
+    where
+        Co: ControllerBehaviour,
+    {
+        let (chain_id, version) = self.chain().await?;
+        Ok(Transaction {
+            version,
+            to,
+            data,
+            value,
+            nonce: nonce.unwrap_or_else(|| rand::random::<u64>().to_string()),
+            quota,
+            valid_until_block,
+            chain_id,
+        })
+    }
+
+    /// The `valid_until_block` at `pos`, checked by [`get_valid_until_block`].
+    ///
+    /// For `+h` positions, the current height fetched within [`RECENT_HEIGHT_TTL`] is reused,
+    /// so that back-to-back commands in interactive mode don't each fetch it. It only makes the
+    /// tx expire a block or two earlier. Other positions always fetch the exact current height.
+    pub async fn valid_until_block(&self, pos: Position) -> Result<u64>
+    where
+        Co: ControllerBehaviour,
+    {
+        let Position::FromCurrent(_) = pos else {
+            return get_valid_until_block(&self.controller, pos).await;
+        };
+        let current = match self.recent_height.get() {
+            Some((fetched_at, height))
+                if !self.fresh_height && fetched_at.elapsed() < RECENT_HEIGHT_TTL =>
+            {
+                height
+            }
+            _ => {
+                let height = self.controller.get_block_number(false).await?;
+                self.recent_height.set(Some((Instant::now(), height)));
+                height
+            }
+        };
+        valid_until_block_at(pos, current)
+    }
+
+    /// Make sure the tx is for this chain, so that it won't be sent to another chain by mistake.
+    pub async fn check_chain_id(&self, expected: &[u8]) -> Result<()>
+    where
+        Co: ControllerBehaviour,
+    {
+        let chain_id = self.chain_id().await?;
+        ensure!(
+            chain_id == expected,
+            "chain id mismatched, the node's chain id is `{}` but `{}` is expected",
+            hex(&chain_id),
+            hex(expected)
+        );
+        Ok(())
+    }
+
     pub fn current_controller_addr(&self) -> &str {
         &self.current_setting.controller_addr
     }
 
+    /// How to connect to the current controller and executor, e.g. for extra connections in `bench`.
+    pub fn client_setting(&self) -> ClientSetting {
+        ClientSetting::of(&self.current_setting, self.request_policy)
+    }
+
+    pub fn request_policy(&self) -> RequestPolicy {
+        self.request_policy
+    }
+
+    /// Change the policy of the following requests without reconnecting.
+    pub fn set_request_policy(&mut self, policy: RequestPolicy)
+    where
+        Co: GrpcClientBehaviour,
+        Ex: GrpcClientBehaviour,
+        Ev: GrpcClientBehaviour,
+    {
+        self.request_policy = policy;
+        self.controller.set_policy(policy);
+        self.executor.set_policy(policy);
+        self.evm.set_policy(policy);
+    }
+
     pub fn current_executor_addr(&self) -> &str {
         &self.current_setting.executor_addr
     }
@@ -127,16 +673,57 @@ impl<Co, Ex, Ev> Context<Co, Ex, Ev> {
             return Ok(());
         }
 
-        let (controller, executor, evm) = self.rt.block_on(async {
-            let co = Co::connect_lazy(&setting.controller_addr)?;
-            let ex = Ex::connect_lazy(&setting.executor_addr)?;
-            let ev = Ev::connect_lazy(&setting.executor_addr)?;
-            anyhow::Ok((co, ex, ev))
-        })??;
-        self.controller = controller;
-        self.executor = executor;
-        self.evm = evm;
+        // Keep the connections if only e.g. the account is changed.
+        let current = &self.current_setting;
+        let reconnect = current.controller_addr != setting.controller_addr
+            || current.executor_addr != setting.executor_addr
+            || current.tls != setting.tls
+            || current.pool_size != setting.pool_size
+            || current.keep_alive != setting.keep_alive;
+        // Nothing is changed until all of those that can fail succeed.
+        let clients = if reconnect {
+            let clients = self.rt.block_on(async {
+                let client_setting = ClientSetting::of(&setting, self.request_policy);
+                let co = Co::connect_lazy(&setting.controller_addr, &client_setting)?;
+                let ex = Ex::connect_lazy(&setting.executor_addr, &client_setting)?;
+                let ev = Ev::connect_lazy(&setting.executor_addr, &client_setting)?;
+                anyhow::Ok((co, ex, ev))
+            })??;
+            Some(clients)
+        } else {
+            None
+        };
+        let wallet_dir = self.config.wallet_dir(&setting);
+        let wallet = if self.config.wallet_dir(&self.current_setting) != wallet_dir {
+            Some(Wallet::open(wallet_dir)?)
+        } else {
+            None
+        };
+
+        if let Some((controller, executor, evm)) = clients {
+            self.controller = controller;
+            self.executor = executor;
+            self.evm = evm;
+        }
+        if let Some(wallet) = wallet {
+            self.wallet = wallet;
+        }
+        if self.current_setting.controller_addr != setting.controller_addr {
+            self.block_cache.clear();
+        }
+        if self.current_setting.executor_addr != setting.executor_addr {
+            self.abi_cache.clear();
+        }
+        if reconnect || self.current_setting.crypto_type != setting.crypto_type {
+            self.crypto_checked.set(false);
+        }
+        if reconnect {
+            self.chain.take();
+            self.recent_height.take();
+        }
         self.current_setting = setting;
+        self.unlocked_signer.take();
+        self.local_nonce.take();
 
         Ok(())
     }
@@ -146,15 +733,150 @@ impl<Co, Ex, Ev> Context<Co, Ex, Ev> {
 #[error("Canceled")]
 pub struct Canceled;
 
-pub struct CtrlCSignalCapturedRuntime(tokio::runtime::Runtime);
+pub struct CtrlCSignalCapturedRuntime {
+    rt: tokio::runtime::Runtime,
+    // Whether `cancel_scope` captures Ctrl-C, it's enabled in interactive mode.
+    scoped: Cell<bool>,
+    // Set if Ctrl-C is pressed in the current cancel scope.
+    canceled: Arc<AtomicBool>,
+    worker_threads: Option<NonZeroUsize>,
+}
 
 impl CtrlCSignalCapturedRuntime {
+    /// Build a multi-thread runtime, it has a worker per CPU core if `worker_threads` isn't given.
+    pub fn new(worker_threads: Option<NonZeroUsize>) -> Result<Self> {
+        let mut builder = tokio::runtime::Builder::new_multi_thread();
+        builder.enable_all();
+        if let Some(n) = worker_threads {
+            builder.worker_threads(n.get());
+        }
+        Ok(Self {
+            rt: builder.build()?,
+            scoped: Cell::new(false),
+            canceled: Arc::new(AtomicBool::new(false)),
+            worker_threads,
+        })
+    }
+
+    /// The worker threads it's built with, `None` for the default.
+    pub fn worker_threads(&self) -> Option<NonZeroUsize> {
+        self.worker_threads
+    }
+
+    /// Run the future, it's canceled if Ctrl-C is pressed, or has been pressed in the current [`CancelScope`].
     pub fn block_on<F: Future>(&self, future: F) -> Result<F::Output, Canceled> {
-        self.0.block_on(async {
+        if self.canceled.load(Ordering::SeqCst) {
+            return Err(Canceled);
+        }
+        self.rt.block_on(async {
             tokio::select! {
-                _ = tokio::signal::ctrl_c() => Err(Canceled),
+                _ = tokio::signal::ctrl_c() => {
+                    self.canceled.store(self.scoped.get(), Ordering::SeqCst);
+                    Err(Canceled)
+                }
                 res = future => Ok(res),
             }
         })
     }
+
+    /// Capture Ctrl-C in cancel scopes, so that it cancels the running command instead of killing the process.
+    pub fn set_scoped(&self, scoped: bool) {
+        self.scoped.set(scoped);
+    }
+
+    /// Start a scope for a command. If Ctrl-C is pressed in it, the in-flight `block_on` and the rest of them
+    /// return [`Canceled`], even if the signal arrives between them, so that the command returns early.
+    ///
+    /// It does nothing unless enabled by [`set_scoped`](Self::set_scoped).
+    pub fn cancel_scope(&self) -> CancelScope {
+        let listener = self.scoped.get().then(|| {
+            let canceled = Arc::clone(&self.canceled);
+            self.rt.spawn(async move {
+                if tokio::signal::ctrl_c().await.is_ok() {
+                    canceled.store(true, Ordering::SeqCst);
+                }
+            })
+        });
+        CancelScope {
+            listener,
+            canceled: Arc::clone(&self.canceled),
+        }
+    }
+}
+
+/// See [`CtrlCSignalCapturedRuntime::cancel_scope`].
+pub struct CancelScope {
+    listener: Option<tokio::task::JoinHandle<()>>,
+    canceled: Arc<AtomicBool>,
+}
+
+impl Drop for CancelScope {
+    fn drop(&mut self) {
+        if let Some(listener) = self.listener.take() {
+            listener.abort();
+        }
+        self.canceled.store(false, Ordering::SeqCst);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cancel_scope() {
+        let rt = CtrlCSignalCapturedRuntime::new(None).unwrap();
+        rt.set_scoped(true);
+        {
+            let _scope = rt.cancel_scope();
+            assert!(rt.block_on(async { 1 }).is_ok());
+            // As if Ctrl-C is pressed between two requests.
+            rt.canceled.store(true, Ordering::SeqCst);
+            assert!(rt.block_on(async { 1 }).is_err());
+        }
+        // The next command isn't affected.
+        assert_eq!(rt.block_on(async { 1 }).unwrap(), 1);
+    }
+
+    #[test]
+    fn test_capture_output() {
+        use crate::cmd::cldi_cmd;
+        use crate::core::mock::context;
+        use serde_json::json;
+
+        let cldi_cmd = cldi_cmd();
+        let (mut ctx, _temp_dir) = context();
+        ctx.controller
+            .expect_get_block_number()
+            .returning(|_| Ok(100));
+
+        let (ret, outputs) =
+            ctx.capture(|ctx| cldi_cmd.exec_from(["cldi", "get", "block-number"], ctx));
+        ret.unwrap();
+        assert_eq!(outputs, [json!(100)]);
+    }
+
+    #[test]
+    fn test_address_checksum() {
+        use crate::core::mock::context;
+        use serde_json::json;
+
+        let (mut ctx, _temp_dir) = context();
+        let lower = "0x5aaeb6053f3e94c9b9a09f33669435e7ef1beaed";
+        let bad = "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAeD";
+        let addr = ctx.parse_addr(bad).unwrap();
+        let (_, outputs) = ctx.capture(|ctx| ctx.emit(&addr));
+        assert_eq!(outputs, [json!(lower)]);
+
+        let mut setting = ctx.current_setting.clone();
+        setting.crypto_type = CryptoType::Eth;
+        ctx.switch_context(setting).unwrap();
+        assert!(ctx.parse_addr(bad).is_err());
+        assert!(ctx.parse_addr(lower).is_ok());
+        let (_, outputs) = ctx.capture(|ctx| ctx.emit(&addr));
+        assert_eq!(
+            outputs,
+            [json!("0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed")]
+        );
+    }
 }
@@ -16,252 +16,854 @@
 
 use anyhow::Context;
 use anyhow::Result;
+use anyhow::{anyhow, bail, ensure};
 
+use futures::{stream, StreamExt};
 use prost::Message;
+use serde_json::json;
+use std::ops::RangeInclusive;
+use std::time::Duration;
 use tonic::transport::Channel;
 
-use crate::crypto::{ArrayLike, Hash};
+use super::client::{call_with_retry, call_with_timeout, check_dry_run, GrpcClient};
+use super::error::{is_dry_run, SdkError, SdkResult};
+use super::signer::{build_raw_tx, build_raw_utxo, Signer};
+use crate::crypto::{recover_signer, Address, ArrayLike, Crypto, Hash};
+use crate::display::{Display as _, DisplayOptions};
 use crate::proto::{
     blockchain::{
         raw_transaction::Tx, CompactBlock, RawTransaction, Transaction as CloudNormalTransaction,
-        UnverifiedTransaction, UnverifiedUtxoTransaction, UtxoTransaction as CloudUtxoTransaction,
-        Witness,
+        UtxoTransaction as CloudUtxoTransaction, Witness,
     },
     common::{Empty, Hash as CloudHash, NodeNetInfo, TotalNodeInfo},
-    controller::{BlockNumber, Flag, SystemConfig},
+    controller::{rpc_service_client::RpcServiceClient, BlockNumber, Flag, SystemConfig},
 };
+use crate::utils::hex;
 
-pub type ControllerClient = crate::proto::controller::rpc_service_client::RpcServiceClient<Channel>;
+pub type ControllerClient = GrpcClient<RpcServiceClient<Channel>>;
 
 #[tonic::async_trait]
 pub trait ControllerBehaviour {
     // TODO: should I use the protobuf type instead of concrete type? e.g. u64 -> BlockNumber
 
-    async fn send_raw(&self, raw: RawTransaction) -> Result<Hash>;
+    async fn send_raw(&self, raw: RawTransaction) -> SdkResult<Hash>;
 
-    async fn get_version(&self) -> Result<String>;
-    async fn get_system_config(&self) -> Result<SystemConfig>;
+    async fn get_version(&self) -> SdkResult<String>;
+    async fn get_system_config(&self) -> SdkResult<SystemConfig>;
 
-    async fn get_block_number(&self, for_pending: bool) -> Result<u64>;
-    async fn get_block_hash(&self, block_number: u64) -> Result<Hash>;
+    async fn get_block_number(&self, for_pending: bool) -> SdkResult<u64>;
+    async fn get_block_hash(&self, block_number: u64) -> SdkResult<Hash>;
 
-    async fn get_block_by_number(&self, block_number: u64) -> Result<CompactBlock>;
-    async fn get_block_by_hash(&self, hash: Hash) -> Result<CompactBlock>;
+    async fn get_block_by_number(&self, block_number: u64) -> SdkResult<CompactBlock>;
+    async fn get_block_by_hash(&self, hash: Hash) -> SdkResult<CompactBlock>;
 
-    async fn get_tx(&self, tx_hash: Hash) -> Result<RawTransaction>;
-    async fn get_tx_index(&self, tx_hash: Hash) -> Result<u64>;
-    async fn get_tx_block_number(&self, tx_hash: Hash) -> Result<u64>;
+    async fn get_tx(&self, tx_hash: Hash) -> SdkResult<RawTransaction>;
+    async fn get_tx_index(&self, tx_hash: Hash) -> SdkResult<u64>;
+    async fn get_tx_block_number(&self, tx_hash: Hash) -> SdkResult<u64>;
 
-    async fn get_peer_count(&self) -> Result<u64>;
-    async fn get_peers_info(&self) -> Result<TotalNodeInfo>;
+    async fn get_peer_count(&self) -> SdkResult<u64>;
+    async fn get_peers_info(&self) -> SdkResult<TotalNodeInfo>;
 
-    async fn add_node(&self, multiaddr: String) -> Result<u32>;
+    async fn add_node(&self, multiaddr: String) -> SdkResult<u32>;
+}
+
+/// Status codes returned by `add_node`.
+///
+/// See [status_code](https://github.com/cita-cloud/status_code).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AddNodeStatus {
+    Success,
+    ConvertIntError,
+    NoneStatusCode,
+    FatalError,
+    NetworkServerNotReady,
+    SendMsgError,
+    BroadcastMsgError,
+    MultiAddrParseError,
+    DialNodeFail,
+    AddExistedPeer,
+    Unknown(u32),
+}
+
+impl AddNodeStatus {
+    pub fn code(&self) -> u32 {
+        match self {
+            Self::Success => 0,
+            Self::ConvertIntError => 1,
+            Self::NoneStatusCode => 2,
+            Self::FatalError => 3,
+            Self::NetworkServerNotReady => 400,
+            Self::SendMsgError => 401,
+            Self::BroadcastMsgError => 402,
+            Self::MultiAddrParseError => 403,
+            Self::DialNodeFail => 404,
+            Self::AddExistedPeer => 405,
+            Self::Unknown(code) => *code,
+        }
+    }
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            Self::Success => "success",
+            Self::ConvertIntError => "failed to convert status code",
+            Self::NoneStatusCode => "status code is none",
+            Self::FatalError => "fatal error",
+            Self::NetworkServerNotReady => "network server is not ready",
+            Self::SendMsgError => "failed to send message",
+            Self::BroadcastMsgError => "failed to broadcast message",
+            Self::MultiAddrParseError => "invalid multiaddr",
+            Self::DialNodeFail => "failed to dial the node",
+            Self::AddExistedPeer => "the node already exists",
+            Self::Unknown(_) => "unknown status code, please check controller's log",
+        }
+    }
+}
+
+impl From<u32> for AddNodeStatus {
+    fn from(code: u32) -> Self {
+        match code {
+            0 => Self::Success,
+            1 => Self::ConvertIntError,
+            2 => Self::NoneStatusCode,
+            3 => Self::FatalError,
+            400 => Self::NetworkServerNotReady,
+            401 => Self::SendMsgError,
+            402 => Self::BroadcastMsgError,
+            403 => Self::MultiAddrParseError,
+            404 => Self::DialNodeFail,
+            405 => Self::AddExistedPeer,
+            unknown => Self::Unknown(unknown),
+        }
+    }
+}
+
+fn hash_from_node(what: &'static str, bytes: &[u8]) -> SdkResult<Hash> {
+    Hash::try_from_slice(bytes).map_err(|_| SdkError::InvalidHashLength {
+        what,
+        len: bytes.len(),
+    })
 }
 
 #[tonic::async_trait]
 impl ControllerBehaviour for ControllerClient {
-    async fn send_raw(&self, raw: RawTransaction) -> Result<Hash> {
-        let resp = self.clone().send_raw_transaction(raw).await?.into_inner();
+    async fn send_raw(&self, raw: RawTransaction) -> SdkResult<Hash> {
+        check_dry_run(&self.policy, "controller", "send_raw_transaction", || {
+            raw.to_json(DisplayOptions::default())
+        })?;
+        let resp = call_with_timeout(
+            &self.policy,
+            "controller",
+            self.inner.clone().send_raw_transaction(raw),
+        )
+        .await?;
 
-        Hash::try_from_slice(&resp.hash)
-            .context("controller returns an invalid transaction hash, maybe we are using a wrong signing algorithm?")
+        hash_from_node("tx hash", &resp.hash)
     }
 
-    async fn get_version(&self) -> Result<String> {
-        let version = ControllerClient::get_version(&mut self.clone(), Empty {})
-            .await?
-            .into_inner()
-            .version;
+    async fn get_version(&self) -> SdkResult<String> {
+        check_dry_run(&self.policy, "controller", "get_version", || json!({}))?;
+        let version = call_with_retry(
+            &self.policy,
+            "controller",
+            &self.inner,
+            Empty {},
+            |mut c, req| async move { RpcServiceClient::get_version(&mut c, req).await },
+        )
+        .await?
+        .version;
 
         Ok(version)
     }
 
-    async fn get_system_config(&self) -> Result<SystemConfig> {
-        let resp = ControllerClient::get_system_config(&mut self.clone(), Empty {})
-            .await?
-            .into_inner();
+    async fn get_system_config(&self) -> SdkResult<SystemConfig> {
+        let resp = call_with_retry(
+            &self.policy,
+            "controller",
+            &self.inner,
+            Empty {},
+            |mut c, req| async move { RpcServiceClient::get_system_config(&mut c, req).await },
+        )
+        .await?;
 
         Ok(resp)
     }
 
-    async fn get_block_number(&self, for_pending: bool) -> Result<u64> {
+    async fn get_block_number(&self, for_pending: bool) -> SdkResult<u64> {
         let flag = Flag { flag: for_pending };
-        let resp = ControllerClient::get_block_number(&mut self.clone(), flag)
-            .await?
-            .into_inner();
+        let resp = call_with_retry(
+            &self.policy,
+            "controller",
+            &self.inner,
+            flag,
+            |mut c, req| async move { RpcServiceClient::get_block_number(&mut c, req).await },
+        )
+        .await?;
 
         Ok(resp.block_number)
     }
 
-    async fn get_block_hash(&self, block_number: u64) -> Result<Hash> {
+    async fn get_block_hash(&self, block_number: u64) -> SdkResult<Hash> {
         let block_number = BlockNumber { block_number };
-        let resp = ControllerClient::get_block_hash(&mut self.clone(), block_number)
-            .await?
-            .into_inner();
+        check_dry_run(
+            &self.policy,
+            "controller",
+            "get_block_hash",
+            || json!({ "block_number": block_number.block_number }),
+        )?;
+        let resp = call_with_retry(
+            &self.policy,
+            "controller",
+            &self.inner,
+            block_number,
+            |mut c, req| async move { RpcServiceClient::get_block_hash(&mut c, req).await },
+        )
+        .await?;
 
-        Hash::try_from_slice(&resp.hash)
-            .context("controller returns an invalid block hash, maybe we are using a different signing algorithm?")
+        hash_from_node("block hash", &resp.hash)
     }
 
-    async fn get_block_by_number(&self, block_number: u64) -> Result<CompactBlock> {
+    async fn get_block_by_number(&self, block_number: u64) -> SdkResult<CompactBlock> {
         let block_number = BlockNumber { block_number };
-        let resp = ControllerClient::get_block_by_number(&mut self.clone(), block_number)
-            .await?
-            .into_inner();
+        check_dry_run(
+            &self.policy,
+            "controller",
+            "get_block_by_number",
+            || json!({ "block_number": block_number.block_number }),
+        )?;
+        let resp = call_with_retry(
+            &self.policy,
+            "controller",
+            &self.inner,
+            block_number,
+            |mut c, req| async move { RpcServiceClient::get_block_by_number(&mut c, req).await },
+        )
+        .await?;
 
         Ok(resp)
     }
 
-    async fn get_block_by_hash(&self, hash: Hash) -> Result<CompactBlock> {
+    async fn get_block_by_hash(&self, hash: Hash) -> SdkResult<CompactBlock> {
         let hash = CloudHash {
             hash: hash.to_vec(),
         };
-        let resp = ControllerClient::get_block_by_hash(&mut self.clone(), hash)
-            .await?
-            .into_inner();
+        check_dry_run(
+            &self.policy,
+            "controller",
+            "get_block_by_hash",
+            || json!({ "hash": hex(&hash.hash) }),
+        )?;
+        let resp = call_with_retry(
+            &self.policy,
+            "controller",
+            &self.inner,
+            hash,
+            |mut c, req| async move { RpcServiceClient::get_block_by_hash(&mut c, req).await },
+        )
+        .await?;
 
         Ok(resp)
     }
 
-    async fn get_tx(&self, tx_hash: Hash) -> Result<RawTransaction> {
-        let resp = self
-            .clone()
-            .get_transaction(CloudHash {
-                hash: tx_hash.to_vec(),
-            })
-            .await?
-            .into_inner();
+    async fn get_tx(&self, tx_hash: Hash) -> SdkResult<RawTransaction> {
+        let hash = CloudHash {
+            hash: tx_hash.to_vec(),
+        };
+        check_dry_run(
+            &self.policy,
+            "controller",
+            "get_transaction",
+            || json!({ "hash": hex(&hash.hash) }),
+        )?;
+        let resp = call_with_retry(
+            &self.policy,
+            "controller",
+            &self.inner,
+            hash,
+            |mut c, req| async move { RpcServiceClient::get_transaction(&mut c, req).await },
+        )
+        .await?;
 
         Ok(resp)
     }
 
-    async fn get_tx_index(&self, tx_hash: Hash) -> Result<u64> {
-        let resp = self
-            .clone()
-            .get_transaction_index(CloudHash {
-                hash: tx_hash.to_vec(),
-            })
-            .await?
-            .into_inner();
+    async fn get_tx_index(&self, tx_hash: Hash) -> SdkResult<u64> {
+        let hash = CloudHash {
+            hash: tx_hash.to_vec(),
+        };
+        check_dry_run(
+            &self.policy,
+            "controller",
+            "get_transaction_index",
+            || json!({ "hash": hex(&hash.hash) }),
+        )?;
+        let resp = call_with_retry(
+            &self.policy,
+            "controller",
+            &self.inner,
+            hash,
+            |mut c, req| async move { RpcServiceClient::get_transaction_index(&mut c, req).await },
+        )
+        .await?;
 
         Ok(resp.tx_index)
     }
 
-    async fn get_tx_block_number(&self, tx_hash: Hash) -> Result<u64> {
-        let resp = self
-            .clone()
-            .get_transaction_block_number(CloudHash {
-                hash: tx_hash.to_vec(),
-            })
-            .await?
-            .into_inner();
+    async fn get_tx_block_number(&self, tx_hash: Hash) -> SdkResult<u64> {
+        let hash = CloudHash {
+            hash: tx_hash.to_vec(),
+        };
+        check_dry_run(
+            &self.policy,
+            "controller",
+            "get_transaction_block_number",
+            || json!({ "hash": hex(&hash.hash) }),
+        )?;
+        let resp = call_with_retry(
+            &self.policy,
+            "controller",
+            &self.inner,
+            hash,
+            |mut c, req| async move {
+                RpcServiceClient::get_transaction_block_number(&mut c, req).await
+            },
+        )
+        .await?;
 
         Ok(resp.block_number)
     }
 
-    async fn get_peer_count(&self) -> Result<u64> {
-        let resp = ControllerClient::get_peer_count(&mut self.clone(), Empty {})
-            .await?
-            .into_inner();
+    async fn get_peer_count(&self) -> SdkResult<u64> {
+        check_dry_run(&self.policy, "controller", "get_peer_count", || json!({}))?;
+        let resp = call_with_retry(
+            &self.policy,
+            "controller",
+            &self.inner,
+            Empty {},
+            |mut c, req| async move { RpcServiceClient::get_peer_count(&mut c, req).await },
+        )
+        .await?;
 
         Ok(resp.peer_count)
     }
 
-    async fn get_peers_info(&self) -> Result<TotalNodeInfo> {
-        let resp = ControllerClient::get_peers_info(&mut self.clone(), Empty {})
-            .await?
-            .into_inner();
+    async fn get_peers_info(&self) -> SdkResult<TotalNodeInfo> {
+        check_dry_run(&self.policy, "controller", "get_peers_info", || json!({}))?;
+        let resp = call_with_retry(
+            &self.policy,
+            "controller",
+            &self.inner,
+            Empty {},
+            |mut c, req| async move { RpcServiceClient::get_peers_info(&mut c, req).await },
+        )
+        .await?;
 
         Ok(resp)
     }
 
-    async fn add_node(&self, multiaddr: String) -> Result<u32> {
+    async fn add_node(&self, multiaddr: String) -> SdkResult<u32> {
         let node_info = NodeNetInfo {
             multi_address: multiaddr,
             ..Default::default()
         };
-        let resp = ControllerClient::add_node(&mut self.clone(), node_info)
-            .await?
-            .into_inner();
+        check_dry_run(
+            &self.policy,
+            "controller",
+            "add_node",
+            || json!({ "multi_address": node_info.multi_address }),
+        )?;
+        let resp = call_with_timeout(
+            &self.policy,
+            "controller",
+            RpcServiceClient::add_node(&mut self.inner.clone(), node_info),
+        )
+        .await?;
 
         Ok(resp.code)
     }
 }
 
+/// Batches of requests with a result for each item.
+///
+/// A dry run fails the whole batch instead of each item, see [`SdkError::DryRun`].
+#[tonic::async_trait]
+pub trait ControllerBehaviourExt {
+    /// Get txs concurrently with at most `concurrency` in-flight requests.
+    /// The results are in the same order as the given hashes.
+    async fn get_txs(
+        &self,
+        tx_hashes: &[Hash],
+        concurrency: usize,
+    ) -> Result<Vec<Result<RawTransaction>>>;
+    /// Get block hashes of the heights concurrently with at most `concurrency` in-flight requests.
+    /// The results are in the order of heights.
+    async fn get_block_hashes(
+        &self,
+        heights: RangeInclusive<u64>,
+        concurrency: usize,
+    ) -> Result<Vec<Result<Hash>>>;
+    /// Get blocks of the heights concurrently with at most `concurrency` in-flight requests.
+    /// The results are in the order of heights.
+    async fn get_blocks(
+        &self,
+        heights: RangeInclusive<u64>,
+        concurrency: usize,
+    ) -> Result<Vec<Result<CompactBlock>>>;
+    /// Binary search the block at the `bound` of the unix `timestamp` in milliseconds, by
+    /// the timestamps in block headers. `None` if there is no such block.
+    ///
+    /// Timestamps may go backwards a bit for the proposers' clock skew, so the boundary found is
+    /// widened by checking [`TIME_SKEW_WINDOW`] blocks beyond it, and moved to the furthest one
+    /// that still matches. It takes O(log n) plus a window of `get_block_by_number` calls.
+    async fn find_block_by_time(&self, timestamp: u64, bound: TimeBound) -> Result<Option<u64>>;
+}
+
+/// Which side of a time [`ControllerBehaviourExt::find_block_by_time`] looks for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimeBound {
+    /// The last block before the time.
+    Before,
+    /// The first block at or after the time.
+    After,
+}
+
+/// How many blocks beyond the boundary are also checked by [`ControllerBehaviourExt::find_block_by_time`].
+pub const TIME_SKEW_WINDOW: u64 = 16;
+
+fn stop_at_dry_run<T>(mut results: Vec<Result<T>>) -> Result<Vec<Result<T>>> {
+    let dry_run = results
+        .iter()
+        .position(|res| res.as_ref().is_err_and(is_dry_run));
+    match dry_run {
+        Some(i) => Err(results.swap_remove(i).err().unwrap()),
+        None => Ok(results),
+    }
+}
+
+fn block_timestamp(block: &CompactBlock) -> Result<u64> {
+    block
+        .header
+        .as_ref()
+        .map(|header| header.timestamp)
+        .ok_or_else(|| anyhow!("the block has no header"))
+}
+
+#[tonic::async_trait]
+impl<T> ControllerBehaviourExt for T
+where
+    T: ControllerBehaviour + Send + Sync,
+{
+    async fn get_txs(
+        &self,
+        tx_hashes: &[Hash],
+        concurrency: usize,
+    ) -> Result<Vec<Result<RawTransaction>>> {
+        let results = stream::iter(tx_hashes.iter().copied())
+            .map(|tx_hash| async move {
+                self.get_tx(tx_hash)
+                    .await
+                    .with_context(|| format!("failed to get tx `{}`", hex(&tx_hash)))
+            })
+            .buffered(concurrency.max(1))
+            .collect()
+            .await;
+        stop_at_dry_run(results)
+    }
+
+    async fn get_block_hashes(
+        &self,
+        heights: RangeInclusive<u64>,
+        concurrency: usize,
+    ) -> Result<Vec<Result<Hash>>> {
+        let results = stream::iter(heights)
+            .map(|h| async move {
+                self.get_block_hash(h)
+                    .await
+                    .with_context(|| format!("failed to get block hash at height `{h}`"))
+            })
+            .buffered(concurrency.max(1))
+            .collect()
+            .await;
+        stop_at_dry_run(results)
+    }
+
+    async fn get_blocks(
+        &self,
+        heights: RangeInclusive<u64>,
+        concurrency: usize,
+    ) -> Result<Vec<Result<CompactBlock>>> {
+        let results = stream::iter(heights)
+            .map(|h| async move {
+                self.get_block_by_number(h)
+                    .await
+                    .with_context(|| format!("failed to get block at height `{h}`"))
+            })
+            .buffered(concurrency.max(1))
+            .collect()
+            .await;
+        stop_at_dry_run(results)
+    }
+
+    async fn find_block_by_time(&self, timestamp: u64, bound: TimeBound) -> Result<Option<u64>> {
+        let latest = self.get_block_number(false).await?;
+        // The first height whose timestamp is at or after the time, `latest + 1` if none.
+        let (mut lo, mut hi) = (0, latest + 1);
+        while lo < hi {
+            let mid = lo + (hi - lo) / 2;
+            let block = self
+                .get_block_by_number(mid)
+                .await
+                .with_context(|| format!("failed to get block at height `{mid}`"))?;
+            if block_timestamp(&block)? >= timestamp {
+                hi = mid;
+            } else {
+                lo = mid + 1;
+            }
+        }
+        let first = lo;
+
+        // Widen it outward, away from the time, so blocks with skewed timestamps
+        // near the boundary are included.
+        let window = match bound {
+            TimeBound::After if first > 0 => {
+                Some(first.saturating_sub(TIME_SKEW_WINDOW)..=first - 1)
+            }
+            TimeBound::Before if first <= latest => {
+                Some(first..=latest.min(first + TIME_SKEW_WINDOW - 1))
+            }
+            _ => None,
+        };
+        let mut matched = vec![];
+        if let Some(window) = window {
+            let start = *window.start();
+            let blocks = self.get_blocks(window, TIME_SKEW_WINDOW as usize).await?;
+            for (h, block) in (start..).zip(blocks) {
+                let at_or_after = block_timestamp(&block?)? >= timestamp;
+                if at_or_after == (bound == TimeBound::After) {
+                    matched.push(h);
+                }
+            }
+        }
+        let found = match bound {
+            TimeBound::After => matched
+                .first()
+                .copied()
+                .or_else(|| (first <= latest).then_some(first)),
+            TimeBound::Before => matched.last().copied().or_else(|| first.checked_sub(1)),
+        };
+        Ok(found)
+    }
+}
+
 pub trait SignerBehaviour {
     fn hash(&self, msg: &[u8]) -> Vec<u8>;
     fn address(&self) -> &[u8];
     fn sign(&self, msg: &[u8]) -> Vec<u8>;
 
     fn sign_raw_tx(&self, tx: CloudNormalTransaction) -> RawTransaction {
-        // calc tx hash
-        let tx_hash = {
-            // build tx bytes
-            let tx_bytes = {
-                let mut buf = Vec::with_capacity(tx.encoded_len());
-                tx.encode(&mut buf).unwrap();
-                buf
-            };
-            self.hash(tx_bytes.as_slice())
+        let tx_hash = self.hash(&tx.encode_to_vec());
+        let signature = self.sign(&tx_hash);
+        build_raw_tx(tx, tx_hash, self.address().to_vec(), signature)
+    }
+
+    fn sign_raw_utxo(&self, utxo: CloudUtxoTransaction) -> RawTransaction {
+        let utxo_hash = self.hash(&utxo.encode_to_vec());
+        let signature = self.sign(&utxo_hash);
+        build_raw_utxo(utxo, utxo_hash, self.address().to_vec(), signature)
+    }
+
+    /// Co-sign an utxo signed by others, e.g. multisig admin.
+    fn append_witness_to_utxo(&self, raw: &mut RawTransaction) -> Result<()> {
+        let unverified_utxo = match &mut raw.tx {
+            Some(Tx::UtxoTx(unverified_utxo)) => unverified_utxo,
+            _ => bail!("not an utxo transaction"),
         };
+        let utxo = unverified_utxo
+            .transaction
+            .as_ref()
+            .ok_or_else(|| anyhow!("utxo transaction is missing"))?;
+
+        let utxo_hash = {
+            let mut buf = Vec::with_capacity(utxo.encoded_len());
+            utxo.encode(&mut buf).unwrap();
+            self.hash(buf.as_slice())
+        };
+        ensure!(
+            utxo_hash == unverified_utxo.transaction_hash,
+            "utxo hash mismatched, expected `{}`, got `{}`. Are we using the same crypto type?",
+            hex(&utxo_hash),
+            hex(&unverified_utxo.transaction_hash),
+        );
 
-        // sign tx hash
         let sender = self.address().to_vec();
-        let signature = self.sign(tx_hash.as_slice()).to_vec();
+        ensure!(
+            unverified_utxo.witnesses.iter().all(|w| w.sender != sender),
+            "this utxo has already been signed by `{}`",
+            hex(&sender),
+        );
+        let signature = self.sign(utxo_hash.as_slice());
+        unverified_utxo
+            .witnesses
+            .push(Witness { sender, signature });
 
-        // build raw tx
-        let raw_tx = {
-            let witness = Witness { sender, signature };
+        Ok(())
+    }
+}
 
-            let unverified_tx = UnverifiedTransaction {
-                transaction: Some(tx),
-                transaction_hash: tx_hash.to_vec(),
-                witness: Some(witness),
-            };
+/// The results of checking a raw tx, see [`verify_raw_tx`].
+pub struct TxVerification {
+    /// The hash stored in the tx.
+    pub tx_hash: Vec<u8>,
+    /// The hash recomputed from the inner tx.
+    pub computed_hash: Hash,
+    /// (declared sender, recovered signer) of each witness.
+    pub signers: Vec<(Vec<u8>, Result<Address>)>,
+}
 
-            RawTransaction {
-                tx: Some(Tx::NormalTx(unverified_tx)),
-            }
-        };
+impl TxVerification {
+    pub fn hash_matched(&self) -> bool {
+        self.computed_hash.as_slice() == self.tx_hash.as_slice()
+    }
 
-        raw_tx
+    pub fn is_valid(&self) -> bool {
+        self.hash_matched()
+            && !self.signers.is_empty()
+            && self.signers.iter().all(|(sender, recovered)| {
+                matches!(recovered, Ok(addr) if addr.as_slice() == sender.as_slice())
+            })
     }
+}
 
-    fn sign_raw_utxo(&self, utxo: CloudUtxoTransaction) -> RawTransaction {
-        // calc utxo hash
-        let utxo_hash = {
-            // build utxo bytes
-            let utxo_bytes = {
-                let mut buf = Vec::with_capacity(utxo.encoded_len());
-                utxo.encode(&mut buf).unwrap();
-                buf
-            };
-            self.hash(utxo_bytes.as_slice())
-        };
+/// Recompute the tx hash and recover the signers from witnesses using crypto `C`,
+/// without trusting the declared senders.
+pub fn verify_raw_tx<C: Crypto>(raw: &RawTransaction) -> Result<TxVerification> {
+    let (encoded, tx_hash, witnesses) = match &raw.tx {
+        Some(Tx::NormalTx(tx)) => (
+            tx.transaction
+                .as_ref()
+                .ok_or_else(|| anyhow!("transaction is missing"))?
+                .encode_to_vec(),
+            &tx.transaction_hash,
+            tx.witness.iter().collect::<Vec<_>>(),
+        ),
+        Some(Tx::UtxoTx(utxo)) => (
+            utxo.transaction
+                .as_ref()
+                .ok_or_else(|| anyhow!("utxo transaction is missing"))?
+                .encode_to_vec(),
+            &utxo.transaction_hash,
+            utxo.witnesses.iter().collect(),
+        ),
+        None => bail!("empty raw transaction"),
+    };
 
-        // sign utxo hash
-        let sender = self.address().to_vec();
-        let signature = self.sign(utxo_hash.as_slice()).to_vec();
+    // Signatures are made over the stored hash.
+    let signers = witnesses
+        .into_iter()
+        .map(|w| (w.sender.clone(), recover_signer::<C>(tx_hash, &w.signature)))
+        .collect();
 
-        // build raw utxo
-        let raw_utxo = {
-            let witness = Witness { sender, signature };
+    Ok(TxVerification {
+        tx_hash: tx_hash.clone(),
+        computed_hash: C::hash(&encoded),
+        signers,
+    })
+}
 
-            let unverified_utxo = UnverifiedUtxoTransaction {
-                transaction: Some(utxo),
-                transaction_hash: utxo_hash.to_vec(),
-                witnesses: vec![witness],
-            };
+/// Check if the chain uses crypto `C` by recomputing the latest block hash from its header.
+///
+/// The system config doesn't tell the crypto type, but the block hash is the hash of the encoded header.
+pub async fn chain_uses_crypto<C: Crypto, Co: ControllerBehaviour>(
+    controller: &Co,
+) -> Result<bool> {
+    let height = controller.get_block_number(false).await?;
+    let header = controller
+        .get_block_by_number(height)
+        .await?
+        .header
+        .ok_or_else(|| anyhow!("block header is missing"))?;
+    let block_hash = controller.get_block_hash(height).await?;
 
-            RawTransaction {
-                tx: Some(Tx::UtxoTx(unverified_utxo)),
-            }
-        };
+    Ok(C::hash(&header.encode_to_vec()) == block_hash)
+}
+
+/// Compute the transactions root the same way as the controller does.
+///
+/// It's not a merkle tree, but the hash of all the tx hashes concatenated in order.
+/// So the empty block's root is the hash of empty bytes.
+pub fn transactions_root<C: Crypto>(tx_hashes: &[Vec<u8>]) -> Hash {
+    C::hash(&tx_hashes.concat())
+}
 
-        raw_utxo
+/// The results of checking a block, see [`verify_block`].
+pub struct BlockVerification {
+    pub height: u64,
+    pub tx_count: usize,
+    /// The root stored in the block header.
+    pub transactions_root: Vec<u8>,
+    /// The root recomputed from the tx hashes in block body.
+    pub computed_root: Hash,
+}
+
+impl BlockVerification {
+    pub fn is_valid(&self) -> bool {
+        self.computed_root.as_slice() == self.transactions_root.as_slice()
     }
 }
 
+/// Recompute the transactions root of the block using crypto `C`.
+pub fn verify_block<C: Crypto>(block: &CompactBlock) -> Result<BlockVerification> {
+    let header = block
+        .header
+        .as_ref()
+        .ok_or_else(|| anyhow!("block header is missing"))?;
+    let tx_hashes = block
+        .body
+        .as_ref()
+        .map(|body| body.tx_hashes.as_slice())
+        .unwrap_or_default();
+
+    Ok(BlockVerification {
+        height: header.height,
+        tx_count: tx_hashes.len(),
+        transactions_root: header.transactions_root.clone(),
+        computed_root: transactions_root::<C>(tx_hashes),
+    })
+}
+
+/// The field names of [`SystemConfig`] in the output of `get-system-config`.
+pub const SYSTEM_CONFIG_FIELDS: [&str; 12] = [
+    "version",
+    "chain_id",
+    "admin",
+    "block_interval",
+    "validators",
+    "emergency_brake",
+    "version_pre_hash",
+    "chain_id_pre_hash",
+    "admin_pre_hash",
+    "block_interval_pre_hash",
+    "validators_pre_hash",
+    "emergency_brake_pre_hash",
+];
+
+/// Typed accessors of the raw fields.
+impl SystemConfig {
+    pub fn chain_id_hash(&self) -> SdkResult<Hash> {
+        Hash::try_from_slice(&self.chain_id).map_err(|_| SdkError::InvalidHashLength {
+            what: "chain id",
+            len: self.chain_id.len(),
+        })
+    }
+
+    /// The validators formatted as addresses, see [`DisplayOptions::addr`]. Those that aren't
+    /// 20 bytes, e.g. BLS public keys, are formatted as plain hex.
+    pub fn validator_addresses(&self, opts: DisplayOptions) -> Vec<String> {
+        self.validators.iter().map(|v| opts.addr(v)).collect()
+    }
+
+    pub fn block_interval_duration(&self) -> Duration {
+        Duration::from_secs(self.block_interval.into())
+    }
+
+    /// The version as `v<n>`.
+    pub fn version_string(&self) -> String {
+        format!("v{}", self.version)
+    }
+}
+
+/// A change between two system configs, see [`diff_system_config`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SystemConfigChange {
+    /// Values are formatted as in the output of `get-system-config`.
+    Field {
+        name: &'static str,
+        old: String,
+        new: String,
+    },
+    ValidatorAdded(Vec<u8>),
+    ValidatorRemoved(Vec<u8>),
+    /// Same validators in a different order, which changes the proposer rotation.
+    ValidatorsReordered,
+}
+
+/// Compare the system configs field by field. Validators are compared as a set,
+/// so a single added or removed validator shows up as exactly that. The values of the changed
+/// fields are formatted with `opts`.
+pub fn diff_system_config(
+    old: &SystemConfig,
+    new: &SystemConfig,
+    opts: DisplayOptions,
+) -> Vec<SystemConfigChange> {
+    let mut changes = vec![];
+    let mut field = |name, old: String, new: String| {
+        if old != new {
+            changes.push(SystemConfigChange::Field { name, old, new });
+        }
+    };
+    field("version", old.version.to_string(), new.version.to_string());
+    field("chain_id", hex(&old.chain_id), hex(&new.chain_id));
+    field("admin", opts.addr(&old.admin), opts.addr(&new.admin));
+    field(
+        "block_interval",
+        old.block_interval.to_string(),
+        new.block_interval.to_string(),
+    );
+    field(
+        "emergency_brake",
+        old.emergency_brake.to_string(),
+        new.emergency_brake.to_string(),
+    );
+    let pre_hashes = [
+        (
+            "version_pre_hash",
+            &old.version_pre_hash,
+            &new.version_pre_hash,
+        ),
+        (
+            "chain_id_pre_hash",
+            &old.chain_id_pre_hash,
+            &new.chain_id_pre_hash,
+        ),
+        ("admin_pre_hash", &old.admin_pre_hash, &new.admin_pre_hash),
+        (
+            "block_interval_pre_hash",
+            &old.block_interval_pre_hash,
+            &new.block_interval_pre_hash,
+        ),
+        (
+            "validators_pre_hash",
+            &old.validators_pre_hash,
+            &new.validators_pre_hash,
+        ),
+        (
+            "emergency_brake_pre_hash",
+            &old.emergency_brake_pre_hash,
+            &new.emergency_brake_pre_hash,
+        ),
+    ];
+    for (name, old, new) in pre_hashes {
+        field(name, hex(old), hex(new));
+    }
+
+    let removed = old
+        .validators
+        .iter()
+        .filter(|v| !new.validators.contains(v))
+        .map(|v| SystemConfigChange::ValidatorRemoved(v.clone()));
+    let added = new
+        .validators
+        .iter()
+        .filter(|v| !old.validators.contains(v))
+        .map(|v| SystemConfigChange::ValidatorAdded(v.clone()));
+    let len = changes.len();
+    changes.extend(removed.chain(added));
+    if changes.len() == len && old.validators != new.validators {
+        changes.push(SystemConfigChange::ValidatorsReordered);
+    }
+
+    changes
+}
+
 // It's actually the implementation details of the current controller service.
 #[repr(u64)]
 #[derive(Debug, Clone, Copy)]
@@ -272,14 +874,28 @@ pub enum UtxoType {
     EmergencyBrake = 1005,
 }
 
+impl UtxoType {
+    pub const ALL: [Self; 4] = [
+        Self::Admin,
+        Self::BlockInterval,
+        Self::Validators,
+        Self::EmergencyBrake,
+    ];
+
+    /// The utxo type of the lock id, `None` if it's unknown to us.
+    pub fn from_lock_id(lock_id: u64) -> Option<Self> {
+        Self::ALL.into_iter().find(|t| *t as u64 == lock_id)
+    }
+}
+
 #[tonic::async_trait]
 pub trait TransactionSenderBehaviour {
     async fn send_raw_tx<S>(&self, signer: &S, raw_tx: CloudNormalTransaction) -> Result<Hash>
     where
-        S: SignerBehaviour + Send + Sync;
+        S: Signer + ?Sized;
     async fn send_raw_utxo<S>(&self, signer: &S, raw_utxo: CloudUtxoTransaction) -> Result<Hash>
     where
-        S: SignerBehaviour + Send + Sync;
+        S: Signer + ?Sized;
 
     async fn send_tx<S>(
         &self,
@@ -292,10 +908,33 @@ pub trait TransactionSenderBehaviour {
         valid_until_block: u64,
     ) -> Result<Hash>
     where
-        S: SignerBehaviour + Send + Sync;
+        S: Signer + ?Sized;
+    /// Same as [`send_tx`], but with an explicit nonce. `None` means a random one.
+    ///
+    /// [`send_tx`]: TransactionSenderBehaviour::send_tx
+    #[allow(clippy::too_many_arguments)]
+    async fn send_tx_with_nonce<S>(
+        &self,
+        signer: &S,
+        to: Vec<u8>,
+        data: Vec<u8>,
+        value: Vec<u8>,
+        quota: u64,
+        valid_until_block: u64,
+        nonce: Option<String>,
+    ) -> Result<Hash>
+    where
+        S: Signer + ?Sized;
     async fn send_utxo<S>(&self, signer: &S, output: Vec<u8>, utxo_type: UtxoType) -> Result<Hash>
     where
-        S: SignerBehaviour + Send + Sync;
+        S: Signer + ?Sized;
+
+    /// Build an unsigned utxo based on the current system config.
+    async fn build_utxo(
+        &self,
+        output: Vec<u8>,
+        utxo_type: UtxoType,
+    ) -> Result<CloudUtxoTransaction>;
 }
 
 #[tonic::async_trait]
@@ -305,17 +944,17 @@ where
 {
     async fn send_raw_tx<S>(&self, signer: &S, raw_tx: CloudNormalTransaction) -> Result<Hash>
     where
-        S: SignerBehaviour + Send + Sync,
+        S: Signer + ?Sized,
     {
-        let raw = signer.sign_raw_tx(raw_tx);
+        let raw = signer.sign_raw_tx(raw_tx).await?;
         self.send_raw(raw).await.context("failed to send raw")
     }
 
     async fn send_raw_utxo<S>(&self, signer: &S, raw_utxo: CloudUtxoTransaction) -> Result<Hash>
     where
-        S: SignerBehaviour + Send + Sync,
+        S: Signer + ?Sized,
     {
-        let raw = signer.sign_raw_utxo(raw_utxo);
+        let raw = signer.sign_raw_utxo(raw_utxo).await?;
         self.send_raw(raw).await.context("failed to send raw")
     }
 
@@ -329,7 +968,24 @@ where
         valid_until_block: u64,
     ) -> Result<Hash>
     where
-        S: SignerBehaviour + Send + Sync,
+        S: Signer + ?Sized,
+    {
+        self.send_tx_with_nonce(signer, to, data, value, quota, valid_until_block, None)
+            .await
+    }
+
+    async fn send_tx_with_nonce<S>(
+        &self,
+        signer: &S,
+        to: Vec<u8>,
+        data: Vec<u8>,
+        value: Vec<u8>,
+        quota: u64,
+        valid_until_block: u64,
+        nonce: Option<String>,
+    ) -> Result<Hash>
+    where
+        S: Signer + ?Sized,
     {
         let system_config = self
             .get_system_config()
@@ -341,7 +997,7 @@ where
             to,
             data,
             value,
-            nonce: rand::random::<u64>().to_string(),
+            nonce: nonce.unwrap_or_else(|| rand::random::<u64>().to_string()),
             quota,
             valid_until_block,
             chain_id: system_config.chain_id.clone(),
@@ -352,8 +1008,17 @@ where
 
     async fn send_utxo<S>(&self, signer: &S, output: Vec<u8>, utxo_type: UtxoType) -> Result<Hash>
     where
-        S: SignerBehaviour + Send + Sync,
+        S: Signer + ?Sized,
     {
+        let raw_utxo = self.build_utxo(output, utxo_type).await?;
+        self.send_raw_utxo(signer, raw_utxo).await
+    }
+
+    async fn build_utxo(
+        &self,
+        output: Vec<u8>,
+        utxo_type: UtxoType,
+    ) -> Result<CloudUtxoTransaction> {
         let system_config = self
             .get_system_config()
             .await
@@ -376,6 +1041,275 @@ where
             }
         };
 
-        self.send_raw_utxo(signer, raw_utxo).await
+        Ok(raw_utxo)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::mock::MockControllerClient;
+    use crate::proto::blockchain::{UnverifiedTransaction, UnverifiedUtxoTransaction};
+
+    #[test]
+    fn test_get_txs() {
+        let mut controller = MockControllerClient::default();
+        controller.expect_get_tx().returning(|tx_hash| {
+            if tx_hash[0] == 0 {
+                return Err(SdkError::NotFound("tx".into()));
+            }
+            let utxo = UnverifiedUtxoTransaction {
+                transaction_hash: tx_hash.to_vec(),
+                ..Default::default()
+            };
+            Ok(RawTransaction {
+                tx: Some(Tx::UtxoTx(utxo)),
+            })
+        });
+
+        let tx_hashes: Vec<Hash> = (0..50u8).rev().map(|i| [i; 32]).collect();
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        let txs = rt.block_on(controller.get_txs(&tx_hashes, 16)).unwrap();
+
+        assert_eq!(txs.len(), tx_hashes.len());
+        for (tx_hash, tx) in tx_hashes.iter().zip(txs) {
+            match tx {
+                Ok(RawTransaction {
+                    tx: Some(Tx::UtxoTx(utxo)),
+                }) => assert_eq!(&utxo.transaction_hash, tx_hash),
+                Ok(_) => panic!("unexpected tx"),
+                Err(_) => assert_eq!(tx_hash[0], 0),
+            }
+        }
+    }
+
+    #[test]
+    fn test_add_node_status() {
+        for code in [0, 1, 2, 3, 400, 401, 402, 403, 404, 405, 999] {
+            assert_eq!(AddNodeStatus::from(code).code(), code);
+        }
+        assert_eq!(AddNodeStatus::from(405), AddNodeStatus::AddExistedPeer);
+        assert_eq!(AddNodeStatus::from(999), AddNodeStatus::Unknown(999));
+    }
+
+    #[test]
+    fn test_transactions_root() {
+        use crate::crypto::{EthCrypto, SmCrypto};
+        use crate::proto::blockchain::{BlockHeader, CompactBlockBody};
+
+        // Empty block.
+        assert_eq!(
+            hex(&transactions_root::<SmCrypto>(&[])),
+            "0x1ab21d8355cfa17f8e61194831e81a8f22bec8c728fefb747ed035eb5082aa2b"
+        );
+        assert_eq!(
+            hex(&transactions_root::<EthCrypto>(&[])),
+            "0xc5d2460186f7233c927e7db2dcc703c0e500b653ca82273b7bfad8045d85a470"
+        );
+
+        let tx_hashes = vec![vec![1; 32], vec![2; 32]];
+        let root = transactions_root::<SmCrypto>(&tx_hashes);
+        assert_eq!(root, SmCrypto::hash(&[[1; 32], [2; 32]].concat()));
+
+        let mut block = CompactBlock {
+            version: 0,
+            header: Some(BlockHeader {
+                height: 10,
+                transactions_root: root.to_vec(),
+                ..Default::default()
+            }),
+            body: Some(CompactBlockBody { tx_hashes }),
+        };
+        let verification = verify_block::<SmCrypto>(&block).unwrap();
+        assert_eq!(verification.tx_count, 2);
+        assert!(verification.is_valid());
+        assert!(!verify_block::<EthCrypto>(&block).unwrap().is_valid());
+
+        block.body.as_mut().unwrap().tx_hashes.swap(0, 1);
+        assert!(!verify_block::<SmCrypto>(&block).unwrap().is_valid());
+    }
+
+    #[test]
+    fn test_chain_uses_crypto() {
+        use crate::crypto::{EthCrypto, SmCrypto};
+        use crate::proto::blockchain::BlockHeader;
+
+        let header = BlockHeader {
+            height: 10,
+            ..Default::default()
+        };
+        let block_hash = EthCrypto::hash(&header.encode_to_vec());
+
+        let mut controller = MockControllerClient::default();
+        controller
+            .expect_get_block_number()
+            .returning(|_for_pending| Ok(10));
+        controller.expect_get_block_by_number().returning(move |_| {
+            Ok(CompactBlock {
+                header: Some(header.clone()),
+                ..Default::default()
+            })
+        });
+        controller
+            .expect_get_block_hash()
+            .returning(move |_| Ok(block_hash));
+
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        assert!(rt
+            .block_on(chain_uses_crypto::<EthCrypto, _>(&controller))
+            .unwrap());
+        assert!(!rt
+            .block_on(chain_uses_crypto::<SmCrypto, _>(&controller))
+            .unwrap());
+    }
+
+    #[test]
+    fn test_system_config_accessors() {
+        use crate::display::Display;
+
+        let config = SystemConfig {
+            version: 1,
+            chain_id: vec![1; 32],
+            admin: vec![2; 20],
+            block_interval: 3,
+            ..Default::default()
+        };
+        assert_eq!(
+            config.chain_id_hash().unwrap(),
+            Hash::try_from_slice(&[1; 32]).unwrap()
+        );
+        assert_eq!(config.block_interval_duration(), Duration::from_secs(3));
+        assert_eq!(config.version_string(), "v1");
+        assert!(SystemConfig::default().chain_id_hash().is_err());
+
+        let text = config.display(DisplayOptions::default());
+        assert!(text.contains("Version:                  v1\n"));
+        assert!(text.contains("Block interval:           3s\n"));
+        assert!(text.contains("Validators:               none\n"));
+        let json = config.to_json(DisplayOptions::default());
+        for field in SYSTEM_CONFIG_FIELDS {
+            assert!(json.get(field).is_some(), "missing `{field}`");
+        }
+    }
+
+    #[test]
+    fn test_diff_system_config() {
+        let old = SystemConfig {
+            block_interval: 3,
+            validators: vec![vec![1], vec![2]],
+            ..Default::default()
+        };
+        assert!(diff_system_config(&old, &old, DisplayOptions::default()).is_empty());
+
+        let new = SystemConfig {
+            block_interval: 6,
+            validators: vec![vec![2], vec![3]],
+            emergency_brake: true,
+            ..old.clone()
+        };
+        assert_eq!(
+            diff_system_config(&old, &new, DisplayOptions::default()),
+            [
+                SystemConfigChange::Field {
+                    name: "block_interval",
+                    old: "3".into(),
+                    new: "6".into(),
+                },
+                SystemConfigChange::Field {
+                    name: "emergency_brake",
+                    old: "false".into(),
+                    new: "true".into(),
+                },
+                SystemConfigChange::ValidatorRemoved(vec![1]),
+                SystemConfigChange::ValidatorAdded(vec![3]),
+            ]
+        );
+
+        let reordered = SystemConfig {
+            validators: vec![vec![2], vec![1]],
+            ..old.clone()
+        };
+        assert_eq!(
+            diff_system_config(&old, &reordered, DisplayOptions::default()),
+            [SystemConfigChange::ValidatorsReordered]
+        );
+    }
+
+    /// Sign a fixed tx with a fixed key through a recording controller,
+    /// returns the tx sent and the signer's public key.
+    fn sign_fixed_tx<C: Crypto>() -> (UnverifiedTransaction, Vec<u8>) {
+        use crate::core::{mock::recording_controller, wallet::Account};
+        use crate::utils::parse_sk;
+
+        let sk = parse_sk::<C>(&format!("0x{}", "11".repeat(32))).unwrap();
+        let signer = Account::<C>::from_secret_key(sk);
+        let (controller, sent) = recording_controller(SystemConfig {
+            version: 0,
+            chain_id: vec![0x22; 32],
+            ..Default::default()
+        });
+
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        let tx_hash = rt
+            .block_on(controller.send_tx_with_nonce(
+                &signer,
+                vec![0x33; 20],
+                vec![0x44; 4],
+                vec![0; 32],
+                200_000,
+                100,
+                Some("1".into()),
+            ))
+            .unwrap();
+
+        let sent = sent.lock().unwrap();
+        assert_eq!(sent.len(), 1);
+        assert!(verify_raw_tx::<C>(&sent[0]).unwrap().is_valid());
+        let Some(Tx::NormalTx(unverified)) = sent[0].tx.clone() else {
+            panic!("expected a normal tx");
+        };
+        assert_eq!(unverified.transaction_hash, tx_hash);
+        (unverified, signer.public_key().as_slice().to_vec())
+    }
+
+    #[test]
+    fn test_sign_raw_tx_vectors() {
+        use crate::crypto::{EthCrypto, SmCrypto};
+
+        let (tx, pk) = sign_fixed_tx::<SmCrypto>();
+        let witness = tx.witness.unwrap();
+        assert_eq!(
+            hex(&tx.transaction_hash),
+            "0xe2737bd596a1223c608a122ead55372ef9ca303e849e6cbdda14fe9377fec038"
+        );
+        assert_eq!(
+            hex(&witness.sender),
+            "0x7dd1e0c8602757f3d100c0244ad39e12fe8d675d"
+        );
+        // SM2 signatures are randomized, only the public key part is fixed.
+        assert_eq!(witness.signature.len(), 128);
+        assert_eq!(witness.signature[64..], pk);
+        assert_eq!(
+            hex(&pk),
+            "0x852611f744af045689dcfbf4c0437730d2d2de332ab7f0fc02769c5fab8a8943\
+               7d9384f19ab882ed668a28936db92475aa79aef8690ee36f6fb77c69b9b571f8"
+        );
+
+        // secp256k1 signatures are deterministic(RFC 6979).
+        let (tx, _pk) = sign_fixed_tx::<EthCrypto>();
+        let witness = tx.witness.unwrap();
+        assert_eq!(
+            hex(&tx.transaction_hash),
+            "0x8661d4c623c798c1933ab1c9d7b2936328fc39639a32f88a2e20b9df34abc7e3"
+        );
+        assert_eq!(
+            hex(&witness.sender),
+            "0x19e7e376e7c213b7e7e7e46cc70a5dd086daff2a"
+        );
+        assert_eq!(
+            hex(&witness.signature),
+            "0x1a0dd67170e688d8eb35a6d4def2d984542c5d8c9dcd0371805d4f29c2e040ca\
+               5d7ae063e90f7e794926192ee3f21cef298e0c0698e7e6269832feed1e80613a01"
+        );
     }
 }
@@ -0,0 +1,170 @@
+// Copyright Rivtower Technologies LLC.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::time::Duration;
+
+use tonic::{Code, Status};
+
+use crate::config::CryptoType;
+
+/// Errors of the behaviour traits, so that callers can tell them apart.
+///
+/// Commands use `anyhow`, which this converts into with `?`.
+#[derive(Debug, thiserror::Error)]
+pub enum SdkError {
+    /// The gRPC request failed.
+    #[error("request to {service} failed{}: {:?}: {}", attempts_note(*attempts), status.code(), status.message())]
+    Transport {
+        service: String,
+        attempts: u32,
+        // Boxed since it's large.
+        status: Box<Status>,
+    },
+    #[error("request to {service} timed out after {}s", timeout.as_secs_f64())]
+    Timeout { service: String, timeout: Duration },
+    /// The node returns a hash that isn't 32 bytes.
+    #[error("{what} returned by the node has {len} bytes instead of 32, maybe we are using a wrong crypto type?")]
+    InvalidHashLength { what: &'static str, len: usize },
+    #[error("the chain doesn't seem to use the configured crypto type `{expected}`, {reason}")]
+    CryptoMismatch {
+        expected: CryptoType,
+        reason: String,
+    },
+    /// The node doesn't implement the request, e.g. an older EVM service without `get_storage_at`.
+    #[error("{service} doesn't support this request, maybe the node is an older version?")]
+    Unsupported { service: String },
+    /// The requested item, e.g. a tx or a receipt, isn't there.
+    #[error("{0} not found")]
+    NotFound(String),
+    /// The call or tx reverted. The reason is decoded from `data` if possible, see [`decode_revert_reason`].
+    ///
+    /// [`decode_revert_reason`]: super::executor::decode_revert_reason
+    #[error("reverted: {reason}")]
+    Reverted { reason: String, data: Vec<u8> },
+    /// Dry run is enabled and the request isn't sent, see [`check_dry_run`].
+    ///
+    /// [`check_dry_run`]: super::client::check_dry_run
+    #[error("dry run, the `{service}.{method}` request is not sent")]
+    DryRun {
+        service: String,
+        method: String,
+        request: serde_json::Value,
+    },
+    #[error(transparent)]
+    Other(#[from] anyhow::Error),
+}
+
+pub type SdkResult<T> = std::result::Result<T, SdkError>;
+
+fn attempts_note(attempts: u32) -> String {
+    if attempts > 1 {
+        format!(" after {attempts} attempts")
+    } else {
+        String::new()
+    }
+}
+
+impl SdkError {
+    /// Whether the node rejects the tx since it's already in the pool or on chain.
+    ///
+    /// The controller reports it as `DupTransaction` or `HistoryDupTx` in the status message.
+    pub fn is_dup_tx(&self) -> bool {
+        match self {
+            Self::Transport { status, .. } => {
+                let msg = status.message();
+                msg.contains("DupTransaction") || msg.contains("HistoryDupTx")
+            }
+            _ => false,
+        }
+    }
+
+    /// Whether it's a request stopped by a dry run, which should stop the command instead of
+    /// being warned or counted like other failures.
+    pub fn is_dry_run(&self) -> bool {
+        matches!(self, Self::DryRun { .. })
+    }
+
+    pub fn from_status(service: &str, attempts: u32, status: Status) -> Self {
+        if status.code() == Code::NotFound {
+            let what = match status.message() {
+                "" => format!("the requested item of {service}"),
+                msg => msg.to_string(),
+            };
+            return Self::NotFound(what);
+        }
+        if status.code() == Code::Unimplemented {
+            return Self::Unsupported {
+                service: service.into(),
+            };
+        }
+        Self::Transport {
+            service: service.into(),
+            attempts,
+            status: Box::new(status),
+        }
+    }
+}
+
+/// Same as [`SdkError::is_dry_run`] for the errors with context.
+pub fn is_dry_run(e: &anyhow::Error) -> bool {
+    e.downcast_ref::<SdkError>()
+        .is_some_and(SdkError::is_dry_run)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sdk_error() {
+        let e = SdkError::from_status("controller", 1, Status::unavailable("down"));
+        assert!(matches!(e, SdkError::Transport { .. }));
+        assert_eq!(
+            e.to_string(),
+            "request to controller failed: Unavailable: down"
+        );
+        let e = SdkError::from_status("controller", 3, Status::unavailable("down"));
+        assert_eq!(
+            e.to_string(),
+            "request to controller failed after 3 attempts: Unavailable: down"
+        );
+        assert!(!e.is_dup_tx());
+        let e = SdkError::from_status("controller", 1, Status::invalid_argument("HistoryDupTx"));
+        assert!(e.is_dup_tx());
+        let e = SdkError::from_status("evm", 1, Status::not_found("receipt"));
+        assert_eq!(e.to_string(), "receipt not found");
+        let e = SdkError::from_status("evm", 1, Status::unimplemented(""));
+        assert_eq!(
+            e.to_string(),
+            "evm doesn't support this request, maybe the node is an older version?"
+        );
+
+        // Converts into anyhow and back.
+        let e: anyhow::Error = SdkError::NotFound("tx".into()).into();
+        assert!(matches!(
+            e.downcast_ref::<SdkError>(),
+            Some(SdkError::NotFound(_))
+        ));
+        let e = SdkError::DryRun {
+            service: "controller".into(),
+            method: "get_block_hash".into(),
+            request: serde_json::json!({}),
+        };
+        assert!(e.is_dry_run());
+        assert!(is_dry_run(
+            &anyhow::Error::from(e).context("failed to get block hash")
+        ));
+        assert!(!is_dry_run(&anyhow::anyhow!("down")));
+    }
+}
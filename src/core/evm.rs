@@ -12,18 +12,29 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use anyhow::Context as _;
-use anyhow::Result;
+use anyhow::{Context as _, Result};
+use serde_json::json;
+use std::time::Duration;
+use tokio::time::Instant;
 use tonic::transport::Channel;
 
-use super::controller::{SignerBehaviour, TransactionSenderBehaviour};
+use super::client::{call_with_retry, check_dry_run, GrpcClient};
+use super::controller::TransactionSenderBehaviour;
+use super::error::{SdkError, SdkResult};
+use super::executor::check_revert_error;
+use super::signer::Signer;
 use crate::{
     crypto::{Address, ArrayLike, Hash},
     proto::{
+        blockchain::{raw_transaction::Tx, RawTransaction},
         common::{Address as CloudAddress, Hash as CloudHash},
-        evm::{Balance, ByteAbi, ByteCode, Nonce, Receipt},
+        evm::{
+            rpc_service_client::RpcServiceClient, Balance, ByteAbi, ByteCode, ByteQuota,
+            GetStorageAtRequest, Log, Nonce, Receipt,
+        },
+        executor::CallRequest,
     },
-    utils::parse_addr,
+    utils::{be_bytes_to_u64, display_addr, hex, parse_addr},
 };
 
 // TODO: use constant array for these constant to avoid runtime parsing.
@@ -47,71 +58,264 @@ mod constant {
     pub const AMEND_BALANCE: &str = "0x05";
 }
 
-pub type EvmClient = crate::proto::evm::rpc_service_client::RpcServiceClient<Channel>;
+pub type EvmClient = GrpcClient<RpcServiceClient<Channel>>;
 
 #[tonic::async_trait]
 pub trait EvmBehaviour {
     // TODO: better address name
 
-    async fn get_receipt(&self, hash: Hash) -> Result<Receipt>;
-    async fn get_code(&self, addr: Address) -> Result<ByteCode>;
-    async fn get_balance(&self, addr: Address) -> Result<Balance>;
-    async fn get_tx_count(&self, addr: Address) -> Result<Nonce>;
-    async fn get_abi(&self, addr: Address) -> Result<ByteAbi>;
+    async fn get_receipt(&self, hash: Hash) -> SdkResult<Receipt>;
+    async fn get_code(&self, addr: Address) -> SdkResult<ByteCode>;
+    async fn get_balance(&self, addr: Address) -> SdkResult<Balance>;
+    async fn get_tx_count(&self, addr: Address) -> SdkResult<Nonce>;
+    async fn get_abi(&self, addr: Address) -> SdkResult<ByteAbi>;
+    /// The raw 32-byte value at the storage `position` of a contract.
+    /// Older EVM services don't have it and return [`SdkError::Unsupported`].
+    ///
+    /// [`SdkError::Unsupported`]: super::error::SdkError::Unsupported
+    async fn get_storage_at(&self, addr: Address, position: Hash) -> SdkResult<CloudHash>;
+    /// The quota used by executing the call without committing it. A revert is returned as
+    /// [`SdkError::Reverted`]. Older EVM services don't have it and return [`SdkError::Unsupported`].
+    async fn estimate_quota(&self, call: CallRequest) -> SdkResult<ByteQuota>;
 }
 
 #[tonic::async_trait]
 impl EvmBehaviour for EvmClient {
-    async fn get_receipt(&self, hash: Hash) -> Result<Receipt> {
+    async fn get_receipt(&self, hash: Hash) -> SdkResult<Receipt> {
         let hash = CloudHash {
             hash: hash.to_vec(),
         };
-        self.clone()
-            .get_transaction_receipt(hash)
-            .await
-            .map(|resp| resp.into_inner())
-            .context("failed to get receipt")
+        check_dry_run(
+            &self.policy,
+            "evm",
+            "get_transaction_receipt",
+            || json!({ "hash": hex(&hash.hash) }),
+        )?;
+        call_with_retry(&self.policy, "evm", &self.inner, hash, |mut c, req| async move {
+            RpcServiceClient::get_transaction_receipt(&mut c, req).await
+        })
+        .await
     }
 
-    async fn get_code(&self, addr: Address) -> Result<ByteCode> {
+    async fn get_code(&self, addr: Address) -> SdkResult<ByteCode> {
         let addr = CloudAddress {
             address: addr.to_vec(),
         };
-        EvmClient::get_code(&mut self.clone(), addr)
-            .await
-            .map(|resp| resp.into_inner())
-            .context("failed to get code")
+        check_dry_run(
+            &self.policy,
+            "evm",
+            "get_code",
+            || json!({ "address": display_addr(&addr.address) }),
+        )?;
+        call_with_retry(
+            &self.policy,
+            "evm",
+            &self.inner,
+            addr,
+            |mut c, req| async move { RpcServiceClient::get_code(&mut c, req).await },
+        )
+        .await
     }
 
-    async fn get_balance(&self, addr: Address) -> Result<Balance> {
+    async fn get_balance(&self, addr: Address) -> SdkResult<Balance> {
         let addr = CloudAddress {
             address: addr.to_vec(),
         };
-        EvmClient::get_balance(&mut self.clone(), addr)
-            .await
-            .map(|resp| resp.into_inner())
-            .context("failed to get balance")
+        check_dry_run(
+            &self.policy,
+            "evm",
+            "get_balance",
+            || json!({ "address": display_addr(&addr.address) }),
+        )?;
+        call_with_retry(
+            &self.policy,
+            "evm",
+            &self.inner,
+            addr,
+            |mut c, req| async move { RpcServiceClient::get_balance(&mut c, req).await },
+        )
+        .await
     }
 
-    async fn get_tx_count(&self, addr: Address) -> Result<Nonce> {
+    async fn get_tx_count(&self, addr: Address) -> SdkResult<Nonce> {
         let addr = CloudAddress {
             address: addr.to_vec(),
         };
-        self.clone()
-            .get_transaction_count(addr)
-            .await
-            .map(|resp| resp.into_inner())
-            .context("failed to get tx count")
+        call_with_retry(
+            &self.policy,
+            "evm",
+            &self.inner,
+            addr,
+            |mut c, req| async move { RpcServiceClient::get_transaction_count(&mut c, req).await },
+        )
+        .await
     }
 
-    async fn get_abi(&self, addr: Address) -> Result<ByteAbi> {
+    async fn get_abi(&self, addr: Address) -> SdkResult<ByteAbi> {
         let addr = CloudAddress {
             address: addr.to_vec(),
         };
-        EvmClient::get_abi(&mut self.clone(), addr)
-            .await
-            .map(|resp| resp.into_inner())
-            .context("failed to get abi")
+        check_dry_run(
+            &self.policy,
+            "evm",
+            "get_abi",
+            || json!({ "address": display_addr(&addr.address) }),
+        )?;
+        call_with_retry(
+            &self.policy,
+            "evm",
+            &self.inner,
+            addr,
+            |mut c, req| async move { RpcServiceClient::get_abi(&mut c, req).await },
+        )
+        .await
+    }
+
+    async fn get_storage_at(&self, addr: Address, position: Hash) -> SdkResult<CloudHash> {
+        let req = GetStorageAtRequest {
+            address: addr.to_vec(),
+            position: position.to_vec(),
+        };
+        check_dry_run(&self.policy, "evm", "get_storage_at", || {
+            json!({
+                "address": display_addr(&req.address),
+                "position": hex(&req.position),
+            })
+        })?;
+        call_with_retry(
+            &self.policy,
+            "evm",
+            &self.inner,
+            req,
+            |mut c, req| async move { RpcServiceClient::get_storage_at(&mut c, req).await },
+        )
+        .await
+    }
+
+    async fn estimate_quota(&self, call: CallRequest) -> SdkResult<ByteQuota> {
+        check_dry_run(&self.policy, "evm", "estimate_quota", || {
+            json!({
+                "from": display_addr(&call.from),
+                "to": display_addr(&call.to),
+                "method": hex(&call.method),
+            })
+        })?;
+        call_with_retry(
+            &self.policy,
+            "evm",
+            &self.inner,
+            call,
+            |mut c, req| async move { RpcServiceClient::estimate_quota(&mut c, req).await },
+        )
+        .await
+        .map_err(check_revert_error)
+    }
+}
+
+/// The percentage added to the estimated quota by default, see [`estimate_quota`].
+pub const DEFAULT_QUOTA_MARGIN: u64 = 20;
+
+/// Estimate the quota of sending the call as a tx, with `margin` percent added for safety.
+///
+/// A reverted call fails with its reason, since a tx that reverts has no meaningful estimate.
+pub async fn estimate_quota<E: EvmBehaviour>(
+    evm: &E,
+    call: CallRequest,
+    margin: u64,
+) -> Result<u64> {
+    let estimated = match evm.estimate_quota(call).await {
+        Ok(quota) => be_bytes_to_u64(&quota.bytes_quota).context("invalid quota estimate")?,
+        Err(e @ SdkError::Reverted { .. }) => {
+            return Err(e).context("cannot estimate the quota since the tx would revert")
+        }
+        Err(e) => return Err(e.into()),
+    };
+    let with_margin = u128::from(estimated) * u128::from(100 + margin) / 100;
+    Ok(with_margin.try_into().unwrap_or(u64::MAX))
+}
+
+const WAIT_RECEIPT_MIN_BACKOFF: Duration = Duration::from_millis(500);
+const WAIT_RECEIPT_MAX_BACKOFF: Duration = Duration::from_secs(5);
+
+/// Poll the receipt until the tx is executed. Returns `None` if it's not there before timeout.
+///
+/// Only [`SdkError::NotFound`] is retried, since that's how it's reported before the tx is executed.
+pub async fn wait_receipt<E: EvmBehaviour>(
+    evm: &E,
+    tx_hash: Hash,
+    timeout: Duration,
+) -> SdkResult<Option<Receipt>> {
+    let deadline = Instant::now() + timeout;
+    let mut backoff = WAIT_RECEIPT_MIN_BACKOFF;
+    loop {
+        match evm.get_receipt(tx_hash).await {
+            Ok(receipt) => return Ok(Some(receipt)),
+            Err(SdkError::NotFound(_)) => (),
+            Err(e) => return Err(e),
+        }
+        let now = Instant::now();
+        if now >= deadline {
+            return Ok(None);
+        }
+        tokio::time::sleep(backoff.min(deadline - now)).await;
+        backoff = (backoff * 2).min(WAIT_RECEIPT_MAX_BACKOFF);
+    }
+}
+
+/// Which logs to get, like the filter of `eth_getLogs`. `None` matches any.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LogFilter {
+    pub address: Option<Address>,
+    /// The topics at each position.
+    pub topics: [Option<Hash>; 4],
+}
+
+impl LogFilter {
+    pub fn matches(&self, log: &Log) -> bool {
+        let address_matched = self
+            .address
+            .is_none_or(|addr| log.address == addr.as_slice());
+        let topics_matched = self.topics.iter().enumerate().all(|(i, topic)| {
+            topic.is_none_or(|topic| log.topics.get(i).is_some_and(|t| t == topic.as_slice()))
+        });
+        address_matched && topics_matched
+    }
+}
+
+/// The quota used by a tx versus the quota limit it's sent with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct QuotaUsage {
+    pub used: u64,
+    pub limit: u64,
+}
+
+impl QuotaUsage {
+    pub fn of(receipt: &Receipt, limit: u64) -> Result<Self> {
+        let used = be_bytes_to_u64(&receipt.quota_used).context("invalid quota used")?;
+        Ok(Self { used, limit })
+    }
+
+    pub fn percentage(&self) -> f64 {
+        if self.limit == 0 {
+            return 0.0;
+        }
+        self.used as f64 * 100.0 / self.limit as f64
+    }
+}
+
+/// Whether the tx failed for running out of quota, judging by the error message of its receipt.
+pub fn is_out_of_quota(receipt: &Receipt) -> bool {
+    let msg = receipt.error_message.to_lowercase();
+    ["out of quota", "out of gas", "not enough base quota"]
+        .iter()
+        .any(|e| msg.contains(e))
+}
+
+/// The quota limit of a normal tx, utxos have none.
+pub fn quota_limit(raw: &RawTransaction) -> Option<u64> {
+    match &raw.tx {
+        Some(Tx::NormalTx(tx)) => tx.transaction.as_ref().map(|tx| tx.quota),
+        _ => None,
     }
 }
 
@@ -126,7 +330,14 @@ pub trait EvmBehaviourExt {
         valid_until_block: u64,
     ) -> Result<Hash>
     where
-        S: SignerBehaviour + Send + Sync;
+        S: Signer + ?Sized;
+}
+
+/// Build the `to` and `data` of the tx that stores the ABI of the contract.
+pub fn store_abi_tx(contract_addr: Address, abi: &[u8]) -> Result<(Vec<u8>, Vec<u8>)> {
+    let abi_addr = parse_addr(constant::ABI_ADDRESS)?;
+    let data = [contract_addr.as_slice(), abi].concat();
+    Ok((abi_addr.to_vec(), data))
 }
 
 #[tonic::async_trait]
@@ -144,19 +355,11 @@ where
         valid_until_block: u64,
     ) -> Result<Hash>
     where
-        S: SignerBehaviour + Send + Sync,
+        S: Signer + ?Sized,
     {
-        let abi_addr = parse_addr(constant::ABI_ADDRESS)?;
-        let data = [contract_addr.as_slice(), abi].concat();
+        let (to, data) = store_abi_tx(contract_addr, abi)?;
         let tx_hash = self
-            .send_tx(
-                signer,
-                abi_addr.to_vec(),
-                data,
-                vec![0; 32],
-                quota,
-                valid_until_block,
-            )
+            .send_tx(signer, to, data, vec![0; 32], quota, valid_until_block)
             .await?;
 
         Ok(tx_hash)
@@ -167,8 +370,98 @@ where
 mod tests {
     use super::constant::*;
     use super::*;
+    use crate::core::{error::SdkError, mock::MockEvmClient};
     use crate::utils::parse_data;
 
+    #[test]
+    fn test_wait_receipt() {
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .enable_time()
+            .start_paused(true)
+            .build()
+            .unwrap();
+
+        let mut evm = MockEvmClient::default();
+        let mut polls = 0;
+        evm.expect_get_receipt().returning(move |tx_hash| {
+            polls += 1;
+            if polls < 3 {
+                return Err(SdkError::NotFound("receipt".into()));
+            }
+            Ok(Receipt {
+                transaction_hash: tx_hash.to_vec(),
+                ..Default::default()
+            })
+        });
+        let receipt = rt
+            .block_on(wait_receipt(&evm, [1; 32], Duration::from_secs(10)))
+            .unwrap()
+            .unwrap();
+        assert_eq!(receipt.transaction_hash, vec![1; 32]);
+
+        let mut evm = MockEvmClient::default();
+        evm.expect_get_receipt()
+            .returning(|_| Err(SdkError::NotFound("receipt".into())));
+        assert!(rt
+            .block_on(wait_receipt(&evm, [1; 32], Duration::from_secs(60)))
+            .unwrap()
+            .is_none());
+
+        // Other errors are returned instead of being retried.
+        let mut evm = MockEvmClient::default();
+        evm.expect_get_receipt().times(1).returning(|_| {
+            Err(SdkError::Unsupported {
+                service: "evm".into(),
+            })
+        });
+        assert!(rt
+            .block_on(wait_receipt(&evm, [1; 32], Duration::from_secs(60)))
+            .is_err());
+    }
+
+    #[test]
+    fn test_estimate_quota() {
+        let rt = tokio::runtime::Runtime::new().unwrap();
+
+        let mut evm = MockEvmClient::default();
+        evm.expect_estimate_quota().returning(|_| {
+            Ok(ByteQuota {
+                bytes_quota: 100_000u64.to_be_bytes().to_vec(),
+            })
+        });
+        let quota = rt
+            .block_on(estimate_quota(&evm, CallRequest::default(), 20))
+            .unwrap();
+        assert_eq!(quota, 120_000);
+        let quota = rt
+            .block_on(estimate_quota(&evm, CallRequest::default(), 0))
+            .unwrap();
+        assert_eq!(quota, 100_000);
+
+        let mut evm = MockEvmClient::default();
+        evm.expect_estimate_quota().returning(|_| {
+            Ok(ByteQuota {
+                bytes_quota: u64::MAX.to_be_bytes().to_vec(),
+            })
+        });
+        let quota = rt
+            .block_on(estimate_quota(&evm, CallRequest::default(), 20))
+            .unwrap();
+        assert_eq!(quota, u64::MAX);
+
+        let mut evm = MockEvmClient::default();
+        evm.expect_estimate_quota().returning(|_| {
+            Err(SdkError::Reverted {
+                reason: "insufficient balance".into(),
+                data: vec![],
+            })
+        });
+        let err = rt
+            .block_on(estimate_quota(&evm, CallRequest::default(), 20))
+            .unwrap_err();
+        assert!(format!("{err:#}").contains("insufficient balance"));
+    }
+
     #[test]
     fn test_constant() -> Result<()> {
         // TODO: add sm crypto test
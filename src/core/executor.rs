@@ -12,25 +12,97 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use anyhow::{Context, Result};
-use tonic::transport::Channel;
+use ethabi::{ParamType, Token};
+use serde_json::json;
+use tonic::{transport::Channel, Status};
 
+use super::{
+    client::{call_with_timeout, check_dry_run, GrpcClient},
+    error::{SdkError, SdkResult},
+};
 use crate::{
     crypto::{Address, ArrayLike},
-    proto::executor::{CallRequest, CallResponse},
+    proto::executor::{executor_service_client::ExecutorServiceClient, CallRequest, CallResponse},
+    utils::{display_addr, hex, parse_data},
 };
 
-pub type ExecutorClient =
-    crate::proto::executor::executor_service_client::ExecutorServiceClient<Channel>;
+// The selectors of solidity's `Error(string)` and `Panic(uint256)`.
+const ERROR_SELECTOR: [u8; 4] = [0x08, 0xc3, 0x79, 0xa0];
+const PANIC_SELECTOR: [u8; 4] = [0x4e, 0x48, 0x7b, 0x71];
+
+/// Decode the revert reason from the data returned by a reverted call,
+/// `None` if it's not an `Error(string)` or `Panic(uint256)`.
+pub fn decode_revert_reason(data: &[u8]) -> Option<String> {
+    if data.len() < 4 {
+        return None;
+    }
+    let (selector, params) = data.split_at(4);
+    if selector == ERROR_SELECTOR {
+        match ethabi::decode(&[ParamType::String], params).ok()?.pop()? {
+            Token::String(reason) => Some(reason),
+            _ => None,
+        }
+    } else if selector == PANIC_SELECTOR {
+        match ethabi::decode(&[ParamType::Uint(256)], params)
+            .ok()?
+            .pop()?
+        {
+            Token::Uint(code) => Some(format!("panic code {code:#x}")),
+            _ => None,
+        }
+    } else {
+        None
+    }
+}
+
+/// The revert data carried by the status of a failed call, if any.
+///
+/// Executors may report a revert as an error with the hex-encoded output as its message.
+fn revert_data_of(status: &Status) -> Option<Vec<u8>> {
+    let msg = status.message().trim();
+    msg.starts_with("0x")
+        .then(|| parse_data(msg).ok())
+        .flatten()
+        .filter(|data| data.len() >= 4)
+}
+
+fn reverted(data: Vec<u8>) -> SdkError {
+    SdkError::Reverted {
+        reason: decode_revert_reason(&data).unwrap_or_else(|| hex(&data)),
+        data,
+    }
+}
+
+/// Turn an error that carries revert data into [`SdkError::Reverted`], others are returned as is.
+pub(crate) fn check_revert_error(e: SdkError) -> SdkError {
+    if let SdkError::Transport { status, .. } = &e {
+        if let Some(data) = revert_data_of(status) {
+            return reverted(data);
+        }
+    }
+    e
+}
+
+/// Turn a reverted call into [`SdkError::Reverted`], whether the revert data is returned
+/// as the output or as the error.
+fn check_call(result: SdkResult<CallResponse>) -> SdkResult<CallResponse> {
+    match result {
+        Ok(resp) if decode_revert_reason(&resp.value).is_some() => Err(reverted(resp.value)),
+        Err(e) => Err(check_revert_error(e)),
+        ok => ok,
+    }
+}
+
+pub type ExecutorClient = GrpcClient<ExecutorServiceClient<Channel>>;
 
 #[tonic::async_trait]
 pub trait ExecutorBehaviour {
-    async fn call(&self, from: Address, to: Address, data: Vec<u8>) -> Result<CallResponse>;
+    async fn call(&self, from: Address, to: Address, data: Vec<u8>) -> SdkResult<CallResponse>;
 }
 
 #[tonic::async_trait]
 impl ExecutorBehaviour for ExecutorClient {
-    async fn call(&self, from: Address, to: Address, data: Vec<u8>) -> Result<CallResponse> {
+    async fn call(&self, from: Address, to: Address, data: Vec<u8>) -> SdkResult<CallResponse> {
         let req = CallRequest {
             from: from.to_vec(),
             to: to.to_vec(),
@@ -40,10 +112,61 @@ impl ExecutorBehaviour for ExecutorClient {
             method: data,
             args: vec![],
         };
+        check_dry_run(&self.policy, "executor", "call", || {
+            json!({
+                "from": display_addr(&req.from),
+                "to": display_addr(&req.to),
+                "method": hex(&req.method),
+            })
+        })?;
+
+        check_call(
+            call_with_timeout(
+                &self.policy,
+                "executor",
+                ExecutorServiceClient::call(&mut self.inner.clone(), req),
+            )
+            .await,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_revert_reason() {
+        let mut data = ERROR_SELECTOR.to_vec();
+        data.extend(ethabi::encode(&[Token::String(
+            "not enough balance".into(),
+        )]));
+        assert_eq!(
+            decode_revert_reason(&data).as_deref(),
+            Some("not enough balance")
+        );
+
+        let mut data = PANIC_SELECTOR.to_vec();
+        data.extend(ethabi::encode(&[Token::Uint(0x11.into())]));
+        assert_eq!(
+            decode_revert_reason(&data).as_deref(),
+            Some("panic code 0x11")
+        );
+
+        assert_eq!(decode_revert_reason(&[]), None);
+        assert_eq!(decode_revert_reason(&ERROR_SELECTOR), None);
+        assert_eq!(decode_revert_reason(&[0; 32]), None);
+
+        let status = Status::unknown(format!("0x{}", hex::encode(&data)));
+        assert_eq!(revert_data_of(&status), Some(data.clone()));
+        assert_eq!(revert_data_of(&Status::unknown("Reverted")), None);
 
-        ExecutorClient::call(&mut self.clone(), req)
-            .await
-            .map(|resp| resp.into_inner())
-            .context("failed to do executor gRPC call")
+        let err = check_call(Err(SdkError::from_status("executor", 1, status))).unwrap_err();
+        assert!(
+            matches!(err, SdkError::Reverted { ref reason, .. } if reason == "panic code 0x11")
+        );
+        let err = check_call(Ok(CallResponse { value: data })).unwrap_err();
+        assert_eq!(err.to_string(), "reverted: panic code 0x11");
+        assert!(check_call(Ok(CallResponse { value: vec![0; 32] })).is_ok());
     }
 }
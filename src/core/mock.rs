@@ -13,60 +13,66 @@
 // limitations under the License.
 
 use super::{
-    client::GrpcClientBehaviour, context::Context, controller::ControllerBehaviour,
-    evm::EvmBehaviour, executor::ExecutorBehaviour,
+    client::{ClientSetting, GrpcClientBehaviour},
+    controller::ControllerBehaviour,
+    error::SdkResult,
+    evm::EvmBehaviour,
+    executor::ExecutorBehaviour,
 };
 use crate::{
-    config::Config,
-    core::wallet::Account,
-    crypto::{Address, Hash, SmCrypto},
+    crypto::{Address, Hash},
     proto::{
-        blockchain::{CompactBlock, RawTransaction},
-        common::TotalNodeInfo,
+        blockchain::{raw_transaction::Tx, CompactBlock, RawTransaction, Transaction},
+        common::{Hash as CloudHash, TotalNodeInfo},
         controller::SystemConfig,
-        evm::{Balance, ByteAbi, ByteCode, Nonce, Receipt},
-        executor::CallResponse,
+        evm::{Balance, ByteAbi, ByteCode, ByteQuota, Nonce, Receipt},
+        executor::{CallRequest, CallResponse},
     },
 };
 use anyhow::Result;
 use mockall::mock;
+use std::sync::{Arc, Mutex};
 use std::time::Duration;
-use tempfile::tempdir;
-use tempfile::TempDir;
 use tonic::transport::Channel;
+#[cfg(feature = "cli")]
+use {
+    super::{context::Context, wallet::Account},
+    crate::{config::Config, crypto::SmCrypto},
+    tempfile::{tempdir, TempDir},
+};
 
 mock! {
     pub ControllerClient {}
 
     #[tonic::async_trait]
     impl ControllerBehaviour for ControllerClient {
-        async fn send_raw(&self, raw: RawTransaction) -> Result<Hash>;
+        async fn send_raw(&self, raw: RawTransaction) -> SdkResult<Hash>;
 
-        async fn get_version(&self) -> Result<String>;
-        async fn get_system_config(&self) -> Result<SystemConfig>;
+        async fn get_version(&self) -> SdkResult<String>;
+        async fn get_system_config(&self) -> SdkResult<SystemConfig>;
 
-        async fn get_block_number(&self, for_pending: bool) -> Result<u64>;
-        async fn get_block_hash(&self, block_number: u64) -> Result<Hash>;
+        async fn get_block_number(&self, for_pending: bool) -> SdkResult<u64>;
+        async fn get_block_hash(&self, block_number: u64) -> SdkResult<Hash>;
 
-        async fn get_block_by_number(&self, block_number: u64) -> Result<CompactBlock>;
-        async fn get_block_by_hash(&self, hash: Hash) -> Result<CompactBlock>;
+        async fn get_block_by_number(&self, block_number: u64) -> SdkResult<CompactBlock>;
+        async fn get_block_by_hash(&self, hash: Hash) -> SdkResult<CompactBlock>;
 
-        async fn get_tx(&self, tx_hash: Hash) -> Result<RawTransaction>;
-        async fn get_tx_index(&self, tx_hash: Hash) -> Result<u64>;
-        async fn get_tx_block_number(&self, tx_hash: Hash) -> Result<u64>;
+        async fn get_tx(&self, tx_hash: Hash) -> SdkResult<RawTransaction>;
+        async fn get_tx_index(&self, tx_hash: Hash) -> SdkResult<u64>;
+        async fn get_tx_block_number(&self, tx_hash: Hash) -> SdkResult<u64>;
 
-        async fn get_peer_count(&self) -> Result<u64>;
-        async fn get_peers_info(&self) -> Result<TotalNodeInfo>;
+        async fn get_peer_count(&self) -> SdkResult<u64>;
+        async fn get_peers_info(&self) -> SdkResult<TotalNodeInfo>;
 
-        async fn add_node(&self, multiaddr: String) -> Result<u32>;
+        async fn add_node(&self, multiaddr: String) -> SdkResult<u32>;
     }
 
     #[tonic::async_trait]
     impl GrpcClientBehaviour for ControllerClient {
         fn from_channel(ch: Channel) -> Self;
-        async fn connect(addr: &str) -> Result<Self>;
-        fn connect_lazy(addr: &str) -> Result<Self>;
-        async fn connect_timeout(addr: &str, dur: Duration) -> Result<Self> ;
+        async fn connect(addr: &str, setting: &ClientSetting) -> Result<Self>;
+        fn connect_lazy(addr: &str, setting: &ClientSetting) -> Result<Self>;
+        async fn connect_timeout(addr: &str, setting: &ClientSetting, dur: Duration) -> Result<Self>;
     }
 
     impl Clone for ControllerClient {
@@ -84,15 +90,15 @@ mock! {
             from: Address,
             to: Address,
             data: Vec<u8>,
-        ) -> Result<CallResponse>;
+        ) -> SdkResult<CallResponse>;
     }
 
     #[tonic::async_trait]
     impl GrpcClientBehaviour for ExecutorClient {
         fn from_channel(ch: Channel) -> Self;
-        async fn connect(addr: &str) -> Result<Self>;
-        fn connect_lazy(addr: &str) -> Result<Self>;
-        async fn connect_timeout(addr: &str, dur: Duration) -> Result<Self> ;
+        async fn connect(addr: &str, setting: &ClientSetting) -> Result<Self>;
+        fn connect_lazy(addr: &str, setting: &ClientSetting) -> Result<Self>;
+        async fn connect_timeout(addr: &str, setting: &ClientSetting, dur: Duration) -> Result<Self>;
     }
 
     impl Clone for ExecutorClient {
@@ -105,19 +111,21 @@ mock! {
 
     #[tonic::async_trait]
     impl EvmBehaviour for EvmClient {
-        async fn get_receipt(&self, hash: Hash) -> Result<Receipt>;
-        async fn get_code(&self, addr: Address) -> Result<ByteCode>;
-        async fn get_balance(&self, addr: Address) -> Result<Balance>;
-        async fn get_tx_count(&self, addr: Address) -> Result<Nonce>;
-        async fn get_abi(&self, addr: Address) -> Result<ByteAbi>;
+        async fn get_receipt(&self, hash: Hash) -> SdkResult<Receipt>;
+        async fn get_code(&self, addr: Address) -> SdkResult<ByteCode>;
+        async fn get_balance(&self, addr: Address) -> SdkResult<Balance>;
+        async fn get_tx_count(&self, addr: Address) -> SdkResult<Nonce>;
+        async fn get_abi(&self, addr: Address) -> SdkResult<ByteAbi>;
+        async fn get_storage_at(&self, addr: Address, position: Hash) -> SdkResult<CloudHash>;
+        async fn estimate_quota(&self, call: CallRequest) -> SdkResult<ByteQuota>;
     }
 
     #[tonic::async_trait]
     impl GrpcClientBehaviour for EvmClient {
         fn from_channel(ch: Channel) -> Self;
-        async fn connect(addr: &str) -> Result<Self>;
-        fn connect_lazy(addr: &str) -> Result<Self>;
-        async fn connect_timeout(addr: &str, dur: Duration) -> Result<Self> ;
+        async fn connect(addr: &str, setting: &ClientSetting) -> Result<Self>;
+        fn connect_lazy(addr: &str, setting: &ClientSetting) -> Result<Self>;
+        async fn connect_timeout(addr: &str, setting: &ClientSetting, dur: Duration) -> Result<Self>;
     }
 
     impl Clone for EvmClient {
@@ -125,8 +133,44 @@ mock! {
     }
 }
 
+/// Returns a mock controller that records the raw txs sent to it, and answers with their hashes.
+///
+/// `get_system_config` returns `system_config`, other methods need to be set up by the caller.
+pub fn recording_controller(
+    system_config: SystemConfig,
+) -> (MockControllerClient, Arc<Mutex<Vec<RawTransaction>>>) {
+    let sent = Arc::new(Mutex::new(vec![]));
+    let mut controller = MockControllerClient::default();
+    controller
+        .expect_get_system_config()
+        .returning(move || Ok(system_config.clone()));
+    let recorder = sent.clone();
+    controller.expect_send_raw().returning(move |raw| {
+        let tx_hash = match &raw.tx {
+            Some(Tx::NormalTx(tx)) => tx.transaction_hash.clone(),
+            Some(Tx::UtxoTx(utxo)) => utxo.transaction_hash.clone(),
+            None => vec![],
+        };
+        recorder.lock().unwrap().push(raw);
+        Ok(tx_hash.try_into().unwrap_or_default())
+    });
+    (controller, sent)
+}
+
+/// A normal tx for the tests that sign one.
+pub fn tx() -> Transaction {
+    Transaction {
+        to: vec![1; 20],
+        nonce: "1".into(),
+        quota: 200_000,
+        valid_until_block: 100,
+        ..Default::default()
+    }
+}
+
 /// Returns mock context and temp dir guard.
 /// The temp dir guard must be holded to use the mock context.
+#[cfg(feature = "cli")]
 pub fn context() -> (
     Context<MockControllerClient, MockExecutorClient, MockEvmClient>,
     TempDir,
@@ -136,29 +180,35 @@ pub fn context() -> (
     let mock_ctx = MockControllerClient::from_channel_context();
     mock_ctx.expect().returning(|_| Default::default());
     let mock_ctx = MockControllerClient::connect_context();
-    mock_ctx.expect().returning(|_| Ok(Default::default()));
+    mock_ctx.expect().returning(|_, _| Ok(Default::default()));
     let mock_ctx = MockControllerClient::connect_lazy_context();
-    mock_ctx.expect().returning(|_| Ok(Default::default()));
-    let mock_ctx = MockControllerClient::connect_timeout_context();
     mock_ctx.expect().returning(|_, _| Ok(Default::default()));
+    let mock_ctx = MockControllerClient::connect_timeout_context();
+    mock_ctx
+        .expect()
+        .returning(|_, _, _| Ok(Default::default()));
 
     let mock_ctx = MockExecutorClient::from_channel_context();
     mock_ctx.expect().returning(|_| Default::default());
     let mock_ctx = MockExecutorClient::connect_context();
-    mock_ctx.expect().returning(|_| Ok(Default::default()));
+    mock_ctx.expect().returning(|_, _| Ok(Default::default()));
     let mock_ctx = MockExecutorClient::connect_lazy_context();
-    mock_ctx.expect().returning(|_| Ok(Default::default()));
-    let mock_ctx = MockExecutorClient::connect_timeout_context();
     mock_ctx.expect().returning(|_, _| Ok(Default::default()));
+    let mock_ctx = MockExecutorClient::connect_timeout_context();
+    mock_ctx
+        .expect()
+        .returning(|_, _, _| Ok(Default::default()));
 
     let mock_ctx = MockEvmClient::from_channel_context();
     mock_ctx.expect().returning(|_| Default::default());
     let mock_ctx = MockEvmClient::connect_context();
-    mock_ctx.expect().returning(|_| Ok(Default::default()));
+    mock_ctx.expect().returning(|_, _| Ok(Default::default()));
     let mock_ctx = MockEvmClient::connect_lazy_context();
-    mock_ctx.expect().returning(|_| Ok(Default::default()));
-    let mock_ctx = MockEvmClient::connect_timeout_context();
     mock_ctx.expect().returning(|_, _| Ok(Default::default()));
+    let mock_ctx = MockEvmClient::connect_timeout_context();
+    mock_ctx
+        .expect()
+        .returning(|_, _, _| Ok(Default::default()));
 
     let test_dir = tempdir().expect("cannot get temp dir");
     let config = Config {
@@ -167,6 +217,8 @@ pub fn context() -> (
     };
 
     let mut ctx = Context::from_config(config).expect("fail to create test context");
+    // Tests that need it should reset this and set up the expectations.
+    ctx.crypto_checked.set(true);
 
     let default_account = Account::<SmCrypto>::generate();
     ctx.wallet
@@ -0,0 +1,246 @@
+// Copyright Rivtower Technologies LLC.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Signing behind a trait that doesn't need the secret key in this process,
+//! so that a hardware wallet or a remote KMS can sign txs.
+
+use anyhow::{anyhow, ensure, Context as _, Result};
+use prost::Message;
+
+use super::controller::SignerBehaviour;
+use crate::config::CryptoType;
+use crate::crypto::{recover_signer, Address, Crypto, EthCrypto, SmCrypto};
+use crate::proto::blockchain::{
+    raw_transaction::Tx, RawTransaction, Transaction as CloudNormalTransaction,
+    UnverifiedTransaction, UnverifiedUtxoTransaction, UtxoTransaction as CloudUtxoTransaction,
+    Witness,
+};
+use crate::utils::{display_addr, hex, parse_data};
+
+/// What the tx senders need to sign txs, see [`TransactionSenderBehaviour`].
+///
+/// In-process accounts get it from [`SignerBehaviour`].
+///
+/// [`TransactionSenderBehaviour`]: super::controller::TransactionSenderBehaviour
+#[tonic::async_trait]
+pub trait Signer: Send + Sync {
+    fn address(&self) -> &[u8];
+    /// The hash function of the chain's crypto, the tx hash is what's signed.
+    fn hash(&self, msg: &[u8]) -> Vec<u8>;
+    async fn sign(&self, hash: &[u8]) -> Result<Vec<u8>>;
+
+    async fn sign_raw_tx(&self, tx: CloudNormalTransaction) -> Result<RawTransaction> {
+        let tx_hash = self.hash(&tx.encode_to_vec());
+        let signature = self.sign(&tx_hash).await?;
+        Ok(build_raw_tx(
+            tx,
+            tx_hash,
+            self.address().to_vec(),
+            signature,
+        ))
+    }
+
+    async fn sign_raw_utxo(&self, utxo: CloudUtxoTransaction) -> Result<RawTransaction> {
+        let utxo_hash = self.hash(&utxo.encode_to_vec());
+        let signature = self.sign(&utxo_hash).await?;
+        Ok(build_raw_utxo(
+            utxo,
+            utxo_hash,
+            self.address().to_vec(),
+            signature,
+        ))
+    }
+}
+
+#[tonic::async_trait]
+impl<T> Signer for T
+where
+    T: SignerBehaviour + Send + Sync,
+{
+    fn address(&self) -> &[u8] {
+        SignerBehaviour::address(self)
+    }
+
+    fn hash(&self, msg: &[u8]) -> Vec<u8> {
+        SignerBehaviour::hash(self, msg)
+    }
+
+    async fn sign(&self, hash: &[u8]) -> Result<Vec<u8>> {
+        Ok(SignerBehaviour::sign(self, hash))
+    }
+}
+
+pub fn build_raw_tx(
+    tx: CloudNormalTransaction,
+    tx_hash: Vec<u8>,
+    sender: Vec<u8>,
+    signature: Vec<u8>,
+) -> RawTransaction {
+    let witness = Witness { sender, signature };
+    let unverified_tx = UnverifiedTransaction {
+        transaction: Some(tx),
+        transaction_hash: tx_hash,
+        witness: Some(witness),
+    };
+    RawTransaction {
+        tx: Some(Tx::NormalTx(unverified_tx)),
+    }
+}
+
+pub fn build_raw_utxo(
+    utxo: CloudUtxoTransaction,
+    utxo_hash: Vec<u8>,
+    sender: Vec<u8>,
+    signature: Vec<u8>,
+) -> RawTransaction {
+    let witness = Witness { sender, signature };
+    let unverified_utxo = UnverifiedUtxoTransaction {
+        transaction: Some(utxo),
+        transaction_hash: utxo_hash,
+        witnesses: vec![witness],
+    };
+    RawTransaction {
+        tx: Some(Tx::UtxoTx(unverified_utxo)),
+    }
+}
+
+/// Signs by running an external command, e.g. the client of a hardware wallet or a KMS.
+///
+/// The command is given the hex encoded hash as its last arg,
+/// and it should print the hex encoded signature to stdout.
+#[derive(Debug, Clone)]
+pub struct ExternalSigner {
+    program: String,
+    args: Vec<String>,
+    address: Address,
+    crypto_type: CryptoType,
+}
+
+impl ExternalSigner {
+    pub fn new(
+        program: impl Into<String>,
+        args: Vec<String>,
+        address: Address,
+        crypto_type: CryptoType,
+    ) -> Self {
+        Self {
+            program: program.into(),
+            args,
+            address,
+            crypto_type,
+        }
+    }
+}
+
+#[tonic::async_trait]
+impl Signer for ExternalSigner {
+    fn address(&self) -> &[u8] {
+        self.address.as_slice()
+    }
+
+    fn hash(&self, msg: &[u8]) -> Vec<u8> {
+        match self.crypto_type {
+            CryptoType::Sm => SmCrypto::hash(msg).to_vec(),
+            CryptoType::Eth => EthCrypto::hash(msg).to_vec(),
+        }
+    }
+
+    async fn sign(&self, hash: &[u8]) -> Result<Vec<u8>> {
+        let output = tokio::process::Command::new(&self.program)
+            .args(&self.args)
+            .arg(hex(hash))
+            .output()
+            .await
+            .with_context(|| format!("cannot run external signer `{}`", self.program))?;
+        ensure!(
+            output.status.success(),
+            "external signer `{}` failed with {}: {}",
+            self.program,
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+
+        let stdout = String::from_utf8(output.stdout)
+            .map_err(|_| anyhow!("external signer `{}` printed non-utf8", self.program))?;
+        let signature = parse_data(stdout.trim()).with_context(|| {
+            format!(
+                "external signer `{}` printed an invalid signature",
+                self.program
+            )
+        })?;
+        // Catch a misconfigured signer before the node rejects the tx.
+        let signer = match self.crypto_type {
+            CryptoType::Sm => recover_signer::<SmCrypto>(hash, &signature),
+            CryptoType::Eth => recover_signer::<EthCrypto>(hash, &signature),
+        }
+        .with_context(|| format!("external signer `{}` printed a bad signature", self.program))?;
+        ensure!(
+            signer == self.address,
+            "external signer `{}` signed with `{}` instead of `{}`",
+            self.program,
+            display_addr(&signer),
+            display_addr(&self.address)
+        );
+        Ok(signature)
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use crate::core::{mock::tx, wallet::Account};
+
+    #[test]
+    fn test_external_signer() {
+        let account = Account::<EthCrypto>::generate();
+        let tx = tx();
+        let expected = SignerBehaviour::sign_raw_tx(&account, tx.clone());
+        let Some(Tx::NormalTx(unverified)) = &expected.tx else {
+            panic!("expected a normal tx");
+        };
+        let signature = hex(&unverified.witness.as_ref().unwrap().signature);
+
+        // The hash to sign is passed as `$0` and ignored, ETH signatures are deterministic.
+        let external = |address: Address| {
+            ExternalSigner::new(
+                "sh",
+                vec!["-c".into(), format!("echo {signature}")],
+                address,
+                CryptoType::Eth,
+            )
+        };
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        let raw = rt
+            .block_on(Signer::sign_raw_tx(&account, tx.clone()))
+            .unwrap();
+        assert_eq!(raw, expected);
+        let signer: Box<dyn Signer> = Box::new(external(*account.address()));
+        let raw = rt.block_on(signer.sign_raw_tx(tx.clone())).unwrap();
+        assert_eq!(raw, expected);
+
+        let err = rt
+            .block_on(Signer::sign_raw_tx(&external([0; 20]), tx.clone()))
+            .unwrap_err();
+        assert!(err.to_string().contains("instead of"));
+
+        let failing = ExternalSigner::new(
+            "sh",
+            vec!["-c".into(), "echo locked >&2; exit 1".into()],
+            *account.address(),
+            CryptoType::Eth,
+        );
+        let err = rt.block_on(failing.sign(&[0; 32])).unwrap_err();
+        assert!(err.to_string().ends_with("locked"));
+    }
+}
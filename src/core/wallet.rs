@@ -13,18 +13,20 @@
 // limitations under the License.
 
 use anyhow::{anyhow, bail, ensure, Context, Result};
+use rand::Rng;
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use std::{
     collections::BTreeMap,
-    fs,
+    fmt, fs,
     path::{Path, PathBuf},
 };
 
 use crate::{
     config::CryptoType,
     core::controller::SignerBehaviour,
-    crypto::{Address, ArrayLike, Crypto, EthCrypto, SmCrypto},
-    utils::{hex, parse_addr, parse_data, parse_pk, parse_sk, safe_save},
+    crypto::{Address, ArrayLike, Crypto, DerivationPath, EthCrypto, SmCrypto},
+    log::warning,
+    utils::{parse_addr, parse_data, parse_pk, parse_sk, safe_save},
 };
 
 pub struct Account<C: Crypto> {
@@ -56,6 +58,19 @@ impl<C: Crypto> Account<C> {
         }
     }
 
+    /// Derive account from mnemonic, `C::DEFAULT_DERIVATION_PATH` is used if path is not given.
+    pub fn from_mnemonic(
+        mnemonic: &bip39::Mnemonic,
+        path: Option<&DerivationPath>,
+    ) -> Result<Self> {
+        let seed = mnemonic.to_seed("");
+        let sk = match path {
+            Some(path) => C::derive_secret_key(&seed, path)?,
+            None => C::derive_secret_key(&seed, &C::DEFAULT_DERIVATION_PATH.parse()?)?,
+        };
+        Ok(Self::from_secret_key(sk))
+    }
+
     pub fn address(&self) -> &Address {
         &self.address
     }
@@ -75,11 +90,16 @@ impl<C: Crypto> Account<C> {
     }
 
     pub fn lock(self, pw: &[u8]) -> LockedAccount<C> {
-        let encrypted_sk = C::encrypt(self.secret_key.as_slice(), pw);
+        let kdf = ScryptParams::generate();
+        let key = kdf
+            .derive_key(pw)
+            .expect("the generated scrypt params must be valid");
+        let encrypted_sk = C::encrypt(self.secret_key.as_slice(), &key);
         LockedAccount {
             address: self.address,
             public_key: self.public_key,
             encrypted_sk,
+            kdf: Some(kdf),
         }
     }
 
@@ -89,9 +109,9 @@ impl<C: Crypto> Account<C> {
         serializer: S,
     ) -> Result<S::Ok, S::Error> {
         SerializedAccount {
-            address: hex(self.address.as_slice()),
-            public_key: hex(self.public_key.as_slice()),
-            secret_key: hex(self.secret_key.as_slice()),
+            address: self.address.to_hex(),
+            public_key: self.public_key.to_hex(),
+            secret_key: self.secret_key.to_hex(),
         }
         .serialize(serializer)
     }
@@ -174,12 +194,68 @@ impl<C: Crypto> TryFrom<SerializedAccount> for Account<C> {
     }
 }
 
+/// The scrypt params for deriving the key that encrypts the secret key from the password.
+#[derive(Clone, Serialize, Deserialize)]
+struct ScryptParams {
+    log_n: u8,
+    r: u32,
+    p: u32,
+    salt: String,
+}
+
+impl ScryptParams {
+    // 8 MiB memory, it takes about 50ms in release build.
+    const LOG_N: u8 = 13;
+    const R: u32 = 8;
+    const P: u32 = 1;
+
+    fn generate() -> Self {
+        Self {
+            log_n: Self::LOG_N,
+            r: Self::R,
+            p: Self::P,
+            salt: hex::encode(rand::thread_rng().gen::<[u8; 32]>()),
+        }
+    }
+
+    fn derive_key(&self, pw: &[u8]) -> Result<[u8; 32]> {
+        let params = scrypt::Params::new(self.log_n, self.r, self.p)
+            .map_err(|e| anyhow!("invalid scrypt params: {e}"))?;
+        let salt = parse_data(&self.salt).context("invalid scrypt salt")?;
+        let mut key = [0; 32];
+        scrypt::scrypt(pw, &salt, &params, &mut key).map_err(|e| anyhow!("scrypt failed: {e}"))?;
+        Ok(key)
+    }
+}
+
 #[derive(Deserialize)]
 #[serde(try_from = "SerializedLockedAccount")]
 pub struct LockedAccount<C: Crypto> {
     address: Address,
     public_key: C::PublicKey,
     encrypted_sk: Vec<u8>,
+    // Accounts locked by old versions use the password as the key directly.
+    kdf: Option<ScryptParams>,
+}
+
+// Debug never shows the secret key, so it's safe to log and panic with accounts.
+impl<C: Crypto> fmt::Debug for Account<C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Account")
+            .field("address", &self.address.to_hex())
+            .field("public_key", &self.public_key.to_hex())
+            .field("secret_key", &format_args!("<redacted>"))
+            .finish()
+    }
+}
+
+impl<C: Crypto> fmt::Debug for LockedAccount<C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LockedAccount")
+            .field("address", &self.address.to_hex())
+            .field("public_key", &self.public_key.to_hex())
+            .finish_non_exhaustive()
+    }
 }
 
 impl<C: Crypto> LockedAccount<C> {
@@ -192,8 +268,11 @@ impl<C: Crypto> LockedAccount<C> {
     }
 
     pub fn unlock(&self, pw: &[u8]) -> Result<Account<C>> {
-        let decrypted =
-            C::decrypt(&self.encrypted_sk, pw).ok_or_else(|| anyhow!("invalid password"))?;
+        let decrypted = match &self.kdf {
+            Some(kdf) => C::decrypt(&self.encrypted_sk, &kdf.derive_key(pw)?),
+            None => C::decrypt(&self.encrypted_sk, pw),
+        }
+        .ok_or_else(|| anyhow!("invalid password"))?;
         let secret_key = C::SecretKey::try_from_slice(&decrypted)
             .map_err(|_| anyhow!("the decrypted secret key is invalid"))?;
         let public_key = C::sk2pk(&secret_key);
@@ -218,9 +297,10 @@ impl<C: Crypto> LockedAccount<C> {
     // We don't want to impl Serialize for it directly in case of leaking secret key without noticing.
     fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
         SerializedLockedAccount {
-            address: hex(self.address.as_slice()),
-            public_key: hex(self.public_key.as_slice()),
-            encrypted_sk: hex(self.encrypted_sk.as_slice()),
+            address: self.address.to_hex(),
+            public_key: self.public_key.to_hex(),
+            encrypted_sk: self.encrypted_sk.to_hex(),
+            kdf: self.kdf.clone(),
         }
         .serialize(serializer)
     }
@@ -260,6 +340,7 @@ impl<C: Crypto> LockedAccount<C> {
             address,
             public_key,
             encrypted_sk,
+            kdf: serialized.kdf,
         })
     }
 }
@@ -270,6 +351,9 @@ struct SerializedLockedAccount {
     address: String,
     public_key: String,
     encrypted_sk: String,
+    // Must be the last field since it's a table in toml.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    kdf: Option<ScryptParams>,
 }
 
 impl<C: Crypto> TryFrom<SerializedLockedAccount> for LockedAccount<C> {
@@ -289,11 +373,12 @@ impl<C: Crypto> TryFrom<SerializedLockedAccount> for LockedAccount<C> {
             address,
             public_key,
             encrypted_sk,
+            kdf: serialized.kdf,
         })
     }
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize)]
 #[serde(tag = "crypto_type")]
 #[serde(rename_all = "UPPERCASE")]
 pub enum MultiCryptoAccount {
@@ -355,7 +440,7 @@ impl From<Account<EthCrypto>> for MultiCryptoAccount {
     }
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize)]
 #[serde(tag = "crypto_type")]
 #[serde(rename_all = "UPPERCASE")]
 pub enum LockedMultiCryptoAccount {
@@ -406,7 +491,7 @@ impl From<LockedAccount<EthCrypto>> for LockedMultiCryptoAccount {
     }
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize)]
 #[serde(untagged)]
 pub enum MaybeLocked {
     Unlocked(MultiCryptoAccount),
@@ -584,8 +669,11 @@ impl Wallet {
                     .to_string_lossy();
 
                 if let Err(e) = this.load(&name) {
-                    // TODO: use logger
-                    eprintln!("fail to load account `{}`: {}", name, e);
+                    warning!(
+                        "skipping malformed account file `{}`: {}",
+                        path.display(),
+                        e
+                    );
                 }
             }
         }
@@ -673,6 +761,34 @@ impl Wallet {
         Ok(())
     }
 
+    /// Rename account in both memory and keystore.
+    ///
+    /// The account file is hard linked to the new name before the old one is removed,
+    /// so it never overwrites an existing account and the account won't get lost halfway.
+    pub fn rename(&mut self, old_name: &str, new_name: &str) -> Result<()> {
+        self.get(old_name)?;
+        ensure!(
+            !new_name.is_empty() && !new_name.contains(['/', '\\']),
+            "invalid account name `{new_name}`"
+        );
+        ensure!(
+            !self.accounts.contains_key(new_name),
+            "account `{new_name}` already exists"
+        );
+
+        let accounts_dir = self.wallet_dir.join(Self::ACCOUNTS_DIR);
+        let old_file = accounts_dir.join(format!("{old_name}.toml"));
+        let new_file = accounts_dir.join(format!("{new_name}.toml"));
+        fs::hard_link(&old_file, &new_file)
+            .with_context(|| format!("cannot create account file `{}`", new_file.display()))?;
+        fs::remove_file(&old_file)
+            .with_context(|| format!("cannot remove account file `{}`", old_file.display()))?;
+
+        let maybe_locked = self.accounts.remove(old_name).unwrap();
+        self.accounts.insert(new_name.into(), maybe_locked);
+        Ok(())
+    }
+
     // FIXME: It's not secure, secret key data may be still in the disk.
     fn remove_account_from_keystore(
         wallet_dir: impl AsRef<Path>,
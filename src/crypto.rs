@@ -13,12 +13,17 @@
 // limitations under the License.
 
 mod eth;
+mod hd;
+mod keystore;
 mod sm;
 
+use anyhow::anyhow;
 use anyhow::Context;
 use anyhow::Result;
 
-pub use eth::EthCrypto;
+pub use eth::{checksum_address, EthCrypto};
+pub use hd::DerivationPath;
+pub use keystore::{decrypt_keystore, encrypt_keystore, Kdf};
 pub use sm::SmCrypto;
 
 // I tried this, but it's not easy to constrain the Error type of TryFrom
@@ -35,6 +40,21 @@ pub trait ArrayLike: Clone + PartialEq + Eq + Sized + Send + Sync + 'static {
     fn to_vec(&self) -> Vec<u8> {
         self.as_slice().to_vec()
     }
+
+    /// Parse from hex, the `0x` prefix is optional and letters can be in either case.
+    fn from_hex(s: &str) -> Result<Self> {
+        let s = s
+            .strip_prefix("0x")
+            .or_else(|| s.strip_prefix("0X"))
+            .unwrap_or(s);
+        let bytes = hex::decode(s).context("invalid hex input")?;
+        Self::try_from_slice(&bytes)
+    }
+
+    /// `0x`-prefixed lowercase hex.
+    fn to_hex(&self) -> String {
+        format!("0x{}", hex::encode(self.as_slice()))
+    }
 }
 
 impl<const N: usize> ArrayLike for [u8; N] {
@@ -43,13 +63,9 @@ impl<const N: usize> ArrayLike for [u8; N] {
     }
 
     fn try_from_slice(slice: &[u8]) -> Result<Self> {
-        slice.try_into().with_context(|| {
-            format!(
-                "length mismatched, expected: `{}`, got: `{}`",
-                N,
-                slice.len()
-            )
-        })
+        slice
+            .try_into()
+            .map_err(|_| anyhow!("expected {} bytes, got {}", N, slice.len()))
     }
 }
 
@@ -87,7 +103,13 @@ pub trait Crypto: Send + Sync + 'static {
         (pk, sk)
     }
 
+    /// The default BIP-32 path for deriving account from mnemonic.
+    const DEFAULT_DERIVATION_PATH: &'static str;
+    fn derive_secret_key(seed: &[u8], path: &DerivationPath) -> Result<Self::SecretKey>;
+
     fn sign(msg: &[u8], sk: &Self::SecretKey) -> Self::Signature;
+    /// Returns the signer's pk if the signature is valid.
+    fn recover_signature(msg: &[u8], signature: &Self::Signature) -> Option<Self::PublicKey>;
 
     fn sk2pk(sk: &Self::SecretKey) -> Self::PublicKey;
     fn pk2addr(pk: &Self::PublicKey) -> Address;
@@ -95,3 +117,59 @@ pub trait Crypto: Send + Sync + 'static {
         Self::pk2addr(&Self::sk2pk(sk))
     }
 }
+
+/// Recover the address of the signer who made the `signature` over `msg`.
+///
+/// - ETH: the public key is recovered from the recoverable secp256k1 signature.
+/// - SM: an SM2 signature is `r || s || pk`, there is nothing to recover. Instead, it's verified
+///   against the embedded pk and the address is derived from that pk. So a valid signature proves
+///   that it's made by the owner of the embedded pk.
+pub fn recover_signer<C: Crypto>(msg: &[u8], signature: &[u8]) -> Result<Address> {
+    let signature = C::Signature::try_from_slice(signature).context("invalid signature")?;
+    let pk = C::recover_signature(msg, &signature)
+        .ok_or_else(|| anyhow!("signature verification failed"))?;
+    Ok(C::pk2addr(&pk))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_array_like_hex() {
+        let hash: Hash = [0xab; 32];
+        assert_eq!(hash.to_hex(), format!("0x{}", "ab".repeat(32)));
+        assert_eq!(Hash::from_hex(&hash.to_hex()).unwrap(), hash);
+        assert_eq!(Hash::from_hex(&"AB".repeat(32)).unwrap(), hash);
+        assert_eq!(
+            Hash::from_hex(&format!("0X{}", "aB".repeat(32))).unwrap(),
+            hash
+        );
+
+        let err = Hash::from_hex(&"ab".repeat(31)).unwrap_err();
+        assert_eq!(err.to_string(), "expected 32 bytes, got 31");
+        assert!(Address::from_hex("0xzz").is_err());
+        assert_eq!(Vec::<u8>::from_hex("0x").unwrap(), Vec::<u8>::new());
+    }
+
+    fn test_recover_signer_with<C: Crypto>() {
+        let (pk, sk) = C::generate_keypair();
+        let msg = [1; 32];
+        let sig = C::sign(&msg, &sk);
+        assert_eq!(
+            recover_signer::<C>(&msg, sig.as_slice()).unwrap(),
+            C::pk2addr(&pk)
+        );
+        assert_ne!(
+            recover_signer::<C>(&[2; 32], sig.as_slice()).ok(),
+            Some(C::pk2addr(&pk))
+        );
+        assert!(recover_signer::<C>(&msg, &[0; 3]).is_err());
+    }
+
+    #[test]
+    fn test_recover_signer() {
+        test_recover_signer_with::<EthCrypto>();
+        test_recover_signer_with::<SmCrypto>();
+    }
+}
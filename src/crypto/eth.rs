@@ -16,6 +16,7 @@
 /// This crypto impl must be compatible with `kms_eth` to work with it.
 use tiny_keccak::{Hasher, Keccak};
 
+use secp256k1::ecdsa::{RecoverableSignature, RecoveryId};
 use secp256k1::rand::rngs::OsRng;
 use secp256k1::Message;
 use secp256k1::PublicKey as RawPublicKey;
@@ -24,7 +25,10 @@ use secp256k1::SecretKey as RawSecretKey;
 
 use ctr::cipher::{NewCipher, StreamCipher};
 
-use super::{Address, Crypto, Hash, ADDR_BYTES_LEN, HASH_BYTES_LEN};
+use anyhow::Result;
+
+use super::hd::{derive_secret_key, Curve};
+use super::{Address, Crypto, DerivationPath, Hash, ADDR_BYTES_LEN, HASH_BYTES_LEN};
 
 pub const PUBLIC_KEY_BYTES_LEN: usize = 64;
 pub type PublicKey = [u8; PUBLIC_KEY_BYTES_LEN];
@@ -81,6 +85,21 @@ fn secp256k1_pk2addr(pk: &PublicKey) -> Address {
         .unwrap()
 }
 
+const SECP256K1_CURVE: Curve = Curve {
+    seed_key: b"Bitcoin seed",
+    order: [
+        0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+        0xfe, 0xba, 0xae, 0xdc, 0xe6, 0xaf, 0x48, 0xa0, 0x3b, 0xbf, 0xd2, 0x5e, 0x8c, 0xd0, 0x36,
+        0x41, 0x41,
+    ],
+    compressed_pk: Some(secp256k1_compressed_pk),
+};
+
+fn secp256k1_compressed_pk(sk: &SecretKey) -> [u8; 33] {
+    let raw_sk = RawSecretKey::from_slice(sk).unwrap();
+    RawPublicKey::from_secret_key(&SECP256K1, &raw_sk).serialize()
+}
+
 fn secp256k1_sign(msg: &[u8], sk: &SecretKey) -> Signature {
     let hashed_msg = keccak_hash(msg);
     let raw_sk = RawSecretKey::from_slice(sk).unwrap();
@@ -96,6 +115,37 @@ fn secp256k1_sign(msg: &[u8], sk: &SecretKey) -> Signature {
     output
 }
 
+fn secp256k1_recover(msg: &[u8], signature: &Signature) -> Option<PublicKey> {
+    let hashed_msg = keccak_hash(msg);
+    let recovery_id = RecoveryId::from_i32(signature[SIGNATURE_BYTES_LEN - 1] as i32).ok()?;
+    let sig =
+        RecoverableSignature::from_compact(&signature[..SIGNATURE_BYTES_LEN - 1], recovery_id)
+            .ok()?;
+    let raw_pk = SECP256K1
+        .recover_ecdsa(&Message::from_slice(&hashed_msg).unwrap(), &sig)
+        .ok()?;
+    Some(raw_pk.serialize_uncompressed()[1..65].try_into().unwrap())
+}
+
+/// The EIP-55 mixed-case checksum encoding of the address, with `0x` prefix.
+pub fn checksum_address(addr: &Address) -> String {
+    let addr = hex::encode(addr);
+    let hash = keccak_hash(addr.as_bytes());
+    let checksummed: String = addr
+        .chars()
+        .enumerate()
+        .map(|(i, c)| {
+            let nibble = (hash[i / 2] >> if i % 2 == 0 { 4 } else { 0 }) & 0x0f;
+            if nibble >= 8 {
+                c.to_ascii_uppercase()
+            } else {
+                c
+            }
+        })
+        .collect();
+    format!("0x{checksummed}")
+}
+
 #[derive(Debug)]
 pub struct EthCrypto;
 
@@ -121,10 +171,21 @@ impl Crypto for EthCrypto {
         secp256k1_generate_secret_key()
     }
 
+    // Same as the one used by most ethereum wallets.
+    const DEFAULT_DERIVATION_PATH: &'static str = "m/44'/60'/0'/0/0";
+
+    fn derive_secret_key(seed: &[u8], path: &DerivationPath) -> Result<Self::SecretKey> {
+        derive_secret_key(&SECP256K1_CURVE, seed, path)
+    }
+
     fn sign(msg: &[u8], sk: &Self::SecretKey) -> Self::Signature {
         secp256k1_sign(msg, sk)
     }
 
+    fn recover_signature(msg: &[u8], signature: &Self::Signature) -> Option<Self::PublicKey> {
+        secp256k1_recover(msg, signature)
+    }
+
     fn pk2addr(pk: &Self::PublicKey) -> Address {
         secp256k1_pk2addr(pk)
     }
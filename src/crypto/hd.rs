@@ -0,0 +1,209 @@
+// Copyright Rivtower Technologies LLC.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! BIP-32 private key derivation for deriving accounts from a BIP-39 seed.
+//!
+//! The derivation is parametrized over the curve so that both secp256k1 and SM2
+//! can share it. Only private derivation (sk -> child sk) is implemented.
+//!
+//! For SM2, there is no public derivation scheme that other wallets agree on,
+//! so only hardened indexes are accepted (in the same way as SLIP-10 does for ed25519).
+
+use std::str::FromStr;
+
+use anyhow::{anyhow, ensure, Context, Result};
+use hmac::{Hmac, Mac};
+use sha2::Sha512;
+
+type HmacSha512 = Hmac<Sha512>;
+type CompressedPkFn = fn(&[u8; 32]) -> [u8; 33];
+
+pub const HARDENED_OFFSET: u32 = 1 << 31;
+
+/// A BIP-32 derivation path like `m/44'/60'/0'/0/0`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DerivationPath(Vec<u32>);
+
+impl DerivationPath {
+    pub fn indexes(&self) -> &[u32] {
+        &self.0
+    }
+}
+
+impl FromStr for DerivationPath {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let mut parts = s.trim().split('/');
+        ensure!(
+            parts.next() == Some("m"),
+            "derivation path must start with `m/`"
+        );
+        parts
+            .map(|part| {
+                let (index, hardened) = match part.strip_suffix(['\'', 'h']) {
+                    Some(index) => (index, true),
+                    None => (part, false),
+                };
+                let index: u32 = index
+                    .parse()
+                    .with_context(|| format!("invalid derivation path component `{part}`"))?;
+                ensure!(
+                    index < HARDENED_OFFSET,
+                    "derivation path index `{index}` is too large"
+                );
+                Ok(if hardened {
+                    index | HARDENED_OFFSET
+                } else {
+                    index
+                })
+            })
+            .collect::<Result<_>>()
+            .map(Self)
+    }
+}
+
+/// Curve parameters needed by the derivation.
+pub struct Curve {
+    /// HMAC key for generating the master key from seed.
+    pub seed_key: &'static [u8],
+    /// Big-endian curve order.
+    pub order: [u8; 32],
+    /// sk -> compressed pk, required for non-hardened derivation.
+    pub compressed_pk: Option<CompressedPkFn>,
+}
+
+pub fn derive_secret_key(curve: &Curve, seed: &[u8], path: &DerivationPath) -> Result<[u8; 32]> {
+    let (mut sk, mut chain_code) = hmac_sha512(curve.seed_key, &[seed]);
+    ensure!(
+        is_valid_scalar(&sk, &curve.order),
+        "invalid master key derived from seed"
+    );
+
+    for &index in path.indexes() {
+        let (tweak, child_chain_code) = if index >= HARDENED_OFFSET {
+            hmac_sha512(&chain_code, &[&[0], &sk, &index.to_be_bytes()])
+        } else {
+            let compressed_pk = curve.compressed_pk.ok_or_else(|| {
+                anyhow!(
+                    "non-hardened derivation isn't supported for this crypto type, use `{index}'` instead of `{index}`"
+                )
+            })?;
+            hmac_sha512(&chain_code, &[&compressed_pk(&sk), &index.to_be_bytes()])
+        };
+        ensure!(
+            tweak < curve.order,
+            "invalid child key at index `{index}`, try another path"
+        );
+        sk = add_mod(&tweak, &sk, &curve.order);
+        ensure!(
+            is_valid_scalar(&sk, &curve.order),
+            "invalid child key at index `{index}`, try another path"
+        );
+        chain_code = child_chain_code;
+    }
+
+    Ok(sk)
+}
+
+fn hmac_sha512(key: &[u8], data: &[&[u8]]) -> ([u8; 32], [u8; 32]) {
+    let mut mac = HmacSha512::new_from_slice(key).expect("HMAC can take key of any size");
+    for d in data {
+        mac.update(d);
+    }
+    let output = mac.finalize().into_bytes();
+    (
+        output[..32].try_into().unwrap(),
+        output[32..].try_into().unwrap(),
+    )
+}
+
+fn is_valid_scalar(k: &[u8; 32], order: &[u8; 32]) -> bool {
+    k != &[0; 32] && k < order
+}
+
+// (a + b) mod n, where a, b < n.
+fn add_mod(a: &[u8; 32], b: &[u8; 32], n: &[u8; 32]) -> [u8; 32] {
+    let mut sum = [0u8; 32];
+    let mut carry = 0u16;
+    for i in (0..32).rev() {
+        let s = a[i] as u16 + b[i] as u16 + carry;
+        sum[i] = s as u8;
+        carry = s >> 8;
+    }
+
+    if carry != 0 || &sum >= n {
+        let mut borrow = 0i16;
+        for i in (0..32).rev() {
+            let d = sum[i] as i16 - n[i] as i16 - borrow;
+            sum[i] = d.rem_euclid(256) as u8;
+            borrow = (d < 0) as i16;
+        }
+    }
+    sum
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::{Crypto, EthCrypto};
+    use crate::utils::{hex, parse_mnemonic};
+
+    #[test]
+    fn test_derivation_path() {
+        let path: DerivationPath = "m/44'/60h/0'/0/1".parse().unwrap();
+        assert_eq!(
+            path.indexes(),
+            &[
+                44 | HARDENED_OFFSET,
+                60 | HARDENED_OFFSET,
+                HARDENED_OFFSET,
+                0,
+                1
+            ]
+        );
+        assert!("44'/60'".parse::<DerivationPath>().is_err());
+        assert!("m/x".parse::<DerivationPath>().is_err());
+        assert!("m/2147483648".parse::<DerivationPath>().is_err());
+    }
+
+    #[test]
+    fn test_bip32_vector() {
+        // Test vector 1 from BIP-32.
+        let seed = ::hex::decode("000102030405060708090a0b0c0d0e0f").unwrap();
+        let path = "m/0'/1/2'/2/1000000000".parse().unwrap();
+        let sk = EthCrypto::derive_secret_key(&seed, &path).unwrap();
+        assert_eq!(
+            hex(&sk),
+            "0x471b76e389e528d6de6d816857e012c5455051cad6660850e58372a6c3e6e7c8"
+        );
+    }
+
+    #[test]
+    fn test_mnemonic_derivation() {
+        let mnemonic =
+            parse_mnemonic("test test test test test test test test test test test junk").unwrap();
+        let seed = mnemonic.to_seed("");
+        let path = EthCrypto::DEFAULT_DERIVATION_PATH.parse().unwrap();
+        let sk = EthCrypto::derive_secret_key(&seed, &path).unwrap();
+        assert_eq!(
+            hex(&EthCrypto::sk2addr(&sk)),
+            "0xf39fd6e51aad88f6f4ce6ab8827279cfffb92266"
+        );
+
+        let err = parse_mnemonic("test test test test test test test test test test test test")
+            .unwrap_err();
+        assert!(err.to_string().contains("checksum"));
+    }
+}
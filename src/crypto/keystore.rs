@@ -0,0 +1,269 @@
+// Copyright Rivtower Technologies LLC.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Ethereum V3 keystore, see [Web3 Secret Storage Definition](https://ethereum.org/en/developers/docs/data-structures-and-encoding/web3-secret-storage/).
+//!
+//! It's only for ETH accounts, the format is tied to keccak and secp256k1.
+
+use anyhow::{anyhow, bail, ensure, Context, Result};
+use ctr::cipher::{NewCipher, StreamCipher};
+use hmac::Hmac;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+
+use super::{eth, Crypto, EthCrypto};
+
+const DKLEN: usize = 32;
+
+/// KDF and its parameters for encrypting the keystore.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kdf {
+    Scrypt { log_n: u8, r: u32, p: u32 },
+    Pbkdf2 { c: u32 },
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct KeystoreV3 {
+    version: u32,
+    id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    address: Option<String>,
+    // Some old keystores use `Crypto`.
+    #[serde(alias = "Crypto")]
+    crypto: CryptoJson,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct CryptoJson {
+    cipher: String,
+    cipherparams: CipherParams,
+    ciphertext: String,
+    kdf: String,
+    kdfparams: KdfParams,
+    mac: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct CipherParams {
+    iv: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(untagged)]
+enum KdfParams {
+    Scrypt {
+        dklen: usize,
+        n: u64,
+        r: u32,
+        p: u32,
+        salt: String,
+    },
+    Pbkdf2 {
+        dklen: usize,
+        c: u32,
+        prf: String,
+        salt: String,
+    },
+}
+
+fn derive_key(pw: &[u8], params: &KdfParams) -> Result<Vec<u8>> {
+    match params {
+        KdfParams::Scrypt {
+            dklen,
+            n,
+            r,
+            p,
+            salt,
+        } => {
+            ensure!(n.is_power_of_two(), "invalid scrypt param n `{n}`");
+            let log_n = n.trailing_zeros() as u8;
+            let params = scrypt::Params::new(log_n, *r, *p)
+                .map_err(|e| anyhow!("invalid scrypt params: {e}"))?;
+            let mut dk = vec![0; *dklen];
+            scrypt::scrypt(pw, &hex::decode(salt)?, &params, &mut dk)
+                .map_err(|e| anyhow!("scrypt failed: {e}"))?;
+            Ok(dk)
+        }
+        KdfParams::Pbkdf2 {
+            dklen,
+            c,
+            prf,
+            salt,
+        } => {
+            ensure!(prf == "hmac-sha256", "unsupported pbkdf2 prf `{prf}`");
+            let mut dk = vec![0; *dklen];
+            pbkdf2::pbkdf2::<Hmac<Sha256>>(pw, &hex::decode(salt)?, *c, &mut dk);
+            Ok(dk)
+        }
+    }
+}
+
+fn aes_128_ctr(data: &[u8], key: &[u8], iv: &[u8]) -> Result<Vec<u8>> {
+    type Aes128Ctr = ctr::Ctr128BE<aes::Aes128>;
+
+    let mut cipher =
+        Aes128Ctr::new_from_slices(key, iv).map_err(|_| anyhow!("invalid aes key or iv"))?;
+    let mut output = data.to_vec();
+    cipher.apply_keystream(&mut output);
+    Ok(output)
+}
+
+fn mac(dk: &[u8], ciphertext: &[u8]) -> [u8; 32] {
+    EthCrypto::hash(&[&dk[16..32], ciphertext].concat())
+}
+
+fn random_uuid() -> String {
+    let mut b: [u8; 16] = rand::thread_rng().gen();
+    // version 4, variant 1
+    b[6] = (b[6] & 0x0f) | 0x40;
+    b[8] = (b[8] & 0x3f) | 0x80;
+    let h = hex::encode(b);
+    format!(
+        "{}-{}-{}-{}-{}",
+        &h[..8],
+        &h[8..12],
+        &h[12..16],
+        &h[16..20],
+        &h[20..]
+    )
+}
+
+/// Encrypt the secret key into a V3 keystore JSON.
+pub fn encrypt_keystore(sk: &eth::SecretKey, pw: &[u8], kdf: Kdf) -> Result<String> {
+    let mut rng = rand::thread_rng();
+    let salt = hex::encode(rng.gen::<[u8; 32]>());
+    let iv: [u8; 16] = rng.gen();
+
+    let kdfparams = match kdf {
+        Kdf::Scrypt { log_n, r, p } => KdfParams::Scrypt {
+            dklen: DKLEN,
+            n: 1 << log_n,
+            r,
+            p,
+            salt,
+        },
+        Kdf::Pbkdf2 { c } => KdfParams::Pbkdf2 {
+            dklen: DKLEN,
+            c,
+            prf: "hmac-sha256".into(),
+            salt,
+        },
+    };
+    let dk = derive_key(pw, &kdfparams)?;
+    let ciphertext = aes_128_ctr(sk, &dk[..16], &iv)?;
+
+    let keystore = KeystoreV3 {
+        version: 3,
+        id: random_uuid(),
+        address: Some(hex::encode(EthCrypto::sk2addr(sk))),
+        crypto: CryptoJson {
+            cipher: "aes-128-ctr".into(),
+            cipherparams: CipherParams {
+                iv: hex::encode(iv),
+            },
+            mac: hex::encode(mac(&dk, &ciphertext)),
+            ciphertext: hex::encode(ciphertext),
+            kdf: match kdf {
+                Kdf::Scrypt { .. } => "scrypt".into(),
+                Kdf::Pbkdf2 { .. } => "pbkdf2".into(),
+            },
+            kdfparams,
+        },
+    };
+    Ok(serde_json::to_string_pretty(&keystore)?)
+}
+
+/// Decrypt the secret key from a V3 keystore JSON.
+pub fn decrypt_keystore(json: &str, pw: &[u8]) -> Result<eth::SecretKey> {
+    let keystore: KeystoreV3 = serde_json::from_str(json).context("invalid keystore")?;
+    ensure!(
+        keystore.version == 3,
+        "unsupported keystore version `{}`",
+        keystore.version
+    );
+    let crypto = &keystore.crypto;
+    ensure!(
+        crypto.cipher == "aes-128-ctr",
+        "unsupported cipher `{}`",
+        crypto.cipher
+    );
+    match (crypto.kdf.as_str(), &crypto.kdfparams) {
+        ("scrypt", KdfParams::Scrypt { .. }) | ("pbkdf2", KdfParams::Pbkdf2 { .. }) => (),
+        (kdf, _) => bail!("unsupported kdf `{kdf}` or mismatched kdfparams"),
+    }
+
+    let dk = derive_key(pw, &crypto.kdfparams)?;
+    ensure!(dk.len() >= 32, "dklen must be at least 32");
+    let ciphertext = hex::decode(&crypto.ciphertext).context("invalid ciphertext")?;
+    let expected_mac = hex::decode(&crypto.mac).context("invalid mac")?;
+    ensure!(
+        mac(&dk, &ciphertext).as_slice() == expected_mac,
+        "mac mismatched, wrong password?"
+    );
+
+    let iv = hex::decode(&crypto.cipherparams.iv).context("invalid iv")?;
+    let sk = aes_128_ctr(&ciphertext, &dk[..16], &iv)?;
+    sk.try_into()
+        .map_err(|sk: Vec<u8>| anyhow!("expected 32 bytes secret key, got {}", sk.len()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_keystore_pbkdf2_vector() {
+        // Test vector from Web3 Secret Storage Definition.
+        let json = r#"{
+            "crypto": {
+                "cipher": "aes-128-ctr",
+                "cipherparams": {"iv": "6087dab2f9fdbbfaddc31a909735c1e6"},
+                "ciphertext": "5318b4d5bcd28de64ee5559e671353e16f075ecae9f99c7a79a38af5f869aa46",
+                "kdf": "pbkdf2",
+                "kdfparams": {
+                    "c": 262144,
+                    "dklen": 32,
+                    "prf": "hmac-sha256",
+                    "salt": "ae3cd4e7013836a3df6bd7241b12db061dbe2c6785853cce422d148a624ce0bd"
+                },
+                "mac": "517ead924a9d0dc3124507e3393d175ce3ff7c1e96529c6c555ce9e51205e9b2"
+            },
+            "id": "3198bc9c-6672-5ab3-d995-4942343ae5b6",
+            "version": 3
+        }"#;
+        let sk = decrypt_keystore(json, b"testpassword").unwrap();
+        assert_eq!(
+            hex::encode(sk),
+            "7a28b5ba57c53603b0b07b56bba752f7784bf506fa95edc395f5cf6c7514fe9d"
+        );
+        assert!(decrypt_keystore(json, b"wrongpassword").is_err());
+    }
+
+    #[test]
+    fn test_keystore_round_trip() {
+        let sk = EthCrypto::generate_secret_key();
+        for kdf in [
+            Kdf::Scrypt {
+                log_n: 10,
+                r: 8,
+                p: 1,
+            },
+            Kdf::Pbkdf2 { c: 1024 },
+        ] {
+            let json = encrypt_keystore(&sk, b"pw", kdf).unwrap();
+            assert_eq!(decrypt_keystore(&json, b"pw").unwrap(), sk);
+        }
+    }
+}
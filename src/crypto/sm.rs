@@ -17,7 +17,9 @@
 
 /// Please refer to [kms_sm](https://github.com/cita-cloud/kms_sm).
 /// This crypto impl must be compatible with `kms_sm` to work with it.
-use super::Crypto;
+use super::hd::{derive_secret_key, Curve};
+use super::{Crypto, DerivationPath};
+use anyhow::Result;
 use efficient_sm2::KeyPair;
 use rand::Rng;
 
@@ -61,6 +63,17 @@ pub fn sm2_generate_secret_key() -> SecretKey {
     rand::thread_rng().gen()
 }
 
+// Non-hardened derivation is not supported for SM2, see the `hd` module.
+const SM2_CURVE: Curve = Curve {
+    seed_key: b"SM2 seed",
+    order: [
+        0xff, 0xff, 0xff, 0xfe, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+        0xff, 0x72, 0x03, 0xdf, 0x6b, 0x21, 0xc6, 0x05, 0x2b, 0x53, 0xbb, 0xf4, 0x09, 0x39, 0xd5,
+        0x41, 0x23,
+    ],
+    compressed_pk: None,
+};
+
 // FIXME: sk -> kp is an expensive operation, use keypair directly.
 // (that will need a wrapper type and impl some traits)
 pub fn sm2_sign(msg: &[u8], sk: &SecretKey) -> Signature {
@@ -74,7 +87,6 @@ pub fn sm2_sign(msg: &[u8], sk: &SecretKey) -> Signature {
     sig_bytes
 }
 
-#[allow(unused)]
 pub fn sm2_recover_signature(msg: &[u8], signature: &Signature) -> Option<PublicKey> {
     let r = &signature[0..32];
     let s = &signature[32..64];
@@ -129,10 +141,21 @@ impl Crypto for SmCrypto {
         sm2_generate_secret_key()
     }
 
+    // The ethereum path with every level hardened.
+    const DEFAULT_DERIVATION_PATH: &'static str = "m/44'/60'/0'/0'/0'";
+
+    fn derive_secret_key(seed: &[u8], path: &DerivationPath) -> Result<Self::SecretKey> {
+        derive_secret_key(&SM2_CURVE, seed, path)
+    }
+
     fn sign(msg: &[u8], sk: &Self::SecretKey) -> Self::Signature {
         sm2_sign(msg, sk)
     }
 
+    fn recover_signature(msg: &[u8], signature: &Self::Signature) -> Option<Self::PublicKey> {
+        sm2_recover_signature(msg, signature)
+    }
+
     fn pk2addr(pk: &Self::PublicKey) -> Address {
         pk2addr(pk)
     }
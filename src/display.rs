@@ -12,13 +12,27 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use std::collections::HashMap;
+use std::io::{IsTerminal, Write};
+use std::str::FromStr;
+use std::sync::Arc;
+
+use anyhow::bail;
+use ethabi::{Contract, RawLog, Token};
 use serde_json::json;
 use serde_json::map::Map;
 use serde_json::Value as Json;
 use tentacle_multiaddr::{Multiaddr, Protocol};
 
 use crate::{
-    crypto::{Address, Hash},
+    core::{
+        controller::{
+            verify_raw_tx, AddNodeStatus, BlockVerification, SystemConfigChange, TxVerification,
+        },
+        evm::QuotaUsage,
+        wallet::{MaybeLocked, MultiCryptoAccount},
+    },
+    crypto::{checksum_address, Address, ArrayLike, Crypto, Hash},
     proto::{
         blockchain::{
             raw_transaction::Tx, CompactBlock, RawTransaction, Transaction, UnverifiedTransaction,
@@ -26,68 +40,308 @@ use crate::{
         },
         common::{NodeInfo, TotalNodeInfo},
         controller::SystemConfig,
-        evm::{Balance, ByteAbi, ByteCode, Log, Nonce, Receipt},
+        evm::{ByteAbi, ByteCode, Log, Receipt},
         executor::CallResponse,
     },
-    utils::{display_time, hex},
+    utils::{display_time, hex, U256},
 };
 
+/// How command outputs are formatted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OutputFormat {
+    /// Human-readable output, some of them are pretty-printed JSON.
+    #[default]
+    Text,
+    /// Compact JSON for scripts.
+    Json,
+    /// The raw protobuf bytes of the response, see [`OutputSink::emit_bin`].
+    /// Commands whose output isn't a single protobuf message print compact JSON instead.
+    Bin,
+}
+
+impl FromStr for OutputFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let format = match s.to_lowercase().as_str() {
+            "text" => Self::Text,
+            "json" => Self::Json,
+            "bin" => Self::Bin,
+            unknown => bail!("unknown output format `{}`", unknown),
+        };
+        Ok(format)
+    }
+}
+
+/// When to colorize the text output.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum ColorMode {
+    /// Colorize if stdout is a terminal and `NO_COLOR` isn't set.
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+impl FromStr for ColorMode {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mode = match s.to_lowercase().as_str() {
+            "auto" => Self::Auto,
+            "always" => Self::Always,
+            "never" => Self::Never,
+            unknown => bail!("unknown color mode `{}`", unknown),
+        };
+        Ok(mode)
+    }
+}
+
+impl ColorMode {
+    /// Whether to colorize the text output, `no_color` is the `NO_COLOR` env var.
+    pub fn enabled(self, no_color: Option<&str>) -> bool {
+        match self {
+            Self::Always => true,
+            Self::Never => false,
+            // See https://no-color.org
+            Self::Auto => {
+                no_color.unwrap_or_default().is_empty() && std::io::stdout().is_terminal()
+            }
+        }
+    }
+}
+
+const ADDR_COLOR: &str = "\x1b[36m";
+const HEX_COLOR: &str = "\x1b[33m";
+const NUMBER_COLOR: &str = "\x1b[35m";
+const RESET: &str = "\x1b[0m";
+
+/// Colorize addresses, other hex values like hashes, and numbers in the text.
+///
+/// A token is only colorized as a whole, digits in words, times or decimals are left as is.
+pub fn colorize(text: &str) -> String {
+    // Chars that can't be next to a token, so `v2`, `12:00` or `1.5` isn't colorized.
+    fn is_joint(c: char) -> bool {
+        c.is_alphanumeric() || matches!(c, '_' | '-' | '.' | ':')
+    }
+
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    let mut prev: Option<char> = None;
+    while let Some(c) = rest.chars().next() {
+        if prev.is_some_and(is_joint) || !c.is_ascii_digit() {
+            out.push(c);
+            prev = Some(c);
+            rest = &rest[c.len_utf8()..];
+            continue;
+        }
+
+        let (len, color) = match rest.strip_prefix("0x") {
+            Some(digits) => {
+                let n = digits
+                    .find(|c: char| !c.is_ascii_hexdigit())
+                    .unwrap_or(digits.len());
+                let color = if n == 40 { ADDR_COLOR } else { HEX_COLOR };
+                (2 + n, color)
+            }
+            None => {
+                let n = rest
+                    .find(|c: char| !c.is_ascii_digit())
+                    .unwrap_or(rest.len());
+                (n, NUMBER_COLOR)
+            }
+        };
+        let (token, after) = rest.split_at(len);
+        if after.chars().next().is_some_and(is_joint) {
+            out.push_str(token);
+        } else {
+            out.push_str(color);
+            out.push_str(token);
+            out.push_str(RESET);
+        }
+        prev = token.chars().last();
+        rest = after;
+    }
+    out
+}
+
+/// Remove the colors added by [`colorize`].
+pub fn strip_colors(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find("\x1b[") {
+        out.push_str(&rest[..start]);
+        rest = &rest[start..];
+        match rest.find('m') {
+            Some(end) => rest = &rest[end + 1..],
+            None => break,
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+/// How the outputs are rendered, see [`Context::display_options`].
+///
+/// [`Context::display_options`]: crate::core::context::Context::display_options
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DisplayOptions {
+    pub format: OutputFormat,
+    /// Show addresses in the EIP-55 checksum form, it's enabled for ETH chains.
+    pub address_checksum: bool,
+    /// Colorize the text output, see [`ColorMode`].
+    pub color: bool,
+}
+
+impl DisplayOptions {
+    /// Display the address in the EIP-55 checksum form if it's enabled, otherwise lowercase hex.
+    pub fn addr(&self, addr: &[u8]) -> String {
+        match Address::try_from_slice(addr) {
+            Ok(addr) if self.address_checksum => checksum_address(&addr),
+            _ => hex(addr),
+        }
+    }
+}
+
 pub trait Display {
-    fn to_json(&self) -> Json;
-    fn display(&self) -> String {
-        serde_json::to_string_pretty(&self.to_json()).unwrap()
+    fn to_json(&self, opts: DisplayOptions) -> Json;
+    fn display(&self, opts: DisplayOptions) -> String {
+        serde_json::to_string_pretty(&self.to_json(opts)).unwrap()
+    }
+
+    /// Format the output in `opts.format`, the text output is colorized if enabled,
+    /// JSON is never colorized.
+    fn display_with(&self, opts: DisplayOptions) -> String {
+        match opts.format {
+            OutputFormat::Text if opts.color => colorize(&self.display(opts)),
+            OutputFormat::Text => self.display(opts),
+            OutputFormat::Json | OutputFormat::Bin => {
+                serde_json::to_string(&self.to_json(opts)).unwrap()
+            }
+        }
+    }
+}
+
+/// Where the outputs of commands go, see [`Context::emit`]. Library users can replace
+/// the context's sink, or use [`Context::capture`], to get the outputs as values.
+///
+/// [`Context::emit`]: crate::core::context::Context::emit
+/// [`Context::capture`]: crate::core::context::Context::capture
+pub trait OutputSink {
+    /// `rendered` is the value formatted with the current output format.
+    fn emit(&mut self, value: Json, rendered: String);
+
+    /// The prost-encoded message of the `bin` output format. It's written to stdout as is,
+    /// without a trailing newline, by default.
+    fn emit_bin(&mut self, bytes: &[u8]) -> std::io::Result<()> {
+        let mut stdout = std::io::stdout().lock();
+        stdout.write_all(bytes)?;
+        stdout.flush()
+    }
+}
+
+/// Print the outputs to stdout, one per line. It's the default sink.
+pub struct StdoutSink;
+
+impl OutputSink for StdoutSink {
+    fn emit(&mut self, _value: Json, rendered: String) {
+        println!("{rendered}");
     }
 }
 
 impl Display for Json {
-    fn to_json(&self) -> Json {
+    fn to_json(&self, _opts: DisplayOptions) -> Json {
         self.clone()
     }
 
-    fn display(&self) -> String {
+    fn display(&self, _opts: DisplayOptions) -> String {
         serde_json::to_string_pretty(&self).unwrap()
     }
 }
 
 impl<T: Display> Display for &T {
-    fn to_json(&self) -> Json {
-        (**self).to_json()
+    fn to_json(&self, opts: DisplayOptions) -> Json {
+        (**self).to_json(opts)
+    }
+
+    fn display(&self, opts: DisplayOptions) -> String {
+        (**self).display(opts)
+    }
+}
+
+impl Display for u64 {
+    fn to_json(&self, _opts: DisplayOptions) -> Json {
+        json!(self)
+    }
+
+    fn display(&self, _opts: DisplayOptions) -> String {
+        self.to_string()
+    }
+}
+
+impl Display for String {
+    fn to_json(&self, _opts: DisplayOptions) -> Json {
+        json!(self)
+    }
+
+    fn display(&self, _opts: DisplayOptions) -> String {
+        self.clone()
     }
 }
 
 impl Display for Address {
-    fn to_json(&self) -> Json {
-        json!(hex(self.as_slice()))
+    fn to_json(&self, opts: DisplayOptions) -> Json {
+        json!(opts.addr(self))
     }
 
-    fn display(&self) -> String {
-        hex(self.as_slice())
+    fn display(&self, opts: DisplayOptions) -> String {
+        opts.addr(self)
     }
 }
 
 impl Display for Hash {
-    fn to_json(&self) -> Json {
-        json!(hex(self.as_slice()))
+    fn to_json(&self, _opts: DisplayOptions) -> Json {
+        json!(self.to_hex())
     }
 
-    fn display(&self) -> String {
-        hex(self.as_slice())
+    fn display(&self, _opts: DisplayOptions) -> String {
+        self.to_hex()
+    }
+}
+
+impl Display for AddNodeStatus {
+    fn to_json(&self, _opts: DisplayOptions) -> Json {
+        json!({
+            "code": self.code(),
+            "status": self.description(),
+        })
+    }
+
+    fn display(&self, _opts: DisplayOptions) -> String {
+        match self {
+            AddNodeStatus::Success => "Success".into(),
+            failed => format!(
+                "Failed with status code `{}`: {}",
+                failed.code(),
+                failed.description()
+            ),
+        }
     }
 }
 
 impl Display for CallResponse {
-    fn to_json(&self) -> Json {
+    fn to_json(&self, _opts: DisplayOptions) -> Json {
         json!(hex(&self.value))
     }
 
     // don't display ""
-    fn display(&self) -> String {
+    fn display(&self, _opts: DisplayOptions) -> String {
         hex(&self.value)
     }
 }
 
 impl Display for CompactBlock {
-    fn to_json(&self) -> Json {
+    fn to_json(&self, _opts: DisplayOptions) -> Json {
         let tx_hashes = match self.body.as_ref() {
             Some(body) => body.tx_hashes.iter().map(|h| hex(h)).collect(),
             None => vec![],
@@ -98,7 +352,8 @@ impl Display for CompactBlock {
                 json!({
                     "version": self.version,
                     "height": header.height,
-                    "prev_hash": hex(&header.prevhash),
+                    // The genesis block has no parent.
+                    "prev_hash": (header.height != 0).then(|| hex(&header.prevhash)),
                     "tx_count": tx_hashes.len(),
                     "tx_hashes": tx_hashes,
                     "timestamp": display_time(header.timestamp),
@@ -111,11 +366,125 @@ impl Display for CompactBlock {
     }
 }
 
+/// Block 0 with the genesis config, see `rpc genesis`.
+pub struct Genesis<'a> {
+    pub block: &'a CompactBlock,
+    pub hash: Hash,
+    /// The current system config, the fields never changed since genesis are part of the genesis config.
+    pub system_config: &'a SystemConfig,
+}
+
+impl Display for Genesis<'_> {
+    fn to_json(&self, opts: DisplayOptions) -> Json {
+        // A field is never changed if it has no previous utxo tx.
+        let unchanged = |pre_hash: &[u8]| pre_hash.iter().all(|&b| b == 0);
+        let sc = self.system_config;
+        let mut config = Map::new();
+        config.insert("chain_id".into(), json!(hex(&sc.chain_id)));
+        if unchanged(&sc.version_pre_hash) {
+            config.insert("version".into(), json!(sc.version));
+        }
+        if unchanged(&sc.admin_pre_hash) {
+            config.insert("admin".into(), json!(opts.addr(&sc.admin)));
+        }
+        if unchanged(&sc.block_interval_pre_hash) {
+            config.insert("block_interval".into(), json!(sc.block_interval));
+        }
+        if unchanged(&sc.validators_pre_hash) {
+            config.insert("validators".into(), json!(sc.validator_addresses(opts)));
+        }
+        if unchanged(&sc.emergency_brake_pre_hash) {
+            config.insert("emergency_brake".into(), json!(sc.emergency_brake));
+        }
+
+        let mut json = self.block.to_json(opts);
+        json["hash"] = json!(hex(&self.hash));
+        json["genesis_config"] = Json::Object(config);
+        json
+    }
+}
+
+/// The stats of a block for monitoring, see `rpc tx-count-in-block`.
+///
+/// Fields of a missing header are `None`, so are an empty proposer and a zero timestamp.
+pub struct BlockStats {
+    pub height: Option<u64>,
+    pub tx_count: usize,
+    /// The encoded size of the compact block, it only has the tx hashes instead of the txs.
+    pub size: usize,
+    pub timestamp: Option<u64>,
+    pub proposer: Option<Vec<u8>>,
+}
+
+impl BlockStats {
+    pub fn of(block: &CompactBlock) -> Self {
+        let header = block.header.as_ref();
+        Self {
+            height: header.map(|h| h.height),
+            tx_count: block.body.as_ref().map_or(0, |b| b.tx_hashes.len()),
+            size: prost::Message::encoded_len(block),
+            timestamp: header.map(|h| h.timestamp).filter(|&ts| ts != 0),
+            proposer: header.map(|h| h.proposer.clone()).filter(|p| !p.is_empty()),
+        }
+    }
+}
+
+impl Display for BlockStats {
+    fn to_json(&self, _opts: DisplayOptions) -> Json {
+        json!({
+            "height": self.height,
+            "tx_count": self.tx_count,
+            "size": self.size,
+            "timestamp": self.timestamp.map(display_time),
+            "proposer": self.proposer.as_deref().map(hex),
+        })
+    }
+}
+
+/// A block with its txs expanded, see `get-block --full`.
+pub struct FullBlock<'a> {
+    pub block: &'a CompactBlock,
+    /// The fetched txs, in the same order as the block's tx hashes.
+    pub txs: Vec<anyhow::Result<RawTransaction>>,
+    /// Number of txs that aren't fetched due to `--max-txs`.
+    pub omitted: usize,
+}
+
+impl Display for FullBlock<'_> {
+    fn to_json(&self, opts: DisplayOptions) -> Json {
+        let mut json = self.block.to_json(opts);
+        let txs = self
+            .txs
+            .iter()
+            .map(|tx| match tx {
+                Ok(tx) => tx.to_json(opts),
+                Err(e) => json!({ "error": format!("{e:#}") }),
+            })
+            .collect::<Vec<_>>();
+        if let Some(obj) = json.as_object_mut() {
+            obj.remove("tx_hashes");
+            obj.insert("transactions".into(), json!(txs));
+            if self.omitted > 0 {
+                obj.insert("omitted_tx_count".into(), json!(self.omitted));
+            }
+        }
+        json
+    }
+
+    fn display(&self, opts: DisplayOptions) -> String {
+        let mut s = serde_json::to_string_pretty(&self.to_json(opts)).unwrap();
+        if self.omitted > 0 {
+            s += &format!("\n...and {} more", self.omitted);
+        }
+        s
+    }
+}
+
 impl Display for Transaction {
-    fn to_json(&self) -> Json {
+    fn to_json(&self, opts: DisplayOptions) -> Json {
         json!({
             "version": self.version,
-            "to": hex(&self.to),
+            "to": opts.addr(&self.to),
             "nonce": self.nonce,
             "quota": self.quota,
             "valid_until_block": self.valid_until_block,
@@ -127,24 +496,23 @@ impl Display for Transaction {
 }
 
 impl Display for UnverifiedTransaction {
-    fn to_json(&self) -> Json {
+    fn to_json(&self, opts: DisplayOptions) -> Json {
         json!({
-            "transaction": self.transaction.as_ref().map(|tx| tx.to_json()).unwrap_or_else(|| json!({})),
+            "transaction": self.transaction.as_ref().map(|tx| tx.to_json(opts)).unwrap_or_else(|| json!({})),
             "transaction_hash": hex(&self.transaction_hash),
-            "witness": self.witness.as_ref().map(|tx| tx.to_json()).unwrap_or_else(|| json!({})),
+            "witness": self.witness.as_ref().map(|tx| tx.to_json(opts)).unwrap_or_else(|| json!({})),
         })
     }
 }
 
 impl Display for SystemConfig {
-    fn to_json(&self) -> Json {
-        let validators = self.validators.iter().map(|v| hex(v)).collect::<Vec<_>>();
+    fn to_json(&self, opts: DisplayOptions) -> Json {
         json!({
             "version": self.version,
             "chain_id": hex(&self.chain_id),
-            "admin": hex(&self.admin),
+            "admin": opts.addr(&self.admin),
             "block_interval": self.block_interval,
-            "validators": validators,
+            "validators": self.validator_addresses(opts),
             "emergency_brake": self.emergency_brake,
             "version_pre_hash": hex(&self.version_pre_hash),
             "chain_id_pre_hash": hex(&self.chain_id_pre_hash),
@@ -154,10 +522,101 @@ impl Display for SystemConfig {
             "emergency_brake_pre_hash": hex(&self.emergency_brake_pre_hash),
         })
     }
+
+    fn display(&self, opts: DisplayOptions) -> String {
+        // Aligned to the longest label `Emergency brake pre-hash:`.
+        let line = |label: &str, value: &str| format!("{:<26}{value}", format!("{label}:"));
+        let validators = match self.validator_addresses(opts).as_slice() {
+            [] => "none".to_string(),
+            validators => validators.join(&format!("\n{:26}", "")),
+        };
+        [
+            line("Version", &self.version_string()),
+            line("Chain ID", &hex(&self.chain_id)),
+            line("Admin", &opts.addr(&self.admin)),
+            line(
+                "Block interval",
+                &format!("{}s", self.block_interval_duration().as_secs()),
+            ),
+            line("Validators", &validators),
+            line(
+                "Emergency brake",
+                if self.emergency_brake { "on" } else { "off" },
+            ),
+            line("Version pre-hash", &hex(&self.version_pre_hash)),
+            line("Chain ID pre-hash", &hex(&self.chain_id_pre_hash)),
+            line("Admin pre-hash", &hex(&self.admin_pre_hash)),
+            line(
+                "Block interval pre-hash",
+                &hex(&self.block_interval_pre_hash),
+            ),
+            line("Validators pre-hash", &hex(&self.validators_pre_hash)),
+            line(
+                "Emergency brake pre-hash",
+                &hex(&self.emergency_brake_pre_hash),
+            ),
+        ]
+        .join("\n")
+    }
+}
+
+/// A single field picked from an output, e.g. by `get-system-config --field`. In text,
+/// strings are printed without quotes and arrays one item per line, for scripting.
+pub struct FieldValue(pub Json);
+
+impl Display for FieldValue {
+    fn to_json(&self, _opts: DisplayOptions) -> Json {
+        self.0.clone()
+    }
+
+    fn display(&self, _opts: DisplayOptions) -> String {
+        match &self.0 {
+            Json::String(s) => s.clone(),
+            Json::Array(items) => items
+                .iter()
+                .map(|item| FieldValue(item.clone()).display(DisplayOptions::default()))
+                .collect::<Vec<_>>()
+                .join("\n"),
+            other => other.to_string(),
+        }
+    }
+}
+
+impl Display for SystemConfigChange {
+    fn to_json(&self, opts: DisplayOptions) -> Json {
+        match self {
+            Self::Field { name, old, new } => json!({
+                "field": name,
+                "old": old,
+                "new": new,
+            }),
+            Self::ValidatorAdded(v) => json!({
+                "field": "validators",
+                "added": opts.addr(v),
+            }),
+            Self::ValidatorRemoved(v) => json!({
+                "field": "validators",
+                "removed": opts.addr(v),
+            }),
+            Self::ValidatorsReordered => json!({
+                "field": "validators",
+                "reordered": true,
+            }),
+        }
+    }
+
+    fn display(&self, opts: DisplayOptions) -> String {
+        match self {
+            Self::Field { name, old, new } => format!("{name}: {old} -> {new}"),
+            Self::ValidatorAdded(v) => format!("validators: + {}", opts.addr(v)),
+            Self::ValidatorRemoved(v) => format!("validators: - {}", opts.addr(v)),
+            Self::ValidatorsReordered => "validators: reordered".into(),
+        }
+    }
 }
 
 impl Display for UtxoTransaction {
-    fn to_json(&self) -> Json {
+    fn to_json(&self, _opts: DisplayOptions) -> Json {
         json!({
             "version": self.version,
             "pre_tx_hash": hex(&self.pre_tx_hash),
@@ -168,38 +627,80 @@ impl Display for UtxoTransaction {
 }
 
 impl Display for UnverifiedUtxoTransaction {
-    fn to_json(&self) -> Json {
+    fn to_json(&self, opts: DisplayOptions) -> Json {
         let witnesses = self
             .witnesses
             .iter()
-            .map(|w| w.to_json())
+            .map(|w| w.to_json(opts))
             .collect::<Vec<_>>();
         json!({
-            "transaction": self.transaction.as_ref().map(|tx| tx.to_json()).unwrap_or_else(|| json!({})),
+            "transaction": self.transaction.as_ref().map(|tx| tx.to_json(opts)).unwrap_or_else(|| json!({})),
             "transaction_hash": hex(&self.transaction_hash),
             "witnesses": witnesses,
         })
     }
 }
 
+// Only the public data, use `Account::serialize_with_secret_key` to include the secret key.
+impl Display for MultiCryptoAccount {
+    fn to_json(&self, opts: DisplayOptions) -> Json {
+        json!({
+            "crypto_type": self.crypto_type(),
+            "address": opts.addr(self.address()),
+            "public_key": hex(self.public_key()),
+        })
+    }
+}
+
+impl Display for MaybeLocked {
+    fn to_json(&self, opts: DisplayOptions) -> Json {
+        json!({
+            "crypto_type": self.crypto_type(),
+            "address": opts.addr(self.address()),
+            "public_key": hex(self.public_key()),
+            "is_locked": self.is_locked(),
+        })
+    }
+}
+
 impl Display for Witness {
-    fn to_json(&self) -> Json {
+    fn to_json(&self, opts: DisplayOptions) -> Json {
         json!({
             "signature": hex(&self.signature),
-            "sender": hex(&self.sender),
+            "sender": opts.addr(&self.sender),
         })
     }
 }
 
+impl Display for RawTransaction {
+    fn to_json(&self, opts: DisplayOptions) -> Json {
+        match &self.tx {
+            Some(Tx::NormalTx(tx)) => {
+                json!({
+                    "type": "Normal",
+                    "transaction": tx.to_json(opts)
+                })
+            }
+            Some(Tx::UtxoTx(utxo)) => {
+                json!({
+                    "type": "Utxo",
+                    "transaction": utxo.to_json(opts)
+                })
+            }
+            None => json!({}),
+        }
+    }
+}
+
 impl Display for (RawTransaction, u64, u64) {
-    fn to_json(&self) -> Json {
+    fn to_json(&self, opts: DisplayOptions) -> Json {
         match &self.0.tx {
             Some(Tx::NormalTx(tx)) => {
                 json!({
                     "type": "Normal",
                     "height": self.1,
                     "index": self.2,
-                    "transaction": tx.to_json()
+                    "transaction": tx.to_json(opts)
                 })
             }
             Some(Tx::UtxoTx(utxo)) => {
@@ -207,7 +708,7 @@ impl Display for (RawTransaction, u64, u64) {
                     "type": "Utxo",
                     "height": self.1,
                     "index": self.2,
-                    "transaction": utxo.to_json()
+                    "transaction": utxo.to_json(opts)
                 })
             }
             None => json!({}),
@@ -215,31 +716,138 @@ impl Display for (RawTransaction, u64, u64) {
     }
 }
 
+impl Display for BlockVerification {
+    fn to_json(&self, _opts: DisplayOptions) -> Json {
+        json!({
+            "height": self.height,
+            "tx_count": self.tx_count,
+            "transactions_root": hex(&self.transactions_root),
+            "computed_root": hex(&self.computed_root),
+            "verified": self.is_valid(),
+        })
+    }
+}
+
+impl Display for TxVerification {
+    fn to_json(&self, opts: DisplayOptions) -> Json {
+        let witnesses = self
+            .signers
+            .iter()
+            .map(|(sender, recovered)| match recovered {
+                Ok(addr) => json!({
+                    "sender": opts.addr(sender),
+                    "recovered_sender": opts.addr(addr),
+                    "matched": addr.as_slice() == sender.as_slice(),
+                }),
+                Err(e) => json!({
+                    "sender": opts.addr(sender),
+                    "error": format!("{e:#}"),
+                    "matched": false,
+                }),
+            })
+            .collect::<Vec<_>>();
+        json!({
+            "tx_hash": hex(&self.tx_hash),
+            "computed_hash": hex(&self.computed_hash),
+            "hash_matched": self.hash_matched(),
+            "witnesses": witnesses,
+            "verified": self.is_valid(),
+        })
+    }
+}
+
+/// Recompute the tx hash and recover the witness senders using crypto `C`.
+///
+/// The results are added into the tx json as `hash_matched` and `recovered_sender`.
+/// Returns the recomputed hash if it mismatches the stored one.
+pub fn check_raw_tx<C: Crypto>(
+    raw: &RawTransaction,
+    tx_json: &mut Json,
+    opts: DisplayOptions,
+) -> Option<Hash> {
+    let verification = verify_raw_tx::<C>(raw).ok()?;
+    let hash_matched = verification.hash_matched();
+
+    let recovered_senders = verification
+        .signers
+        .iter()
+        .map(|(_, recovered)| match recovered {
+            Ok(addr) => json!(opts.addr(addr)),
+            Err(_) => Json::Null,
+        })
+        .collect::<Vec<_>>();
+    let unverified = &mut tx_json["transaction"];
+    unverified["hash_matched"] = json!(hash_matched);
+    if let Some(witness) = unverified.get_mut("witness") {
+        if let Some(sender) = recovered_senders.first() {
+            witness["recovered_sender"] = sender.clone();
+        }
+    }
+    if let Some(Json::Array(witnesses)) = unverified.get_mut("witnesses") {
+        for (w, sender) in witnesses.iter_mut().zip(recovered_senders) {
+            w["recovered_sender"] = sender;
+        }
+    }
+
+    (!hash_matched).then_some(verification.computed_hash)
+}
+
+/// Shown in place of the fields missing from a malformed peer entry.
+const UNKNOWN: &str = "<unknown>";
+
+fn or_unknown(s: String) -> String {
+    if s.is_empty() {
+        UNKNOWN.into()
+    } else {
+        s
+    }
+}
+
 impl Display for NodeInfo {
-    fn to_json(&self) -> Json {
+    fn to_json(&self, _opts: DisplayOptions) -> Json {
         let mut info_pair = Map::new();
+        let address = (!self.address.is_empty()).then(|| hex(&self.address));
         info_pair.insert(
             String::from("address"),
-            Json::from(format!("0x{}", hex::encode(&self.address))),
+            Json::from(address.unwrap_or_else(|| UNKNOWN.into())),
         );
-        let net_info = self.net_info.as_ref().unwrap();
+        // Show what we have, the entry may come from a buggy or older node.
+        let Some(net_info) = &self.net_info else {
+            info_pair.insert(String::from("origin"), Json::from(UNKNOWN));
+            info_pair.insert(String::from("multi_address"), Json::from(UNKNOWN));
+            return Json::from(info_pair);
+        };
         info_pair.insert(String::from("origin"), Json::from(net_info.origin));
-        let multi_address: Multiaddr = net_info.multi_address[..].parse().unwrap();
+        let Ok(multi_address) = net_info.multi_address.parse::<Multiaddr>() else {
+            info_pair.insert(
+                String::from("multi_address"),
+                Json::from(or_unknown(net_info.multi_address.clone())),
+            );
+            return Json::from(info_pair);
+        };
         for ptcl in multi_address.iter() {
             match ptcl {
-                Protocol::Dns4(host) => {
+                Protocol::Dns4(host) | Protocol::Dns6(host) => {
                     info_pair.insert(String::from("host"), Json::from(host));
                 }
                 Protocol::Ip4(host) => {
                     info_pair.insert(String::from("host"), Json::from(host.to_string()));
                 }
+                Protocol::Ip6(host) => {
+                    info_pair.insert(String::from("host"), Json::from(host.to_string()));
+                }
                 Protocol::Tcp(port) => {
                     info_pair.insert(String::from("port"), Json::from(port));
                 }
                 Protocol::Tls(domain) => {
                     info_pair.insert(String::from("domain"), Json::from(domain));
                 }
-                _ => panic!("multi address({:?}) can't parse", net_info.multi_address),
+                _ => {
+                    info_pair.insert(
+                        String::from("multi_address"),
+                        Json::from(net_info.multi_address.clone()),
+                    );
+                }
             };
         }
         Json::from(info_pair)
@@ -247,16 +855,70 @@ impl Display for NodeInfo {
 }
 
 impl Display for TotalNodeInfo {
-    fn to_json(&self) -> Json {
-        let nodes: Vec<Json> = self.nodes.iter().map(Display::to_json).collect();
+    fn to_json(&self, opts: DisplayOptions) -> Json {
+        let nodes: Vec<Json> = self.nodes.iter().map(|node| node.to_json(opts)).collect();
         json!({ "nodes": nodes })
     }
+
+    fn display(&self, _opts: DisplayOptions) -> String {
+        if self.nodes.is_empty() {
+            return "no peers".into();
+        }
+        let rows = self
+            .nodes
+            .iter()
+            .map(|node| {
+                let address = (!node.address.is_empty()).then(|| hex(&node.address));
+                let (origin, multi_address) = match &node.net_info {
+                    Some(net_info) => (
+                        net_info.origin.to_string(),
+                        or_unknown(net_info.multi_address.clone()),
+                    ),
+                    None => (UNKNOWN.into(), UNKNOWN.into()),
+                };
+                vec![
+                    address.unwrap_or_else(|| UNKNOWN.into()),
+                    origin,
+                    multi_address,
+                ]
+            })
+            .collect::<Vec<_>>();
+        format_table(&["ADDRESS", "ORIGIN", "MULTIADDR"], &rows)
+    }
+}
+
+/// Format the rows into a left-aligned table, columns are separated by 2 spaces.
+pub fn format_table(headers: &[&str], rows: &[Vec<String>]) -> String {
+    let mut widths: Vec<usize> = headers.iter().map(|h| h.len()).collect();
+    for row in rows {
+        for (w, cell) in widths.iter_mut().zip(row) {
+            *w = (*w).max(cell.len());
+        }
+    }
+    let format_row = |cells: Vec<&str>| {
+        cells
+            .iter()
+            .zip(&widths)
+            .map(|(cell, &w)| format!("{cell:<w$}"))
+            .collect::<Vec<_>>()
+            .join("  ")
+            .trim_end()
+            .to_string()
+    };
+
+    std::iter::once(format_row(headers.to_vec()))
+        .chain(
+            rows.iter()
+                .map(|row| format_row(row.iter().map(String::as_str).collect())),
+        )
+        .collect::<Vec<_>>()
+        .join("\n")
 }
 
 impl Display for Log {
-    fn to_json(&self) -> Json {
+    fn to_json(&self, opts: DisplayOptions) -> Json {
         json!({
-            "address": hex(&self.address),
+            "address": opts.addr(&self.address),
             "topics": json!(self.topics.iter().map(|t| hex(t)).collect::<Vec<_>>()),
             "data": hex(&self.data),
             "legacy_cita_block_hash": hex(&self.block_hash),
@@ -269,63 +931,491 @@ impl Display for Log {
     }
 }
 
+/// Log with its event and params decoded by the ABI. It's shown as is if the ABI doesn't match.
+pub struct DecodedLog<'a> {
+    pub log: &'a Log,
+    pub abi: Option<&'a Contract>,
+}
+
+impl Display for DecodedLog<'_> {
+    fn to_json(&self, opts: DisplayOptions) -> Json {
+        let mut json = self.log.to_json(opts);
+        if let Some((event, params)) = self.abi.and_then(|abi| decode_log(self.log, abi, opts)) {
+            json["event"] = json!(event);
+            json["params"] = params;
+        }
+        json
+    }
+}
+
 impl Display for Receipt {
-    fn to_json(&self) -> Json {
-        let logs = self.logs.iter().map(Log::to_json).collect::<Vec<_>>();
-        json!({
-            "tx_hash": hex(&self.transaction_hash),
-            "legacy_cita_block_hash": hex(&self.block_hash),
-            "block_number": self.block_number,
-            "tx_index": self.transaction_index,
-            "contract_addr": hex(&self.contract_address),
+    fn to_json(&self, opts: DisplayOptions) -> Json {
+        ReceiptDetail {
+            receipt: self,
+            abi: None,
+            contract_abis: HashMap::new(),
+            quota_limit: None,
+        }
+        .to_json(opts)
+    }
+}
+
+/// Receipt with logs decoded by the contract ABI, and the quota used versus the quota limit
+/// of its tx. The receipt itself doesn't have the limit.
+pub struct ReceiptDetail<'a> {
+    pub receipt: &'a Receipt,
+    /// Decode all of the logs with it, e.g. the one given by `--abi`.
+    pub abi: Option<&'a Contract>,
+    /// Otherwise decode the logs with the ABI of the contract that emits it.
+    pub contract_abis: HashMap<Address, Arc<Contract>>,
+    pub quota_limit: Option<u64>,
+}
+
+impl Display for ReceiptDetail<'_> {
+    fn to_json(&self, opts: DisplayOptions) -> Json {
+        let receipt = self.receipt;
+        let logs = receipt
+            .logs
+            .iter()
+            .map(|log| {
+                let abi = self.abi.or_else(|| {
+                    let addr = Address::try_from_slice(&log.address).ok()?;
+                    self.contract_abis.get(&addr).map(|abi| abi.as_ref())
+                });
+                DecodedLog { log, abi }.to_json(opts)
+            })
+            .collect::<Vec<_>>();
+        let mut json = json!({
+            "tx_hash": hex(&receipt.transaction_hash),
+            "legacy_cita_block_hash": hex(&receipt.block_hash),
+            "block_number": receipt.block_number,
+            "tx_index": receipt.transaction_index,
+            "contract_addr": opts.addr(&receipt.contract_address),
             "logs": logs,
-            "cumulative_quota_used": hex(&self.cumulative_quota_used),
-            "quota_used": hex(&self.quota_used),
-            "state_root": hex(&self.state_root),
-            "logs_bloom": hex(&self.logs_bloom),
-            "error_msg": self.error_message,
+            "cumulative_quota_used": hex(&receipt.cumulative_quota_used),
+            "quota_used": hex(&receipt.quota_used),
+            "state_root": hex(&receipt.state_root),
+            "logs_bloom": hex(&receipt.logs_bloom),
+            "error_msg": receipt.error_message,
+        });
+        if let Some(usage) = self
+            .quota_limit
+            .and_then(|limit| QuotaUsage::of(receipt, limit).ok())
+        {
+            json["quota"] = json!({
+                "used": usage.used,
+                "limit": usage.limit,
+                "utilization": format!("{:.2}%", usage.percentage()),
+            });
+        }
+        json
+    }
+}
+
+/// Returns the event signature and its named params.
+///
+/// Anonymous events have no signature topic, so they are tried one by one
+/// and the first one that matches the topic count and data layout wins.
+fn decode_log(log: &Log, abi: &Contract, opts: DisplayOptions) -> Option<(String, Json)> {
+    let topics = log
+        .topics
+        .iter()
+        .map(|t| (t.len() == 32).then(|| ethabi::Hash::from_slice(t)))
+        .collect::<Option<Vec<_>>>()?;
+    let raw = || RawLog {
+        topics: topics.clone(),
+        data: log.data.clone(),
+    };
+
+    let signature_topic = topics.first();
+    abi.events()
+        .filter(|event| !event.anonymous && Some(&event.signature()) == signature_topic)
+        .chain(abi.events().filter(|event| event.anonymous))
+        .find_map(|event| {
+            let indexed = event.inputs.iter().filter(|p| p.indexed).count();
+            let expected_topics = if event.anonymous {
+                indexed
+            } else {
+                indexed + 1
+            };
+            if expected_topics != topics.len() {
+                return None;
+            }
+            let parsed = event.parse_log(raw()).ok()?;
+            let params = parsed
+                .params
+                .into_iter()
+                .map(|p| (p.name, token_to_json(&p.value, opts)))
+                .collect::<Map<_, _>>();
+            let signature = format!(
+                "{}({})",
+                event.name,
+                event
+                    .inputs
+                    .iter()
+                    .map(|p| p.kind.to_string())
+                    .collect::<Vec<_>>()
+                    .join(",")
+            );
+            Some((signature, Json::Object(params)))
         })
+}
+
+fn token_to_json(token: &Token, opts: DisplayOptions) -> Json {
+    match token {
+        Token::Address(addr) => json!(opts.addr(addr.as_bytes())),
+        Token::FixedBytes(bytes) | Token::Bytes(bytes) => json!(hex(bytes)),
+        Token::Uint(v) => json!(v.to_string()),
+        Token::Int(v) => {
+            // two's complement
+            if v.bit(255) {
+                json!(format!("-{}", (!*v).overflowing_add(1.into()).0))
+            } else {
+                json!(v.to_string())
+            }
+        }
+        Token::Bool(b) => json!(b),
+        Token::String(s) => json!(s),
+        Token::FixedArray(tokens) | Token::Array(tokens) | Token::Tuple(tokens) => {
+            Json::Array(tokens.iter().map(|t| token_to_json(t, opts)).collect())
+        }
     }
 }
 
 impl Display for ByteCode {
-    fn to_json(&self) -> Json {
+    fn to_json(&self, _opts: DisplayOptions) -> Json {
         json!(hex(&self.byte_code))
     }
 
-    fn display(&self) -> String {
+    fn display(&self, _opts: DisplayOptions) -> String {
         hex(&self.byte_code)
     }
 }
 
-impl Display for Balance {
-    fn to_json(&self) -> Json {
-        json!(hex(&self.value))
-    }
-
-    fn display(&self) -> String {
-        hex(&self.value)
-    }
+/// Format the bytes like `xxd`, 16 bytes per line with offset, hex and ASCII columns.
+pub fn hexdump(data: &[u8]) -> String {
+    data.chunks(16)
+        .enumerate()
+        .map(|(i, line)| {
+            let hex = line
+                .chunks(2)
+                .map(hex::encode)
+                .collect::<Vec<_>>()
+                .join(" ");
+            let ascii: String = line
+                .iter()
+                .map(|&b| {
+                    if b.is_ascii_graphic() || b == b' ' {
+                        b as char
+                    } else {
+                        '.'
+                    }
+                })
+                .collect();
+            format!("{:08x}: {hex:<39}  {ascii}", i * 16)
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
 }
 
-impl Display for Nonce {
-    fn to_json(&self) -> Json {
-        json!(hex(&self.nonce))
+impl Display for U256 {
+    // Use decimal str to avoid overflow in JS's number.
+    fn to_json(&self, _opts: DisplayOptions) -> Json {
+        json!(self.to_decimal())
     }
 
-    fn display(&self) -> String {
-        hex(&self.nonce)
+    fn display(&self, _opts: DisplayOptions) -> String {
+        self.to_hex()
     }
 }
 
 impl Display for ByteAbi {
-    fn to_json(&self) -> Json {
+    fn to_json(&self, _opts: DisplayOptions) -> Json {
         // ByteAbi and bytes_abi..
         json!(String::from_utf8_lossy(&self.bytes_abi).to_string())
     }
 
-    fn display(&self) -> String {
+    fn display(&self, _opts: DisplayOptions) -> String {
         // Don't display ""
         String::from_utf8_lossy(&self.bytes_abi).to_string()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::{controller::SignerBehaviour, mock::tx, wallet::Account};
+    use crate::crypto::{EthCrypto, SmCrypto};
+
+    fn test_check_raw_tx_with<C: Crypto>() {
+        let account = Account::<C>::generate();
+        let tx = tx();
+        let mut raw = account.sign_raw_tx(tx);
+
+        let mut tx_json = raw.to_json(DisplayOptions::default());
+        assert_eq!(
+            check_raw_tx::<C>(&raw, &mut tx_json, DisplayOptions::default()),
+            None
+        );
+        assert_eq!(tx_json["transaction"]["hash_matched"], json!(true));
+        assert_eq!(
+            tx_json["transaction"]["witness"]["recovered_sender"],
+            json!(hex(account.address()))
+        );
+
+        if let Some(Tx::NormalTx(tx)) = &mut raw.tx {
+            tx.transaction.as_mut().unwrap().quota += 1;
+        }
+        let mut tx_json = raw.to_json(DisplayOptions::default());
+        assert!(check_raw_tx::<C>(&raw, &mut tx_json, DisplayOptions::default()).is_some());
+        assert_eq!(tx_json["transaction"]["hash_matched"], json!(false));
+    }
+
+    #[test]
+    fn test_check_raw_tx() {
+        test_check_raw_tx_with::<SmCrypto>();
+        test_check_raw_tx_with::<EthCrypto>();
+    }
+
+    #[test]
+    fn test_peers_table() {
+        use crate::proto::common::NodeNetInfo;
+
+        assert_eq!(
+            TotalNodeInfo { nodes: vec![] }.display(DisplayOptions::default()),
+            "no peers"
+        );
+        let node = |address: Vec<u8>, origin, multi_address: &str| NodeInfo {
+            address,
+            net_info: Some(NodeNetInfo {
+                multi_address: multi_address.into(),
+                origin,
+            }),
+        };
+        let peers = TotalNodeInfo {
+            nodes: vec![
+                node(vec![0xab], 1, "/ip4/127.0.0.1/tcp/40000"),
+                node(vec![0xcd, 0xef], 12345, "/dns4/node1/tcp/40001"),
+            ],
+        };
+        assert_eq!(
+            peers.display(DisplayOptions::default()),
+            "ADDRESS  ORIGIN  MULTIADDR\n\
+             0xab     1       /ip4/127.0.0.1/tcp/40000\n\
+             0xcdef   12345   /dns4/node1/tcp/40001"
+        );
+    }
+
+    #[test]
+    fn test_malformed_peers() {
+        use crate::proto::common::NodeNetInfo;
+
+        let peers = TotalNodeInfo {
+            nodes: vec![
+                NodeInfo {
+                    address: vec![0xab],
+                    net_info: None,
+                },
+                NodeInfo {
+                    address: vec![],
+                    net_info: Some(NodeNetInfo {
+                        multi_address: "garbage".into(),
+                        origin: 2,
+                    }),
+                },
+                NodeInfo {
+                    address: vec![0xcd],
+                    net_info: Some(NodeNetInfo::default()),
+                },
+            ],
+        };
+        assert_eq!(
+            peers.display(DisplayOptions::default()),
+            "ADDRESS    ORIGIN     MULTIADDR\n\
+             0xab       <unknown>  <unknown>\n\
+             <unknown>  2          garbage\n\
+             0xcd       0          <unknown>"
+        );
+
+        let json = peers.to_json(DisplayOptions::default());
+        let nodes = json["nodes"].as_array().unwrap();
+        assert_eq!(nodes[0]["address"], "0xab");
+        assert_eq!(nodes[0]["origin"], UNKNOWN);
+        assert_eq!(nodes[1]["address"], UNKNOWN);
+        assert_eq!(nodes[1]["multi_address"], "garbage");
+        assert_eq!(nodes[2]["multi_address"], UNKNOWN);
+    }
+
+    #[test]
+    fn test_account_redacted() {
+        let account = Account::<SmCrypto>::generate();
+        let sk = hex(account.expose_secret_key().as_slice());
+        let sk = sk.trim_start_matches("0x");
+
+        let unlocked = MultiCryptoAccount::from(account);
+        let displayed = [
+            format!("{unlocked:?}"),
+            unlocked.display(DisplayOptions::default()),
+            serde_json::to_string(&unlocked.to_json(DisplayOptions::default())).unwrap(),
+        ];
+        let maybe_locked = MaybeLocked::from(unlocked);
+        let displayed = displayed.into_iter().chain([
+            format!("{maybe_locked:?}"),
+            format!("{maybe_locked:#?}"),
+            maybe_locked.display(DisplayOptions::default()),
+        ]);
+        for s in displayed {
+            assert!(!s.contains(sk), "secret key leaked in `{s}`");
+            let addr = hex(maybe_locked.address());
+            assert!(s.to_lowercase().contains(addr.trim_start_matches("0x")));
+        }
+    }
+
+    #[test]
+    fn test_hexdump() {
+        assert_eq!(hexdump(&[]), "");
+        let data = b"\x60\x80\x60\x40hello, world!\x00\xff\x01";
+        assert_eq!(
+            hexdump(data),
+            "00000000: 6080 6040 6865 6c6c 6f2c 2077 6f72 6c64  `.`@hello, world\n\
+             00000010: 2100 ff01                                !..."
+        );
+    }
+
+    #[test]
+    fn test_colorize() {
+        let addr = format!("0x{}", "ab".repeat(20));
+        let hash = format!("0x{}", "cd".repeat(32));
+        let text =
+            format!("{{\n  \"to\": \"{addr}\",\n  \"hash\": \"{hash}\",\n  \"height\": 42\n}}");
+        assert_eq!(
+            colorize(&text),
+            format!(
+                "{{\n  \"to\": \"{ADDR_COLOR}{addr}{RESET}\",\n  \"hash\": \"{HEX_COLOR}{hash}{RESET}\",\n  \"height\": {NUMBER_COLOR}42{RESET}\n}}"
+            )
+        );
+        assert_eq!(strip_colors(&colorize(&text)), text);
+
+        for plain in ["v2", "12:00:00", "2022-01-01", "1.5", "abc_1", "0xzz", "ÿ1"] {
+            assert_eq!(colorize(plain), plain);
+        }
+        assert_eq!("never".parse::<ColorMode>().unwrap(), ColorMode::Never);
+        assert!("sometimes".parse::<ColorMode>().is_err());
+        assert!(ColorMode::Always.enabled(Some("1")));
+        assert!(!ColorMode::Never.enabled(None));
+        assert!(!ColorMode::Auto.enabled(Some("1")));
+
+        let opts = DisplayOptions {
+            color: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            json!(42).display_with(opts),
+            format!("{NUMBER_COLOR}42{RESET}")
+        );
+        assert_eq!(json!(42).display_with(DisplayOptions::default()), "42");
+    }
+
+    #[test]
+    fn test_address_checksum() {
+        let tx = Transaction {
+            to: Address::from_hex("0x5aaeb6053f3e94c9b9a09f33669435e7ef1beaed")
+                .unwrap()
+                .to_vec(),
+            ..Default::default()
+        };
+        let checksum = DisplayOptions {
+            address_checksum: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            tx.to_json(DisplayOptions::default())["to"],
+            "0x5aaeb6053f3e94c9b9a09f33669435e7ef1beaed"
+        );
+        assert_eq!(
+            tx.to_json(checksum)["to"],
+            "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed"
+        );
+        // Not an address.
+        assert_eq!(checksum.addr(&[0xab; 4]), "0xabababab");
+    }
+
+    #[test]
+    fn test_json_output_format() {
+        let text = DisplayOptions::default();
+        let json = DisplayOptions {
+            format: OutputFormat::Json,
+            ..text
+        };
+        let hash: Hash = [0xab; 32];
+        assert_eq!(hash.display_with(text), hex(&hash));
+        assert_eq!(hash.display_with(json), format!("\"{}\"", hex(&hash)));
+
+        let balance = U256::from(255);
+        assert_eq!(
+            balance.display_with(text),
+            format!("0x{}ff", "00".repeat(31))
+        );
+        assert_eq!(balance.display_with(json), "\"255\"");
+    }
+
+    #[test]
+    fn test_decode_receipt_logs() {
+        let abi = r#"[
+            {"type":"event","name":"Transfer","anonymous":false,"inputs":[
+                {"name":"from","type":"address","indexed":true},
+                {"name":"to","type":"address","indexed":true},
+                {"name":"value","type":"int256","indexed":false}]},
+            {"type":"event","name":"Note","anonymous":true,"inputs":[
+                {"name":"id","type":"uint256","indexed":true},
+                {"name":"memo","type":"string","indexed":false}]}
+        ]"#;
+        let abi = Contract::load(abi.as_bytes()).unwrap();
+        let transfer = abi.event("Transfer").unwrap();
+
+        let transfer_log = Log {
+            topics: vec![
+                transfer.signature().as_bytes().to_vec(),
+                [[0u8; 12].as_slice(), &[1; 20]].concat(),
+                [[0u8; 12].as_slice(), &[2; 20]].concat(),
+            ],
+            data: ethabi::encode(&[Token::Int((!ethabi::Uint::zero()) - 4)]),
+            ..Default::default()
+        };
+        let note_log = Log {
+            topics: vec![ethabi::encode(&[Token::Uint(7.into())])],
+            data: ethabi::encode(&[Token::String("hi".into())]),
+            ..Default::default()
+        };
+        let unknown_log = Log {
+            topics: vec![vec![0xff; 32]],
+            data: vec![],
+            ..Default::default()
+        };
+        let receipt = Receipt {
+            logs: vec![transfer_log, note_log, unknown_log],
+            ..Default::default()
+        };
+
+        let json = ReceiptDetail {
+            receipt: &receipt,
+            abi: Some(&abi),
+            contract_abis: HashMap::new(),
+            quota_limit: None,
+        }
+        .to_json(DisplayOptions::default());
+        let logs = json["logs"].as_array().unwrap();
+        assert_eq!(logs[0]["event"], "Transfer(address,address,int256)");
+        assert_eq!(logs[0]["params"]["from"], hex(&[1; 20]));
+        assert_eq!(logs[0]["params"]["value"], "-5");
+        assert_eq!(logs[1]["event"], "Note(uint256,string)");
+        assert_eq!(logs[1]["params"]["id"], "7");
+        assert_eq!(logs[1]["params"]["memo"], "hi");
+        assert!(logs[2].get("event").is_none());
+
+        // Fallback to raw display without ABI.
+        assert!(receipt.to_json(DisplayOptions::default())["logs"][0]
+            .get("event")
+            .is_none());
+    }
+}
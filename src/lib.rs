@@ -0,0 +1,31 @@
+// Copyright Rivtower Technologies LLC.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Talk to CITA-Cloud from Rust, see [`sdk::CloudClient`].
+//!
+//! The `cldi` command line is behind the default `cli` feature.
+
+pub mod abi;
+#[cfg(feature = "cli")]
+pub mod cmd;
+pub mod config;
+pub mod core;
+pub mod crypto;
+pub mod display;
+#[cfg(feature = "cli")]
+pub mod legacy;
+pub mod log;
+pub mod proto;
+pub mod sdk;
+pub mod utils;
@@ -0,0 +1,59 @@
+// Copyright Rivtower Technologies LLC.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Messages that aren't the result of a command, e.g. progress and warnings.
+//! They go to stderr so that stdout only has the results for scripts.
+
+/// Set by `--quiet` and `--verbose`, see [`Context::verbosity`].
+///
+/// [`Context::verbosity`]: crate::core::context::Context::verbosity
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Verbosity {
+    /// Only warnings.
+    Quiet,
+    /// Progress and warnings.
+    #[default]
+    Normal,
+    /// Also the gRPC requests and responses.
+    Verbose,
+}
+
+/// Progress, hidden if the verbosity is [`Verbosity::Quiet`].
+#[cfg_attr(not(feature = "cli"), allow(unused_macros))]
+macro_rules! info {
+    ($verbosity:expr, $($arg:tt)*) => {
+        if $verbosity != $crate::log::Verbosity::Quiet {
+            eprintln!($($arg)*);
+        }
+    };
+}
+
+macro_rules! warning {
+    ($($arg:tt)*) => {
+        eprintln!("warning: {}", format_args!($($arg)*));
+    };
+}
+
+/// Only shown if the verbosity is [`Verbosity::Verbose`].
+macro_rules! debug {
+    ($verbosity:expr, $($arg:tt)*) => {
+        if $verbosity == $crate::log::Verbosity::Verbose {
+            eprintln!("debug: {}", format_args!($($arg)*));
+        }
+    };
+}
+
+#[cfg(feature = "cli")]
+pub(crate) use info;
+pub(crate) use {debug, warning};
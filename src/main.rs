@@ -12,27 +12,24 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-mod cmd;
-mod config;
-mod core;
-mod crypto;
-mod display;
-mod legacy;
-mod proto;
-mod utils;
-
 use anyhow::Result;
 use rustyline::error::ReadlineError;
 use std::fs;
 
-use crate::{
-    config::{Config, CLOUD_CLI_DATA_DIR_NAME},
+use cldi::{
+    cmd,
+    config::{Config, CryptoType, CLOUD_CLI_DATA_DIR_NAME},
     core::{
-        context::Context, controller::ControllerClient, evm::EvmClient, executor::ExecutorClient,
-        wallet::Account, wallet::Wallet,
+        client::ClientPool,
+        context::{Canceled, Context},
+        controller::ControllerClient,
+        evm::EvmClient,
+        executor::ExecutorClient,
+        wallet::{Account, MultiCryptoAccount, Wallet},
     },
-    crypto::SmCrypto,
-    utils::init_local_utc_offset,
+    crypto::{EthCrypto, SmCrypto},
+    legacy,
+    utils::{expand_history, init_local_utc_offset, restrict_permissions},
 };
 
 fn main() -> Result<()> {
@@ -49,8 +46,8 @@ fn main() -> Result<()> {
     if is_legacy {
         const LEGACY_FILE_BACKUP_NAME: &str = ".cloud-cli-v0.2.0-legacy";
 
-        println!("Migrating cloud-cli v0.2.0 accounts..");
-        println!(
+        eprintln!("Migrating cloud-cli v0.2.0 accounts..");
+        eprintln!(
             "Data backup can be found in `{}/{}`.",
             CLOUD_CLI_DATA_DIR_NAME, LEGACY_FILE_BACKUP_NAME
         );
@@ -85,40 +82,98 @@ fn main() -> Result<()> {
             wallet.save(name, account)?;
         }
 
-        println!("Successfully migrated.");
+        eprintln!("Successfully migrated.");
     }
 
-    let config = Config::open(data_dir)?;
-    let mut ctx: Context<ControllerClient, ExecutorClient, EvmClient> =
-        Context::from_config(config)?;
+    let cldi = cmd::cldi_cmd();
+    let m = cldi.get_matches();
+
+    let mut config = Config::open(data_dir)?;
+    // The runtime is built with the context, so it's applied here instead of in the `cldi` handler.
+    if let Some(n) = m.value_of("worker-threads") {
+        config.worker_threads = Some(n.parse()?);
+    }
+    let mut ctx: Context<
+        ClientPool<ControllerClient>,
+        ClientPool<ExecutorClient>,
+        ClientPool<EvmClient>,
+    > = Context::from_config(config)?;
 
     if is_init {
-        let default_account = Account::<SmCrypto>::generate();
+        // Respect the crypto type given at the first run(e.g. `cldi --crypto ETH`),
+        // so that users of ETH chains won't end up with a SM default account.
+        if let Some(crypto_type) = m.value_of("crypto-type") {
+            let crypto_type: CryptoType = crypto_type.parse()?;
+            let default_context = ctx.config.default_context.clone();
+            if let Some(setting) = ctx.config.context_settings.get_mut(&default_context) {
+                setting.crypto_type = crypto_type;
+                ctx.config.save()?;
+            }
+            ctx.current_setting.crypto_type = crypto_type;
+        }
+
+        let default_account: MultiCryptoAccount = match ctx.current_setting.crypto_type {
+            CryptoType::Sm => Account::<SmCrypto>::generate().into(),
+            CryptoType::Eth => Account::<EthCrypto>::generate().into(),
+        };
         ctx.wallet
             .save("default".into(), default_account)
             .expect("cannot save default account");
     }
 
-    let cldi = cmd::cldi_cmd();
-    let m = cldi.get_matches();
-
-    cldi.exec_with(&m, &mut ctx).map_err(|e| {
+    cldi.exec_with(&m, &mut ctx).inspect_err(|e| {
         if let Some(e) = e.downcast_ref::<clap::Error>() {
             e.exit();
         }
-        e
     })?;
 
     // Enter interactive mode if no subcommand provided
     if m.subcommand().is_none() {
+        // Ctrl-C cancels the running command and returns to the prompt.
+        ctx.rt.set_scoped(true);
         // TODO: put editor into context
+        let history_file = ctx.config.history_file();
+        match ctx.editor.load_history(&history_file) {
+            // It's fine if there is no history yet.
+            Err(ReadlineError::Io(e)) if e.kind() == std::io::ErrorKind::NotFound => (),
+            Err(e) => eprintln!(
+                "warning: cannot load history from `{}`: {}",
+                history_file.display(),
+                e
+            ),
+            Ok(()) => (),
+        }
+        // The history file doesn't have the lines with secrets, so keep the last one here for `!!`.
+        let mut previous: Option<String> = None;
         loop {
-            let line = ctx.editor.readline("cldi> ");
+            let prompt = format!("cldi({})> ", ctx.current_setting.account_name);
+            let line = ctx.editor.readline(&prompt);
             match line {
                 Ok(line) => {
-                    ctx.editor.add_history_entry(&line);
+                    let line = match expand_history(&line, previous.as_deref()) {
+                        Ok(expanded) if expanded != line => {
+                            println!("{}", expanded);
+                            expanded
+                        }
+                        Ok(_) => line,
+                        Err(e) => {
+                            println!("{}", e);
+                            continue;
+                        }
+                    };
+                    previous = Some(line.clone());
 
-                    let args = match shell_words::split(&line) {
+                    let args = shell_words::split(&line);
+                    // Lines that fail to parse are checked word by word, and kept for fixing if clean.
+                    let words = match &args {
+                        Ok(args) => args.clone(),
+                        Err(_) => line.split_whitespace().map(String::from).collect(),
+                    };
+                    if !cldi.contains_secret(&words) {
+                        ctx.editor.add_history_entry(&line);
+                    }
+
+                    let args = match args {
                         Ok(args) => args,
                         Err(e) => {
                             println!("parse error: `{}`", e);
@@ -126,8 +181,10 @@ fn main() -> Result<()> {
                         }
                     };
                     let input = std::iter::once(cldi.get_name().into()).chain(args);
-                    if let Err(e) = cldi.exec_from(input, &mut ctx) {
-                        println!("{:?}", e);
+                    match cldi.exec_from(input, &mut ctx) {
+                        Err(e) if e.downcast_ref::<Canceled>().is_some() => println!("canceled"),
+                        Err(e) => println!("{:?}", e),
+                        Ok(()) => (),
                     }
                 }
                 Err(ReadlineError::Eof) => break,
@@ -138,6 +195,15 @@ fn main() -> Result<()> {
                 }
             }
         }
+        if let Err(e) = ctx.editor.save_history(&history_file) {
+            println!("cannot save history to `{}`: {}", history_file.display(), e);
+        } else if let Err(e) = restrict_permissions(&history_file) {
+            println!(
+                "cannot restrict the permissions of `{}`: {}",
+                history_file.display(),
+                e
+            );
+        }
     }
 
     Ok(())
@@ -14,7 +14,9 @@
 
 // CITA-Cloud v6.3.0 proto
 // https://github.com/cita-cloud/cita_cloud_proto/tree/v6.3.0
+// with local additions, which are marked in the proto files.
 
+#[allow(dead_code)]
 pub mod common {
     tonic::include_proto!("common");
 }
@@ -0,0 +1,341 @@
+// Copyright Rivtower Technologies LLC.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! An async client for using this crate as a library, without the CLI.
+//!
+//! ```no_run
+//! use cldi::{crypto::SmCrypto, sdk::*};
+//!
+//! # async fn run() -> anyhow::Result<()> {
+//! let client = CloudClient::<SmCrypto>::connect("localhost:50004", "localhost:50002", &ClientSetting::default())
+//!     .await?
+//!     .with_signer(Account::generate());
+//! let height = client.get_block_number(false).await?;
+//! let block = client.get_block_by_number(height).await?;
+//! let tx_hash = client.send(vec![0; 20], vec![], vec![0; 32], 200_000).await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::time::Duration;
+
+use anyhow::{Context as _, Result};
+use tonic::transport::Channel;
+
+pub use crate::core::{
+    admin::AdminBehaviour,
+    client::{ClientSetting, RequestPolicy},
+    controller::{
+        ControllerBehaviour, ControllerBehaviourExt, SignerBehaviour, TransactionSenderBehaviour,
+    },
+    error::{SdkError, SdkResult},
+    evm::{wait_receipt, EvmBehaviour},
+    executor::ExecutorBehaviour,
+    signer::{ExternalSigner, Signer},
+    wallet::Account,
+};
+use crate::{
+    config::DEFAULT_VALID_UNTIL_OFFSET,
+    core::{
+        client::GrpcClientBehaviour, controller::ControllerClient, evm::EvmClient,
+        executor::ExecutorClient,
+    },
+    crypto::{Address, Crypto, Hash},
+    proto::{
+        blockchain::{CompactBlock, RawTransaction},
+        common::{Hash as CloudHash, TotalNodeInfo},
+        controller::SystemConfig,
+        evm::{Balance, ByteAbi, ByteCode, ByteQuota, Nonce, Receipt},
+        executor::{CallRequest, CallResponse},
+    },
+    utils::{get_valid_until_block, Position},
+};
+
+/// A client of a CITA-Cloud node, with an optional signer for sending txs.
+///
+/// It implements the behaviour traits, so all their methods, e.g. `get_block_by_number` and
+/// `send_tx`, are available on it. Import them from this module to use them.
+///
+/// The connections and requests are configured by the given [`ClientSetting`], except its
+/// `pool_size`, since each service uses one connection here.
+pub struct CloudClient<C: Crypto> {
+    controller: ControllerClient,
+    executor: ExecutorClient,
+    evm: EvmClient,
+    signer: Option<Account<C>>,
+}
+
+impl<C: Crypto> CloudClient<C> {
+    /// Connect to the controller and the executor, evm is served by the executor.
+    pub async fn connect(
+        controller_addr: &str,
+        executor_addr: &str,
+        setting: &ClientSetting,
+    ) -> Result<Self> {
+        let controller =
+            <ControllerClient as GrpcClientBehaviour>::connect(controller_addr, setting)
+                .await
+                .context("failed to connect to controller")?;
+        let executor = <ExecutorClient as GrpcClientBehaviour>::connect(executor_addr, setting)
+            .await
+            .context("failed to connect to executor")?;
+        let evm = <EvmClient as GrpcClientBehaviour>::connect(executor_addr, setting)
+            .await
+            .context("failed to connect to evm")?;
+        Ok(Self::from_clients(controller, executor, evm))
+    }
+
+    /// Same as [`connect`], but the connections are established on the first request.
+    ///
+    /// It must be called in a tokio runtime.
+    ///
+    /// [`connect`]: CloudClient::connect
+    pub fn connect_lazy(
+        controller_addr: &str,
+        executor_addr: &str,
+        setting: &ClientSetting,
+    ) -> Result<Self> {
+        let controller = ControllerClient::connect_lazy(controller_addr, setting)?;
+        let executor = ExecutorClient::connect_lazy(executor_addr, setting)?;
+        let evm = EvmClient::connect_lazy(executor_addr, setting)?;
+        Ok(Self::from_clients(controller, executor, evm))
+    }
+
+    pub fn from_channels(controller: Channel, executor: Channel) -> Self {
+        Self::from_clients(
+            ControllerClient::from_channel(controller),
+            ExecutorClient::from_channel(executor.clone()),
+            EvmClient::from_channel(executor),
+        )
+    }
+
+    pub fn from_clients(
+        controller: ControllerClient,
+        executor: ExecutorClient,
+        evm: EvmClient,
+    ) -> Self {
+        Self {
+            controller,
+            executor,
+            evm,
+            signer: None,
+        }
+    }
+
+    /// Use `signer` for [`send`](CloudClient::send).
+    pub fn with_signer(mut self, signer: Account<C>) -> Self {
+        self.signer = Some(signer);
+        self
+    }
+
+    pub fn signer(&self) -> Option<&Account<C>> {
+        self.signer.as_ref()
+    }
+
+    /// Change the timeout, retries and dry run of the following requests.
+    pub fn set_request_policy(&mut self, policy: RequestPolicy) {
+        self.controller.set_policy(policy);
+        self.executor.set_policy(policy);
+        self.evm.set_policy(policy);
+    }
+
+    pub fn controller(&self) -> &ControllerClient {
+        &self.controller
+    }
+
+    pub fn executor(&self) -> &ExecutorClient {
+        &self.executor
+    }
+
+    pub fn evm(&self) -> &EvmClient {
+        &self.evm
+    }
+
+    /// Sign the tx with the client's signer and send it, it's valid for
+    /// [`DEFAULT_VALID_UNTIL_OFFSET`] blocks from the current height with a random nonce.
+    ///
+    /// Use [`send_tx_with_nonce`](TransactionSenderBehaviour::send_tx_with_nonce) for more control.
+    pub async fn send(
+        &self,
+        to: Vec<u8>,
+        data: Vec<u8>,
+        value: Vec<u8>,
+        quota: u64,
+    ) -> Result<Hash> {
+        let signer = self
+            .signer
+            .as_ref()
+            .context("no signer to send the tx, see `CloudClient::with_signer`")?;
+        let valid_until_block =
+            get_valid_until_block(self, Position::FromCurrent(DEFAULT_VALID_UNTIL_OFFSET)).await?;
+        self.send_tx(signer, to, data, value, quota, valid_until_block)
+            .await
+    }
+
+    /// [`send`](CloudClient::send) and wait for the receipt, `None` if it isn't there before `timeout`.
+    pub async fn send_and_wait(
+        &self,
+        to: Vec<u8>,
+        data: Vec<u8>,
+        value: Vec<u8>,
+        quota: u64,
+        timeout: Duration,
+    ) -> Result<Option<Receipt>> {
+        let tx_hash = self.send(to, data, value, quota).await?;
+        Ok(wait_receipt(self, tx_hash, timeout).await?)
+    }
+}
+
+impl<C: Crypto> Clone for CloudClient<C>
+where
+    Account<C>: Clone,
+{
+    fn clone(&self) -> Self {
+        Self {
+            controller: self.controller.clone(),
+            executor: self.executor.clone(),
+            evm: self.evm.clone(),
+            signer: self.signer.clone(),
+        }
+    }
+}
+
+#[tonic::async_trait]
+impl<C: Crypto> ControllerBehaviour for CloudClient<C> {
+    async fn send_raw(&self, raw: RawTransaction) -> SdkResult<Hash> {
+        self.controller.send_raw(raw).await
+    }
+
+    async fn get_version(&self) -> SdkResult<String> {
+        self.controller.get_version().await
+    }
+
+    async fn get_system_config(&self) -> SdkResult<SystemConfig> {
+        self.controller.get_system_config().await
+    }
+
+    async fn get_block_number(&self, for_pending: bool) -> SdkResult<u64> {
+        self.controller.get_block_number(for_pending).await
+    }
+
+    async fn get_block_hash(&self, block_number: u64) -> SdkResult<Hash> {
+        self.controller.get_block_hash(block_number).await
+    }
+
+    async fn get_block_by_number(&self, block_number: u64) -> SdkResult<CompactBlock> {
+        self.controller.get_block_by_number(block_number).await
+    }
+
+    async fn get_block_by_hash(&self, hash: Hash) -> SdkResult<CompactBlock> {
+        self.controller.get_block_by_hash(hash).await
+    }
+
+    async fn get_tx(&self, tx_hash: Hash) -> SdkResult<RawTransaction> {
+        self.controller.get_tx(tx_hash).await
+    }
+
+    async fn get_tx_index(&self, tx_hash: Hash) -> SdkResult<u64> {
+        self.controller.get_tx_index(tx_hash).await
+    }
+
+    async fn get_tx_block_number(&self, tx_hash: Hash) -> SdkResult<u64> {
+        self.controller.get_tx_block_number(tx_hash).await
+    }
+
+    async fn get_peer_count(&self) -> SdkResult<u64> {
+        self.controller.get_peer_count().await
+    }
+
+    async fn get_peers_info(&self) -> SdkResult<TotalNodeInfo> {
+        self.controller.get_peers_info().await
+    }
+
+    async fn add_node(&self, multiaddr: String) -> SdkResult<u32> {
+        self.controller.add_node(multiaddr).await
+    }
+}
+
+#[tonic::async_trait]
+impl<C: Crypto> ExecutorBehaviour for CloudClient<C> {
+    async fn call(&self, from: Address, to: Address, data: Vec<u8>) -> SdkResult<CallResponse> {
+        self.executor.call(from, to, data).await
+    }
+}
+
+#[tonic::async_trait]
+impl<C: Crypto> EvmBehaviour for CloudClient<C> {
+    async fn get_receipt(&self, hash: Hash) -> SdkResult<Receipt> {
+        self.evm.get_receipt(hash).await
+    }
+
+    async fn get_code(&self, addr: Address) -> SdkResult<ByteCode> {
+        self.evm.get_code(addr).await
+    }
+
+    async fn get_balance(&self, addr: Address) -> SdkResult<Balance> {
+        self.evm.get_balance(addr).await
+    }
+
+    async fn get_tx_count(&self, addr: Address) -> SdkResult<Nonce> {
+        self.evm.get_tx_count(addr).await
+    }
+
+    async fn get_abi(&self, addr: Address) -> SdkResult<ByteAbi> {
+        self.evm.get_abi(addr).await
+    }
+
+    async fn get_storage_at(&self, addr: Address, position: Hash) -> SdkResult<CloudHash> {
+        self.evm.get_storage_at(addr, position).await
+    }
+
+    async fn estimate_quota(&self, call: CallRequest) -> SdkResult<ByteQuota> {
+        self.evm.estimate_quota(call).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::SmCrypto;
+
+    #[test]
+    fn test_cloud_client() {
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        // Nothing is listening there.
+        let addr = rt.block_on(async {
+            let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+            listener.local_addr().unwrap().to_string()
+        });
+        let client = rt
+            .block_on(async {
+                CloudClient::<SmCrypto>::connect_lazy(&addr, &addr, &ClientSetting::default())
+            })
+            .unwrap();
+
+        let res = rt.block_on(client.get_block_number(false));
+        assert!(matches!(res, Err(SdkError::Transport { .. })), "{res:?}");
+
+        let e = rt
+            .block_on(client.send(vec![0; 20], vec![], vec![0; 32], 200_000))
+            .unwrap_err();
+        assert!(e.to_string().contains("no signer"), "{e}");
+
+        let client = client.with_signer(Account::generate());
+        assert!(client.signer().is_some());
+        // Fails on getting the current height, not on signing.
+        assert!(rt
+            .block_on(client.send(vec![0; 20], vec![], vec![0; 32], 200_000))
+            .is_err());
+    }
+}
@@ -12,62 +12,354 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use std::io::Write;
+use std::collections::BTreeMap;
+use std::io::{Read, Write};
+use std::net::{Ipv4Addr, Ipv6Addr};
+use std::ops::RangeInclusive;
 use std::path::Path;
 
 use anyhow::anyhow;
+use anyhow::bail;
+use anyhow::ensure;
 use anyhow::Context;
 use anyhow::Result;
 use crossbeam::atomic::AtomicCell;
+use serde_json::Value as Json;
 use tempfile::NamedTempFile;
+use tentacle_multiaddr::Multiaddr;
 use time::UtcOffset;
 
 use crate::{
     core::controller::ControllerBehaviour,
-    crypto::{Address, ArrayLike, Crypto, Hash},
+    crypto::{checksum_address, Address, ArrayLike, Crypto, Hash, ADDR_BYTES_LEN, HASH_BYTES_LEN},
+    display::{Display, DisplayOptions},
+    proto::blockchain::{RawTransaction, Witness},
 };
 
 // Use an Option because UtcOffset::from_hms returns a Result
 // that cannot be unwraped in constant expr...
 static LOCAL_UTC_OFFSET: AtomicCell<Option<UtcOffset>> = AtomicCell::new(None);
 
+/// Whether the input is meant to be a hex address rather than an alias.
+fn is_hex_addr(s: &str) -> bool {
+    s.starts_with("0x") || s.starts_with("0X") || s.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+/// Alias names are restricted so that they can't be confused with hex addresses.
+pub fn validate_alias(alias: &str) -> Result<()> {
+    ensure!(
+        !alias.is_empty()
+            && alias
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.')),
+        "invalid alias `{alias}`, only letters, digits, `_`, `-` and `.` are allowed"
+    );
+    ensure!(
+        !is_hex_addr(alias),
+        "invalid alias `{alias}`, it cannot start with `0x` or consist of only hex digits"
+    );
+    Ok(())
+}
+
+/// Validate the input of an address arg, which is a hex address, `@<alias>` or a bare alias.
+/// Aliases are resolved by [`Context::parse_addr`] since the address book is in the context.
+///
+/// [`Context::parse_addr`]: crate::core::context::Context::parse_addr
+pub fn validate_addr(s: &str) -> Result<()> {
+    match s.strip_prefix('@') {
+        Some(alias) => validate_alias(alias),
+        None if is_hex_addr(s) => parse_addr(s).map(|_| ()),
+        None => validate_alias(s),
+    }
+}
+
+/// Resolve the address of an alias from the address book, see [`validate_addr`] for the input.
+/// It returns `None` if the input is a hex address.
+pub fn resolve_alias(book: &BTreeMap<String, String>, s: &str) -> Result<Option<Address>> {
+    let alias = match s.strip_prefix('@') {
+        Some(alias) => alias,
+        None if is_hex_addr(s) => return Ok(None),
+        None => s,
+    };
+    let addr = book
+        .get(alias)
+        .ok_or_else(|| anyhow!("no such alias '{alias}'"))?;
+    let addr = Address::from_hex(addr)
+        .with_context(|| format!("invalid address of alias `{alias}` in address book"))?;
+    Ok(Some(addr))
+}
+
+/// Parse address from hex, in any case. The EIP-55 checksum is checked by [`Context::parse_addr`]
+/// on ETH chains.
+///
+/// Aliases aren't accepted here, see [`resolve_alias`].
+///
+/// [`Context::parse_addr`]: crate::core::context::Context::parse_addr
 pub fn parse_addr(s: &str) -> Result<Address> {
-    let input = parse_data(s)?;
-    Address::try_from_slice(&input)
+    Address::from_hex(s).map_err(|e| explain_len(s, "an address (20 bytes)", e))
+}
+
+/// Mixed-case input must pass the EIP-55 checksum, all-lowercase or all-uppercase input is
+/// accepted as is.
+pub fn check_address_checksum(s: &str, addr: &Address) -> Result<()> {
+    let digits = s
+        .strip_prefix("0x")
+        .or_else(|| s.strip_prefix("0X"))
+        .unwrap_or(s);
+    let is_mixed_case = digits.chars().any(|c| c.is_ascii_lowercase())
+        && digits.chars().any(|c| c.is_ascii_uppercase());
+    if is_mixed_case {
+        let expected = checksum_address(addr);
+        ensure!(
+            digits == &expected[2..],
+            "invalid address checksum, expected `{expected}`"
+        );
+    }
+    Ok(())
+}
+
+/// Display address in lowercase hex, e.g. in messages. Outputs are shown with
+/// [`DisplayOptions::addr`] instead, which checksums them on ETH chains.
+///
+/// [`DisplayOptions::addr`]: crate::display::DisplayOptions::addr
+pub fn display_addr(addr: &[u8]) -> String {
+    hex(addr)
 }
 
 pub fn parse_pk<C: Crypto>(s: &str) -> Result<C::PublicKey> {
-    let input = parse_data(s)?;
-    C::PublicKey::try_from_slice(&input)
+    C::PublicKey::from_hex(s)
 }
 
 pub fn parse_sk<C: Crypto>(s: &str) -> Result<C::SecretKey> {
-    let input = parse_data(s)?;
-    C::SecretKey::try_from_slice(&input)
+    C::SecretKey::from_hex(s)
+}
+
+pub fn parse_mnemonic(s: &str) -> Result<bip39::Mnemonic> {
+    bip39::Mnemonic::parse(s).map_err(|e| anyhow!("invalid mnemonic: {e}"))
 }
 
 pub fn parse_hash(s: &str) -> Result<Hash> {
-    let input = parse_data(s)?;
-    Hash::try_from_slice(&input)
+    Hash::from_hex(s).map_err(|e| explain_len(s, "a hash (32 bytes)", e))
 }
 
-// TODO: Should we do the padding?
-pub fn parse_value(s: &str) -> Result<[u8; 32]> {
-    let s = remove_0x(s);
-    if s.len() > 64 {
-        return Err(anyhow!("can't parse value, the given str is too long"));
+/// Explain a mismatched length if the input looks like another kind of value, e.g. a tx hash
+/// pasted as an address. The lengths are the same for SM and ETH.
+fn explain_len(s: &str, expected: &str, e: anyhow::Error) -> anyhow::Error {
+    let looks_like = match parse_data(s).map(|bytes| bytes.len()) {
+        Ok(ADDR_BYTES_LEN) => "an address (20 bytes)",
+        Ok(HASH_BYTES_LEN) => "a hash (32 bytes)",
+        Ok(64) => "a public key (64 bytes)",
+        _ => return e,
+    };
+    anyhow!("`{s}` looks like {looks_like}, but {expected} is expected")
+}
+
+/// A 256 bits unsigned integer, e.g. tx value, balance and nonce, stored as 32 bytes big-endian.
+///
+/// It's displayed in decimal, use [`U256::to_hex`] for hex.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct U256(pub [u8; 32]);
+
+impl U256 {
+    pub const ZERO: Self = Self([0; 32]);
+
+    /// From a big-endian unsigned integer of any length, e.g. what the node returns.
+    /// Leading zeros are trimmed, the rest must fit in 32 bytes.
+    pub fn from_be_slice(bytes: &[u8]) -> Result<Self> {
+        let start = bytes.iter().position(|&b| b != 0).unwrap_or(bytes.len());
+        let significant = &bytes[start..];
+        ensure!(
+            significant.len() <= 32,
+            "`{}` overflows 256 bits",
+            hex(significant)
+        );
+        let mut buf = [0; 32];
+        buf[32 - significant.len()..].copy_from_slice(significant);
+        Ok(Self(buf))
+    }
+
+    /// From hex with an optional `0x` prefix, leading zeros don't count towards the 64 digits limit.
+    pub fn from_hex(s: &str) -> Result<Self> {
+        let digits = remove_0x(s);
+        ensure!(!digits.is_empty(), "empty hex value `{s}`");
+        let significant = digits.trim_start_matches('0');
+        ensure!(
+            significant.len() <= 64,
+            "hex value `{s}` overflows 256 bits"
+        );
+        let bytes = hex::decode(format!("{significant:0>64}"))
+            .context(format!("invalid hex value `{s}`"))?;
+        Ok(Self(bytes.try_into().unwrap()))
+    }
+
+    pub fn from_decimal(s: &str) -> Result<Self> {
+        ensure!(!s.is_empty(), "empty decimal value");
+        let mut bytes = [0u8; 32];
+        for c in s.chars() {
+            let d = c
+                .to_digit(10)
+                .ok_or_else(|| anyhow!("invalid decimal digit `{c}` in value `{s}`"))?;
+            // bytes = bytes * 10 + d
+            let mut carry = d;
+            for b in bytes.iter_mut().rev() {
+                let v = *b as u32 * 10 + carry;
+                *b = v as u8;
+                carry = v >> 8;
+            }
+            ensure!(carry == 0, "value `{s}` overflows 256 bits");
+        }
+        Ok(Self(bytes))
+    }
+
+    /// From a decimal amount in a unit of `decimals`, e.g. `1.5` with 18 decimals for `1.5ether`.
+    pub fn from_units(amount: &str, decimals: usize) -> Result<Self> {
+        let (int_part, frac_part) = amount.split_once('.').unwrap_or((amount, ""));
+        ensure!(
+            !int_part.is_empty() || !frac_part.is_empty(),
+            "invalid value `{amount}`"
+        );
+        ensure!(
+            frac_part.len() <= decimals,
+            "too many fractional digits in value `{amount}`, at most {decimals} allowed"
+        );
+        Self::from_decimal(&format!("{int_part}{frac_part:0<decimals$}"))
+    }
+
+    pub fn to_be_bytes(self) -> [u8; 32] {
+        self.0
+    }
+
+    pub fn to_vec(self) -> Vec<u8> {
+        self.0.to_vec()
+    }
+
+    pub fn to_decimal(self) -> String {
+        be_bytes_to_decimal(&self.0)
+    }
+
+    /// Hex of the 32 bytes with `0x` prefix.
+    pub fn to_hex(self) -> String {
+        hex(&self.0)
+    }
+
+    pub fn to_u64(self) -> Result<u64> {
+        be_bytes_to_u64(&self.0)
+    }
+}
+
+impl From<u64> for U256 {
+    fn from(v: u64) -> Self {
+        let mut buf = [0; 32];
+        buf[24..].copy_from_slice(&v.to_be_bytes());
+        Self(buf)
+    }
+}
+
+impl std::fmt::Display for U256 {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.to_decimal())
+    }
+}
+
+impl std::str::FromStr for U256 {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        parse_value(s)
+    }
+}
+
+/// Parse tx value.
+///
+/// It can be hex with `0x` prefix, or decimal with an optional unit of `wei`, `gwei` or `ether`,
+/// e.g. `1.5ether`, `200gwei`. Decimal without unit is in wei.
+pub fn parse_value(s: &str) -> Result<U256> {
+    if s.starts_with("0x") {
+        return U256::from_hex(s);
+    }
+
+    let lower = s.trim().to_ascii_lowercase();
+    let (amount, decimals) = [("gwei", 9), ("wei", 0), ("ether", 18)]
+        .into_iter()
+        .find_map(|(unit, decimals)| Some((lower.strip_suffix(unit)?, decimals)))
+        .unwrap_or((&lower, 0));
+    U256::from_units(amount.trim_end(), decimals)
+}
+
+/// Parse a contract storage slot, hex with `0x` prefix or decimal.
+pub fn parse_slot(s: &str) -> Result<U256> {
+    if s.starts_with("0x") {
+        U256::from_hex(s)
+    } else {
+        U256::from_decimal(s)
     }
-    // padding 0 to 32 bytes
-    let padded = format!("{:0>64}", s);
-    hex::decode(&padded)
-        .map(|v| v.try_into().unwrap())
-        .map_err(|e| anyhow!("invalid value: {e}"))
 }
 
 pub fn parse_data(s: &str) -> Result<Vec<u8>> {
     hex::decode(remove_0x(s)).context("invalid hex input")
 }
 
+/// Read the input from `@<path>` or `-` for stdin. Returns `None` if it's neither.
+pub fn read_input(s: &str) -> Result<Option<Vec<u8>>> {
+    if s == "-" {
+        let mut buf = vec![];
+        std::io::stdin()
+            .read_to_end(&mut buf)
+            .context("cannot read from stdin")?;
+        Ok(Some(buf))
+    } else if let Some(path) = s.strip_prefix('@') {
+        let buf = std::fs::read(path).with_context(|| format!("cannot read from `{path}`"))?;
+        Ok(Some(buf))
+    } else {
+        Ok(None)
+    }
+}
+
+/// Like [`parse_data`], but also accepts hex from `@<path>` or `-` for stdin.
+pub fn parse_data_input(s: &str) -> Result<Vec<u8>> {
+    match read_input(s)? {
+        Some(input) => {
+            let len = input.len();
+            std::str::from_utf8(&input)
+                .map_err(anyhow::Error::from)
+                .and_then(|hex| parse_data(hex.trim()))
+                .with_context(|| format!("invalid hex data from `{s}` ({len} bytes)"))
+        }
+        None => parse_data(s),
+    }
+}
+
+/// Check that it's a valid Solidity ABI JSON array, and minify it to save space on chain.
+pub fn canonical_abi(abi: &[u8]) -> Result<Vec<u8>> {
+    let json: serde_json::Value = serde_json::from_slice(abi).context("ABI is not valid JSON")?;
+    ensure!(json.is_array(), "ABI must be a JSON array");
+    ethabi::Contract::load(abi).context("invalid ABI")?;
+    Ok(serde_json::to_vec(&json)?)
+}
+
+/// Read raw binary data from `@<path>` or `-` for stdin.
+pub fn parse_binary_input(s: &str) -> Result<Vec<u8>> {
+    read_input(s)?.ok_or_else(|| anyhow!("binary data must be given as `@<path>` or `-`"))
+}
+
+/// Validator for [`parse_data_input`], it won't read the input.
+pub fn validate_data_input(s: &str) -> Result<()> {
+    if s == "-" || s.starts_with('@') {
+        Ok(())
+    } else {
+        parse_data(s).map(drop)
+    }
+}
+
+/// Validator for [`parse_binary_input`], it won't read the input.
+pub fn validate_binary_input(s: &str) -> Result<()> {
+    ensure!(
+        s == "-" || s.starts_with('@'),
+        "binary data must be given as `@<path>` or `-`"
+    );
+    Ok(())
+}
+
 #[derive(Debug, Clone, Copy)]
 pub enum Position {
     // v
@@ -122,6 +414,56 @@ pub fn parse_position(s: &str) -> Result<Position> {
     Ok(pos)
 }
 
+/// Parse an inclusive height range like `100-200`.
+pub fn parse_height_range(s: &str) -> Result<RangeInclusive<u64>> {
+    let (from, to) = s
+        .split_once('-')
+        .ok_or_else(|| anyhow!("invalid range `{s}`, expected `<from>-<to>`"))?;
+    let from = from.trim().parse::<u64>().context("invalid range start")?;
+    let to = to.trim().parse::<u64>().context("invalid range end")?;
+    ensure!(
+        from <= to,
+        "range start `{from}` is greater than its end `{to}`"
+    );
+    Ok(from..=to)
+}
+
+/// How a block is referred to by user.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BlockId {
+    Number(u64),
+    Hash(Hash),
+    Latest,
+    Pending,
+}
+
+/// Parse block id from `latest`, `pending`, decimal height, `0x` prefixed hex height,
+/// or `0x` prefixed block hash. A hex str of the hash's length is treated as hash.
+pub fn parse_block_id(s: &str) -> Result<BlockId> {
+    let id = match s {
+        "latest" => BlockId::Latest,
+        "pending" => BlockId::Pending,
+        s if s.starts_with("0x") => {
+            let hex_str = remove_0x(s);
+            if hex_str.len() == HASH_BYTES_LEN * 2 {
+                BlockId::Hash(parse_hash(s)?)
+            } else {
+                let height = u64::from_str_radix(hex_str, 16)
+                    .context("cannot parse hex block number, or it's an invalid block hash")?;
+                BlockId::Number(height)
+            }
+        }
+        s => {
+            let height = s.parse::<u64>().context(
+                "cannot parse block number, expected `latest`, `pending`, decimal or `0x` prefixed hex number, or block hash",
+            )?;
+            BlockId::Number(height)
+        }
+    };
+
+    Ok(id)
+}
+
 pub async fn get_block_height_at<Co: ControllerBehaviour>(
     controller: &Co,
     pos: Position,
@@ -134,10 +476,166 @@ pub async fn get_block_height_at<Co: ControllerBehaviour>(
     }
 }
 
+/// Like [`get_block_height_at`], but also ensure the height is above the current one,
+/// otherwise the tx would be rejected.
+pub async fn get_valid_until_block<Co: ControllerBehaviour>(
+    controller: &Co,
+    pos: Position,
+) -> Result<u64> {
+    let current = controller.get_block_number(false).await?;
+    valid_until_block_at(pos, current)
+}
+
+/// The `valid_until_block` at `pos` with the `current` height, see [`get_valid_until_block`].
+pub fn valid_until_block_at(pos: Position, current: u64) -> Result<u64> {
+    let valid_until_block = pos.with_current(current);
+    ensure!(
+        valid_until_block > current,
+        "valid_until_block `{valid_until_block}` must be above the current height `{current}`"
+    );
+    Ok(valid_until_block)
+}
+
+/// Parse the tx nonce, `rand` means a random one and is returned as `None`.
+pub fn parse_nonce(s: &str) -> Result<Option<String>> {
+    if s == "rand" {
+        return Ok(None);
+    }
+    // The length limit is from the controller.
+    ensure!(
+        !s.is_empty() && s.len() <= 128,
+        "nonce must be non-empty and at most 128 bytes"
+    );
+    Ok(Some(s.to_string()))
+}
+
+/// How the nonces of normal txs are chosen when sending many of them from one account.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NonceStrategy {
+    /// Fetch the account's nonce from the node for each tx.
+    Fetch,
+    /// Fetch the starting nonce once, then increment it locally for each tx in this session.
+    /// It assumes no other sender uses the account concurrently.
+    LocalIncrement,
+}
+
+pub fn parse_nonce_strategy(s: &str) -> Result<NonceStrategy> {
+    match s {
+        "fetch" => Ok(NonceStrategy::Fetch),
+        "local-increment" => Ok(NonceStrategy::LocalIncrement),
+        _ => bail!("unknown nonce strategy `{s}`, expected `fetch` or `local-increment`"),
+    }
+}
+
+fn is_valid_domain(s: &str) -> bool {
+    s.len() <= 253
+        && s.split('.').all(|label| {
+            !label.is_empty()
+                && label.len() <= 63
+                && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+                && !label.starts_with('-')
+                && !label.ends_with('-')
+        })
+}
+
+/// Parse the multiaddr of a node, e.g. `/ip4/1.2.3.4/tcp/40000` or `/dns4/host/tcp/40000/tls/host`.
+///
+/// It must be a host in `ip4`, `ip6`, `dns4` or `dns6` followed by a `tcp` port, and an optional
+/// `tls` domain name. The error points at the malformed component.
+pub fn parse_multiaddr(s: &str) -> Result<Multiaddr> {
+    let err = |msg: String| anyhow!("invalid multiaddr `{s}`: {msg}");
+    let parts: Vec<&str> = s
+        .strip_prefix('/')
+        .ok_or_else(|| err("it must start with `/`".into()))?
+        .split('/')
+        .collect();
+    let component = |i: usize, what: &str| match parts.get(i) {
+        Some(p) if !p.is_empty() => Ok(*p),
+        _ => Err(err(format!("missing {what}"))),
+    };
+
+    let (ptcl, host) = (component(0, "protocol")?, component(1, "host")?);
+    match ptcl {
+        "ip4" => {
+            host.parse::<Ipv4Addr>()
+                .map_err(|_| err(format!("invalid ip4 address `{host}`")))?;
+        }
+        "ip6" => {
+            host.parse::<Ipv6Addr>()
+                .map_err(|_| err(format!("invalid ip6 address `{host}`")))?;
+        }
+        "dns4" | "dns6" => ensure!(
+            is_valid_domain(host),
+            err(format!("invalid domain name `{host}`"))
+        ),
+        unknown => bail!(err(format!(
+            "unsupported protocol `{unknown}`, expected `ip4`, `ip6`, `dns4` or `dns6`"
+        ))),
+    }
+    match component(2, "`tcp` after the host")? {
+        "tcp" => (),
+        other => bail!(err(format!(
+            "expected `tcp` after the host, found `{other}`"
+        ))),
+    }
+    let port = component(3, "tcp port")?;
+    port.parse::<u16>()
+        .map_err(|_| err(format!("invalid tcp port `{port}`")))?;
+    match &parts[4..] {
+        [] => (),
+        ["tls"] | ["tls", ""] => bail!(err("missing tls domain name".into())),
+        ["tls", domain] => ensure!(
+            is_valid_domain(domain),
+            err(format!("invalid tls domain name `{domain}`"))
+        ),
+        [""] => bail!(err("unexpected trailing `/`".into())),
+        [other, ..] if *other != "tls" => bail!(err(format!(
+            "unexpected component `{other}`, only `tls` is allowed after the port"
+        ))),
+        _ => bail!(err("unexpected components after the tls domain name".into())),
+    }
+
+    // Those checks above should have covered it, but let the real parser have the final say.
+    s.parse::<Multiaddr>().map_err(|e| err(e.to_string()))
+}
+
 pub fn hex(data: &[u8]) -> String {
     format!("0x{}", hex::encode(data))
 }
 
+/// Convert a big-endian unsigned integer of any length into decimal str.
+pub fn be_bytes_to_decimal(bytes: &[u8]) -> String {
+    // Little-endian decimal digits.
+    let mut digits = vec![0u8];
+    for &b in bytes {
+        // digits = digits * 256 + b
+        let mut carry = b as u32;
+        for d in digits.iter_mut() {
+            let v = *d as u32 * 256 + carry;
+            *d = (v % 10) as u8;
+            carry = v / 10;
+        }
+        while carry > 0 {
+            digits.push((carry % 10) as u8);
+            carry /= 10;
+        }
+    }
+    digits.iter().rev().map(|d| char::from(b'0' + d)).collect()
+}
+
+/// Convert a big-endian unsigned integer of any length, e.g. a quota in receipt, into u64.
+pub fn be_bytes_to_u64(bytes: &[u8]) -> Result<u64> {
+    let start = bytes.iter().position(|&b| b != 0).unwrap_or(bytes.len());
+    ensure!(
+        bytes.len() - start <= 8,
+        "`{}` is too large for u64",
+        hex(&bytes[start..])
+    );
+    Ok(bytes[start..]
+        .iter()
+        .fold(0, |acc, &b| (acc << 8) | b as u64))
+}
+
 /// This should be called without any other concurrent running threads.
 pub fn init_local_utc_offset() {
     let local_utc_offset =
@@ -153,6 +651,7 @@ pub fn display_time(timestamp: u64) -> String {
     let local_offset = LOCAL_UTC_OFFSET
         .load()
         .unwrap_or_else(|| UtcOffset::from_hms(8, 0, 0).unwrap());
+    #[allow(deprecated)]
     let format = time::format_description::parse(
         "[year]-[month]-[day] [hour]:[minute]:[second] [offset_hour sign:mandatory]:[offset_minute]",
     )
@@ -164,6 +663,17 @@ pub fn display_time(timestamp: u64) -> String {
         .unwrap()
 }
 
+/// Parse an RFC 3339 time, e.g. `2022-01-02T15:04:05+08:00`, into unix timestamp in milliseconds
+/// as in block headers.
+pub fn parse_time(s: &str) -> Result<u64> {
+    let t = time::OffsetDateTime::parse(s, &time::format_description::well_known::Rfc3339)
+        .with_context(|| {
+            format!("invalid time `{s}`, expected RFC 3339 like `2022-01-02T15:04:05+08:00`")
+        })?;
+    let millis = t.unix_timestamp_nanos() / 1_000_000;
+    u64::try_from(millis).map_err(|_| anyhow!("time `{s}` is before 1970"))
+}
+
 pub fn remove_0x(s: &str) -> &str {
     s.strip_prefix("0x").unwrap_or(s)
 }
@@ -171,9 +681,12 @@ pub fn remove_0x(s: &str) -> &str {
 // Safe in the sense of file integrity, not cryptography.
 pub fn safe_save(path: impl AsRef<Path>, content: &[u8], overwrite_existing: bool) -> Result<()> {
     let path = path.as_ref();
-    let dir = path
-        .parent()
-        .ok_or_else(|| anyhow!("cannot load containing dir"))?;
+    let dir = match path.parent() {
+        // A relative path with a single component e.g. `a.txt`.
+        Some(dir) if dir.as_os_str().is_empty() => Path::new("."),
+        Some(dir) => dir,
+        None => return Err(anyhow!("cannot load containing dir")),
+    };
 
     let mut tmp = NamedTempFile::new_in(dir)?;
     tmp.write_all(content)?;
@@ -188,10 +701,289 @@ pub fn safe_save(path: impl AsRef<Path>, content: &[u8], overwrite_existing: boo
     Ok(())
 }
 
+/// Expand `!!` in an interactive input line into the previous command, like shells do.
+/// It's kept as is inside quotes or after a backslash.
+pub fn expand_history(line: &str, previous: Option<&str>) -> Result<String> {
+    let mut expanded = String::with_capacity(line.len());
+    let mut quote = None;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match (c, quote) {
+            ('!', None) if chars.peek() == Some(&'!') => {
+                chars.next();
+                let previous = previous.ok_or_else(|| anyhow!("`!!`: no previous command"))?;
+                expanded.push_str(previous);
+                continue;
+            }
+            ('\\', q) if q != Some('\'') => {
+                expanded.push(c);
+                if let Some(escaped) = chars.next() {
+                    expanded.push(escaped);
+                }
+                continue;
+            }
+            ('\'' | '"', None) => quote = Some(c),
+            (c, Some(q)) if c == q => quote = None,
+            _ => (),
+        }
+        expanded.push(c);
+    }
+    Ok(expanded)
+}
+
+/// Make the file only readable and writable by the owner, for files with secrets.
+pub fn restrict_permissions(path: impl AsRef<Path>) -> Result<()> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600))?;
+    }
+    #[cfg(not(unix))]
+    let _ = path;
+    Ok(())
+}
+
+/// Load a protobuf encoded `RawTransaction` from file.
+pub fn load_raw_tx(path: impl AsRef<Path>) -> Result<RawTransaction> {
+    use prost::Message;
+
+    let path = path.as_ref();
+    let bytes = std::fs::read(path)
+        .with_context(|| format!("cannot read raw tx from `{}`", path.display()))?;
+    RawTransaction::decode(bytes.as_slice())
+        .with_context(|| format!("`{}` doesn't contain a valid raw tx", path.display()))
+}
+
+/// Save `RawTransaction` to file in protobuf encoding.
+pub fn save_raw_tx(
+    path: impl AsRef<Path>,
+    raw: &RawTransaction,
+    overwrite_existing: bool,
+) -> Result<()> {
+    use prost::Message;
+
+    let path = path.as_ref();
+    safe_save(path, &raw.encode_to_vec(), overwrite_existing)
+        .with_context(|| format!("cannot save raw tx to `{}`", path.display()))
+}
+
+/// Parse a `RawTransaction` from its JSON form, i.e. what `-o json tx decode` prints.
+///
+/// Byte fields are hex, so it encodes to the same protobuf as the original tx.
+/// Extra fields, e.g. `hash_matched` from `tx decode`, are ignored.
+pub fn raw_tx_from_json(json: &Json) -> Result<RawTransaction> {
+    use crate::proto::blockchain::{
+        raw_transaction::Tx, Transaction, UnverifiedTransaction, UnverifiedUtxoTransaction,
+        UtxoTransaction,
+    };
+
+    // It's how an empty raw tx is displayed.
+    if json.as_object().is_some_and(|obj| obj.is_empty()) {
+        return Ok(RawTransaction::default());
+    }
+    let unverified = json_field(json, "transaction")?;
+    let tx = match json_field(json, "type")?.as_str() {
+        Some("Normal") => {
+            let transaction = json_message(unverified, "transaction")?
+                .map(|tx| {
+                    anyhow::Ok(Transaction {
+                        version: json_u64(tx, "version")?.try_into()?,
+                        to: json_hex(tx, "to")?,
+                        nonce: json_str(tx, "nonce")?.into(),
+                        quota: json_u64(tx, "quota")?,
+                        valid_until_block: json_u64(tx, "valid_until_block")?,
+                        data: json_hex(tx, "data")?,
+                        value: json_hex(tx, "value")?,
+                        chain_id: json_hex(tx, "chain_id")?,
+                    })
+                })
+                .transpose()?;
+            Tx::NormalTx(UnverifiedTransaction {
+                transaction,
+                transaction_hash: json_hex(unverified, "transaction_hash")?,
+                witness: json_message(unverified, "witness")?
+                    .map(witness_from_json)
+                    .transpose()?,
+            })
+        }
+        Some("Utxo") => {
+            let transaction = json_message(unverified, "transaction")?
+                .map(|tx| {
+                    anyhow::Ok(UtxoTransaction {
+                        version: json_u64(tx, "version")?.try_into()?,
+                        pre_tx_hash: json_hex(tx, "pre_tx_hash")?,
+                        output: json_hex(tx, "output")?,
+                        lock_id: json_u64(tx, "lock_id")?,
+                    })
+                })
+                .transpose()?;
+            let witnesses = json_field(unverified, "witnesses")?
+                .as_array()
+                .context("`witnesses` must be an array")?
+                .iter()
+                .map(witness_from_json)
+                .collect::<Result<_>>()?;
+            Tx::UtxoTx(UnverifiedUtxoTransaction {
+                transaction,
+                transaction_hash: json_hex(unverified, "transaction_hash")?,
+                witnesses,
+            })
+        }
+        _ => bail!("`type` must be `Normal` or `Utxo`"),
+    };
+    Ok(RawTransaction { tx: Some(tx) })
+}
+
+fn witness_from_json(json: &Json) -> Result<Witness> {
+    Ok(Witness {
+        signature: json_hex(json, "signature")?,
+        sender: json_hex(json, "sender")?,
+    })
+}
+
+fn json_field<'a>(json: &'a Json, key: &str) -> Result<&'a Json> {
+    json.get(key)
+        .with_context(|| format!("missing field `{key}`"))
+}
+
+fn json_str<'a>(json: &'a Json, key: &str) -> Result<&'a str> {
+    json_field(json, key)?
+        .as_str()
+        .with_context(|| format!("`{key}` must be a string"))
+}
+
+fn json_u64(json: &Json, key: &str) -> Result<u64> {
+    json_field(json, key)?
+        .as_u64()
+        .with_context(|| format!("`{key}` must be an unsigned integer"))
+}
+
+fn json_hex(json: &Json, key: &str) -> Result<Vec<u8>> {
+    parse_data(json_str(json, key)?).with_context(|| format!("`{key}` must be hex"))
+}
+
+/// `None` for an empty object, which is how a missing message is displayed.
+fn json_message<'a>(json: &'a Json, key: &str) -> Result<Option<&'a Json>> {
+    match json_field(json, key)? {
+        Json::Object(obj) if obj.is_empty() => Ok(None),
+        msg @ Json::Object(_) => Ok(Some(msg)),
+        _ => bail!("`{key}` must be an object"),
+    }
+}
+
+/// Like [`load_raw_tx`], but the file is in the JSON form of [`raw_tx_from_json`].
+pub fn load_raw_tx_json(path: impl AsRef<Path>) -> Result<RawTransaction> {
+    let path = path.as_ref();
+    let content = std::fs::read_to_string(path)
+        .with_context(|| format!("cannot read raw tx from `{}`", path.display()))?;
+    serde_json::from_str(&content)
+        .map_err(anyhow::Error::from)
+        .and_then(|json| raw_tx_from_json(&json))
+        .with_context(|| format!("`{}` doesn't contain a valid raw tx json", path.display()))
+}
+
+/// Like [`save_raw_tx`], but in the JSON form of [`raw_tx_from_json`].
+pub fn save_raw_tx_json(
+    path: impl AsRef<Path>,
+    raw: &RawTransaction,
+    overwrite_existing: bool,
+) -> Result<()> {
+    let path = path.as_ref();
+    // Addresses in lowercase, the file isn't tied to a chain.
+    let json = serde_json::to_string_pretty(&raw.to_json(DisplayOptions::default()))?;
+    safe_save(path, json.as_bytes(), overwrite_existing)
+        .with_context(|| format!("cannot save raw tx to `{}`", path.display()))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_canonical_abi() {
+        let abi = r#"[
+            {
+                "type": "function",
+                "name": "get",
+                "inputs": [],
+                "outputs": [{"name": "", "type": "uint256"}],
+                "stateMutability": "view"
+            }
+        ]"#;
+        let minified = canonical_abi(abi.as_bytes()).unwrap();
+        assert!(!minified.contains(&b'\n'));
+        assert_eq!(canonical_abi(&minified).unwrap(), minified);
+
+        let err = canonical_abi(b"not json").unwrap_err();
+        assert_eq!(err.to_string(), "ABI is not valid JSON");
+        let err = canonical_abi(br#"{"type": "function"}"#).unwrap_err();
+        assert_eq!(err.to_string(), "ABI must be a JSON array");
+        assert!(canonical_abi(br#"[{"type": "function", "name": 1}]"#).is_err());
+    }
+
+    #[test]
+    fn test_address_checksum() {
+        // Test vectors from EIP-55.
+        for s in [
+            "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed",
+            "0xfB6916095ca1df60bB79Ce92cE3Ea74c37c5d359",
+            "0xdbF03B407c01E7cD3CBea99509d93f8DDDC8C6FB",
+            "0xD1220A0cf47c7B9Be7A2E6BA89F429762e7b9aDb",
+        ] {
+            let addr = Address::from_hex(s).unwrap();
+            assert_eq!(checksum_address(&addr), s);
+            assert!(check_address_checksum(s, &addr).is_ok());
+            assert!(check_address_checksum(&s.to_lowercase(), &addr).is_ok());
+            assert!(check_address_checksum(&s[2..].to_uppercase(), &addr).is_ok());
+        }
+
+        let s = "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAeD";
+        let err = check_address_checksum(s, &Address::from_hex(s).unwrap()).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "invalid address checksum, expected `0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed`"
+        );
+    }
+
+    #[test]
+    fn test_explain_len() {
+        let hash = format!("0x{}", "ab".repeat(32));
+        let addr = format!("0x{}", "ab".repeat(20));
+        assert_eq!(
+            parse_addr(&hash).unwrap_err().to_string(),
+            format!("`{hash}` looks like a hash (32 bytes), but an address (20 bytes) is expected")
+        );
+        assert_eq!(
+            parse_hash(&addr).unwrap_err().to_string(),
+            format!("`{addr}` looks like an address (20 bytes), but a hash (32 bytes) is expected")
+        );
+        assert_eq!(
+            parse_hash("0xabcd").unwrap_err().to_string(),
+            "expected 32 bytes, got 2"
+        );
+    }
+
+    #[test]
+    fn test_parse_data_input() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let hex_file = dir.path().join("code.hex");
+        std::fs::write(&hex_file, "0x6080\n")?;
+        let hex_arg = format!("@{}", hex_file.display());
+        assert_eq!(parse_data_input(&hex_arg)?, [0x60, 0x80]);
+        assert_eq!(parse_data_input("0x6080")?, [0x60, 0x80]);
+
+        let bin_file = dir.path().join("code.bin");
+        std::fs::write(&bin_file, [0x60, 0x80, 0xff])?;
+        let bin_arg = format!("@{}", bin_file.display());
+        assert_eq!(parse_binary_input(&bin_arg)?, [0x60, 0x80, 0xff]);
+        let err = parse_data_input(&bin_arg).unwrap_err();
+        assert!(err.to_string().contains("(3 bytes)"));
+
+        assert!(validate_data_input("@does-not-matter").is_ok());
+        assert!(validate_binary_input("0x6080").is_err());
+        Ok(())
+    }
+
     #[test]
     fn test_position() -> Result<()> {
         assert_eq!(parse_position("100")?, Position::Absolute(100));
@@ -215,4 +1007,165 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_parse_value() -> Result<()> {
+        let wei = |v: u128| {
+            let mut bytes = [0u8; 32];
+            bytes[16..].copy_from_slice(&v.to_be_bytes());
+            U256(bytes)
+        };
+        assert_eq!(parse_value("0x0")?, wei(0));
+        assert_eq!(parse_value("0x100")?, wei(256));
+        assert_eq!(parse_value("100")?, wei(100));
+        assert_eq!(parse_value("100wei")?, wei(100));
+        assert_eq!(parse_value("200gwei")?, wei(200_000_000_000));
+        assert_eq!(parse_value("1.5ether")?, wei(1_500_000_000_000_000_000));
+        assert_eq!(parse_value("1.5 Ether")?, wei(1_500_000_000_000_000_000));
+        assert_eq!(parse_value(".5gwei")?, wei(500_000_000));
+        assert_eq!(
+            parse_value(
+                "115792089237316195423570985008687907853269984665640564039457584007913129639935"
+            )?
+            .to_string(),
+            "115792089237316195423570985008687907853269984665640564039457584007913129639935"
+        );
+        // Leading zeros don't count.
+        assert_eq!(parse_value(&format!("0x{}ff", "0".repeat(100)))?, wei(255));
+        assert!(parse_value(&format!("0x1{}", "0".repeat(64))).is_err());
+        assert!(parse_value("0x").is_err());
+
+        assert!(parse_value(
+            "115792089237316195423570985008687907853269984665640564039457584007913129639936"
+        )
+        .is_err());
+        assert!(parse_value("1.5").is_err());
+        assert!(parse_value("1.0000000001gwei").is_err());
+        assert!(parse_value("1.2.3ether").is_err());
+        assert!(parse_value("-1").is_err());
+        assert!(parse_value("ether").is_err());
+        assert!(parse_value("").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_u256() -> Result<()> {
+        assert_eq!(U256::from_be_slice(&[])?, U256::ZERO);
+        assert_eq!(U256::from_be_slice(&[0, 0, 1, 0])?, U256::from(256));
+        // Leading zeros beyond 32 bytes are trimmed, the rest must fit.
+        assert_eq!(
+            U256::from_be_slice(&[&[0; 40][..], &[0xff; 32]].concat())?,
+            U256([0xff; 32])
+        );
+        assert!(U256::from_be_slice(&[1; 33]).is_err());
+
+        let v = U256::from(12345);
+        assert_eq!(v.to_string(), "12345");
+        assert_eq!(v.to_hex(), format!("0x{}3039", "00".repeat(30)));
+        assert_eq!(v.to_u64()?, 12345);
+        assert_eq!(U256::from_hex("0x3039")?, v);
+        assert_eq!(U256::from_decimal("012345")?, v);
+        assert_eq!("12345wei".parse::<U256>()?, v);
+        assert!(U256([0xff; 32]).to_u64().is_err());
+        assert!(U256::from_decimal("").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_be_bytes_to_decimal() {
+        assert_eq!(be_bytes_to_decimal(&[]), "0");
+        assert_eq!(be_bytes_to_decimal(&[0; 32]), "0");
+        assert_eq!(be_bytes_to_decimal(&[1, 0]), "256");
+        assert_eq!(be_bytes_to_decimal(&[0, 0, 0x30, 0x39]), "12345");
+        assert_eq!(
+            be_bytes_to_decimal(&[0xff; 32]),
+            "115792089237316195423570985008687907853269984665640564039457584007913129639935"
+        );
+
+        assert_eq!(be_bytes_to_u64(&[]).unwrap(), 0);
+        assert_eq!(be_bytes_to_u64(&[0, 0, 0x30, 0x39]).unwrap(), 12345);
+        assert_eq!(
+            be_bytes_to_u64(&[&[0; 24][..], &[0xff; 8]].concat()).unwrap(),
+            u64::MAX
+        );
+        assert!(be_bytes_to_u64(&[1, 0, 0, 0, 0, 0, 0, 0, 0]).is_err());
+    }
+
+    #[test]
+    fn test_parse_height_range() -> Result<()> {
+        assert_eq!(parse_height_range("100-200")?, 100..=200);
+        assert_eq!(parse_height_range("7-7")?, 7..=7);
+        assert!(parse_height_range("200-100").is_err());
+        assert!(parse_height_range("100").is_err());
+        assert!(parse_height_range("-100").is_err());
+        assert!(parse_height_range("a-b").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_interactive_history() -> Result<()> {
+        assert_eq!(expand_history("get bn", None)?, "get bn");
+        assert_eq!(expand_history("!!", Some("get bn"))?, "get bn");
+        assert_eq!(
+            expand_history("!! --for-pending", Some("get bn"))?,
+            "get bn --for-pending"
+        );
+        assert!(expand_history("!!", None).is_err());
+        // Not inside quotes.
+        assert_eq!(
+            expand_history(
+                "send 0x00 --function 'f(string)' --args 'hi!!'",
+                Some("get bn")
+            )?,
+            "send 0x00 --function 'f(string)' --args 'hi!!'"
+        );
+        assert_eq!(
+            expand_history(r#"!! "a!!" \!! 'b!!'"#, Some("get bn"))?,
+            r#"get bn "a!!" \!! 'b!!'"#
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_block_id() -> Result<()> {
+        assert_eq!(parse_block_id("latest")?, BlockId::Latest);
+        assert_eq!(parse_block_id("pending")?, BlockId::Pending);
+        assert_eq!(parse_block_id("100")?, BlockId::Number(100));
+        assert_eq!(parse_block_id("0x64")?, BlockId::Number(100));
+        assert_eq!(
+            parse_block_id(&format!("0x{}", "ab".repeat(32)))?,
+            BlockId::Hash([0xab; 32])
+        );
+
+        assert!(parse_block_id("0xzz").is_err());
+        assert!(parse_block_id("lastest").is_err());
+        assert!(parse_block_id("-1").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_multiaddr() {
+        for ok in [
+            "/ip4/1.2.3.4/tcp/40000",
+            "/ip6/::1/tcp/40000",
+            "/dns4/node-1.example.com/tcp/40000",
+            "/dns4/localhost/tcp/40000/tls/node-1.example.com",
+        ] {
+            assert_eq!(parse_multiaddr(ok).unwrap().to_string(), ok);
+        }
+
+        let err = |s: &str| parse_multiaddr(s).unwrap_err().to_string();
+        assert!(err("ip4/1.2.3.4/tcp/40000").contains("must start with `/`"));
+        assert!(err("/ip4/1.2.3.256/tcp/40000").contains("invalid ip4 address `1.2.3.256`"));
+        assert!(err("/dns4/bad_host/tcp/40000").contains("invalid domain name `bad_host`"));
+        assert!(err("/udp/1.2.3.4/tcp/40000").contains("unsupported protocol `udp`"));
+        assert!(err("/ip4/1.2.3.4/udp/40000").contains("expected `tcp` after the host"));
+        assert!(err("/ip4/1.2.3.4/tcp/65536").contains("invalid tcp port `65536`"));
+        assert!(err("/ip4/1.2.3.4").contains("missing `tcp` after the host"));
+        assert!(err("/ip4/1.2.3.4/tcp/40000/").contains("trailing `/`"));
+        assert!(err("/ip4/1.2.3.4/tcp/40000/tls").contains("missing tls domain name"));
+        assert!(err("/ip4/1.2.3.4/tcp/40000/ws").contains("unexpected component `ws`"));
+    }
 }